@@ -0,0 +1,203 @@
+//! Support for rendering templates into asynchronous writers
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, ready};
+use std::io;
+
+use crate::RenderChunks;
+
+/// Size of the chunks that are handed to the writer
+pub(crate) const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Future returned by [`Template::write_into_async()`][crate::Template::write_into_async]
+///
+/// The template is evaluated in a background thread by a [`RenderChunks`] iterator. Whenever
+/// 8 KiB of output are ready, they are handed to the writer, so a large page starts streaming
+/// while the rest of the template is still being evaluated. If the writer is not ready to accept
+/// more data, the evaluation pauses, so at most a few chunks are held in memory, no matter how
+/// slow the writer is.
+#[cfg(feature = "futures-io")]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct WriteIntoAsync<'a, W: ?Sized> {
+    chunks: RenderChunks,
+    writer: &'a mut W,
+    state: State,
+}
+
+/// Future returned by [`Template::write_into_tokio()`][crate::Template::write_into_tokio]
+///
+/// It behaves exactly like `WriteIntoAsync`, but writes into a [`tokio::io::AsyncWrite`].
+#[cfg(feature = "tokio")]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct WriteIntoTokio<'a, W: ?Sized> {
+    chunks: RenderChunks,
+    writer: &'a mut W,
+    state: State,
+}
+
+#[cfg(feature = "futures-io")]
+impl<'a, W: ?Sized> WriteIntoAsync<'a, W> {
+    pub(crate) fn new(chunks: RenderChunks, writer: &'a mut W) -> Self {
+        Self {
+            chunks,
+            writer,
+            state: State::Next,
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<'a, W: ?Sized> WriteIntoTokio<'a, W> {
+    pub(crate) fn new(chunks: RenderChunks, writer: &'a mut W) -> Self {
+        Self {
+            chunks,
+            writer,
+            state: State::Next,
+        }
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<W> Future for WriteIntoAsync<'_, W>
+where
+    W: futures_io::AsyncWrite + Unpin + ?Sized,
+{
+    type Output = io::Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let mut writer = FuturesIo(&mut *this.writer);
+        this.state.poll(&mut this.chunks, cx, &mut writer)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<W> Future for WriteIntoTokio<'_, W>
+where
+    W: tokio::io::AsyncWrite + Unpin + ?Sized,
+{
+    type Output = io::Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let mut writer = TokioIo(&mut *this.writer);
+        this.state.poll(&mut this.chunks, cx, &mut writer)
+    }
+}
+
+/// Common interface of [`futures_io::AsyncWrite`] and [`tokio::io::AsyncWrite`]
+trait PollWrite {
+    fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>>;
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>>;
+}
+
+#[cfg(feature = "futures-io")]
+struct FuturesIo<'a, W: ?Sized>(&'a mut W);
+
+#[cfg(feature = "futures-io")]
+impl<W: futures_io::AsyncWrite + Unpin + ?Sized> PollWrite for FuturesIo<'_, W> {
+    #[inline]
+    fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut *self.0).poll_write(cx, buf)
+    }
+
+    #[inline]
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.0).poll_flush(cx)
+    }
+}
+
+#[cfg(feature = "tokio")]
+struct TokioIo<'a, W: ?Sized>(&'a mut W);
+
+#[cfg(feature = "tokio")]
+impl<W: tokio::io::AsyncWrite + Unpin + ?Sized> PollWrite for TokioIo<'_, W> {
+    #[inline]
+    fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut *self.0).poll_write(cx, buf)
+    }
+
+    #[inline]
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.0).poll_flush(cx)
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<W: ?Sized> fmt::Debug for WriteIntoAsync<'_, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WriteIntoAsync")
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<W: ?Sized> fmt::Debug for WriteIntoTokio<'_, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WriteIntoTokio")
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug)]
+enum State {
+    /// Waiting for the next chunk of the rendered template
+    Next,
+    /// `buf[pos..]` still needs to be written
+    Write { buf: Vec<u8>, pos: usize },
+    /// All data was written, the writer needs to be flushed
+    Flush,
+    /// The future has completed
+    Done,
+}
+
+impl State {
+    fn poll(
+        &mut self,
+        chunks: &mut RenderChunks,
+        cx: &mut Context<'_>,
+        writer: &mut impl PollWrite,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            match self {
+                State::Next => match ready!(chunks.poll_chunk(cx)) {
+                    Some(Ok(buf)) => *self = State::Write { buf, pos: 0 },
+                    Some(Err(err)) => {
+                        *self = State::Done;
+                        return Poll::Ready(Err(err.into()));
+                    }
+                    None => *self = State::Flush,
+                },
+                State::Write { buf, pos } => {
+                    while *pos < buf.len() {
+                        match ready!(writer.poll_write(cx, &buf[*pos..])) {
+                            Ok(0) => {
+                                *self = State::Done;
+                                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+                            }
+                            Ok(written) => *pos += written,
+                            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                            Err(err) => {
+                                *self = State::Done;
+                                return Poll::Ready(Err(err));
+                            }
+                        }
+                    }
+                    *self = State::Next;
+                }
+                State::Flush => {
+                    let result = ready!(writer.poll_flush(cx));
+                    *self = State::Done;
+                    return Poll::Ready(result);
+                }
+                State::Done => panic!("future polled after completion"),
+            }
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! Rendering a template as a sequence of byte chunks
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::mem::replace;
+use core::task::Waker;
+use std::panic::resume_unwind;
+use std::sync::mpsc::{Receiver, RecvError, SyncSender, sync_channel};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+
+use crate::{Error, Result, Template, Values};
+
+/// Iterator over the chunks of a rendered template, returned by [`Template::render_chunks()`]
+///
+/// The template is evaluated once, in a background thread. The thread hands every completed
+/// chunk over through a channel with a single slot, and waits until the consumer has taken it
+/// before it continues. So rendering a template this way needs a bounded amount of memory, no
+/// matter how large the output is.
+///
+/// Every chunk is exactly `chunk_size` bytes long, except for the last one, which may be shorter.
+/// A chunk may end in the middle of a UTF-8 encoded character.
+///
+/// If the template cannot be rendered, or if the thread cannot be spawned, the error is returned
+/// as the last item. If the template panics, the panic is propagated to the consumer after the
+/// chunks that were completed before. If the `RenderChunks` is dropped early, the template stops
+/// at its next write.
+///
+/// With the feature `"futures-io"` or `"tokio"`, this type implements [`futures_core::Stream`],
+/// too, so it can be used e.g. as the body of an HTTP response. Polling the stream never blocks.
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct RenderChunks {
+    receiver: Receiver<Result<Vec<u8>>>,
+    /// The task that polled the stream last, it is woken by the rendering thread
+    #[cfg_attr(not(any(feature = "futures-io", feature = "tokio")), allow(dead_code))]
+    waker: Arc<Mutex<Option<Waker>>>,
+    /// `None` if the thread could not be spawned, or after it was joined
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RenderChunks {
+    pub(crate) fn new<T, V>(template: T, chunk_size: usize, values: V) -> Self
+    where
+        T: Template + Send + 'static,
+        V: Values + Send + 'static,
+    {
+        let (sender, receiver) = sync_channel(1);
+        let waker = Arc::new(Mutex::new(None));
+        let chunk_sender = ChunkSender {
+            sender: Some(sender.clone()),
+            waker: Arc::clone(&waker),
+        };
+        let thread = thread::Builder::new().spawn(move || {
+            let mut writer = ChunkWriter {
+                buf: Vec::new(),
+                chunk_size: chunk_size.max(1),
+                sender: &chunk_sender,
+            };
+            let _ = writer.buf.try_reserve(writer.chunk_size);
+            let _ = match template.render_into_with_values(&mut writer, &values) {
+                Ok(()) if writer.buf.is_empty() => Ok(()),
+                Ok(()) => chunk_sender.send(Ok(writer.buf)),
+                // The consumer is gone, if the error is caused by a failed `send()`.
+                Err(err) => chunk_sender.send(Err(err)),
+            };
+        });
+        let thread = match thread {
+            Ok(thread) => Some(thread),
+            Err(err) => {
+                // The slot is still empty, so this cannot fail.
+                let _ = sender.try_send(Err(Error::custom(err)));
+                None
+            }
+        };
+        Self {
+            receiver,
+            waker,
+            thread,
+        }
+    }
+
+    /// Returns the next chunk if it is ready, or else registers `cx` to be woken up
+    #[cfg(any(feature = "futures-io", feature = "tokio"))]
+    pub(crate) fn poll_chunk(
+        &mut self,
+        cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<Option<Result<Vec<u8>>>> {
+        // The waker is stored before the channel is checked, so a chunk that is sent in between
+        // wakes the task.
+        match &mut *self.waker.lock().unwrap_or_else(PoisonError::into_inner) {
+            Some(waker) => waker.clone_from(cx.waker()),
+            waker @ None => *waker = Some(cx.waker().clone()),
+        }
+        match self.receiver.try_recv() {
+            Ok(chunk) => core::task::Poll::Ready(Some(chunk)),
+            Err(std::sync::mpsc::TryRecvError::Empty) => core::task::Poll::Pending,
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                self.join();
+                core::task::Poll::Ready(None)
+            }
+        }
+    }
+
+    /// The rendering thread has stopped, propagate its panic if there was one
+    fn join(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            if let Err(payload) = thread.join() {
+                resume_unwind(payload);
+            }
+        }
+    }
+}
+
+impl Iterator for RenderChunks {
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.receiver.recv() {
+            Ok(chunk) => Some(chunk),
+            Err(RecvError) => {
+                self.join();
+                None
+            }
+        }
+    }
+}
+
+#[cfg(any(feature = "futures-io", feature = "tokio"))]
+impl futures_core::Stream for RenderChunks {
+    type Item = Result<Vec<u8>>;
+
+    #[inline]
+    fn poll_next(
+        self: core::pin::Pin<&mut Self>,
+        cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<Option<Self::Item>> {
+        self.get_mut().poll_chunk(cx)
+    }
+}
+
+impl fmt::Debug for RenderChunks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RenderChunks").finish_non_exhaustive()
+    }
+}
+
+/// The rendering thread's end of the channel
+///
+/// Dropping it closes the channel, even if the template panicked, and wakes the consumer.
+struct ChunkSender {
+    sender: Option<SyncSender<Result<Vec<u8>>>>,
+    waker: Arc<Mutex<Option<Waker>>>,
+}
+
+impl ChunkSender {
+    /// Wait until the slot is free, then store the `chunk`; fails if the consumer is gone
+    fn send(&self, chunk: Result<Vec<u8>>) -> fmt::Result {
+        let Some(sender) = &self.sender else {
+            return Err(fmt::Error);
+        };
+        sender.send(chunk).map_err(|_| fmt::Error)?;
+        self.wake();
+        Ok(())
+    }
+
+    fn wake(&self) {
+        let waker = self
+            .waker
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl Drop for ChunkSender {
+    fn drop(&mut self) {
+        // The channel has to be closed before the consumer is woken up.
+        drop(self.sender.take());
+        self.wake();
+    }
+}
+
+/// [`fmt::Write`] implementation that sends every completed chunk through the [`ChunkSender`]
+struct ChunkWriter<'a> {
+    buf: Vec<u8>,
+    chunk_size: usize,
+    sender: &'a ChunkSender,
+}
+
+impl fmt::Write for ChunkWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut s = s.as_bytes();
+        while self.buf.len() + s.len() >= self.chunk_size {
+            let (head, tail) = s.split_at(self.chunk_size - self.buf.len());
+            self.buf.extend_from_slice(head);
+            s = tail;
+
+            let chunk = replace(&mut self.buf, Vec::with_capacity(self.chunk_size));
+            self.sender.send(Ok(chunk))?;
+        }
+        self.buf.extend_from_slice(s);
+        Ok(())
+    }
+}
@@ -23,6 +23,8 @@ pub enum Error {
     ValueMissing,
     /// Incompatible value type for key in [`Values`][crate::Values]
     ValueType,
+    /// The template selected by a dynamic `{% include %}` is not one of its candidates
+    UnknownTemplate,
     /// An error raised by using `?` in a template
     #[cfg(feature = "alloc")]
     Custom(Box<dyn StdError + Send + Sync>),
@@ -47,6 +49,7 @@ impl Error {
             Error::Fmt => fmt::Error.into(),
             Error::ValueMissing => Box::new(Error::ValueMissing),
             Error::ValueType => Box::new(Error::ValueType),
+            Error::UnknownTemplate => Box::new(Error::UnknownTemplate),
             Error::Custom(err) => err,
             #[cfg(feature = "serde_json")]
             Error::Json(err) => err.into(),
@@ -74,6 +77,7 @@ impl StdError for Error {
             Error::Fmt => Some(&fmt::Error),
             Error::ValueMissing => None,
             Error::ValueType => None,
+            Error::UnknownTemplate => None,
             #[cfg(feature = "alloc")]
             Error::Custom(err) => Some(err.as_ref()),
             #[cfg(feature = "serde_json")]
@@ -88,6 +92,7 @@ impl fmt::Display for Error {
             Error::Fmt => fmt::Error.fmt(f),
             Error::ValueMissing => f.write_str("key missing in values"),
             Error::ValueType => f.write_str("value has wrong type"),
+            Error::UnknownTemplate => f.write_str("unknown template in dynamic include"),
             #[cfg(feature = "alloc")]
             Error::Custom(err) => err.fmt(f),
             #[cfg(feature = "serde_json")]
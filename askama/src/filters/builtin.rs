@@ -5,7 +5,7 @@ use core::ops::Deref;
 use core::pin::Pin;
 
 use super::MAX_LEN;
-use super::escape::FastWritable;
+use super::escape::{FastWritable, HtmlSafe};
 use crate::{Error, Result};
 
 /// Limit string length, appends '...' if truncated
@@ -448,6 +448,333 @@ impl<S: FastWritable, P: FastWritable> FastWritable for Pluralize<S, P> {
     }
 }
 
+/// Returns the fallback value if the input value is missing, like Jinja's `default` filter
+///
+/// An [`Option`] is missing if it is [`None`]. If the second argument is `true`, then empty
+/// strings and empty slices are treated as missing, too, even if they are wrapped in `Some`.
+///
+/// This function needs both values to implement [`DefaultValue`]. In a template the filter
+/// accepts any value: an `Option<T>` of another type `T` is only missing if it is `None`,
+/// and every other value is never missing.
+///
+/// The filter must be enabled with `builtin_filters = ["default"]` in the crate's configuration
+/// file.
+///
+/// The result can be rendered or iterated over. The fallback is written as is, without any
+/// intermediate allocation.
+///
+/// ```
+/// # #[cfg(feature = "code-in-doc")] {
+/// # use askama::Template;
+/// /// ```jinja
+/// /// <div>{{ nickname|default("anonymous") }}</div>
+/// /// <div>{{ bio|default("no bio", true) }}</div>
+/// /// <ul>{% for tag in tags|default(["untagged"]) %}<li>{{ tag }}</li>{% endfor %}</ul>
+/// /// ```
+/// #[derive(Template)]
+/// #[template(ext = "html", in_doc = true)]
+/// struct Example<'a> {
+///     nickname: Option<&'a str>,
+///     bio: &'a str,
+///     tags: Option<Vec<&'a str>>,
+/// }
+///
+/// assert_eq!(
+///     Example { nickname: None, bio: "", tags: None }.to_string(),
+///     "<div>anonymous</div>\n<div>no bio</div>\n<ul><li>untagged</li></ul>"
+/// );
+/// assert_eq!(
+///     Example { nickname: Some("kim"), bio: "Hi!", tags: Some(vec!["a", "b"]) }.to_string(),
+///     "<div>kim</div>\n<div>Hi!</div>\n<ul><li>a</li><li>b</li></ul>"
+/// );
+/// # }
+/// ```
+#[inline]
+pub fn default<'a, 'b, T, F>(
+    value: &'a T,
+    fallback: &'b F,
+    empty_is_missing: bool,
+) -> Result<DefaultFilter<T::Value<'a>, F::Value<'b>>, Infallible>
+where
+    T: DefaultValue + ?Sized,
+    F: DefaultValue + ?Sized,
+{
+    Ok(match value.default_value(empty_is_missing) {
+        Some(value) => DefaultFilter::Value(value),
+        None => match fallback.default_value(false) {
+            Some(fallback) => DefaultFilter::Fallback(fallback),
+            None => DefaultFilter::Missing,
+        },
+    })
+}
+
+/// A value that can be missing, used by the filter [`default()`]
+pub trait DefaultValue {
+    /// The present value
+    type Value<'a>
+    where
+        Self: 'a;
+
+    /// Returns `None` if the value is missing, or if it is empty and `empty_is_missing` is `true`
+    fn default_value(&self, empty_is_missing: bool) -> Option<Self::Value<'_>>;
+}
+
+const _: () = {
+    crate::impl_for_ref! {
+        impl DefaultValue for T {
+            type Value<'a> = T::Value<'a> where Self: 'a;
+
+            #[inline]
+            fn default_value(&self, empty_is_missing: bool) -> Option<Self::Value<'_>> {
+                <T>::default_value(self, empty_is_missing)
+            }
+        }
+    }
+
+    impl<T: DefaultValue> DefaultValue for Option<T> {
+        type Value<'a>
+            = T::Value<'a>
+        where
+            Self: 'a;
+
+        #[inline]
+        fn default_value(&self, empty_is_missing: bool) -> Option<Self::Value<'_>> {
+            self.as_ref()?.default_value(empty_is_missing)
+        }
+    }
+
+    impl DefaultValue for str {
+        type Value<'a> = &'a str;
+
+        #[inline]
+        fn default_value(&self, empty_is_missing: bool) -> Option<Self::Value<'_>> {
+            (!empty_is_missing || !self.is_empty()).then_some(self)
+        }
+    }
+
+    impl<T> DefaultValue for [T] {
+        type Value<'a>
+            = &'a [T]
+        where
+            Self: 'a;
+
+        #[inline]
+        fn default_value(&self, empty_is_missing: bool) -> Option<Self::Value<'_>> {
+            (!empty_is_missing || !self.is_empty()).then_some(self)
+        }
+    }
+
+    impl<T, const N: usize> DefaultValue for [T; N] {
+        type Value<'a>
+            = &'a [T]
+        where
+            Self: 'a;
+
+        #[inline]
+        fn default_value(&self, empty_is_missing: bool) -> Option<Self::Value<'_>> {
+            self.as_slice().default_value(empty_is_missing)
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl DefaultValue for alloc::string::String {
+        type Value<'a> = &'a str;
+
+        #[inline]
+        fn default_value(&self, empty_is_missing: bool) -> Option<Self::Value<'_>> {
+            self.as_str().default_value(empty_is_missing)
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl DefaultValue for alloc::borrow::Cow<'_, str> {
+        type Value<'a>
+            = &'a str
+        where
+            Self: 'a;
+
+        #[inline]
+        fn default_value(&self, empty_is_missing: bool) -> Option<Self::Value<'_>> {
+            (**self).default_value(empty_is_missing)
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl<T> DefaultValue for alloc::vec::Vec<T> {
+        type Value<'a>
+            = &'a [T]
+        where
+            Self: 'a;
+
+        #[inline]
+        fn default_value(&self, empty_is_missing: bool) -> Option<Self::Value<'_>> {
+            self.as_slice().default_value(empty_is_missing)
+        }
+    }
+
+    /// implement `DefaultValue` for types that are never missing
+    macro_rules! impl_default_value_for_primitive {
+        ($($ty:ty)*) => { $(
+            impl DefaultValue for $ty {
+                type Value<'a> = $ty;
+
+                #[inline]
+                fn default_value(&self, _: bool) -> Option<Self::Value<'_>> {
+                    Some(*self)
+                }
+            }
+        )* };
+    }
+
+    impl_default_value_for_primitive!(
+        i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize f32 f64 bool char
+    );
+};
+
+/// Find the present value of the input and fallback of the filter [`default()`],
+/// implementation detail of the code generator
+///
+/// A type that implements [`DefaultValue`] is used as such. Otherwise an [`Option`] is missing if
+/// it is [`None`], and any other value is never missing.
+#[doc(hidden)]
+pub struct DefaultValueOf<'a, T: ?Sized>(pub &'a T);
+
+#[doc(hidden)]
+pub trait DefaultValueLookup<'a> {
+    type Value;
+
+    fn askama_default_value(&self, empty_is_missing: bool) -> Option<Self::Value>;
+}
+
+impl<'a, T: DefaultValue + ?Sized> DefaultValueLookup<'a> for &DefaultValueOf<'a, T> {
+    type Value = T::Value<'a>;
+
+    #[inline]
+    fn askama_default_value(&self, empty_is_missing: bool) -> Option<Self::Value> {
+        self.0.default_value(empty_is_missing)
+    }
+}
+
+impl<'a, T> DefaultValueLookup<'a> for &&DefaultValueOf<'a, Option<T>> {
+    type Value = &'a T;
+
+    #[inline]
+    fn askama_default_value(&self, _: bool) -> Option<Self::Value> {
+        self.0.as_ref()
+    }
+}
+
+impl<'a, T: ?Sized> DefaultValueLookup<'a> for DefaultValueOf<'a, T> {
+    type Value = &'a T;
+
+    #[inline]
+    fn askama_default_value(&self, _: bool) -> Option<Self::Value> {
+        Some(self.0)
+    }
+}
+
+/// Combine the looked up input value and fallback of the filter [`default()`],
+/// implementation detail of the code generator
+#[doc(hidden)]
+#[inline]
+pub fn default_of<V, F>(
+    value: Option<V>,
+    fallback: Option<F>,
+) -> Result<DefaultFilter<V, F>, Infallible> {
+    Ok(match (value, fallback) {
+        (Some(value), _) => DefaultFilter::Value(value),
+        (None, Some(fallback)) => DefaultFilter::Fallback(fallback),
+        (None, None) => DefaultFilter::Missing,
+    })
+}
+
+/// The result of the filter [`default()`]
+pub enum DefaultFilter<V, F> {
+    /// The input value was present
+    Value(V),
+    /// The input value was missing, so the fallback is used
+    Fallback(F),
+    /// Both, the input value and the fallback, were missing
+    Missing,
+}
+
+impl<V: fmt::Display, F: fmt::Display> fmt::Display for DefaultFilter<V, F> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DefaultFilter::Value(value) => write!(f, "{value}"),
+            DefaultFilter::Fallback(value) => write!(f, "{value}"),
+            DefaultFilter::Missing => Ok(()),
+        }
+    }
+}
+
+impl<V: FastWritable, F: FastWritable> FastWritable for DefaultFilter<V, F> {
+    #[inline]
+    fn write_into<W: fmt::Write + ?Sized>(&self, dest: &mut W) -> crate::Result<()> {
+        match self {
+            DefaultFilter::Value(value) => value.write_into(dest),
+            DefaultFilter::Fallback(value) => value.write_into(dest),
+            DefaultFilter::Missing => Ok(()),
+        }
+    }
+}
+
+impl<V: HtmlSafe, F: HtmlSafe> HtmlSafe for DefaultFilter<V, F> {}
+
+impl<V, F> IntoIterator for DefaultFilter<V, F>
+where
+    V: IntoIterator,
+    F: IntoIterator<Item = V::Item>,
+{
+    type Item = V::Item;
+    type IntoIter = DefaultIter<V::IntoIter, F::IntoIter>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        DefaultIter(match self {
+            DefaultFilter::Value(value) => DefaultIterInner::Value(value.into_iter()),
+            DefaultFilter::Fallback(value) => DefaultIterInner::Fallback(value.into_iter()),
+            DefaultFilter::Missing => DefaultIterInner::Missing,
+        })
+    }
+}
+
+/// The iterator of a [`DefaultFilter`]
+pub struct DefaultIter<V, F>(DefaultIterInner<V, F>);
+
+enum DefaultIterInner<V, F> {
+    Value(V),
+    Fallback(F),
+    Missing,
+}
+
+impl<V, F> Iterator for DefaultIter<V, F>
+where
+    V: Iterator,
+    F: Iterator<Item = V::Item>,
+{
+    type Item = V::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.0 {
+            DefaultIterInner::Value(iter) => iter.next(),
+            DefaultIterInner::Fallback(iter) => iter.next(),
+            DefaultIterInner::Missing => None,
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.0 {
+            DefaultIterInner::Value(iter) => iter.size_hint(),
+            DefaultIterInner::Fallback(iter) => iter.size_hint(),
+            DefaultIterInner::Missing => (0, Some(0)),
+        }
+    }
+}
+
 #[cfg(all(test, feature = "alloc"))]
 mod tests {
     use alloc::string::{String, ToString};
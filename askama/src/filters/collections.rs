@@ -0,0 +1,746 @@
+//! Filters that reshape or aggregate the items of an [`IntoIterator`]
+//!
+//! In templates, the filters `sort`, `unique`, `groupby`, `sum`, `min` and `max` take an optional
+//! argument `attribute="path.to.field"`, that is resolved to a field access at compile time.
+//! The functions in this module take a key function `|item| &item.path.to.field` instead.
+//!
+//! All of these filters must be enabled with `builtin_filters = ["collections"]` in the crate's
+//! configuration file.
+//!
+//! The filters `map`, `select`, `reject`, `selectattr` and `rejectattr` are lazy iterator
+//! adaptors, resolved to [`map()`] and [`select()`] with a closure that is generated at compile
+//! time.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::convert::Infallible;
+use core::iter::Sum;
+use core::ops::Add;
+
+use crate::helpers::PrimitiveType;
+
+/// Returns the first item of a collection, or `None` if it is empty
+///
+/// ```
+/// # #[cfg(feature = "code-in-doc")] {
+/// # use askama::Template;
+/// /// ```jinja
+/// /// {% if let Some(winner) = names|first %}And the winner is: {{ winner }}!{% endif %}
+/// /// ```
+/// #[derive(Template)]
+/// #[template(ext = "txt", in_doc = true)]
+/// struct Example<'a> {
+///     names: &'a [&'a str],
+/// }
+///
+/// assert_eq!(
+///     Example { names: &["Alice", "Bob"] }.to_string(),
+///     "And the winner is: Alice!"
+/// );
+/// # }
+/// ```
+#[inline]
+pub fn first<I: IntoIterator>(iter: I) -> Result<Option<I::Item>, Infallible> {
+    Ok(iter.into_iter().next())
+}
+
+/// Returns the last item of a collection, or `None` if it is empty
+#[inline]
+pub fn last<I: IntoIterator>(iter: I) -> Result<Option<I::Item>, Infallible> {
+    Ok(iter.into_iter().last())
+}
+
+/// Returns the number of items in a collection
+#[inline]
+pub fn length<I: IntoIterator>(iter: I) -> Result<usize, Infallible> {
+    Ok(iter.into_iter().count())
+}
+
+/// Adds up the numeric keys of all items in a collection, plus an optional start value
+///
+/// ```
+/// # #[cfg(feature = "code-in-doc")] {
+/// # use askama::Template;
+/// /// ```jinja
+/// /// Total: {{ items|sum(attribute="price") }}
+/// /// ```
+/// #[derive(Template)]
+/// #[template(ext = "txt", in_doc = true)]
+/// struct Example<'a> {
+///     items: &'a [Item],
+/// }
+///
+/// struct Item {
+///     price: u32,
+/// }
+///
+/// assert_eq!(
+///     Example { items: &[Item { price: 3 }, Item { price: 4 }] }.to_string(),
+///     "Total: 7"
+/// );
+/// # }
+/// ```
+#[inline]
+pub fn sum<I, F, K>(iter: I, mut key: F, start: Option<K::Value>) -> Result<K::Value, Infallible>
+where
+    I: IntoIterator,
+    F: FnMut(&I::Item) -> &K,
+    K: PrimitiveType + ?Sized,
+    K::Value: Sum + Add<Output = K::Value>,
+{
+    let total = iter.into_iter().map(|item| key(&item).get()).sum();
+    Ok(match start {
+        Some(start) => start + total,
+        None => total,
+    })
+}
+
+/// Returns the first item with the smallest key, or `None` if the collection is empty
+#[inline]
+pub fn min<I, F, K>(iter: I, key: F) -> Result<Option<I::Item>, Infallible>
+where
+    I: IntoIterator,
+    F: FnMut(&I::Item) -> &K,
+    K: PartialOrd + ?Sized,
+{
+    Ok(extremum(iter, key, Ordering::Less))
+}
+
+/// Returns the first item with the largest key, or `None` if the collection is empty
+#[inline]
+pub fn max<I, F, K>(iter: I, key: F) -> Result<Option<I::Item>, Infallible>
+where
+    I: IntoIterator,
+    F: FnMut(&I::Item) -> &K,
+    K: PartialOrd + ?Sized,
+{
+    Ok(extremum(iter, key, Ordering::Greater))
+}
+
+fn extremum<I, F, K>(iter: I, mut key: F, wanted: Ordering) -> Option<I::Item>
+where
+    I: IntoIterator,
+    F: FnMut(&I::Item) -> &K,
+    K: PartialOrd + ?Sized,
+{
+    iter.into_iter().reduce(
+        |best, item| match key(&item).partial_cmp(key(&best)) == Some(wanted) {
+            true => item,
+            false => best,
+        },
+    )
+}
+
+/// Sorts the items of a collection by their keys
+///
+/// The sort is stable, i.e. items with equal keys keep their relative order, even if `reverse`
+/// is `true`. Incomparable keys, e.g. `NaN`, are treated as equal.
+///
+/// ```
+/// # #[cfg(feature = "code-in-doc")] {
+/// # use askama::Template;
+/// /// ```jinja
+/// /// {% for user in users|sort(attribute="name") %}{{ user.name }} {% endfor %}
+/// /// {% for n in numbers|sort(reverse=true) %}{{ n }} {% endfor %}
+/// /// ```
+/// #[derive(Template)]
+/// #[template(ext = "txt", in_doc = true)]
+/// struct Example<'a> {
+///     users: &'a [User<'a>],
+///     numbers: &'a [i32],
+/// }
+///
+/// struct User<'a> {
+///     name: &'a str,
+/// }
+///
+/// assert_eq!(
+///     Example {
+///         users: &[User { name: "Kim" }, User { name: "Ann" }],
+///         numbers: &[2, 3, 1],
+///     }
+///     .to_string(),
+///     "Ann Kim \n3 2 1 "
+/// );
+/// # }
+/// ```
+#[cfg(feature = "alloc")]
+pub fn sort<I, F, K>(
+    iter: I,
+    mut key: F,
+    reverse: bool,
+) -> Result<alloc::vec::IntoIter<I::Item>, Infallible>
+where
+    I: IntoIterator,
+    F: FnMut(&I::Item) -> &K,
+    K: PartialOrd + ?Sized,
+{
+    let mut items = iter.into_iter().collect::<Vec<_>>();
+    items.sort_by(|a, b| {
+        let (a, b) = if reverse { (b, a) } else { (a, b) };
+        key(a).partial_cmp(key(b)).unwrap_or(Ordering::Equal)
+    });
+    Ok(items.into_iter())
+}
+
+/// Reverses the order of the items of a collection
+#[cfg(feature = "alloc")]
+pub fn reverse<I: IntoIterator>(
+    iter: I,
+) -> Result<core::iter::Rev<alloc::vec::IntoIter<I::Item>>, Infallible> {
+    Ok(iter.into_iter().collect::<Vec<_>>().into_iter().rev())
+}
+
+/// Removes all items whose key is equal to the key of a preceding item
+///
+/// The keys only need to implement [`PartialEq`], so every item is compared to all preceding
+/// unique items, which takes `O(n²)` time. For large collections, remove the duplicates before
+/// rendering.
+#[cfg(feature = "alloc")]
+pub fn unique<I, F, K>(iter: I, mut key: F) -> Result<alloc::vec::IntoIter<I::Item>, Infallible>
+where
+    I: IntoIterator,
+    F: FnMut(&I::Item) -> &K,
+    K: PartialEq + ?Sized,
+{
+    let mut items = Vec::<I::Item>::new();
+    for item in iter {
+        if !items.iter().any(|seen| key(seen) == key(&item)) {
+            items.push(item);
+        }
+    }
+    Ok(items.into_iter())
+}
+
+/// Sorts the items of a collection by their keys, and groups items with equal keys
+///
+/// Yields `(key, items)` pairs, that can be destructured in a `{% for %}` loop.
+///
+/// ```
+/// # #[cfg(feature = "code-in-doc")] {
+/// # use askama::Template;
+/// /// ```jinja
+/// /// {% for (category, products) in products|groupby("category") -%}
+/// ///     {{ category }}: {{ products|length }}
+/// /// {% endfor %}
+/// /// ```
+/// #[derive(Template)]
+/// #[template(ext = "txt", in_doc = true)]
+/// struct Example<'a> {
+///     products: &'a [Product<'a>],
+/// }
+///
+/// struct Product<'a> {
+///     category: &'a str,
+/// }
+///
+/// assert_eq!(
+///     Example {
+///         products: &[
+///             Product { category: "fruit" },
+///             Product { category: "bread" },
+///             Product { category: "fruit" },
+///         ],
+///     }
+///     .to_string(),
+///     "bread: 1\nfruit: 2\n"
+/// );
+/// # }
+/// ```
+#[cfg(feature = "alloc")]
+#[allow(clippy::type_complexity)]
+pub fn groupby<I, F, K>(
+    iter: I,
+    mut key: F,
+) -> Result<alloc::vec::IntoIter<(K, Vec<I::Item>)>, Infallible>
+where
+    I: IntoIterator,
+    F: FnMut(&I::Item) -> &K,
+    K: PartialOrd + Clone,
+{
+    let mut groups = Vec::<(K, Vec<I::Item>)>::new();
+    for item in sort(iter, &mut key, false)? {
+        match groups.last_mut() {
+            Some((group, items)) if *group == *key(&item) => items.push(item),
+            _ => groups.push((key(&item).clone(), alloc::vec![item])),
+        }
+    }
+    Ok(groups.into_iter())
+}
+
+/// Splits a collection into chunks of `size` items
+///
+/// The last chunk is padded with clones of `fill`, if it is given.
+/// A `size` of zero is treated as one.
+///
+/// ```
+/// # #[cfg(feature = "code-in-doc")] {
+/// # use askama::Template;
+/// /// ```jinja
+/// /// {% for row in cells|batch(3, &"-") -%}
+/// ///     {{ row|join(" ") }}
+/// /// {% endfor %}
+/// /// ```
+/// #[derive(Template)]
+/// #[template(ext = "txt", in_doc = true)]
+/// struct Example<'a> {
+///     cells: &'a [&'a str],
+/// }
+///
+/// assert_eq!(
+///     Example { cells: &["a", "b", "c", "d"] }.to_string(),
+///     "a b c\nd - -\n"
+/// );
+/// # }
+/// ```
+#[cfg(feature = "alloc")]
+#[inline]
+pub fn batch<I: IntoIterator>(
+    iter: I,
+    size: usize,
+    fill: Option<I::Item>,
+) -> Result<Batch<I::IntoIter>, Infallible> {
+    Ok(Batch {
+        iter: iter.into_iter(),
+        size: size.max(1),
+        fill,
+    })
+}
+
+/// The iterator returned by the filter [`batch()`]
+#[cfg(feature = "alloc")]
+pub struct Batch<I: Iterator> {
+    iter: I,
+    size: usize,
+    fill: Option<I::Item>,
+}
+
+#[cfg(feature = "alloc")]
+impl<I> Iterator for Batch<I>
+where
+    I: Iterator,
+    I::Item: Clone,
+{
+    type Item = Vec<I::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk = Vec::with_capacity(self.size);
+        chunk.extend(self.iter.by_ref().take(self.size));
+        if chunk.is_empty() {
+            return None;
+        }
+        if let Some(fill) = &self.fill {
+            chunk.resize(self.size, fill.clone());
+        }
+        Some(chunk)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.iter.size_hint();
+        (
+            lower.div_ceil(self.size),
+            upper.map(|upper| upper.div_ceil(self.size)),
+        )
+    }
+}
+
+/// Splits a collection into `slices` columns of (almost) equal length
+///
+/// The first columns get one item more than the last ones, if the items cannot be distributed
+/// evenly. Then the shorter columns are padded with a clone of `fill`, if it is given.
+/// A `slices` count of zero is treated as one.
+///
+/// ```
+/// # #[cfg(feature = "code-in-doc")] {
+/// # use askama::Template;
+/// /// ```jinja
+/// /// {% for column in items|slice(3) -%}
+/// ///     {{ column|join(", ") }}
+/// /// {% endfor %}
+/// /// ```
+/// #[derive(Template)]
+/// #[template(ext = "txt", in_doc = true)]
+/// struct Example<'a> {
+///     items: &'a [u32],
+/// }
+///
+/// assert_eq!(
+///     Example { items: &[1, 2, 3, 4, 5, 6, 7] }.to_string(),
+///     "1, 2, 3\n4, 5\n6, 7\n"
+/// );
+/// # }
+/// ```
+#[cfg(feature = "alloc")]
+pub fn slice<I>(
+    iter: I,
+    slices: usize,
+    fill: Option<I::Item>,
+) -> Result<alloc::vec::IntoIter<Vec<I::Item>>, Infallible>
+where
+    I: IntoIterator,
+    I::Item: Clone,
+{
+    let slices = slices.max(1);
+    let items = iter.into_iter().collect::<Vec<_>>();
+    let per_slice = items.len() / slices;
+    let with_extra = items.len() % slices;
+
+    let mut items = items.into_iter();
+    let mut columns = Vec::with_capacity(slices);
+    for index in 0..slices {
+        let len = per_slice + usize::from(index < with_extra);
+        let mut column = Vec::with_capacity(per_slice + 1);
+        column.extend(items.by_ref().take(len));
+        if let (Some(fill), true) = (&fill, index >= with_extra) {
+            column.push(fill.clone());
+        }
+        columns.push(column);
+    }
+    Ok(columns.into_iter())
+}
+
+/// Lazily maps each item of a collection, e.g. to one of its fields
+///
+/// In templates, use `map(attribute="path.to.field")`:
+///
+/// ```
+/// # #[cfg(feature = "code-in-doc")] {
+/// # use askama::Template;
+/// /// ```jinja
+/// /// <a href="mailto:{{ users|map(attribute="email")|join(",") }}">Mail all</a>
+/// /// ```
+/// #[derive(Template)]
+/// #[template(ext = "html", in_doc = true)]
+/// struct Example<'a> {
+///     users: &'a [User<'a>],
+/// }
+///
+/// struct User<'a> {
+///     email: &'a str,
+/// }
+///
+/// assert_eq!(
+///     Example { users: &[User { email: "a@x.org" }, User { email: "b@x.org" }] }.to_string(),
+///     r#"<a href="mailto:a@x.org,b@x.org">Mail all</a>"#
+/// );
+/// # }
+/// ```
+#[inline]
+pub fn map<I, F, B>(iter: I, f: F) -> Result<core::iter::Map<I::IntoIter, F>, Infallible>
+where
+    I: IntoIterator,
+    F: FnMut(I::Item) -> B,
+{
+    Ok(iter.into_iter().map(f))
+}
+
+/// Lazily keeps only the items of a collection that pass a test
+///
+/// In templates, the filters `select`, `reject`, `selectattr` and `rejectattr` are resolved to a
+/// call of this function. Without a test, the items (or their attribute) are checked with
+/// [`Truthy`]:
+///
+/// ```
+/// # #[cfg(feature = "code-in-doc")] {
+/// # use askama::Template;
+/// /// ```jinja
+/// /// {{ numbers|select("odd")|join(" ") }}
+/// /// {{ numbers|reject("lt", 3)|join(" ") }}
+/// /// {{ users|selectattr("active")|map(attribute="name")|join(" ") }}
+/// /// {{ users|rejectattr("name", "eq", "Kim")|map(attribute="name")|join(" ") }}
+/// /// ```
+/// #[derive(Template)]
+/// #[template(ext = "txt", in_doc = true)]
+/// struct Example<'a> {
+///     numbers: &'a [u32],
+///     users: &'a [User<'a>],
+/// }
+///
+/// struct User<'a> {
+///     name: &'a str,
+///     active: bool,
+/// }
+///
+/// assert_eq!(
+///     Example {
+///         numbers: &[1, 2, 3, 4, 5],
+///         users: &[User { name: "Kim", active: true }, User { name: "Ann", active: false }],
+///     }
+///     .to_string(),
+///     "1 3 5\n3 4 5\nKim\nAnn"
+/// );
+/// # }
+/// ```
+#[inline]
+pub fn select<I, P>(iter: I, predicate: P) -> Result<core::iter::Filter<I::IntoIter, P>, Infallible>
+where
+    I: IntoIterator,
+    P: FnMut(&I::Item) -> bool,
+{
+    Ok(iter.into_iter().filter(predicate))
+}
+
+/// Values that are "truthy" or "falsy", as used by the filters `select` and `reject`
+///
+/// `false`, zero, empty strings, empty collections and `None` are falsy, everything else is
+/// truthy.
+pub trait Truthy {
+    /// Returns `true` if the value is truthy
+    fn is_truthy(&self) -> bool;
+}
+
+/// Values that can be compared by the tests `eq`, `ne`, `lt`, `le`, `gt` and `ge` of the filters
+/// `select` and `reject`
+///
+/// References are compared by their referenced value, and strings are compared as `&str`.
+pub trait CompareValue {
+    /// The value to compare
+    type Value<'a>: PartialOrd
+    where
+        Self: 'a;
+
+    /// Returns the value to compare
+    fn compare_value(&self) -> Self::Value<'_>;
+}
+
+const _: () = {
+    crate::impl_for_ref! {
+        impl Truthy for T {
+            #[inline]
+            fn is_truthy(&self) -> bool {
+                <T>::is_truthy(self)
+            }
+        }
+    }
+
+    crate::impl_for_ref! {
+        impl CompareValue for T {
+            type Value<'a> = T::Value<'a> where Self: 'a;
+
+            #[inline]
+            fn compare_value(&self) -> Self::Value<'_> {
+                <T>::compare_value(self)
+            }
+        }
+    }
+
+    impl Truthy for bool {
+        #[inline]
+        fn is_truthy(&self) -> bool {
+            *self
+        }
+    }
+
+    impl Truthy for char {
+        #[inline]
+        fn is_truthy(&self) -> bool {
+            true
+        }
+    }
+
+    impl Truthy for str {
+        #[inline]
+        fn is_truthy(&self) -> bool {
+            !self.is_empty()
+        }
+    }
+
+    impl<T> Truthy for [T] {
+        #[inline]
+        fn is_truthy(&self) -> bool {
+            !self.is_empty()
+        }
+    }
+
+    impl<T, const N: usize> Truthy for [T; N] {
+        #[inline]
+        fn is_truthy(&self) -> bool {
+            N > 0
+        }
+    }
+
+    impl<T: Truthy> Truthy for Option<T> {
+        #[inline]
+        fn is_truthy(&self) -> bool {
+            self.as_ref().is_some_and(T::is_truthy)
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl Truthy for alloc::string::String {
+        #[inline]
+        fn is_truthy(&self) -> bool {
+            !self.is_empty()
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl Truthy for alloc::borrow::Cow<'_, str> {
+        #[inline]
+        fn is_truthy(&self) -> bool {
+            !self.is_empty()
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl<T> Truthy for Vec<T> {
+        #[inline]
+        fn is_truthy(&self) -> bool {
+            !self.is_empty()
+        }
+    }
+
+    impl CompareValue for str {
+        type Value<'a> = &'a str;
+
+        #[inline]
+        fn compare_value(&self) -> Self::Value<'_> {
+            self
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl CompareValue for alloc::string::String {
+        type Value<'a> = &'a str;
+
+        #[inline]
+        fn compare_value(&self) -> Self::Value<'_> {
+            self
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl CompareValue for alloc::borrow::Cow<'_, str> {
+        type Value<'a>
+            = &'a str
+        where
+            Self: 'a;
+
+        #[inline]
+        fn compare_value(&self) -> Self::Value<'_> {
+            self
+        }
+    }
+
+    /// implement `Truthy` and `CompareValue` for numbers
+    macro_rules! impl_for_numbers {
+        ($($ty:ty)*) => { $(
+            impl Truthy for $ty {
+                #[inline]
+                fn is_truthy(&self) -> bool {
+                    *self != (0 as $ty)
+                }
+            }
+
+            impl CompareValue for $ty {
+                type Value<'a> = $ty;
+
+                #[inline]
+                fn compare_value(&self) -> Self::Value<'_> {
+                    *self
+                }
+            }
+        )* };
+    }
+
+    impl_for_numbers!(i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize f32 f64);
+
+    impl CompareValue for bool {
+        type Value<'a> = bool;
+
+        #[inline]
+        fn compare_value(&self) -> Self::Value<'_> {
+            *self
+        }
+    }
+
+    impl CompareValue for char {
+        type Value<'a> = char;
+
+        #[inline]
+        fn compare_value(&self) -> Self::Value<'_> {
+            *self
+        }
+    }
+};
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn test_sort() {
+        let items = [(2, 'a'), (1, 'b'), (2, 'c'), (0, 'd')];
+        let sorted = sort(&items, |(k, _)| k, false).unwrap().collect::<Vec<_>>();
+        assert_eq!(sorted, [&(0, 'd'), &(1, 'b'), &(2, 'a'), &(2, 'c')]);
+        let sorted = sort(&items, |(k, _)| k, true).unwrap().collect::<Vec<_>>();
+        assert_eq!(sorted, [&(2, 'a'), &(2, 'c'), &(1, 'b'), &(0, 'd')]);
+        let sorted = sort([2.0, f64::NAN, 1.0], |x| x, false).unwrap();
+        assert_eq!(sorted.len(), 3);
+    }
+
+    #[test]
+    fn test_unique_groupby() {
+        let items = [3, 1, 3, 2, 1];
+        let unique = unique(items, |x| x).unwrap().collect::<Vec<_>>();
+        assert_eq!(unique, [3, 1, 2]);
+
+        let groups = groupby(items, |x| x).unwrap().collect::<Vec<_>>();
+        assert_eq!(groups, [(1, vec![1, 1]), (2, vec![2]), (3, vec![3, 3])]);
+        assert_eq!(groupby([0; 0], |x| x).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_batch() {
+        let batches = batch(1..=5, 2, None).unwrap().collect::<Vec<_>>();
+        assert_eq!(batches, [vec![1, 2], vec![3, 4], vec![5]]);
+        let batches = batch(1..=5, 3, Some(0)).unwrap().collect::<Vec<_>>();
+        assert_eq!(batches, [vec![1, 2, 3], vec![4, 5, 0]]);
+        assert_eq!(batch(1..=3, 0, None).unwrap().count(), 3);
+        assert_eq!(batch(0..0, 3, Some(0)).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_slice() {
+        let slices = slice(1..=7, 3, None).unwrap().collect::<Vec<_>>();
+        assert_eq!(slices, [vec![1, 2, 3], vec![4, 5], vec![6, 7]]);
+        let slices = slice(1..=7, 3, Some(0)).unwrap().collect::<Vec<_>>();
+        assert_eq!(slices, [vec![1, 2, 3], vec![4, 5, 0], vec![6, 7, 0]]);
+        let slices = slice(1..=2, 3, None).unwrap().collect::<Vec<_>>();
+        assert_eq!(slices, [vec![1], vec![2], vec![]]);
+    }
+
+    #[test]
+    fn test_aggregates() {
+        let items = [(1, "b"), (3, "a"), (3, "c")];
+        assert_eq!(first(&items).unwrap(), Some(&(1, "b")));
+        assert_eq!(last(&items).unwrap(), Some(&(3, "c")));
+        assert_eq!(first([0; 0]).unwrap(), None);
+        assert_eq!(length(items).unwrap(), 3);
+        assert_eq!(sum(&items, |(n, _)| n, None).unwrap(), 7);
+        assert_eq!(sum(&items, |(n, _)| n, Some(10)).unwrap(), 17);
+        assert_eq!(sum([0.5, 0.25], |x| x, None).unwrap(), 0.75);
+        assert_eq!(min(&items, |(_, s)| s).unwrap(), Some(&(3, "a")));
+        assert_eq!(max(&items, |(n, _)| n).unwrap(), Some(&(3, "a")));
+        assert_eq!(max([0; 0], |x| x).unwrap(), None);
+    }
+
+    #[test]
+    fn test_truthy_compare_value() {
+        assert!(1.is_truthy());
+        assert!(!0.0.is_truthy());
+        assert!(!"".is_truthy());
+        assert!((&&"a").is_truthy());
+        assert!(!Some(false).is_truthy());
+        assert!(!None::<u8>.is_truthy());
+        assert!(!Vec::<u8>::new().is_truthy());
+        assert!(Some(vec![1]).is_truthy());
+
+        assert_eq!((&&"a").compare_value(), "a");
+        assert_eq!(alloc::string::String::from("b").compare_value(), "b");
+        assert!(CompareValue::compare_value(&&3_u8) < 4);
+    }
+}
@@ -0,0 +1,1097 @@
+use core::convert::Infallible;
+use core::fmt;
+
+use super::FastWritable;
+
+/// A date, a time of day, or both, that can be used with the filters [`date()`], [`time()`],
+/// [`datetime()`], [`timesince()`] and [`timeuntil()`]
+///
+/// The trait is implemented for the date and time types of [`chrono`](https://docs.rs/chrono),
+/// [`time`](https://docs.rs/time) and [`jiff`](https://docs.rs/jiff) if the cargo feature of the
+/// same name is enabled, and for [`SystemTime`](std::time::SystemTime) (as UTC).
+pub trait DateTimeValue {
+    /// The calendar date as `(year, month, day)`, with `month` in `1..=12` and `day` in `1..=31`
+    fn date(&self) -> Option<(i32, u8, u8)>;
+
+    /// The time of the day as `(hour, minute, second, nanosecond)`
+    fn time(&self) -> Option<(u8, u8, u8, u32)>;
+
+    /// The offset from UTC in seconds, or `None` for naive / civil values
+    fn utc_offset(&self) -> Option<i32>;
+}
+
+crate::impl_for_ref! {
+    impl DateTimeValue for T {
+        #[inline]
+        fn date(&self) -> Option<(i32, u8, u8)> {
+            T::date(self)
+        }
+
+        #[inline]
+        fn time(&self) -> Option<(u8, u8, u8, u32)> {
+            T::time(self)
+        }
+
+        #[inline]
+        fn utc_offset(&self) -> Option<i32> {
+            T::utc_offset(self)
+        }
+    }
+}
+
+/// Formats a date with a strftime-style format string, by default `"%Y-%m-%d"`
+///
+/// The filter is available if any of the features `"chrono"`, `"time"` or `"jiff"` is enabled,
+/// and if the crate's configuration file contains `builtin_filters = ["datetime"]`.
+/// It accepts every type that implements [`DateTimeValue`].
+///
+/// The supported format specifiers are:
+///
+/// | Spec. | Example | Description |
+/// |-------|---------|-------------|
+/// | `%Y` | `2001` | year, padded to 4 digits |
+/// | `%C` | `20` | year divided by 100 |
+/// | `%y` | `01` | year modulo 100 |
+/// | `%m` | `07` | month |
+/// | `%b`, `%h` | `Jul` | abbreviated month name |
+/// | `%B` | `July` | full month name |
+/// | `%d` | `08` | day of the month |
+/// | `%e` | ` 8` | day of the month, padded with a space |
+/// | `%a` | `Sun` | abbreviated weekday name |
+/// | `%A` | `Sunday` | full weekday name |
+/// | `%u` | `7` | weekday, Monday = 1 |
+/// | `%w` | `0` | weekday, Sunday = 0 |
+/// | `%j` | `189` | day of the year |
+/// | `%H` | `00` | hour, 24-hour clock |
+/// | `%k` | ` 0` | hour, 24-hour clock, padded with a space |
+/// | `%I` | `12` | hour, 12-hour clock |
+/// | `%l` | `12` | hour, 12-hour clock, padded with a space |
+/// | `%P` | `am` | `am` or `pm` |
+/// | `%p` | `AM` | `AM` or `PM` |
+/// | `%M` | `34` | minute |
+/// | `%S` | `59` | second |
+/// | `%f` | `026490000` | nanoseconds; `%3f`, `%6f`, `%9f` for a fixed number of digits |
+/// | `%.f` | `.026490000` | like `%f` but with a leading dot; `%.3f`, `%.6f`, `%.9f` |
+/// | `%z` | `+0930` | UTC offset |
+/// | `%:z` | `+09:30` | UTC offset with a colon |
+/// | `%Z` | `UTC` | `UTC`, or the offset as with `%:z` |
+/// | `%s` | `994518299` | seconds since the UNIX epoch |
+/// | `%F` | `2001-07-08` | `%Y-%m-%d` |
+/// | `%D`, `%x` | `07/08/01` | `%m/%d/%y` |
+/// | `%T`, `%X` | `00:34:59` | `%H:%M:%S` |
+/// | `%R` | `00:34` | `%H:%M` |
+/// | `%r` | `12:34:59 AM` | `%I:%M:%S %p` |
+/// | `%c` | `Sun Jul  8 00:34:59 2001` | `%a %b %e %T %Y` |
+/// | `%n`, `%t`, `%%` | | a newline, a tab, or a literal `%` |
+///
+/// The padding of numeric specifiers can be changed by writing `-` (no padding), `_` (spaces)
+/// or `0` (zeros) after the `%`, e.g. `%-d`.
+///
+/// An error is returned if the format string is invalid, or if it needs a part the value does not
+/// have, e.g. `%H` for a date or `%z` for a naive value.
+///
+/// ## Example
+///
+/// ```
+/// # #[cfg(feature = "chrono")] {
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     source = r#"{{ day|date }} or {{ day|date("%-d %B %Y") }}"#,
+///     ext = "html"
+/// )]
+/// struct Example {
+///     day: chrono::NaiveDate,
+/// }
+///
+/// let tmpl = Example { day: chrono::NaiveDate::from_ymd_opt(2025, 3, 7).unwrap() };
+/// assert_eq!(tmpl.to_string(), "2025-03-07 or 7 March 2025");
+/// # }
+/// ```
+#[inline]
+pub fn date<T: DateTimeValue + ?Sized, F: AsRef<str>>(
+    value: &T,
+    format: F,
+) -> Result<DateTimeFilter<'_, T, F>, Infallible> {
+    Ok(DateTimeFilter { value, format })
+}
+
+/// Formats a time of the day with a strftime-style format string, by default `"%H:%M:%S"`
+///
+/// See [`date()`] for the list of supported format specifiers.
+///
+/// ## Example
+///
+/// ```
+/// # #[cfg(feature = "chrono")] {
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     source = r#"{{ start|time }} or {{ start|time("%-I:%M %p") }}"#,
+///     ext = "html"
+/// )]
+/// struct Example {
+///     start: chrono::NaiveTime,
+/// }
+///
+/// let tmpl = Example { start: chrono::NaiveTime::from_hms_opt(14, 5, 0).unwrap() };
+/// assert_eq!(tmpl.to_string(), "14:05:00 or 2:05 PM");
+/// # }
+/// ```
+#[inline]
+pub fn time<T: DateTimeValue + ?Sized, F: AsRef<str>>(
+    value: &T,
+    format: F,
+) -> Result<DateTimeFilter<'_, T, F>, Infallible> {
+    Ok(DateTimeFilter { value, format })
+}
+
+/// Formats a date and time with a strftime-style format string, by default `"%Y-%m-%d %H:%M:%S"`
+///
+/// See [`date()`] for the list of supported format specifiers.
+///
+/// ## Example
+///
+/// ```
+/// # #[cfg(feature = "chrono")] {
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     source = r#"{{ posted|datetime }} or {{ posted|datetime("%a, %d %b %Y %T %z") }}"#,
+///     ext = "html"
+/// )]
+/// struct Example {
+///     posted: chrono::DateTime<chrono::Utc>,
+/// }
+///
+/// let tmpl = Example { posted: chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap() };
+/// assert_eq!(
+///     tmpl.to_string(),
+///     "2023-11-14 22:13:20 or Tue, 14 Nov 2023 22:13:20 +0000",
+/// );
+/// # }
+/// ```
+#[inline]
+pub fn datetime<T: DateTimeValue + ?Sized, F: AsRef<str>>(
+    value: &T,
+    format: F,
+) -> Result<DateTimeFilter<'_, T, F>, Infallible> {
+    Ok(DateTimeFilter { value, format })
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DateTimeFilter<'a, T: ?Sized, F> {
+    value: &'a T,
+    format: F,
+}
+
+impl<T: DateTimeValue + ?Sized, F: AsRef<str>> fmt::Display for DateTimeFilter<'_, T, F> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Ok(self.write_into(f)?)
+    }
+}
+
+impl<T: DateTimeValue + ?Sized, F: AsRef<str>> FastWritable for DateTimeFilter<'_, T, F> {
+    #[inline]
+    fn write_into<W: fmt::Write + ?Sized>(&self, dest: &mut W) -> crate::Result<()> {
+        let parts = Parts {
+            date: self.value.date(),
+            time: self.value.time(),
+            offset: self.value.utc_offset(),
+        };
+        parts.strftime(dest, self.format.as_ref())
+    }
+}
+
+/// Describes how much time has passed since a date, like Django's `timesince` filter
+///
+/// The output contains at most two adjacent units, e.g. `"2 days, 3 hours"` or `"1 year"`.
+/// A duration of less than a minute, or a date in the future, is written as `"0 minutes"`.
+/// Years are counted as 365 days, and months as 30 days.
+///
+/// The optional argument is the point in time to compare to, by default the current time.
+/// Values without a UTC offset are treated as UTC.
+///
+/// ## Example
+///
+/// ```
+/// # #[cfg(feature = "chrono")] {
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(source = "Posted {{ posted|timesince(now) }} ago.", ext = "html")]
+/// struct Example {
+///     posted: chrono::NaiveDateTime,
+///     now: chrono::NaiveDateTime,
+/// }
+///
+/// let day = chrono::NaiveDate::from_ymd_opt(2025, 3, 7).unwrap();
+/// let tmpl = Example {
+///     posted: day.and_hms_opt(9, 30, 0).unwrap(),
+///     now: day.succ_opt().unwrap().and_hms_opt(13, 0, 0).unwrap(),
+/// };
+/// assert_eq!(tmpl.to_string(), "Posted 1 day, 3 hours ago.");
+/// # }
+/// ```
+#[inline]
+pub fn timesince<T, U>(value: &T, now: &U) -> crate::Result<TimeSinceFilter>
+where
+    T: DateTimeValue + ?Sized,
+    U: DateTimeValue + ?Sized,
+{
+    Ok(TimeSinceFilter(
+        unix_timestamp(now, "timesince")? - unix_timestamp(value, "timesince")?,
+    ))
+}
+
+/// Describes how much time is left until a date, like Django's `timeuntil` filter
+///
+/// This is the counterpart of [`timesince()`], and uses the same output format.
+///
+/// ## Example
+///
+/// ```
+/// # #[cfg(feature = "chrono")] {
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(source = "Starts in {{ start|timeuntil(now) }}.", ext = "html")]
+/// struct Example {
+///     start: chrono::NaiveDate,
+///     now: chrono::NaiveDate,
+/// }
+///
+/// let tmpl = Example {
+///     start: chrono::NaiveDate::from_ymd_opt(2025, 4, 20).unwrap(),
+///     now: chrono::NaiveDate::from_ymd_opt(2025, 3, 7).unwrap(),
+/// };
+/// assert_eq!(tmpl.to_string(), "Starts in 1 month, 2 weeks.");
+/// # }
+/// ```
+#[inline]
+pub fn timeuntil<T, U>(value: &T, now: &U) -> crate::Result<TimeSinceFilter>
+where
+    T: DateTimeValue + ?Sized,
+    U: DateTimeValue + ?Sized,
+{
+    Ok(TimeSinceFilter(
+        unix_timestamp(value, "timeuntil")? - unix_timestamp(now, "timeuntil")?,
+    ))
+}
+
+/// The output of [`timesince()`] and [`timeuntil()`], a duration in seconds
+#[derive(Debug, Clone, Copy)]
+pub struct TimeSinceFilter(i64);
+
+impl fmt::Display for TimeSinceFilter {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Ok(self.write_into(f)?)
+    }
+}
+
+impl FastWritable for TimeSinceFilter {
+    fn write_into<W: fmt::Write + ?Sized>(&self, dest: &mut W) -> crate::Result<()> {
+        const MINUTE: i64 = 60;
+        const HOUR: i64 = 60 * MINUTE;
+        const DAY: i64 = 24 * HOUR;
+        const CHUNKS: &[(i64, &str, &str)] = &[
+            (365 * DAY, "year", "years"),
+            (30 * DAY, "month", "months"),
+            (7 * DAY, "week", "weeks"),
+            (DAY, "day", "days"),
+            (HOUR, "hour", "hours"),
+            (MINUTE, "minute", "minutes"),
+        ];
+
+        let Some(index) = CHUNKS.iter().position(|&(secs, _, _)| self.0 >= secs) else {
+            return Ok(dest.write_str("0 minutes")?);
+        };
+        let (secs, singular, plural) = CHUNKS[index];
+        let count = self.0 / secs;
+        write!(
+            dest,
+            "{count} {}",
+            if count == 1 { singular } else { plural }
+        )?;
+
+        if let Some(&(next_secs, singular, plural)) = CHUNKS.get(index + 1) {
+            let count = (self.0 - count * secs) / next_secs;
+            if count > 0 {
+                write!(
+                    dest,
+                    ", {count} {}",
+                    if count == 1 { singular } else { plural }
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Seconds since the UNIX epoch, treating naive values as UTC and dates as midnight
+fn unix_timestamp<T: DateTimeValue + ?Sized>(
+    value: &T,
+    filter: &'static str,
+) -> crate::Result<i64> {
+    let Some((year, month, day)) = value.date() else {
+        return Err(crate::Error::custom(DateTimeError::TimeSinceNeedsDate(
+            filter,
+        )));
+    };
+    let (hour, minute, second, _) = value.time().unwrap_or_default();
+    Ok(days_from_civil(year, month, day) * 86_400
+        + i64::from(hour) * 3_600
+        + i64::from(minute) * 60
+        + i64::from(second)
+        - i64::from(value.utc_offset().unwrap_or_default()))
+}
+
+#[derive(Clone, Copy)]
+enum Pad {
+    None,
+    Space,
+    Zero,
+}
+
+struct Parts {
+    date: Option<(i32, u8, u8)>,
+    time: Option<(u8, u8, u8, u32)>,
+    offset: Option<i32>,
+}
+
+impl Parts {
+    fn strftime<W: fmt::Write + ?Sized>(&self, dest: &mut W, format: &str) -> crate::Result<()> {
+        let mut rest = format;
+        while let Some(pos) = rest.find('%') {
+            dest.write_str(&rest[..pos])?;
+            rest = &rest[pos + 1..];
+
+            let mut chars = rest.chars();
+            let mut spec = chars.next();
+            let mut pad = None;
+            if let Some(flag @ ('-' | '_' | '0')) = spec {
+                pad = Some(match flag {
+                    '-' => Pad::None,
+                    '_' => Pad::Space,
+                    _ => Pad::Zero,
+                });
+                spec = chars.next();
+            }
+            let dot = spec == Some('.');
+            if dot {
+                spec = chars.next();
+            }
+            let mut digits = None;
+            if let Some(d @ ('3' | '6' | '9')) = spec {
+                digits = Some(d as usize - '0' as usize);
+                spec = chars.next();
+            }
+            let colon = spec == Some(':');
+            if colon {
+                spec = chars.next();
+            }
+            rest = chars.as_str();
+
+            let Some(spec) = spec else {
+                return Err(crate::Error::custom(DateTimeError::IncompleteSpecifier));
+            };
+            if ((dot || digits.is_some()) && spec != 'f') || (colon && spec != 'z') {
+                return Err(crate::Error::custom(DateTimeError::InvalidSpecifier(spec)));
+            }
+            self.write_spec(dest, spec, pad, dot, digits, colon)?;
+        }
+        Ok(dest.write_str(rest)?)
+    }
+
+    fn write_spec<W: fmt::Write + ?Sized>(
+        &self,
+        dest: &mut W,
+        spec: char,
+        pad: Option<Pad>,
+        dot: bool,
+        digits: Option<usize>,
+        colon: bool,
+    ) -> crate::Result<()> {
+        let num = |dest: &mut W, value: i64, width: usize, default: Pad| -> crate::Result<()> {
+            match pad.unwrap_or(default) {
+                Pad::None => write!(dest, "{value}")?,
+                Pad::Space => write!(dest, "{value:width$}")?,
+                Pad::Zero => write!(dest, "{value:0width$}")?,
+            }
+            Ok(())
+        };
+
+        match spec {
+            'Y' => num(dest, self.year(spec)?.into(), 4, Pad::Zero),
+            'C' => num(dest, self.year(spec)?.div_euclid(100).into(), 2, Pad::Zero),
+            'y' => num(dest, self.year(spec)?.rem_euclid(100).into(), 2, Pad::Zero),
+            'm' => num(dest, self.date(spec)?.1.into(), 2, Pad::Zero),
+            'b' | 'h' => Ok(dest.write_str(&MONTHS[usize::from(self.date(spec)?.1 - 1)][..3])?),
+            'B' => Ok(dest.write_str(MONTHS[usize::from(self.date(spec)?.1 - 1)])?),
+            'd' => num(dest, self.date(spec)?.2.into(), 2, Pad::Zero),
+            'e' => num(dest, self.date(spec)?.2.into(), 2, Pad::Space),
+            'a' => Ok(dest.write_str(&WEEKDAYS[self.weekday(spec)?][..3])?),
+            'A' => Ok(dest.write_str(WEEKDAYS[self.weekday(spec)?])?),
+            'u' => num(dest, self.weekday(spec)? as i64 + 1, 1, Pad::Zero),
+            'w' => num(dest, (self.weekday(spec)? as i64 + 1) % 7, 1, Pad::Zero),
+            'j' => {
+                let (year, month, day) = self.date(spec)?;
+                let ordinal = days_from_civil(year, month, day) - days_from_civil(year, 1, 1) + 1;
+                num(dest, ordinal, 3, Pad::Zero)
+            }
+            'H' => num(dest, self.time(spec)?.0.into(), 2, Pad::Zero),
+            'k' => num(dest, self.time(spec)?.0.into(), 2, Pad::Space),
+            'I' => num(dest, self.hour12(spec)?, 2, Pad::Zero),
+            'l' => num(dest, self.hour12(spec)?, 2, Pad::Space),
+            'P' => Ok(dest.write_str(if self.time(spec)?.0 < 12 { "am" } else { "pm" })?),
+            'p' => Ok(dest.write_str(if self.time(spec)?.0 < 12 { "AM" } else { "PM" })?),
+            'M' => num(dest, self.time(spec)?.1.into(), 2, Pad::Zero),
+            'S' => num(dest, self.time(spec)?.2.into(), 2, Pad::Zero),
+            'f' => {
+                let nanos = self.time(spec)?.3 % 1_000_000_000;
+                if dot {
+                    dest.write_char('.')?;
+                }
+                match digits {
+                    Some(3) => write!(dest, "{:03}", nanos / 1_000_000)?,
+                    Some(6) => write!(dest, "{:06}", nanos / 1_000)?,
+                    _ => write!(dest, "{nanos:09}")?,
+                }
+                Ok(())
+            }
+            'z' => self.write_offset(dest, spec, colon),
+            'Z' => match self.offset(spec)? {
+                0 => Ok(dest.write_str("UTC")?),
+                _ => self.write_offset(dest, spec, true),
+            },
+            's' => {
+                let (year, month, day) = self.date(spec)?;
+                let (hour, minute, second, _) = self.time(spec)?;
+                let timestamp = days_from_civil(year, month, day) * 86_400
+                    + i64::from(hour) * 3_600
+                    + i64::from(minute) * 60
+                    + i64::from(second)
+                    - i64::from(self.offset(spec)?);
+                num(dest, timestamp, 1, Pad::Zero)
+            }
+            'F' => self.strftime(dest, "%Y-%m-%d"),
+            'D' | 'x' => self.strftime(dest, "%m/%d/%y"),
+            'T' | 'X' => self.strftime(dest, "%H:%M:%S"),
+            'R' => self.strftime(dest, "%H:%M"),
+            'r' => self.strftime(dest, "%I:%M:%S %p"),
+            'c' => self.strftime(dest, "%a %b %e %H:%M:%S %Y"),
+            'n' => Ok(dest.write_char('\n')?),
+            't' => Ok(dest.write_char('\t')?),
+            '%' => Ok(dest.write_char('%')?),
+            spec => Err(crate::Error::custom(DateTimeError::InvalidSpecifier(spec))),
+        }
+    }
+
+    fn write_offset<W: fmt::Write + ?Sized>(
+        &self,
+        dest: &mut W,
+        spec: char,
+        colon: bool,
+    ) -> crate::Result<()> {
+        let offset = self.offset(spec)?;
+        let sign = if offset < 0 { '-' } else { '+' };
+        let minutes = offset.unsigned_abs() / 60;
+        let colon = if colon { ":" } else { "" };
+        write!(dest, "{sign}{:02}{colon}{:02}", minutes / 60, minutes % 60)?;
+        Ok(())
+    }
+
+    fn date(&self, spec: char) -> crate::Result<(i32, u8, u8)> {
+        self.date
+            .ok_or_else(|| crate::Error::custom(DateTimeError::MissingDate(spec)))
+    }
+
+    fn year(&self, spec: char) -> crate::Result<i32> {
+        Ok(self.date(spec)?.0)
+    }
+
+    /// Monday = 0
+    fn weekday(&self, spec: char) -> crate::Result<usize> {
+        let (year, month, day) = self.date(spec)?;
+        // 1970-01-01 was a Thursday
+        Ok((days_from_civil(year, month, day) + 3).rem_euclid(7) as usize)
+    }
+
+    fn time(&self, spec: char) -> crate::Result<(u8, u8, u8, u32)> {
+        self.time
+            .ok_or_else(|| crate::Error::custom(DateTimeError::MissingTime(spec)))
+    }
+
+    fn hour12(&self, spec: char) -> crate::Result<i64> {
+        Ok(match self.time(spec)?.0 % 12 {
+            0 => 12,
+            hour => hour.into(),
+        })
+    }
+
+    fn offset(&self, spec: char) -> crate::Result<i32> {
+        self.offset
+            .ok_or_else(|| crate::Error::custom(DateTimeError::MissingOffset(spec)))
+    }
+}
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+/// Days since 1970-01-01 in the proleptic Gregorian calendar
+///
+/// The algorithm is described in <https://howardhinnant.github.io/date_algorithms.html>.
+fn days_from_civil(year: i32, month: u8, day: u8) -> i64 {
+    let year = i64::from(year) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = i64::from(month);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The inverse of [`days_from_civil()`]
+#[cfg(feature = "std")]
+fn civil_from_days(days: i64) -> (i32, u8, u8) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u8;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year as i32, month, day)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateTimeError {
+    IncompleteSpecifier,
+    InvalidSpecifier(char),
+    MissingDate(char),
+    MissingTime(char),
+    MissingOffset(char),
+    TimeSinceNeedsDate(&'static str),
+}
+
+impl fmt::Display for DateTimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IncompleteSpecifier => {
+                f.write_str("incomplete format specifier at end of string")
+            }
+            Self::InvalidSpecifier(spec) => write!(f, "invalid format specifier `%{spec}`"),
+            Self::MissingDate(spec) => {
+                write!(f, "format specifier `%{spec}` needs a value with a date")
+            }
+            Self::MissingTime(spec) => {
+                write!(f, "format specifier `%{spec}` needs a value with a time")
+            }
+            Self::MissingOffset(spec) => {
+                write!(
+                    f,
+                    "format specifier `%{spec}` needs a value with a UTC offset"
+                )
+            }
+            Self::TimeSinceNeedsDate(filter) => {
+                write!(f, "the `{filter}` filter needs values with a date")
+            }
+        }
+    }
+}
+
+impl core::error::Error for DateTimeError {}
+
+#[cfg(feature = "std")]
+impl DateTimeValue for std::time::SystemTime {
+    fn date(&self) -> Option<(i32, u8, u8)> {
+        Some(civil_from_days(system_time_secs(self).div_euclid(86_400)))
+    }
+
+    fn time(&self) -> Option<(u8, u8, u8, u32)> {
+        let secs = system_time_secs(self).rem_euclid(86_400);
+        let nanos = match self.duration_since(std::time::UNIX_EPOCH) {
+            Ok(duration) => duration.subsec_nanos(),
+            Err(err) => (1_000_000_000 - err.duration().subsec_nanos()) % 1_000_000_000,
+        };
+        Some((
+            (secs / 3_600) as u8,
+            (secs / 60 % 60) as u8,
+            (secs % 60) as u8,
+            nanos,
+        ))
+    }
+
+    #[inline]
+    fn utc_offset(&self) -> Option<i32> {
+        Some(0)
+    }
+}
+
+/// Whole seconds since the UNIX epoch, rounded down
+#[cfg(feature = "std")]
+fn system_time_secs(time: &std::time::SystemTime) -> i64 {
+    match time.duration_since(std::time::UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(err) => {
+            let duration = err.duration();
+            -(duration.as_secs() as i64) - i64::from(duration.subsec_nanos() > 0)
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+const _: () = {
+    use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeZone};
+
+    fn date(date: &impl Datelike) -> Option<(i32, u8, u8)> {
+        Some((date.year(), date.month() as u8, date.day() as u8))
+    }
+
+    fn time(time: &impl chrono::Timelike) -> Option<(u8, u8, u8, u32)> {
+        Some((
+            time.hour() as u8,
+            time.minute() as u8,
+            time.second() as u8,
+            time.nanosecond(),
+        ))
+    }
+
+    impl DateTimeValue for NaiveDate {
+        #[inline]
+        fn date(&self) -> Option<(i32, u8, u8)> {
+            date(self)
+        }
+
+        #[inline]
+        fn time(&self) -> Option<(u8, u8, u8, u32)> {
+            None
+        }
+
+        #[inline]
+        fn utc_offset(&self) -> Option<i32> {
+            None
+        }
+    }
+
+    impl DateTimeValue for NaiveTime {
+        #[inline]
+        fn date(&self) -> Option<(i32, u8, u8)> {
+            None
+        }
+
+        #[inline]
+        fn time(&self) -> Option<(u8, u8, u8, u32)> {
+            time(self)
+        }
+
+        #[inline]
+        fn utc_offset(&self) -> Option<i32> {
+            None
+        }
+    }
+
+    impl DateTimeValue for NaiveDateTime {
+        #[inline]
+        fn date(&self) -> Option<(i32, u8, u8)> {
+            date(self)
+        }
+
+        #[inline]
+        fn time(&self) -> Option<(u8, u8, u8, u32)> {
+            time(self)
+        }
+
+        #[inline]
+        fn utc_offset(&self) -> Option<i32> {
+            None
+        }
+    }
+
+    impl<Tz: TimeZone> DateTimeValue for DateTime<Tz> {
+        #[inline]
+        fn date(&self) -> Option<(i32, u8, u8)> {
+            date(self)
+        }
+
+        #[inline]
+        fn time(&self) -> Option<(u8, u8, u8, u32)> {
+            time(self)
+        }
+
+        #[inline]
+        fn utc_offset(&self) -> Option<i32> {
+            Some(self.offset().fix().local_minus_utc())
+        }
+    }
+};
+
+#[cfg(feature = "time")]
+const _: () = {
+    use ::time::{Date, OffsetDateTime, PrimitiveDateTime, Time};
+
+    fn date(date: Date) -> Option<(i32, u8, u8)> {
+        Some((date.year(), date.month().into(), date.day()))
+    }
+
+    fn time(time: Time) -> Option<(u8, u8, u8, u32)> {
+        Some((time.hour(), time.minute(), time.second(), time.nanosecond()))
+    }
+
+    impl DateTimeValue for Date {
+        #[inline]
+        fn date(&self) -> Option<(i32, u8, u8)> {
+            date(*self)
+        }
+
+        #[inline]
+        fn time(&self) -> Option<(u8, u8, u8, u32)> {
+            None
+        }
+
+        #[inline]
+        fn utc_offset(&self) -> Option<i32> {
+            None
+        }
+    }
+
+    impl DateTimeValue for Time {
+        #[inline]
+        fn date(&self) -> Option<(i32, u8, u8)> {
+            None
+        }
+
+        #[inline]
+        fn time(&self) -> Option<(u8, u8, u8, u32)> {
+            time(*self)
+        }
+
+        #[inline]
+        fn utc_offset(&self) -> Option<i32> {
+            None
+        }
+    }
+
+    impl DateTimeValue for PrimitiveDateTime {
+        #[inline]
+        fn date(&self) -> Option<(i32, u8, u8)> {
+            date(PrimitiveDateTime::date(*self))
+        }
+
+        #[inline]
+        fn time(&self) -> Option<(u8, u8, u8, u32)> {
+            time(PrimitiveDateTime::time(*self))
+        }
+
+        #[inline]
+        fn utc_offset(&self) -> Option<i32> {
+            None
+        }
+    }
+
+    impl DateTimeValue for OffsetDateTime {
+        #[inline]
+        fn date(&self) -> Option<(i32, u8, u8)> {
+            date(OffsetDateTime::date(*self))
+        }
+
+        #[inline]
+        fn time(&self) -> Option<(u8, u8, u8, u32)> {
+            time(OffsetDateTime::time(*self))
+        }
+
+        #[inline]
+        fn utc_offset(&self) -> Option<i32> {
+            Some(self.offset().whole_seconds())
+        }
+    }
+};
+
+#[cfg(feature = "jiff")]
+const _: () = {
+    use jiff::civil::{Date, DateTime, Time};
+    use jiff::tz::Offset;
+    use jiff::{Timestamp, Zoned};
+
+    fn date(date: Date) -> Option<(i32, u8, u8)> {
+        Some((date.year().into(), date.month() as u8, date.day() as u8))
+    }
+
+    fn time(time: Time) -> Option<(u8, u8, u8, u32)> {
+        Some((
+            time.hour() as u8,
+            time.minute() as u8,
+            time.second() as u8,
+            time.subsec_nanosecond() as u32,
+        ))
+    }
+
+    impl DateTimeValue for Date {
+        #[inline]
+        fn date(&self) -> Option<(i32, u8, u8)> {
+            date(*self)
+        }
+
+        #[inline]
+        fn time(&self) -> Option<(u8, u8, u8, u32)> {
+            None
+        }
+
+        #[inline]
+        fn utc_offset(&self) -> Option<i32> {
+            None
+        }
+    }
+
+    impl DateTimeValue for Time {
+        #[inline]
+        fn date(&self) -> Option<(i32, u8, u8)> {
+            None
+        }
+
+        #[inline]
+        fn time(&self) -> Option<(u8, u8, u8, u32)> {
+            time(*self)
+        }
+
+        #[inline]
+        fn utc_offset(&self) -> Option<i32> {
+            None
+        }
+    }
+
+    impl DateTimeValue for DateTime {
+        #[inline]
+        fn date(&self) -> Option<(i32, u8, u8)> {
+            date(DateTime::date(*self))
+        }
+
+        #[inline]
+        fn time(&self) -> Option<(u8, u8, u8, u32)> {
+            time(DateTime::time(*self))
+        }
+
+        #[inline]
+        fn utc_offset(&self) -> Option<i32> {
+            None
+        }
+    }
+
+    impl DateTimeValue for Timestamp {
+        #[inline]
+        fn date(&self) -> Option<(i32, u8, u8)> {
+            date(Offset::UTC.to_datetime(*self).date())
+        }
+
+        #[inline]
+        fn time(&self) -> Option<(u8, u8, u8, u32)> {
+            time(Offset::UTC.to_datetime(*self).time())
+        }
+
+        #[inline]
+        fn utc_offset(&self) -> Option<i32> {
+            Some(0)
+        }
+    }
+
+    impl DateTimeValue for Zoned {
+        #[inline]
+        fn date(&self) -> Option<(i32, u8, u8)> {
+            date(Zoned::date(self))
+        }
+
+        #[inline]
+        fn time(&self) -> Option<(u8, u8, u8, u32)> {
+            time(Zoned::time(self))
+        }
+
+        #[inline]
+        fn utc_offset(&self) -> Option<i32> {
+            Some(self.offset().seconds())
+        }
+    }
+};
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+
+    struct Value(
+        Option<(i32, u8, u8)>,
+        Option<(u8, u8, u8, u32)>,
+        Option<i32>,
+    );
+
+    impl DateTimeValue for Value {
+        fn date(&self) -> Option<(i32, u8, u8)> {
+            self.0
+        }
+
+        fn time(&self) -> Option<(u8, u8, u8, u32)> {
+            self.1
+        }
+
+        fn utc_offset(&self) -> Option<i32> {
+            self.2
+        }
+    }
+
+    const VALUE: Value = Value(
+        Some((2001, 7, 8)),
+        Some((0, 34, 59, 26_490_000)),
+        Some(34_200),
+    );
+
+    #[test]
+    fn test_strftime() {
+        for (format, expected) in [
+            (
+                "%Y %C %y %m %b %h %B %d %e",
+                "2001 20 01 07 Jul Jul July 08  8",
+            ),
+            ("%a %A %u %w %j", "Sun Sunday 7 0 189"),
+            ("%H %k %I %l %P %p %M %S", "00  0 12 12 am AM 34 59"),
+            (
+                "%f %3f %6f %9f %.f %.3f",
+                "026490000 026 026490 026490000 .026490000 .026",
+            ),
+            ("%z %:z %Z %s", "+0930 +09:30 +09:30 994518299"),
+            (
+                "%F|%D|%x|%T|%X|%R|%r",
+                "2001-07-08|07/08/01|07/08/01|00:34:59|00:34:59|00:34|12:34:59 AM",
+            ),
+            ("%c", "Sun Jul  8 00:34:59 2001"),
+            ("%-d %_m %0e %-H", "8  7 08 0"),
+            ("a%nb%tc%%d", "a\nb\tc%d"),
+            ("no specifiers", "no specifiers"),
+        ] {
+            assert_eq!(
+                date(&VALUE, format).unwrap().to_string(),
+                expected,
+                "{format}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_strftime_errors() {
+        for (value, format, expected) in [
+            (&VALUE, "%Q", "invalid format specifier `%Q`"),
+            (&VALUE, "%.d", "invalid format specifier `%d`"),
+            (&VALUE, "%", "incomplete format specifier at end of string"),
+            (
+                &Value(None, Some((0, 0, 0, 0)), None),
+                "%d",
+                "format specifier `%d` needs a value with a date",
+            ),
+            (
+                &Value(Some((2001, 1, 1)), None, None),
+                "%T",
+                "format specifier `%H` needs a value with a time",
+            ),
+            (
+                &Value(Some((2001, 1, 1)), None, None),
+                "%z",
+                "format specifier `%z` needs a value with a UTC offset",
+            ),
+        ] {
+            let err = date(value, format)
+                .unwrap()
+                .write_into(&mut alloc::string::String::new());
+            assert_eq!(err.unwrap_err().to_string(), expected, "{format}");
+        }
+    }
+
+    #[test]
+    fn test_calendar() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        #[cfg(feature = "std")]
+        for days in [-800_000, -1, 0, 1, 11_016, 11_017, 20_000, 800_000] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn test_timesince() {
+        let base = Value(Some((2020, 1, 1)), Some((0, 0, 0, 0)), None);
+        for (now, expected) in [
+            (
+                Value(Some((2020, 1, 1)), Some((0, 0, 59, 0)), None),
+                "0 minutes",
+            ),
+            (Value(Some((2019, 1, 1)), None, None), "0 minutes"),
+            (
+                Value(Some((2020, 1, 1)), Some((0, 1, 0, 0)), None),
+                "1 minute",
+            ),
+            (
+                Value(Some((2020, 1, 1)), Some((2, 5, 0, 0)), None),
+                "2 hours, 5 minutes",
+            ),
+            (Value(Some((2020, 1, 2)), Some((0, 5, 0, 0)), None), "1 day"),
+            (Value(Some((2020, 1, 17)), None, None), "2 weeks, 2 days"),
+            (Value(Some((2020, 3, 1)), None, None), "2 months"),
+            (Value(Some((2022, 1, 1)), None, None), "2 years"),
+            (
+                Value(Some((2020, 1, 1)), Some((5, 0, 0, 0)), Some(3_600)),
+                "4 hours",
+            ),
+        ] {
+            assert_eq!(timesince(&base, &now).unwrap().to_string(), expected);
+            assert_eq!(timeuntil(&now, &base).unwrap().to_string(), expected);
+        }
+
+        let err = timesince(&Value(None, Some((0, 0, 0, 0)), None), &base).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the `timesince` filter needs values with a date"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_system_time() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let time = UNIX_EPOCH + Duration::new(994_552_499, 5);
+        assert_eq!(
+            datetime(&time, "%F %T.%9f %Z").unwrap().to_string(),
+            "2001-07-08 00:34:59.000000005 UTC",
+        );
+        let time = UNIX_EPOCH - Duration::new(1, 5);
+        assert_eq!(
+            datetime(&time, "%F %T.%9f").unwrap().to_string(),
+            "1969-12-31 23:59:58.999999995",
+        );
+    }
+}
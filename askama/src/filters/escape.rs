@@ -164,6 +164,209 @@ impl Escaper for Text {
     }
 }
 
+/// Escape characters in a safe way for JavaScript string literals
+///
+/// The output can be used inside single, double and backtick quoted strings, both in inline
+/// `<script>` blocks and in quoted event handler attributes like `onclick="…"`.
+///
+/// * `"`, `'`, `` ` ``, `\`, `/`, `<`, `>`, `&`, `=`, `$` => `\u00XX`
+/// * control characters => `\u00XX`
+/// * U+2028 LINE SEPARATOR => `\u2028`
+/// * U+2029 PARAGRAPH SEPARATOR => `\u2029`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Js;
+
+impl Escaper for Js {
+    fn write_escaped_str<W: Write>(&self, mut dest: W, string: &str) -> fmt::Result {
+        let mut last = 0;
+        for (index, c) in string.char_indices() {
+            if js_needs_escaping(c) {
+                dest.write_str(&string[last..index])?;
+                write_js_escaped(&mut dest, c)?;
+                last = index + c.len_utf8();
+            }
+        }
+        dest.write_str(&string[last..])
+    }
+
+    #[inline]
+    fn write_escaped_char<W: Write>(&self, mut dest: W, c: char) -> fmt::Result {
+        match js_needs_escaping(c) {
+            true => write_js_escaped(dest, c),
+            false => dest.write_char(c),
+        }
+    }
+}
+
+#[inline]
+fn js_needs_escaping(c: char) -> bool {
+    matches!(
+        c,
+        '\0'..='\x1f'
+            | '\x7f'
+            | '"'
+            | '\''
+            | '`'
+            | '\\'
+            | '/'
+            | '<'
+            | '>'
+            | '&'
+            | '='
+            | '$'
+            | '\u{2028}'
+            | '\u{2029}'
+    )
+}
+
+#[inline]
+fn write_js_escaped<W: Write>(mut dest: W, c: char) -> fmt::Result {
+    write!(dest, "\\u{:04X}", c as u32)
+}
+
+/// Escape characters in a safe way for CSS property values and CSS strings
+///
+/// All ASCII characters except alphanumerics, `-`, `_`, `.`, `#` and `%` are written as
+/// hexadecimal escape sequences, e.g. `;` => `\3b `.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Css;
+
+impl Escaper for Css {
+    fn write_escaped_str<W: Write>(&self, mut dest: W, string: &str) -> fmt::Result {
+        let mut last = 0;
+        for (index, byte) in string.bytes().enumerate() {
+            if css_needs_escaping(byte) {
+                dest.write_str(&string[last..index])?;
+                write!(dest, "\\{byte:x} ")?;
+                last = index + 1;
+            }
+        }
+        dest.write_str(&string[last..])
+    }
+}
+
+#[inline]
+fn css_needs_escaping(byte: u8) -> bool {
+    byte.is_ascii()
+        && !byte.is_ascii_alphanumeric()
+        && !matches!(byte, b'-' | b'_' | b'.' | b'#' | b'%')
+}
+
+/// Escape characters in a safe way for URLs in HTML attributes like `href="…"`
+///
+/// Characters that are not allowed in URLs are percent-encoded, and `&` is written as `&#38;`.
+/// A colon `:` is only kept verbatim if it ends one of the schemes `http`, `https` or `mailto`,
+/// or if it comes after the path, query or fragment started, so an interpolated value cannot
+/// introduce a `javascript:` or `data:` URL.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Url;
+
+impl Escaper for Url {
+    fn write_escaped_str<W: Write>(&self, mut dest: W, string: &str) -> fmt::Result {
+        let mut last = 0;
+        let mut in_path = false;
+        for (index, byte) in string.bytes().enumerate() {
+            let escaped = match byte {
+                b'/' | b'?' | b'#' => {
+                    in_path = true;
+                    continue;
+                }
+                b':' if in_path || is_safe_url_scheme(&string[..index]) => continue,
+                b'&' => "&#38;",
+                b'a'..=b'z'
+                | b'A'..=b'Z'
+                | b'0'..=b'9'
+                | b'-'
+                | b'.'
+                | b'_'
+                | b'~'
+                | b'['
+                | b']'
+                | b'@'
+                | b'!'
+                | b'$'
+                | b'('
+                | b')'
+                | b'*'
+                | b'+'
+                | b','
+                | b';'
+                | b'='
+                | b'%' => continue,
+                _ => "",
+            };
+            // non-ASCII characters are encoded byte by byte, so `last` can be inside a character
+            if last < index {
+                dest.write_str(&string[last..index])?;
+            }
+            match escaped {
+                "" => write!(dest, "%{byte:02X}")?,
+                escaped => dest.write_str(escaped)?,
+            }
+            last = index + 1;
+        }
+        dest.write_str(&string[last..])
+    }
+}
+
+#[inline]
+fn is_safe_url_scheme(scheme: &str) -> bool {
+    ["http", "https", "mailto"]
+        .iter()
+        .any(|safe| scheme.eq_ignore_ascii_case(safe))
+}
+
+/// Escape characters in a safe way for the query or fragment of URLs in HTML attributes like
+/// `href="?q=…"`
+///
+/// All characters except ASCII alphanumerics, `-`, `.`, `_` and `~` are percent-encoded, so an
+/// interpolated value cannot add query parameters with `&` or `=`, start a fragment with `#`,
+/// or turn into a space with `+`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UrlComponent;
+
+impl Escaper for UrlComponent {
+    fn write_escaped_str<W: Write>(&self, mut dest: W, string: &str) -> fmt::Result {
+        let mut last = 0;
+        for (index, byte) in string.bytes().enumerate() {
+            if !byte.is_ascii_alphanumeric() && !matches!(byte, b'-' | b'.' | b'_' | b'~') {
+                // non-ASCII characters are encoded byte by byte, so `last` can be inside a
+                // character
+                if last < index {
+                    dest.write_str(&string[last..index])?;
+                }
+                write!(dest, "%{byte:02X}")?;
+                last = index + 1;
+            }
+        }
+        dest.write_str(&string[last..])
+    }
+}
+
+/// Escape characters in a safe way for unquoted HTML attribute values
+///
+/// All ASCII characters except alphanumerics, `-`, `_` and `.` are written as numeric character
+/// references, e.g. ` ` => `&#32;`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Attr;
+
+impl Escaper for Attr {
+    fn write_escaped_str<W: Write>(&self, mut dest: W, string: &str) -> fmt::Result {
+        let mut last = 0;
+        for (index, byte) in string.bytes().enumerate() {
+            if byte.is_ascii()
+                && !byte.is_ascii_alphanumeric()
+                && !matches!(byte, b'-' | b'_' | b'.')
+            {
+                dest.write_str(&string[last..index])?;
+                write!(dest, "&#{byte};")?;
+                last = index + 1;
+            }
+        }
+        dest.write_str(&string[last..])
+    }
+}
+
 /// Escapers are used to make generated text safe for printing in some context.
 ///
 /// E.g. in an [`Html`] context, any and all generated text can be used in HTML/XML text nodes and
@@ -662,6 +865,80 @@ fn test_escape() {
     assert_eq!(escape("bla&h", Text).unwrap().to_string(), "bla&h");
 }
 
+#[test]
+#[cfg(feature = "alloc")]
+fn test_js_escaper() {
+    use alloc::string::ToString;
+
+    assert_eq!(escape("", Js).unwrap().to_string(), "");
+    assert_eq!(escape("abc", Js).unwrap().to_string(), "abc");
+    assert_eq!(
+        escape(r#"'"`\"#, Js).unwrap().to_string(),
+        r"\u0027\u0022\u0060\u005C"
+    );
+    assert_eq!(
+        escape("</script>", Js).unwrap().to_string(),
+        r"\u003C\u002Fscript\u003E"
+    );
+    assert_eq!(
+        escape("a\nb\u{2028}c\u{2029}", Js).unwrap().to_string(),
+        r"a\u000Ab\u2028c\u2029"
+    );
+    assert_eq!(escape("${x}", Js).unwrap().to_string(), r"\u0024{x}");
+    assert_eq!(
+        escape("\0\x1f\x7f", Js).unwrap().to_string(),
+        r"\u0000\u001F\u007F"
+    );
+    assert_eq!(escape('\'', Js).unwrap().to_string(), r"\u0027");
+    assert_eq!(escape('a', Js).unwrap().to_string(), "a");
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn test_contextual_escapers() {
+    use alloc::string::ToString;
+
+    assert_eq!(escape("red", Css).unwrap().to_string(), "red");
+    assert_eq!(escape("50%", Css).unwrap().to_string(), "50%");
+    assert_eq!(
+        escape("red;}body{", Css).unwrap().to_string(),
+        r"red\3b \7d body\7b "
+    );
+    assert_eq!(escape("'\"", Css).unwrap().to_string(), r"\27 \22 ");
+
+    assert_eq!(
+        escape("https://example.com/a?b=c&d=e:f", Url)
+            .unwrap()
+            .to_string(),
+        "https://example.com/a?b=c&#38;d=e:f"
+    );
+    assert_eq!(
+        escape("javascript:alert(1)", Url).unwrap().to_string(),
+        "javascript%3Aalert(1)"
+    );
+    assert_eq!(escape(" data:x", Url).unwrap().to_string(), "%20data%3Ax");
+    assert_eq!(
+        escape("a\"b'c<d>", Url).unwrap().to_string(),
+        "a%22b%27c%3Cd%3E"
+    );
+    assert_eq!(escape("é", Url).unwrap().to_string(), "%C3%A9");
+
+    assert_eq!(
+        escape("a b&c=d#e+f", UrlComponent).unwrap().to_string(),
+        "a%20b%26c%3Dd%23e%2Bf"
+    );
+    assert_eq!(
+        escape("x-y.z_~é", UrlComponent).unwrap().to_string(),
+        "x-y.z_~%C3%A9"
+    );
+
+    assert_eq!(escape("a-b_c.d", Attr).unwrap().to_string(), "a-b_c.d");
+    assert_eq!(
+        escape("a b=c>", Attr).unwrap().to_string(),
+        "a&#32;b&#61;c&#62;"
+    );
+}
+
 #[test]
 #[cfg(feature = "alloc")]
 fn test_html_safe_marker() {
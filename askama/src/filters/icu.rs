@@ -0,0 +1,492 @@
+use alloc::string::{String, ToString};
+use core::fmt;
+
+use fixed_decimal::FixedDecimal;
+#[cfg(feature = "icu-unstable")]
+use fixed_decimal::Sign;
+use icu_decimal::FixedDecimalFormatter;
+#[cfg(feature = "icu-unstable")]
+use icu_decimal::provider::DecimalSymbolsV1Marker;
+#[cfg(feature = "icu-unstable")]
+use icu_experimental::dimension::provider::currency::{
+    CurrencyEssentialsV1Marker, PatternSelection, PlaceholderValue,
+};
+#[cfg(feature = "icu-unstable")]
+use icu_experimental::dimension::provider::percent::PercentEssentialsV1Marker;
+#[doc(no_inline)]
+pub use icu_locid::Locale;
+use icu_provider::prelude::*;
+#[cfg(feature = "icu-unstable")]
+use tinystr::TinyAsciiStr;
+use writeable::Writeable;
+
+use super::FastWritable;
+use crate::{Values, get_value};
+
+/// A number that can be formatted by the filters [`number()`], [`intcomma()`], [`percent()`]
+/// and [`currency()`]
+pub trait NumberValue {
+    /// Convert the value into a [`FixedDecimal`]
+    fn to_fixed_decimal(&self) -> crate::Result<FixedDecimal>;
+}
+
+macro_rules! impl_number_value_for_int {
+    ($($ty:ty)*) => { $(
+        impl NumberValue for $ty {
+            #[inline]
+            fn to_fixed_decimal(&self) -> crate::Result<FixedDecimal> {
+                Ok(FixedDecimal::from(*self))
+            }
+        }
+    )* };
+}
+
+impl_number_value_for_int!(i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize);
+
+macro_rules! impl_number_value_for_float {
+    ($($ty:ty)*) => { $(
+        impl NumberValue for $ty {
+            fn to_fixed_decimal(&self) -> crate::Result<FixedDecimal> {
+                if !self.is_finite() {
+                    return Err(crate::Error::custom(NumberError::NotFinite));
+                }
+                // `Display` writes the shortest representation that round-trips, which is what
+                // a user expects to see, e.g. `0.1` and not `0.100000001490116` for an `f32`.
+                let mut buf = StackBuf::default();
+                fmt::Write::write_fmt(&mut buf, format_args!("{self}"))?;
+                FixedDecimal::try_from(buf.as_bytes()).map_err(crate::Error::custom)
+            }
+        }
+    )* };
+}
+
+impl_number_value_for_float!(f32 f64);
+
+impl NumberValue for FixedDecimal {
+    #[inline]
+    fn to_fixed_decimal(&self) -> crate::Result<FixedDecimal> {
+        Ok(self.clone())
+    }
+}
+
+crate::impl_for_ref! {
+    impl NumberValue for T {
+        #[inline]
+        fn to_fixed_decimal(&self) -> crate::Result<FixedDecimal> {
+            T::to_fixed_decimal(self)
+        }
+    }
+}
+
+/// Formats a number with the grouping and decimal separators of a locale
+///
+/// Without an argument, all digits of the value are written. With an argument, the value is
+/// rounded (half to even) or padded with zeros to the given number of decimal places.
+///
+/// The filter is available if the feature `"icu"` is enabled, and if the group `"numbers"` is
+/// enabled in the `builtin_filters` of the configuration file. In a template, the locale is taken
+/// from a variable or field named `locale`, or else from the runtime [`Values`] key `"locale"`.
+/// The locale can be a [`Locale`] or a string, e.g. `"de-CH"`.
+/// If there is no locale, the numbers are formatted like in English.
+///
+/// ## Example
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     source = "{{ distance|number }} km, {{ price|number(2) }}",
+///     ext = "html"
+/// )]
+/// struct Example<'a> {
+///     distance: u32,
+///     price: f64,
+///     locale: &'a str,
+/// }
+///
+/// let tmpl = Example { distance: 1_234_567, price: 1_234.5, locale: "de" };
+/// assert_eq!(tmpl.to_string(), "1.234.567 km, 1.234,50");
+/// let tmpl = Example { distance: 1_234_567, price: 1_234.5, locale: "hi-IN" };
+/// assert_eq!(tmpl.to_string(), "12,34,567 km, 1,234.50");
+/// ```
+pub fn number<T: NumberValue + ?Sized>(
+    value: &T,
+    locale: &Locale,
+    decimals: Option<u8>,
+) -> crate::Result<NumberFilter> {
+    let mut value = value.to_fixed_decimal()?;
+    if let Some(decimals) = decimals {
+        round(&mut value, decimals);
+    }
+    NumberFilter::new(value, locale, Kind::Plain)
+}
+
+/// Formats an integer with the grouping separators of a locale, like Django's `intcomma`
+///
+/// Non-integer values keep all their decimal places. This filter is the same as [`number()`]
+/// without an argument.
+///
+/// ## Example
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(source = "{{ visitors|intcomma }} visitors", ext = "html")]
+/// struct Example {
+///     visitors: u64,
+/// }
+///
+/// let tmpl = Example { visitors: 45_000 };
+/// assert_eq!(tmpl.to_string(), "45,000 visitors");
+/// ```
+#[inline]
+pub fn intcomma<T: NumberValue + ?Sized>(
+    value: &T,
+    locale: &Locale,
+) -> crate::Result<NumberFilter> {
+    number(value, locale, None)
+}
+
+/// Formats a fraction as a percentage, using the percent pattern of a locale
+///
+/// The value is multiplied by 100, e.g. `0.25` is written as `25%`. By default the result is
+/// rounded to an integer, the optional argument is the number of decimal places.
+/// See [`number()`] for how the locale is selected.
+///
+/// The filter needs the feature `"icu-unstable"`, because the percent patterns come from the
+/// crate `icu_experimental`, which has no stability guarantees.
+///
+/// ## Example
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(source = "{{ ratio|percent }} / {{ ratio|percent(1) }}", ext = "html")]
+/// struct Example<'a> {
+///     ratio: f32,
+///     locale: &'a str,
+/// }
+///
+/// let tmpl = Example { ratio: 0.1234, locale: "en" };
+/// assert_eq!(tmpl.to_string(), "12% / 12.3%");
+/// let tmpl = Example { ratio: 0.1234, locale: "fr" };
+/// assert_eq!(tmpl.to_string(), "12\u{a0}% / 12,3\u{a0}%");
+/// ```
+#[cfg(feature = "icu-unstable")]
+pub fn percent<T: NumberValue + ?Sized>(
+    value: &T,
+    locale: &Locale,
+    decimals: Option<u8>,
+) -> crate::Result<NumberFilter> {
+    let mut value = value.to_fixed_decimal()?;
+    value.multiply_pow10(2);
+    value.trim_start();
+    round(&mut value, decimals.unwrap_or(0));
+
+    let essentials = load(&icu_experimental::provider::Baked, &locale.into())?;
+    NumberFilter::new(value, locale, Kind::Percent(essentials))
+}
+
+/// Formats an amount of money with the currency symbol and pattern of a locale
+///
+/// The argument is the [ISO 4217](https://en.wikipedia.org/wiki/ISO_4217) code of the currency,
+/// e.g. `"EUR"`. The amount is rounded to the usual number of decimal places of the currency,
+/// or to the number of decimal places given as second argument.
+/// See [`number()`] for how the locale is selected.
+///
+/// The filter needs the feature `"icu-unstable"`, because the currency patterns come from the
+/// crate `icu_experimental`, which has no stability guarantees.
+///
+/// ## Example
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(source = r#"{{ price|currency("EUR") }}"#, ext = "html")]
+/// struct Example<'a> {
+///     price: f64,
+///     locale: &'a str,
+/// }
+///
+/// let tmpl = Example { price: 1234.5, locale: "en" };
+/// assert_eq!(tmpl.to_string(), "€1,234.50");
+/// let tmpl = Example { price: 1234.5, locale: "de" };
+/// assert_eq!(tmpl.to_string(), "1.234,50\u{a0}€");
+/// ```
+#[cfg(feature = "icu-unstable")]
+pub fn currency<T: NumberValue + ?Sized>(
+    value: &T,
+    code: &str,
+    locale: &Locale,
+    decimals: Option<u8>,
+) -> crate::Result<NumberFilter> {
+    let code = TinyAsciiStr::<3>::from_str(code)
+        .ok()
+        .filter(|code| code.is_ascii_alphabetic())
+        .ok_or_else(|| crate::Error::custom(NumberError::InvalidCurrency(code.to_string())))?
+        .to_ascii_uppercase();
+    let mut value = value.to_fixed_decimal()?;
+    round(
+        &mut value,
+        decimals.unwrap_or_else(|| currency_decimals(&code)),
+    );
+
+    let essentials = load(&icu_experimental::provider::Baked, &locale.into())?;
+    NumberFilter::new(value, locale, Kind::Currency(essentials, code))
+}
+
+/// The number of decimal places that are usually shown for a currency
+#[cfg(feature = "icu-unstable")]
+fn currency_decimals(code: &TinyAsciiStr<3>) -> u8 {
+    match code.as_str() {
+        "BIF" | "CLP" | "DJF" | "GNF" | "ISK" | "JPY" | "KMF" | "KRW" | "PYG" | "RWF" | "UGX"
+        | "UYI" | "VND" | "VUV" | "XAF" | "XOF" | "XPF" => 0,
+        "BHD" | "IQD" | "JOD" | "KWD" | "LYD" | "OMR" | "TND" => 3,
+        _ => 2,
+    }
+}
+
+/// Load compiled CLDR data
+fn load<M: KeyedDataMarker>(
+    provider: &impl DataProvider<M>,
+    locale: &DataLocale,
+) -> crate::Result<DataPayload<M>> {
+    provider
+        .load(DataRequest {
+            locale,
+            metadata: Default::default(),
+        })
+        .and_then(DataResponse::take_payload)
+        .map_err(crate::Error::custom)
+}
+
+fn round(value: &mut FixedDecimal, decimals: u8) {
+    let position = -i16::from(decimals);
+    value.half_even(position);
+    value.pad_end(position);
+}
+
+/// The output of [`number()`], [`intcomma()`], [`percent()`] and [`currency()`]
+#[derive(Debug)]
+pub struct NumberFilter {
+    value: FixedDecimal,
+    formatter: FixedDecimalFormatter,
+    kind: Kind,
+    /// Needed for the minus sign of negative percentages and currencies
+    #[cfg(feature = "icu-unstable")]
+    symbols: Option<DataPayload<DecimalSymbolsV1Marker>>,
+}
+
+#[derive(Debug)]
+enum Kind {
+    Plain,
+    #[cfg(feature = "icu-unstable")]
+    Percent(DataPayload<PercentEssentialsV1Marker>),
+    #[cfg(feature = "icu-unstable")]
+    Currency(DataPayload<CurrencyEssentialsV1Marker>, TinyAsciiStr<3>),
+}
+
+impl NumberFilter {
+    fn new(value: FixedDecimal, locale: &Locale, kind: Kind) -> crate::Result<Self> {
+        let locale = DataLocale::from(locale);
+        let formatter = FixedDecimalFormatter::try_new(&locale, Default::default())
+            .map_err(crate::Error::custom)?;
+        #[cfg(feature = "icu-unstable")]
+        let symbols = match kind {
+            Kind::Plain => None,
+            #[cfg(feature = "icu-unstable")]
+            Kind::Percent(_) | Kind::Currency(..) => {
+                Some(load(&icu_decimal::provider::Baked, &locale)?)
+            }
+        };
+        Ok(Self {
+            value,
+            formatter,
+            kind,
+            #[cfg(feature = "icu-unstable")]
+            symbols,
+        })
+    }
+}
+
+impl fmt::Display for NumberFilter {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Ok(self.write_into(f)?)
+    }
+}
+
+impl FastWritable for NumberFilter {
+    fn write_into<W: fmt::Write + ?Sized>(&self, dest: &mut W) -> crate::Result<()> {
+        #[cfg(feature = "icu-unstable")]
+        if let (Kind::Percent(_) | Kind::Currency(..), Some(symbols)) = (&self.kind, &self.symbols)
+        {
+            return self.write_pattern(symbols, dest);
+        }
+        Ok(self.formatter.format(&self.value).write_to(dest)?)
+    }
+}
+
+#[cfg(feature = "icu-unstable")]
+impl NumberFilter {
+    fn write_pattern<W: fmt::Write + ?Sized>(
+        &self,
+        symbols: &DataPayload<DecimalSymbolsV1Marker>,
+        dest: &mut W,
+    ) -> crate::Result<()> {
+        // The patterns of percentages and currencies describe positive numbers. The minus sign
+        // goes in front of the whole pattern, e.g. `-$1.00` and not `$-1.00`.
+        let minus =
+            (self.value.sign() == Sign::Negative).then(|| &symbols.get().minus_sign_affixes);
+        if let Some(minus) = minus {
+            dest.write_str(&minus.prefix)?;
+        }
+        let abs = self.value.clone().with_sign(Sign::None);
+        let number = self.formatter.format(&abs);
+
+        match &self.kind {
+            Kind::Plain => {}
+            Kind::Percent(essentials) => {
+                let essentials = essentials.get();
+                let sign = [
+                    &*essentials.percent_sign_affixes.prefix,
+                    &*essentials.percent_sign_symbol,
+                    &*essentials.percent_sign_affixes.suffix,
+                ];
+                if essentials.percent_symbol_index > essentials.number_index {
+                    number.write_to(dest)?;
+                    sign.iter().try_for_each(|s| dest.write_str(s))?;
+                } else {
+                    sign.iter().try_for_each(|s| dest.write_str(s))?;
+                    number.write_to(dest)?;
+                }
+            }
+            Kind::Currency(essentials, code) => {
+                let essentials = essentials.get();
+                let config = essentials
+                    .pattern_config_map
+                    .get_copied(&code.to_unvalidated())
+                    .unwrap_or(essentials.default_pattern_config);
+                let symbol = match config.short_placeholder_value {
+                    Some(PlaceholderValue::Index(index)) => essentials
+                        .placeholders
+                        .get(index.into())
+                        .unwrap_or(code.as_str()),
+                    Some(PlaceholderValue::ISO) | None => code.as_str(),
+                };
+                let pattern = match config.short_pattern_selection {
+                    PatternSelection::Standard => essentials.standard_pattern.as_ref(),
+                    PatternSelection::StandardAlphaNextToNumber => {
+                        essentials.standard_alpha_next_to_number_pattern.as_ref()
+                    }
+                };
+                let Some(pattern) = pattern else {
+                    return Err(crate::Error::custom(NumberError::MissingData));
+                };
+                pattern.interpolate((number, symbol)).write_to(dest)?;
+            }
+        }
+
+        if let Some(minus) = minus {
+            dest.write_str(&minus.suffix)?;
+        }
+        Ok(())
+    }
+}
+
+/// Find the locale of a template, implementation detail of the number filters
+///
+/// `locale` is the template field or variable `locale`, if there is one.
+/// Otherwise the [`Values`] key `"locale"` is used.
+#[doc(hidden)]
+pub fn number_locale(locale: Option<&dyn fmt::Display>, values: &dyn Values) -> Locale {
+    let locale = match locale {
+        Some(locale) => locale.to_string().parse().ok(),
+        None => locale_from_values(values),
+    };
+    locale.unwrap_or_default()
+}
+
+fn locale_from_values(values: &dyn Values) -> Option<Locale> {
+    if let Ok(locale) = get_value::<Locale>(values, "locale") {
+        Some(locale.clone())
+    } else if let Ok(locale) = get_value::<String>(values, "locale") {
+        locale.parse().ok()
+    } else if let Ok(locale) = get_value::<&str>(values, "locale") {
+        locale.parse().ok()
+    } else {
+        #[cfg(feature = "i18n")]
+        if let Ok(locale) = get_value::<crate::i18n::LanguageIdentifier>(values, "locale") {
+            return locale.to_string().parse().ok();
+        }
+        None
+    }
+}
+
+/// Convert the number of decimal places, implementation detail of the number filters
+#[doc(hidden)]
+pub fn number_decimals<T: TryInto<u8>>(decimals: T) -> crate::Result<u8> {
+    decimals
+        .try_into()
+        .map_err(|_| crate::Error::custom(NumberError::InvalidDecimals))
+}
+
+/// A `fmt::Write` target for the string representation of a float
+struct StackBuf {
+    buf: [u8; 512],
+    len: usize,
+}
+
+impl Default for StackBuf {
+    fn default() -> Self {
+        Self {
+            buf: [0; 512],
+            len: 0,
+        }
+    }
+}
+
+impl StackBuf {
+    fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+impl fmt::Write for StackBuf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let dest = self
+            .buf
+            .get_mut(self.len..self.len + s.len())
+            .ok_or(fmt::Error)?;
+        dest.copy_from_slice(s.as_bytes());
+        self.len += s.len();
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+enum NumberError {
+    NotFinite,
+    #[cfg(feature = "icu-unstable")]
+    InvalidCurrency(String),
+    InvalidDecimals,
+    #[cfg(feature = "icu-unstable")]
+    MissingData,
+}
+
+impl fmt::Display for NumberError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFinite => f.write_str("cannot format an infinite or NaN number"),
+            #[cfg(feature = "icu-unstable")]
+            Self::InvalidCurrency(code) => write!(f, "invalid currency code {code:?}"),
+            Self::InvalidDecimals => {
+                f.write_str("the number of decimal places must be between 0 and 255")
+            }
+            #[cfg(feature = "icu-unstable")]
+            Self::MissingData => f.write_str("missing locale data to format the number"),
+        }
+    }
+}
+
+impl core::error::Error for NumberError {}
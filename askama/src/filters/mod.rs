@@ -13,8 +13,13 @@
 #[cfg(feature = "alloc")]
 mod alloc;
 mod builtin;
+mod collections;
+#[cfg(any(feature = "chrono", feature = "jiff", feature = "time"))]
+mod datetime;
 mod escape;
 mod humansize;
+#[cfg(feature = "icu")]
+mod icu;
 #[cfg(feature = "serde_json")]
 mod json;
 #[cfg(feature = "urlencode")]
@@ -25,12 +30,26 @@ pub use self::alloc::{
     capitalize, fmt, format, indent, linebreaks, linebreaksbr, lower, lowercase, paragraphbreaks,
     title, trim, upper, uppercase, wordcount,
 };
-pub use self::builtin::{PluralizeCount, center, join, pluralize, truncate};
+pub use self::builtin::{
+    DefaultFilter, DefaultIter, DefaultValue, DefaultValueLookup, DefaultValueOf, PluralizeCount,
+    center, default, default_of, join, pluralize, truncate,
+};
+#[cfg(feature = "alloc")]
+pub use self::collections::{Batch, batch, groupby, reverse, slice, sort, unique};
+pub use self::collections::{
+    CompareValue, Truthy, first, last, length, map, max, min, select, sum,
+};
+#[cfg(any(feature = "chrono", feature = "jiff", feature = "time"))]
+pub use self::datetime::{DateTimeValue, date, datetime, time, timesince, timeuntil};
 pub use self::escape::{
-    AutoEscape, AutoEscaper, Escaper, FastWritable, Html, HtmlSafe, HtmlSafeOutput, MaybeSafe,
-    Safe, Text, Unsafe, Writable, WriteWritable, e, escape, safe,
+    Attr, AutoEscape, AutoEscaper, Css, Escaper, FastWritable, Html, HtmlSafe, HtmlSafeOutput, Js,
+    MaybeSafe, Safe, Text, Unsafe, Url, UrlComponent, Writable, WriteWritable, e, escape, safe,
 };
 pub use self::humansize::filesizeformat;
+#[cfg(feature = "icu")]
+pub use self::icu::{Locale, NumberValue, intcomma, number, number_decimals, number_locale};
+#[cfg(feature = "icu-unstable")]
+pub use self::icu::{currency, percent};
 #[cfg(feature = "serde_json")]
 pub use self::json::{AsIndent, json, json_pretty};
 #[cfg(feature = "urlencode")]
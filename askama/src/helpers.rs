@@ -6,9 +6,11 @@ pub extern crate core;
 #[cfg(feature = "std")]
 pub extern crate std;
 
+#[cfg(feature = "hot-reload")]
+pub mod hot_reload;
+
 use core::cell::Cell;
 use core::fmt;
-use core::iter::{Enumerate, Peekable};
 use core::ops::Deref;
 use core::pin::Pin;
 
@@ -20,7 +22,10 @@ pub struct TemplateLoop<I>
 where
     I: Iterator,
 {
-    iter: Peekable<Enumerate<I>>,
+    iter: I,
+    next: Option<I::Item>,
+    index: usize,
+    length: usize,
 }
 
 impl<I> TemplateLoop<I>
@@ -28,13 +33,80 @@ where
     I: Iterator,
 {
     #[inline]
-    pub fn new(iter: I) -> Self {
+    pub fn new(mut iter: I) -> Self {
         TemplateLoop {
-            iter: iter.enumerate().peekable(),
+            next: iter.next(),
+            iter,
+            index: 0,
+            length: 0,
+        }
+    }
+
+    /// The item that will be yielded in the next iteration, used for `loop.nextitem`
+    #[inline]
+    pub fn next_item(&self) -> Option<&I::Item> {
+        self.next.as_ref()
+    }
+}
+
+impl<I> TemplateLoop<LoopIter<I>>
+where
+    I: LoopLength,
+{
+    /// Like [`TemplateLoop::new()`], but determines the number of items for `loop.length`
+    ///
+    /// If the iterator reports an exact size, e.g. because it is an [`ExactSizeIterator`],
+    /// the size is used as is. Otherwise the items are collected first.
+    #[inline]
+    pub fn with_length(iter: I) -> crate::Result<Self> {
+        let (iter, length) = iter.into_loop_iter();
+        let mut this = Self::new(iter);
+        this.length = length;
+        Ok(this)
+    }
+}
+
+/// An iterator whose number of items can be determined for `loop.length`
+///
+/// Without the feature `"alloc"`, the items cannot be collected, so only
+/// [`ExactSizeIterator`]s can be used.
+#[cfg_attr(
+    not(feature = "alloc"),
+    diagnostic::on_unimplemented(
+        message = "`loop.length`, `loop.revindex` and `loop.revindex0` need an \
+                   `ExactSizeIterator` if the feature \"alloc\" is not enabled",
+        label = "`{Self}` is not an `ExactSizeIterator`",
+    )
+)]
+pub trait LoopLength: Iterator + Sized {
+    /// Returns the iterator to use in the loop, and its number of items
+    fn into_loop_iter(self) -> (LoopIter<Self>, usize);
+}
+
+#[cfg(feature = "alloc")]
+impl<I: Iterator> LoopLength for I {
+    #[inline]
+    fn into_loop_iter(self) -> (LoopIter<Self>, usize) {
+        match self.size_hint() {
+            (lower, Some(upper)) if lower == upper => (LoopIter::Exact(self), lower),
+            _ => {
+                let items = self.collect::<alloc::vec::Vec<_>>();
+                let length = items.len();
+                (LoopIter::Collected(items.into_iter()), length)
+            }
         }
     }
 }
 
+#[cfg(not(feature = "alloc"))]
+impl<I: ExactSizeIterator> LoopLength for I {
+    #[inline]
+    fn into_loop_iter(self) -> (LoopIter<Self>, usize) {
+        let length = self.len();
+        (LoopIter::Exact(self), length)
+    }
+}
+
 impl<I> Iterator for TemplateLoop<I>
 where
     I: Iterator,
@@ -43,16 +115,60 @@ where
 
     #[inline]
     fn next(&mut self) -> Option<(<I as Iterator>::Item, LoopItem)> {
-        self.iter.next().map(|(index, item)| {
-            (
-                item,
-                LoopItem {
-                    index,
-                    first: index == 0,
-                    last: self.iter.peek().is_none(),
-                },
-            )
-        })
+        let item = self.next.take()?;
+        self.next = self.iter.next();
+        let index = self.index;
+        self.index += 1;
+        Some((
+            item,
+            LoopItem {
+                index,
+                first: index == 0,
+                last: self.next.is_none(),
+                length: self.length,
+            },
+        ))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let extra = usize::from(self.next.is_some());
+        let (lower, upper) = self.iter.size_hint();
+        (
+            lower.saturating_add(extra),
+            upper.and_then(|upper| upper.checked_add(extra)),
+        )
+    }
+}
+
+/// The source of a [`TemplateLoop`] that needs to know its length
+pub enum LoopIter<I: Iterator> {
+    /// The iterator knows its exact size
+    Exact(I),
+    /// The items had to be collected to know their number
+    #[cfg(feature = "alloc")]
+    Collected(alloc::vec::IntoIter<I::Item>),
+}
+
+impl<I: Iterator> Iterator for LoopIter<I> {
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Exact(iter) => iter.next(),
+            #[cfg(feature = "alloc")]
+            Self::Collected(iter) => iter.next(),
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Self::Exact(iter) => iter.size_hint(),
+            #[cfg(feature = "alloc")]
+            Self::Collected(iter) => iter.size_hint(),
+        }
     }
 }
 
@@ -61,6 +177,112 @@ pub struct LoopItem {
     pub index: usize,
     pub first: bool,
     pub last: bool,
+    /// Only set if the loop was created with [`TemplateLoop::with_length()`]
+    pub length: usize,
+}
+
+/// Implementation of `loop.changed(…)`: returns `true` if `value` differs from the last call
+#[inline]
+pub fn loop_changed<T: PartialEq>(last: &mut Option<T>, value: T) -> bool {
+    if last.as_ref() == Some(&value) {
+        false
+    } else {
+        *last = Some(value);
+        true
+    }
+}
+
+/// Implementation of `loop.previtem`: stores `current` and returns the previous item
+///
+/// The current item is moved into `last`, so the loop body gets a reference to it.
+#[inline]
+pub fn loop_previtem<T>(last: &mut Option<T>, current: T) -> (Option<T>, &T) {
+    let previous = last.take();
+    (previous, last.insert(current))
+}
+
+/// The body of a `{% for … recursive %}` loop, that can be called again for `loop(children)`
+pub struct RecursiveLoop<'a, T> {
+    body: &'a RecursiveLoopBody<'a, T>,
+}
+
+type RecursiveLoopBody<'a, T> =
+    dyn Fn(&RecursiveLoop<'a, T>, &mut dyn fmt::Write, T, usize) -> crate::Result<()> + 'a;
+
+impl<'a, T> RecursiveLoop<'a, T> {
+    /// Render the outermost level of the loop
+    #[inline]
+    pub fn run<W: fmt::Write + ?Sized>(
+        dest: &mut W,
+        iter: T,
+        body: &'a RecursiveLoopBody<'a, T>,
+    ) -> crate::Result<()> {
+        Self { body }.render(dest, iter, 1)
+    }
+
+    /// Render the loop over `iter` at the nesting level `depth`, starting at 1
+    #[inline]
+    pub fn render<W: fmt::Write + ?Sized>(
+        &self,
+        dest: &mut W,
+        iter: T,
+        depth: usize,
+    ) -> crate::Result<()> {
+        (self.body)(self, &mut DynWriter(dest), iter, depth)
+    }
+
+    /// Implementation of `loop(children)`, rendering the loop again when written
+    #[inline]
+    pub fn call(&self, iter: T, depth: usize) -> RecursiveLoopCall<'_, 'a, T> {
+        RecursiveLoopCall {
+            parent: self,
+            iter: Cell::new(Some(iter)),
+            depth,
+        }
+    }
+}
+
+pub struct RecursiveLoopCall<'b, 'a, T> {
+    parent: &'b RecursiveLoop<'a, T>,
+    iter: Cell<Option<T>>,
+    depth: usize,
+}
+
+impl<T> FastWritable for RecursiveLoopCall<'_, '_, T> {
+    #[inline]
+    fn write_into<W: fmt::Write + ?Sized>(&self, dest: &mut W) -> crate::Result<()> {
+        match self.iter.take() {
+            Some(iter) => self.parent.render(dest, iter, self.depth),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T> fmt::Display for RecursiveLoopCall<'_, '_, T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_into(f).map_err(|_| fmt::Error)
+    }
+}
+
+/// Makes a possibly unsized writer usable as `&mut dyn fmt::Write`
+struct DynWriter<'a, W: ?Sized>(&'a mut W);
+
+impl<W: fmt::Write + ?Sized> fmt::Write for DynWriter<'_, W> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.write_str(s)
+    }
+
+    #[inline]
+    fn write_char(&mut self, c: char) -> fmt::Result {
+        self.0.write_char(c)
+    }
+
+    #[inline]
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> fmt::Result {
+        self.0.write_fmt(args)
+    }
 }
 
 pub struct FmtCell<F> {
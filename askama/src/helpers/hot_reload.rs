@@ -0,0 +1,285 @@
+//! Runtime support for the feature `"hot-reload"`
+//!
+//! In debug builds, `#[derive(Template)]` checks on every render if one of the template files
+//! was modified since the program was compiled. If so, the template is rendered by the
+//! [`interpreter`][crate::interpreter] instead, using a [`Reflect`] implementation of the
+//! template struct to access its fields.
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::time::SystemTime;
+
+use serde::Serialize;
+
+use crate::filters::Escaper;
+use crate::interpreter::{Environment, Syntax, Value, Whitespace};
+
+/// The name and directories of a [`Template`]
+type EnvironmentKey = (&'static str, &'static [&'static str]);
+
+/// A template as it was compiled by `#[derive(Template)]`
+pub struct Template {
+    /// Name of the template, relative to the template directory that contains it
+    pub path: &'static str,
+    /// Paths and compiled source code of all used template files
+    pub sources: &'static [(&'static str, &'static str)],
+    /// The configured template directories
+    pub dirs: &'static [&'static str],
+    /// `[block_start, block_end, expr_start, expr_end, comment_start, comment_end]`
+    pub syntax: [&'static str; 6],
+    pub whitespace: Whitespace,
+}
+
+impl Template {
+    /// Returns `true` if any of the used template files differs from the compiled source
+    ///
+    /// A file is only read again if its modification time changed since the last check.
+    fn is_modified(&self) -> bool {
+        static MODIFIED: OnceLock<Mutex<HashMap<&'static str, (SystemTime, bool)>>> =
+            OnceLock::new();
+
+        let mut cache = MODIFIED
+            .get_or_init(Mutex::default)
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        self.sources.iter().any(|&(path, compiled)| {
+            let Ok(time) = std::fs::metadata(path).and_then(|metadata| metadata.modified()) else {
+                return true;
+            };
+            if let Some(&(cached_time, modified)) = cache.get(path) {
+                if cached_time == time {
+                    return modified;
+                }
+            }
+
+            let modified = match std::fs::read_to_string(path) {
+                Ok(mut source) => {
+                    // the derive macro drops a single trailing newline, too
+                    if source.ends_with('\n') {
+                        let _ = source.pop();
+                    }
+                    source != compiled
+                }
+                Err(_) => true,
+            };
+            cache.insert(path, (time, modified));
+            modified
+        })
+    }
+
+    /// Returns `true` if a failure to reload this template should be reported
+    ///
+    /// An error is reported once for every state of the template files, so it is not repeated
+    /// on every render, but again after the files were modified.
+    fn should_report_error(&self) -> bool {
+        static REPORTED: OnceLock<Mutex<HashMap<EnvironmentKey, Vec<Option<SystemTime>>>>> =
+            OnceLock::new();
+
+        let times = self
+            .sources
+            .iter()
+            .map(|&(path, _)| {
+                std::fs::metadata(path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+            })
+            .collect::<Vec<_>>();
+        let mut reported = REPORTED
+            .get_or_init(Mutex::default)
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        reported.insert((self.path, self.dirs), times.clone()) != Some(times)
+    }
+
+    fn environment<E>(&'static self, escaper: E) -> Result<Arc<Environment>, crate::Error>
+    where
+        E: Escaper + Send + Sync + 'static,
+    {
+        // Keyed by the template name and the directories, because the same name can refer to
+        // different files in different crates.
+        static ENVIRONMENTS: OnceLock<Mutex<HashMap<EnvironmentKey, Arc<Environment>>>> =
+            OnceLock::new();
+
+        let mut environments = ENVIRONMENTS
+            .get_or_init(Mutex::default)
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(env) = environments.get(&(self.path, self.dirs)) {
+            return Ok(Arc::clone(env));
+        }
+
+        let [
+            block_start,
+            block_end,
+            expr_start,
+            expr_end,
+            comment_start,
+            comment_end,
+        ] = self.syntax.map(|delim| Some(delim.into()));
+        let mut env = Environment::new();
+        env.set_syntax(Syntax {
+            block_start,
+            block_end,
+            expr_start,
+            expr_end,
+            comment_start,
+            comment_end,
+        })?;
+        env.set_whitespace(self.whitespace);
+        let ext = Path::new(self.path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default();
+        env.set_escaper([String::from(ext)], escaper);
+        for dir in self.dirs {
+            env.add_dir(dir);
+        }
+
+        let env = Arc::new(env);
+        environments.insert((self.path, self.dirs), Arc::clone(&env));
+        Ok(env)
+    }
+}
+
+/// Render `template` with the interpreter if one of its files was modified
+///
+/// Returns `None` if the compiled code should be used instead, i.e. if the template files are
+/// unchanged, or if the interpreter could not render the modified template.
+pub fn render_into<W, E, T>(
+    writer: &mut W,
+    template: &'static Template,
+    escaper: E,
+    this: &T,
+) -> Option<crate::Result<()>>
+where
+    W: fmt::Write + ?Sized,
+    E: Escaper + Send + Sync + 'static,
+    T: Reflect + ?Sized,
+{
+    if !template.is_modified() {
+        return None;
+    }
+
+    // Render into a buffer first, so nothing is written if we have to fall back.
+    let mut buf = String::new();
+    let result = template
+        .environment(escaper)
+        .and_then(|env| Ok(env.render_value_into(&mut buf, template.path, &this.reflect())?));
+    match result {
+        Ok(()) => Some(writer.write_str(&buf).map_err(Into::into)),
+        Err(err) => {
+            if template.should_report_error() {
+                std::eprintln!(
+                    "askama: could not hot-reload template {:?}, using the compiled template \
+                    instead: {err}",
+                    template.path,
+                );
+            }
+            None
+        }
+    }
+}
+
+/// Access the fields of a template struct, implemented by `#[derive(Template)]`
+pub trait Reflect {
+    fn reflect(&self) -> Value;
+}
+
+/// Collect the reflected fields of a template struct into a [`Value::Map`]
+///
+/// Fields that could not be reflected are left out.
+pub fn fields<const N: usize>(fields: [(&'static str, Option<Value>); N]) -> Value {
+    Value::Map(Arc::new(
+        fields
+            .into_iter()
+            .filter_map(|(name, value)| Some((name.into(), value?)))
+            .collect(),
+    ))
+}
+
+/// A field of a template struct
+///
+/// Autoref-based specialization: `(&&&Field(&self.field)).askama_reflect()` uses the first
+/// applicable implementation, i.e. it serializes the field if possible, or displays it, or
+/// gives up.
+pub struct Field<'a, T: ?Sized>(pub &'a T);
+
+pub trait ReflectSerialize {
+    fn askama_reflect(&self) -> Option<Value>;
+}
+
+impl<T: Serialize + ?Sized> ReflectSerialize for &&Field<'_, T> {
+    #[inline]
+    fn askama_reflect(&self) -> Option<Value> {
+        Value::from_serialize(self.0).ok()
+    }
+}
+
+pub trait ReflectDisplay {
+    fn askama_reflect(&self) -> Option<Value>;
+}
+
+impl<T: fmt::Display + ?Sized> ReflectDisplay for &Field<'_, T> {
+    #[inline]
+    fn askama_reflect(&self) -> Option<Value> {
+        use alloc::string::ToString;
+
+        Some(Value::Str(self.0.to_string().into()))
+    }
+}
+
+pub trait ReflectOpaque {
+    fn askama_reflect(&self) -> Option<Value>;
+}
+
+impl<T: ?Sized> ReflectOpaque for Field<'_, T> {
+    #[inline]
+    fn askama_reflect(&self) -> Option<Value> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::format;
+    use std::fs::File;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_report_error_once_per_modification() {
+        let path = std::env::temp_dir().join(format!("askama-report-{}.html", std::process::id()));
+        let set_modified = |seconds| {
+            std::fs::write(&path, "{{ broken").unwrap();
+            File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+                .unwrap();
+        };
+        let path_str: &'static str = Box::leak(path.to_str().unwrap().into());
+        let template = Template {
+            path: "report.html",
+            sources: Box::leak(Box::new([(path_str, "")])),
+            dirs: &[],
+            syntax: ["{%", "%}", "{{", "}}", "{#", "#}"],
+            whitespace: Whitespace::Preserve,
+        };
+
+        set_modified(1);
+        assert!(template.should_report_error());
+        assert!(!template.should_report_error());
+        set_modified(2);
+        assert!(template.should_report_error());
+        assert!(!template.should_report_error());
+        let _ = std::fs::remove_file(&path);
+    }
+}
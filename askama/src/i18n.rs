@@ -0,0 +1,256 @@
+//! Translations with [Fluent](https://projectfluent.org/)
+//!
+//! With the feature `"i18n"` enabled, templates can use the built-in function `t()` to look up
+//! a message in the `.ftl` files of the configured `[i18n]` directory:
+//!
+//! ```text
+//! {{ t("welcome-user", name = user.name) }}
+//! ```
+//!
+//! The named arguments are converted into Fluent values with the trait [`Argument`].
+//! See the [book](https://askama.readthedocs.io/en/stable/) for more information.
+
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use std::sync::OnceLock;
+
+#[doc(no_inline)]
+pub use fluent_bundle::FluentValue;
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+#[doc(no_inline)]
+pub use unic_langid::LanguageIdentifier;
+
+use crate::{Values, get_value};
+
+/// A value that can be used as a named argument of `t()`
+pub trait Argument {
+    /// Convert the value into a [`FluentValue`]
+    fn to_fluent_value(&self) -> FluentValue<'_>;
+}
+
+macro_rules! impl_argument_for_number {
+    ($($ty:ty)*) => { $(
+        impl Argument for $ty {
+            #[inline]
+            fn to_fluent_value(&self) -> FluentValue<'_> {
+                FluentValue::from(*self)
+            }
+        }
+    )* };
+}
+
+impl_argument_for_number!(i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize f32 f64);
+
+impl Argument for str {
+    #[inline]
+    fn to_fluent_value(&self) -> FluentValue<'_> {
+        FluentValue::from(self)
+    }
+}
+
+impl Argument for String {
+    #[inline]
+    fn to_fluent_value(&self) -> FluentValue<'_> {
+        FluentValue::from(self.as_str())
+    }
+}
+
+impl Argument for Cow<'_, str> {
+    #[inline]
+    fn to_fluent_value(&self) -> FluentValue<'_> {
+        FluentValue::from(&**self)
+    }
+}
+
+impl Argument for bool {
+    #[inline]
+    fn to_fluent_value(&self) -> FluentValue<'_> {
+        FluentValue::from(if *self { "true" } else { "false" })
+    }
+}
+
+impl Argument for char {
+    #[inline]
+    fn to_fluent_value(&self) -> FluentValue<'_> {
+        FluentValue::from(self.to_string())
+    }
+}
+
+impl<T: Argument> Argument for Option<T> {
+    #[inline]
+    fn to_fluent_value(&self) -> FluentValue<'_> {
+        match self {
+            Some(value) => value.to_fluent_value(),
+            None => FluentValue::None,
+        }
+    }
+}
+
+crate::impl_for_ref! {
+    impl Argument for T {
+        #[inline]
+        fn to_fluent_value(&self) -> FluentValue<'_> {
+            T::to_fluent_value(self)
+        }
+    }
+}
+
+/// The Fluent resources of a template, as they were loaded by `#[derive(Template)]`
+#[doc(hidden)]
+pub struct Resources {
+    default_locale: &'static str,
+    /// The name of each locale, and the source code of its `.ftl` files
+    locales: &'static [(&'static str, &'static [&'static str])],
+    bundles: OnceLock<Bundles>,
+}
+
+struct Bundles {
+    default: usize,
+    bundles: Vec<(LanguageIdentifier, FluentBundle<FluentResource>)>,
+}
+
+impl Resources {
+    #[inline]
+    pub const fn new(
+        default_locale: &'static str,
+        locales: &'static [(&'static str, &'static [&'static str])],
+    ) -> Self {
+        Self {
+            default_locale,
+            locales,
+            bundles: OnceLock::new(),
+        }
+    }
+
+    fn bundles(&self) -> crate::Result<&Bundles> {
+        if let Some(bundles) = self.bundles.get() {
+            return Ok(bundles);
+        }
+
+        let mut default = 0;
+        let mut bundles = Vec::with_capacity(self.locales.len());
+        for (index, &(name, sources)) in self.locales.iter().enumerate() {
+            if name == self.default_locale {
+                default = index;
+            }
+            let langid: LanguageIdentifier = name.parse().map_err(crate::Error::custom)?;
+            let mut bundle = FluentBundle::new_concurrent(Vec::from([langid.clone()]));
+            bundle.set_use_isolating(false);
+            for &source in sources {
+                // The resources were already validated by the derive macro.
+                let resource =
+                    FluentResource::try_new(source.into()).unwrap_or_else(|(resource, _)| resource);
+                bundle.add_resource_overriding(resource);
+            }
+            bundles.push((langid, bundle));
+        }
+        Ok(self.bundles.get_or_init(|| Bundles { default, bundles }))
+    }
+}
+
+/// Implemented for every type, so `#[derive(Template)]` can make sure that a template does not
+/// have an own method `t()`: the inherent method would be called instead of [`NoMethodT::t()`],
+/// and the mismatched types are reported as a compile error
+#[doc(hidden)]
+pub trait NoMethodT {
+    #[inline]
+    fn t(&self, _: TemplateMethodTIsShadowedByTranslationFunction) -> TranslationFunction {
+        TranslationFunction
+    }
+}
+
+impl<T: ?Sized> NoMethodT for T {}
+
+/// The argument of [`NoMethodT::t()`], its name is shown in the compile error
+#[doc(hidden)]
+pub struct TemplateMethodTIsShadowedByTranslationFunction;
+
+/// The result of [`NoMethodT::t()`]
+#[doc(hidden)]
+pub struct TranslationFunction;
+
+/// Look up and format the message `id`, implementation of the template function `t()`
+///
+/// The locale is read from the template field or variable `locale` if there is one,
+/// otherwise from the [`Values`] key `"locale"`, otherwise the default locale is used.
+/// If the requested locale or the message in it is unavailable, the best matching other locale
+/// is used, i.e. a locale with the same language, or the default locale.
+#[doc(hidden)]
+pub fn translate(
+    resources: &Resources,
+    locale: Option<&dyn fmt::Display>,
+    values: &dyn Values,
+    id: &str,
+    args: &[(&str, FluentValue<'_>)],
+) -> crate::Result<String> {
+    let bundles = resources.bundles()?;
+
+    let requested = match locale {
+        Some(locale) => locale.to_string().parse().ok(),
+        None => locale_from_values(values),
+    };
+    let (message_id, attribute) = match id.split_once('.') {
+        Some((message_id, attribute)) => (message_id, Some(attribute)),
+        None => (id, None),
+    };
+
+    let candidates = requested
+        .as_ref()
+        .map(|requested| {
+            let exact = bundles
+                .bundles
+                .iter()
+                .position(|(langid, _)| langid == requested);
+            let language = bundles
+                .bundles
+                .iter()
+                .position(|(langid, _)| langid.language == requested.language);
+            [exact, language]
+        })
+        .unwrap_or_default();
+    for index in candidates.into_iter().flatten().chain([bundles.default]) {
+        let (_, bundle) = &bundles.bundles[index];
+        let Some(message) = bundle.get_message(message_id) else {
+            continue;
+        };
+        let pattern = match attribute {
+            Some(attribute) => message.get_attribute(attribute).map(|attr| attr.value()),
+            None => message.value(),
+        };
+        let Some(pattern) = pattern else {
+            continue;
+        };
+
+        let args = (!args.is_empty()).then(|| {
+            args.iter()
+                .map(|(name, value)| (*name, value.clone()))
+                .collect::<FluentArgs<'_>>()
+        });
+        let mut errors = Vec::new();
+        let mut output = String::new();
+        bundle.write_pattern(&mut output, pattern, args.as_ref(), &mut errors)?;
+        return match errors.into_iter().next() {
+            None => Ok(output),
+            Some(err) => Err(crate::Error::Custom(Box::new(err))),
+        };
+    }
+    Err(crate::Error::custom(alloc::format!(
+        "unknown translation message {id:?}"
+    )))
+}
+
+fn locale_from_values(values: &dyn Values) -> Option<LanguageIdentifier> {
+    if let Ok(locale) = get_value::<LanguageIdentifier>(values, "locale") {
+        Some(locale.clone())
+    } else if let Ok(locale) = get_value::<String>(values, "locale") {
+        locale.parse().ok()
+    } else if let Ok(locale) = get_value::<&str>(values, "locale") {
+        locale.parse().ok()
+    } else {
+        None
+    }
+}
@@ -0,0 +1,1565 @@
+//! Translation of the parsed templates into a tree of [`Op`]s
+//!
+//! The translation follows the code generator of `askama_derive` step by step, so whitespace
+//! control, inheritance, includes and macro calls are resolved exactly like in a compiled
+//! template. Only the evaluation of expressions is left to the [`eval`][super::eval] module.
+
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt::Write;
+use core::mem;
+use std::collections::HashMap;
+
+use askama_parser::node::{
+    BlockDef, Call, Caller, CondTest, FilterBlock, If, Include, IncludeTarget, Let, LetBlock, Lit,
+    Loop, Macro, Match, Whitespace, Ws,
+};
+use askama_parser::{Expr, Filter, Node, Span, Target, WithSpan};
+
+use super::Sources;
+use super::filters::UNSUPPORTED_FILTERS;
+
+/// A single instruction for the evaluator
+pub(crate) enum Op<'a> {
+    /// Literal text, after whitespace control was applied
+    Lit(Cow<'a, str>),
+    /// `{{ expr }}`
+    Expr(&'a WithSpan<'a, Expr<'a>>),
+    /// `{% let target = value %}` or `{% let target %}`
+    Let(&'a WithSpan<'a, Let<'a>>),
+    /// `{% set target %}…{% endset %}`: the output of the body is assigned to the target
+    LetBlock(&'a WithSpan<'a, Let<'a>>, Vec<Op<'a>>),
+    /// `{% if %}`: the first branch with a satisfied condition is executed
+    If(Vec<Branch<'a>>),
+    /// `{% match %}`: the first arm with a matching pattern is executed
+    Match(&'a WithSpan<'a, Expr<'a>>, Vec<Arm<'a>>),
+    /// `{% for %}`
+    Loop(Box<LoopOp<'a>>),
+    /// `{% break %}`
+    Break,
+    /// `{% continue %}`
+    Continue,
+    /// A new variable scope, used for included templates and blocks
+    Scope(Vec<Op<'a>>),
+    /// `{% include expr from [...] %}`: the candidate named by the value of the expression is
+    /// executed in a new scope
+    DynamicInclude(&'a WithSpan<'a, Expr<'a>>, Vec<(&'a str, Vec<Op<'a>>)>),
+    /// `{% include … with a = x only %}`: the values are evaluated in the current scope, and
+    /// bound in a new scope for the body. With `only`, the body cannot see anything else.
+    IncludeWith(&'a WithSpan<'a, Include<'a>>, Vec<Op<'a>>),
+    /// A macro call: the arguments are bound one after another in a new scope, then the body is
+    /// executed in the same scope. The flag is set if the call has a `{% call(…) %}` body.
+    Call(
+        Vec<(&'a str, &'a WithSpan<'a, Expr<'a>>)>,
+        Vec<Op<'a>>,
+        bool,
+    ),
+    /// `{{ caller(…) }}`: the arguments are evaluated in the scope of the macro, then the body
+    /// of the `{% call(…) %}` block is executed in the scope of the call site
+    Caller(&'a [WithSpan<'a, Expr<'a>>], &'a [&'a str], Vec<Op<'a>>),
+    /// `{% filter %}`: the filter is applied to the output of the body
+    FilterBlock(&'a WithSpan<'a, FilterBlock<'a>>, Vec<Op<'a>>),
+}
+
+pub(crate) struct Branch<'a> {
+    /// `None` if the branch is taken unconditionally
+    pub(crate) cond: Option<&'a CondTest<'a>>,
+    pub(crate) body: Vec<Op<'a>>,
+}
+
+pub(crate) struct Arm<'a> {
+    pub(crate) targets: &'a [Target<'a>],
+    pub(crate) guard: Option<&'a WithSpan<'a, Expr<'a>>>,
+    pub(crate) body: Vec<Op<'a>>,
+}
+
+pub(crate) struct LoopOp<'a> {
+    pub(crate) node: &'a WithSpan<'a, Loop<'a>>,
+    pub(crate) body: Vec<Op<'a>>,
+    /// `None` if there are no nodes in the `{% else %}` block
+    pub(crate) else_body: Option<Vec<Op<'a>>>,
+}
+
+/// Error during the translation, located at `span`
+pub(crate) struct CompileError<'a> {
+    pub(crate) message: String,
+    pub(crate) span: Span<'a>,
+}
+
+impl<'a> CompileError<'a> {
+    fn new(message: impl ToString, span: Span<'a>) -> Self {
+        Self {
+            message: message.to_string(),
+            span,
+        }
+    }
+}
+
+type Result<'a, T = ()> = core::result::Result<T, CompileError<'a>>;
+
+/// Top-level items of a template, cf. `askama_derive::heritage::Context`
+#[derive(Clone)]
+pub(crate) struct Context<'a> {
+    /// Name of the template
+    name: &'a str,
+    nodes: &'a [Node<'a>],
+    extends: Option<&'a str>,
+    blocks: HashMap<&'a str, &'a BlockDef<'a>>,
+    macros: HashMap<&'a str, &'a Macro<'a>>,
+    imports: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> Context<'a> {
+    pub(crate) fn new(sources: &'a Sources, name: &'a str) -> Result<'a, Self> {
+        let mut extends = None;
+        let mut blocks = HashMap::new();
+        let mut macros = HashMap::new();
+        let mut imports = HashMap::new();
+        let nodes = sources.nodes(name);
+        let mut nested = vec![nodes];
+        let mut top = true;
+
+        while let Some(nodes) = nested.pop() {
+            for n in nodes {
+                match n {
+                    Node::Extends(e) => {
+                        ensure_top(top, e.span(), "extends")?;
+                        if extends.is_some() {
+                            return Err(CompileError::new(
+                                "multiple extend blocks found",
+                                e.span(),
+                            ));
+                        }
+                        extends = Some(sources.resolve(name, e.path));
+                    }
+                    Node::Macro(m) => {
+                        ensure_top(top, m.span(), "macro")?;
+                        macros.insert(m.name, &**m);
+                    }
+                    Node::Import(import) => {
+                        ensure_top(top, import.span(), "import")?;
+                        imports.insert(import.scope, sources.resolve(name, import.path));
+                    }
+                    Node::BlockDef(b) => {
+                        blocks.insert(b.name, &**b);
+                        nested.push(&b.nodes);
+                    }
+                    Node::If(i) => {
+                        for cond in &i.branches {
+                            nested.push(&cond.nodes);
+                        }
+                    }
+                    Node::Loop(l) => {
+                        nested.push(&l.body);
+                        nested.push(&l.else_nodes);
+                    }
+                    Node::Match(m) => {
+                        for arm in &m.arms {
+                            nested.push(&arm.nodes);
+                        }
+                    }
+                    Node::Call(c) => {
+                        if let Some(caller) = &c.caller {
+                            nested.push(&caller.nodes);
+                        }
+                    }
+                    Node::Let(l) => {
+                        if let Some(block) = &l.block {
+                            nested.push(&block.nodes);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            top = false;
+        }
+
+        Ok(Context {
+            name,
+            nodes,
+            extends,
+            blocks,
+            macros,
+            imports,
+        })
+    }
+
+    /// Add the macros and imports of `parent` that are not overridden in `self`
+    fn inherit(&mut self, parent: &Context<'a>) {
+        for (name, mac) in &parent.macros {
+            self.macros.entry(name).or_insert(mac);
+        }
+        for (name, import) in &parent.imports {
+            self.imports.entry(name).or_insert(import);
+        }
+    }
+}
+
+fn ensure_top<'a>(top: bool, span: Span<'a>, kind: &str) -> Result<'a> {
+    if top {
+        Ok(())
+    } else {
+        Err(CompileError::new(
+            format_args!("`{kind}` blocks are not allowed below top level"),
+            span,
+        ))
+    }
+}
+
+/// The chain of templates that a template extends
+struct Heritage<'a> {
+    root: Rc<Context<'a>>,
+    blocks: HashMap<&'a str, Vec<(Rc<Context<'a>>, &'a BlockDef<'a>)>>,
+}
+
+impl<'a> Heritage<'a> {
+    fn new(mut root: Rc<Context<'a>>, contexts: &HashMap<&'a str, Rc<Context<'a>>>) -> Self {
+        let mut blocks: HashMap<_, Vec<_>> = HashMap::new();
+        for (name, def) in &root.blocks {
+            blocks
+                .entry(*name)
+                .or_default()
+                .push((Rc::clone(&root), *def));
+        }
+        while let Some(path) = root.extends {
+            root = Rc::clone(&contexts[path]);
+            for (name, def) in &root.blocks {
+                blocks
+                    .entry(*name)
+                    .or_default()
+                    .push((Rc::clone(&root), *def));
+            }
+        }
+        Self { root, blocks }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum AstLevel {
+    Top,
+    Block,
+    Nested,
+}
+
+/// Translates a template into [`Op`]s, cf. `askama_derive::generator::Generator`
+pub(crate) struct Compiler<'a, 'h> {
+    sources: &'a Sources,
+    contexts: &'h HashMap<&'a str, Rc<Context<'a>>>,
+    /// Default whitespace handling
+    whitespace: Whitespace,
+    /// Returns `true` if the template context has a field with the given name
+    is_field: &'h dyn Fn(&str) -> bool,
+    /// Variables that are known in the current scope
+    locals: Vec<Vec<&'a str>>,
+    /// Suffix whitespace from the previous literal
+    next_ws: Option<&'a str>,
+    /// Whitespace suppression from the previous non-literal
+    skip_ws: Whitespace,
+    heritage: Option<Rc<Heritage<'a>>>,
+    /// The block that is currently being rendered, and its generation
+    super_block: Option<(&'a str, usize)>,
+    /// Macro calls that are currently being translated, to detect recursion
+    seen_macros: Vec<&'a Macro<'a>>,
+    /// `{% call(…) %}` bodies of the macro calls that are currently being translated
+    callers: Vec<ActiveCaller<'a>>,
+    is_in_filter_block: usize,
+    /// Set in templates included with `{% include … only %}`
+    is_isolated: bool,
+    /// Number of includes and macro calls that are currently being translated
+    depth: usize,
+    max_depth: usize,
+}
+
+/// The body of a `{% call(…) %}` block, and the state of its call site
+struct ActiveCaller<'a> {
+    caller: &'a Caller<'a>,
+    ctx: Rc<Context<'a>>,
+    locals: usize,
+    seen_macros: usize,
+}
+
+impl<'a, 'h> Compiler<'a, 'h> {
+    pub(crate) fn new(
+        sources: &'a Sources,
+        contexts: &'h HashMap<&'a str, Rc<Context<'a>>>,
+        whitespace: Whitespace,
+        max_depth: usize,
+        is_field: &'h dyn Fn(&str) -> bool,
+    ) -> Self {
+        Self {
+            sources,
+            contexts,
+            whitespace,
+            is_field,
+            locals: vec![Vec::new()],
+            next_ws: None,
+            skip_ws: Whitespace::Preserve,
+            heritage: None,
+            super_block: None,
+            seen_macros: Vec::new(),
+            callers: Vec::new(),
+            is_in_filter_block: 0,
+            is_isolated: false,
+            depth: 0,
+            max_depth,
+        }
+    }
+
+    /// Translate the template `name`, cf. `Generator::impl_template_inner()`
+    pub(crate) fn compile(mut self, name: &str) -> Result<'a, Vec<Op<'a>>> {
+        let ctx = Rc::clone(&self.contexts[name]);
+        let mut out = Vec::new();
+        let root = if !ctx.blocks.is_empty() || ctx.extends.is_some() {
+            let heritage = Rc::new(Heritage::new(ctx, self.contexts));
+            let root = Rc::clone(&heritage.root);
+            self.heritage = Some(heritage);
+            root
+        } else {
+            ctx
+        };
+        self.handle(&root, root.nodes, AstLevel::Top, &mut out)?;
+        self.flush_ws(Ws(None, None), &mut out);
+        Ok(out)
+    }
+
+    fn push_locals<T>(&mut self, callback: impl FnOnce(&mut Self) -> T) -> T {
+        self.locals.push(Vec::new());
+        let res = callback(self);
+        self.locals.pop();
+        res
+    }
+
+    /// Translate an included template or a block with a fresh whitespace state
+    fn with_child<T>(
+        &mut self,
+        heritage: Option<Rc<Heritage<'a>>>,
+        callback: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let next_ws = self.next_ws.take();
+        let skip_ws = mem::replace(&mut self.skip_ws, Whitespace::Preserve);
+        let heritage = mem::replace(&mut self.heritage, heritage);
+        let super_block = self.super_block.take();
+        let seen_macros = mem::take(&mut self.seen_macros);
+
+        let res = self.push_locals(callback);
+
+        self.next_ws = next_ws;
+        self.skip_ws = skip_ws;
+        self.heritage = heritage;
+        self.super_block = super_block;
+        self.seen_macros = seen_macros;
+        res
+    }
+
+    /// Go one include or macro call deeper, unless that exceeds `max_depth`
+    ///
+    /// The caller decrements `depth` again when it is done.
+    fn enter(&mut self, span: Span<'a>) -> Result<'a> {
+        if self.depth >= self.max_depth {
+            return Err(CompileError::new(
+                format_args!(
+                    "includes and macro calls are nested more than {} levels deep",
+                    self.max_depth,
+                ),
+                span,
+            ));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn add_locals(&mut self, target: &'a Target<'a>) {
+        let mut names = mem::take(self.locals.last_mut().unwrap());
+        collect_names(target, &mut names);
+        *self.locals.last_mut().unwrap() = names;
+    }
+
+    fn is_var_defined(&self, name: &str) -> bool {
+        self.locals.iter().flatten().any(|&local| local == name)
+            || (!self.is_isolated && (self.is_field)(name))
+    }
+
+    fn handle(
+        &mut self,
+        ctx: &Context<'a>,
+        nodes: &'a [Node<'a>],
+        level: AstLevel,
+        out: &mut Vec<Op<'a>>,
+    ) -> Result<'a> {
+        for n in nodes {
+            match n {
+                Node::Lit(lit) => self.write_lit(lit, out),
+                Node::Comment(comment) => self.handle_ws(comment.ws, out),
+                Node::Expr(ws, val) if self.is_caller_call(val) => {
+                    self.write_caller(ctx, *ws, val, out)?;
+                }
+                Node::Expr(ws, val) => {
+                    self.handle_ws(*ws, out);
+                    match &**val {
+                        Expr::Concat(exprs) => out.extend(exprs.iter().map(Op::Expr)),
+                        _ => out.push(Op::Expr(val)),
+                    }
+                }
+                Node::Let(l) => match &l.block {
+                    Some(block) => self.write_let_block(ctx, l, block, out)?,
+                    None => {
+                        self.handle_ws(l.ws, out);
+                        self.add_locals(&l.var);
+                        out.push(Op::Let(l));
+                    }
+                },
+                Node::If(i) => self.write_if(ctx, i, out)?,
+                Node::Match(m) => self.write_match(ctx, m, out)?,
+                Node::Loop(l) => self.write_loop(ctx, l, out)?,
+                Node::BlockDef(b) => {
+                    self.write_block(ctx, Some(b.name), Ws(b.ws1.0, b.ws2.1), b.span(), out)?;
+                }
+                Node::Include(i) => self.handle_include(ctx, i, out)?,
+                Node::Call(call) => self.write_call(ctx, call, out)?,
+                Node::FilterBlock(filter) => self.write_filter_block(ctx, filter, out)?,
+                Node::Macro(m) => {
+                    if level != AstLevel::Top {
+                        return Err(CompileError::new(
+                            "macro blocks only allowed at the top level",
+                            m.span(),
+                        ));
+                    }
+                    self.flush_ws(m.ws1, out);
+                    self.prepare_ws(m.ws2);
+                }
+                Node::Raw(raw) => {
+                    self.handle_ws(raw.ws1, out);
+                    self.write_lit(&raw.lit, out);
+                    self.handle_ws(raw.ws2, out);
+                }
+                Node::Import(i) => {
+                    if level != AstLevel::Top {
+                        return Err(CompileError::new(
+                            "import blocks only allowed at the top level",
+                            i.span(),
+                        ));
+                    }
+                    self.handle_ws(i.ws, out);
+                }
+                Node::Extends(e) => {
+                    if level != AstLevel::Top {
+                        return Err(CompileError::new(
+                            "extend blocks only allowed at the top level",
+                            e.span(),
+                        ));
+                    }
+                }
+                Node::Break(ws) => {
+                    self.handle_ws(**ws, out);
+                    out.push(Op::Break);
+                }
+                Node::Continue(ws) => {
+                    self.handle_ws(**ws, out);
+                    out.push(Op::Continue);
+                }
+            }
+        }
+
+        if level == AstLevel::Top && self.next_ws.is_some() {
+            // Handle any pending whitespace.
+            self.flush_ws(Ws(Some(self.skip_ws), None), out);
+        }
+        Ok(())
+    }
+
+    fn write_if(
+        &mut self,
+        ctx: &Context<'a>,
+        if_: &'a WithSpan<'a, If<'a>>,
+        out: &mut Vec<Op<'a>>,
+    ) -> Result<'a> {
+        let conds = self.compute_branches(if_);
+        if let Some(ws_before) = conds.ws_before {
+            self.handle_ws(ws_before, out);
+        }
+
+        let mut branches = Vec::with_capacity(conds.conds.len());
+        let mut iter = conds.conds.iter().enumerate().peekable();
+        while let Some((pos, cond_info)) = iter.next() {
+            let cond = cond_info.cond;
+            if pos == 0 {
+                self.handle_ws(cond.ws, out);
+            }
+
+            let body = self.push_locals(|this| {
+                let mut body = Vec::new();
+                if let Some(CondTest {
+                    target: Some(target),
+                    ..
+                }) = &cond.cond
+                {
+                    this.add_locals(target);
+                }
+                if cond_info.generate_content {
+                    this.handle(ctx, &cond.nodes, AstLevel::Nested, &mut body)?;
+                }
+                if let Some((_, next)) = iter.peek() {
+                    this.handle_ws(next.cond.ws, &mut body);
+                } else {
+                    if let Some(ws_after) = conds.ws_after {
+                        this.handle_ws(ws_after, &mut body);
+                    }
+                    this.handle_ws(if_.ws, &mut body);
+                }
+                Ok(body)
+            })?;
+            let cond = match &cond.cond {
+                Some(test) if cond_info.generate_condition => Some(test),
+                _ => None,
+            };
+            branches.push(Branch { cond, body });
+        }
+
+        out.push(Op::If(branches));
+        Ok(())
+    }
+
+    /// Constant folding of `{% if %}` conditions, cf. `Conds::compute_branches()`
+    fn compute_branches(&self, i: &'a If<'a>) -> Conds<'a> {
+        let mut conds = Vec::with_capacity(i.branches.len());
+        let mut ws_before = None;
+        let mut ws_after = None;
+        let mut stop_loop = false;
+
+        for cond in &i.branches {
+            if stop_loop {
+                ws_after = Some(cond.ws);
+                break;
+            }
+            if let Some(CondTest {
+                expr,
+                contains_bool_lit_or_is_defined,
+                ..
+            }) = &cond.cond
+            {
+                let mut only_contains_is_defined = true;
+                let evaluated_result = if *contains_bool_lit_or_is_defined {
+                    self.evaluate_condition(expr, &mut only_contains_is_defined)
+                } else {
+                    EvaluatedResult::Unknown
+                };
+
+                match evaluated_result {
+                    EvaluatedResult::AlwaysFalse => {
+                        if only_contains_is_defined {
+                            if conds.is_empty() && ws_before.is_none() {
+                                ws_before = Some(cond.ws);
+                            }
+                            continue;
+                        }
+                        conds.push(CondInfo {
+                            cond,
+                            generate_condition: true,
+                            generate_content: false,
+                        });
+                    }
+                    EvaluatedResult::AlwaysTrue => {
+                        conds.push(CondInfo {
+                            cond,
+                            generate_condition: !only_contains_is_defined,
+                            generate_content: true,
+                        });
+                        stop_loop = true;
+                    }
+                    EvaluatedResult::Unknown => {
+                        conds.push(CondInfo {
+                            cond,
+                            generate_condition: true,
+                            generate_content: true,
+                        });
+                    }
+                }
+            } else {
+                conds.push(CondInfo {
+                    cond,
+                    generate_condition: !conds.is_empty(),
+                    generate_content: true,
+                });
+            }
+        }
+        Conds {
+            conds,
+            ws_before,
+            ws_after,
+        }
+    }
+
+    /// cf. `Generator::evaluate_condition()`
+    fn evaluate_condition(
+        &self,
+        expr: &WithSpan<'a, Expr<'a>>,
+        only_contains_is_defined: &mut bool,
+    ) -> EvaluatedResult {
+        match &**expr {
+            Expr::BoolLit(true) => EvaluatedResult::AlwaysTrue,
+            Expr::BoolLit(false) => EvaluatedResult::AlwaysFalse,
+            Expr::Unary("!", inner) => {
+                match self.evaluate_condition(inner, only_contains_is_defined) {
+                    EvaluatedResult::AlwaysTrue => EvaluatedResult::AlwaysFalse,
+                    EvaluatedResult::AlwaysFalse => EvaluatedResult::AlwaysTrue,
+                    EvaluatedResult::Unknown => EvaluatedResult::Unknown,
+                }
+            }
+            Expr::Unary(_, _) => EvaluatedResult::Unknown,
+            Expr::BinOp("&&", left, right) => {
+                let left = self.evaluate_condition(left, only_contains_is_defined);
+                if left == EvaluatedResult::AlwaysFalse {
+                    return left;
+                }
+                match (
+                    left,
+                    self.evaluate_condition(right, only_contains_is_defined),
+                ) {
+                    (EvaluatedResult::AlwaysTrue, EvaluatedResult::AlwaysTrue) => {
+                        EvaluatedResult::AlwaysTrue
+                    }
+                    (_, EvaluatedResult::AlwaysFalse) => EvaluatedResult::AlwaysFalse,
+                    (EvaluatedResult::AlwaysTrue, right) => right,
+                    (left, EvaluatedResult::AlwaysTrue) => left,
+                    _ => EvaluatedResult::Unknown,
+                }
+            }
+            Expr::BinOp("||", left, right) => {
+                let left = self.evaluate_condition(left, only_contains_is_defined);
+                if left == EvaluatedResult::AlwaysTrue {
+                    return left;
+                }
+                match (
+                    left,
+                    self.evaluate_condition(right, only_contains_is_defined),
+                ) {
+                    (EvaluatedResult::AlwaysFalse, EvaluatedResult::AlwaysFalse) => {
+                        EvaluatedResult::AlwaysFalse
+                    }
+                    (_, EvaluatedResult::AlwaysTrue) => EvaluatedResult::AlwaysTrue,
+                    (EvaluatedResult::AlwaysFalse, right) => right,
+                    (left, EvaluatedResult::AlwaysFalse) => left,
+                    _ => EvaluatedResult::Unknown,
+                }
+            }
+            Expr::Group(inner) => self.evaluate_condition(inner, only_contains_is_defined),
+            Expr::IsDefined(name) => match self.is_var_defined(name) {
+                true => EvaluatedResult::AlwaysTrue,
+                false => EvaluatedResult::AlwaysFalse,
+            },
+            Expr::IsNotDefined(name) => match self.is_var_defined(name) {
+                true => EvaluatedResult::AlwaysFalse,
+                false => EvaluatedResult::AlwaysTrue,
+            },
+            _ => {
+                *only_contains_is_defined = false;
+                EvaluatedResult::Unknown
+            }
+        }
+    }
+
+    fn write_match(
+        &mut self,
+        ctx: &Context<'a>,
+        m: &'a WithSpan<'a, Match<'a>>,
+        out: &mut Vec<Op<'a>>,
+    ) -> Result<'a> {
+        self.flush_ws(m.ws1, out);
+
+        let mut arms = Vec::with_capacity(m.arms.len());
+        let mut iter = m.arms.iter().enumerate().peekable();
+        while let Some((i, arm)) = iter.next() {
+            if i == 0 {
+                self.handle_ws(arm.ws, out);
+            }
+
+            let body = self.push_locals(|this| {
+                for target in &arm.target {
+                    this.add_locals(target);
+                }
+                let mut body = Vec::new();
+                this.handle(ctx, &arm.nodes, AstLevel::Nested, &mut body)?;
+                match iter.peek() {
+                    Some((_, next)) => this.handle_ws(next.ws, &mut body),
+                    None => this.handle_ws(m.ws2, &mut body),
+                }
+                Ok(body)
+            })?;
+            arms.push(Arm {
+                targets: &arm.target,
+                guard: arm.guard.as_ref(),
+                body,
+            });
+        }
+
+        out.push(Op::Match(&m.expr, arms));
+        Ok(())
+    }
+
+    fn write_loop(
+        &mut self,
+        ctx: &Context<'a>,
+        loop_block: &'a WithSpan<'a, Loop<'a>>,
+        out: &mut Vec<Op<'a>>,
+    ) -> Result<'a> {
+        self.handle_ws(loop_block.ws1, out);
+        self.push_locals(|this| {
+            let body = this.push_locals(|this| {
+                this.add_locals(&loop_block.var);
+                let mut body = Vec::new();
+                this.handle(ctx, &loop_block.body, AstLevel::Nested, &mut body)?;
+                this.handle_ws(loop_block.ws2, &mut body);
+                Ok(body)
+            })?;
+
+            let else_body = if !loop_block.else_nodes.is_empty() {
+                let else_body = this.push_locals(|this| {
+                    let mut else_body = Vec::new();
+                    this.handle(
+                        ctx,
+                        &loop_block.else_nodes,
+                        AstLevel::Nested,
+                        &mut else_body,
+                    )?;
+                    this.handle_ws(loop_block.ws3, &mut else_body);
+                    Ok(else_body)
+                })?;
+                Some(else_body)
+            } else {
+                None
+            };
+
+            out.push(Op::Loop(Box::new(LoopOp {
+                node: loop_block,
+                body,
+                else_body,
+            })));
+            if loop_block.else_nodes.is_empty() {
+                this.handle_ws(loop_block.ws3, out);
+            }
+            Ok(())
+        })
+    }
+
+    fn write_call(
+        &mut self,
+        ctx: &Context<'a>,
+        call: &'a WithSpan<'a, Call<'a>>,
+        out: &mut Vec<Op<'a>>,
+    ) -> Result<'a> {
+        let Call {
+            ws, scope, name, ..
+        } = **call;
+        if name == "super" {
+            return self.write_block(ctx, None, ws, call.span(), out);
+        }
+
+        let (def, own_ctx) = if let Some(s) = scope {
+            let path = ctx.imports.get(s).ok_or_else(|| {
+                CompileError::new(format_args!("no import found for scope {s:?}"), call.span())
+            })?;
+            let mctx = &self.contexts[path];
+            let def = mctx.macros.get(name).ok_or_else(|| {
+                CompileError::new(
+                    format_args!("macro {name:?} not found in scope {s:?}"),
+                    call.span(),
+                )
+            })?;
+            (*def, Rc::clone(mctx))
+        } else {
+            let def = ctx.macros.get(name).ok_or_else(|| {
+                CompileError::new(format_args!("macro {name:?} not found"), call.span())
+            })?;
+            (*def, Rc::new(ctx.clone()))
+        };
+
+        if self.seen_macros.iter().any(|s| core::ptr::eq(*s, def)) {
+            let mut message = "Found recursion in macro calls:".to_string();
+            for m in &self.seen_macros {
+                let _ = write!(message, "\n`{}`", m.name.escape_debug());
+            }
+            return Err(CompileError::new(message, call.span()));
+        }
+        let active_caller = call.caller.as_ref().map(|caller| ActiveCaller {
+            caller,
+            ctx: Rc::new(ctx.clone()),
+            locals: self.locals.len(),
+            seen_macros: self.seen_macros.len(),
+        });
+        self.enter(call.span())?;
+        self.seen_macros.push(def);
+
+        // Cannot handle_ws() here: whitespace from macro definition comes first
+        self.flush_ws(ws, out);
+        let res = self.push_locals(|this| {
+            let bindings = bind_macro_args(call, def)?;
+            this.prepare_ws(def.ws1);
+            this.locals
+                .last_mut()
+                .unwrap()
+                .extend(bindings.iter().map(|(name, _)| *name));
+
+            let has_caller = active_caller.is_some();
+            this.callers.extend(active_caller);
+            let mut body = Vec::new();
+            let res = this.handle(&own_ctx, &def.nodes, AstLevel::Nested, &mut body);
+            if has_caller {
+                this.callers.pop();
+            }
+            res?;
+            this.flush_ws(def.ws2, &mut body);
+            out.push(Op::Call(bindings, body, has_caller));
+            Ok(())
+        });
+        self.prepare_ws(ws);
+        self.seen_macros.pop();
+        self.depth -= 1;
+        res
+    }
+
+    fn is_caller_call(&self, expr: &Expr<'a>) -> bool {
+        match expr {
+            Expr::Call { path, .. } => {
+                matches!(***path, Expr::Var("caller"))
+                    && !self.callers.is_empty()
+                    && !self.locals.iter().flatten().any(|&local| local == "caller")
+            }
+            _ => false,
+        }
+    }
+
+    /// Translate `{{ caller(…) }}`, cf. `Generator::write_caller()`
+    fn write_caller(
+        &mut self,
+        ctx: &Context<'a>,
+        ws: Ws,
+        expr: &'a WithSpan<'a, Expr<'a>>,
+        out: &mut Vec<Op<'a>>,
+    ) -> Result<'a> {
+        let Expr::Call { args, .. } = &**expr else {
+            unreachable!("checked by is_caller_call()")
+        };
+        let active = self.callers.pop().unwrap();
+        let res = self.write_caller_inner(ctx, ws, expr.span(), args, &active, out);
+        self.callers.push(active);
+        res
+    }
+
+    fn write_caller_inner(
+        &mut self,
+        _ctx: &Context<'a>,
+        ws: Ws,
+        span: Span<'a>,
+        args: &'a [WithSpan<'a, Expr<'a>>],
+        active: &ActiveCaller<'a>,
+        out: &mut Vec<Op<'a>>,
+    ) -> Result<'a> {
+        let caller = active.caller;
+        if let Some(arg) = args
+            .iter()
+            .find(|arg| matches!(***arg, Expr::NamedArgument(..)))
+        {
+            return Err(CompileError::new(
+                "named arguments are not supported by `caller(…)`",
+                arg.span(),
+            ));
+        }
+        if args.len() != caller.args.len() {
+            return Err(CompileError::new(
+                format_args!(
+                    "`caller(…)` expects {} argument{}, found {}",
+                    caller.args.len(),
+                    if caller.args.len() == 1 { "" } else { "s" },
+                    args.len(),
+                ),
+                span,
+            ));
+        }
+
+        self.flush_ws(ws, out);
+        // The body only sees the locals of the call site.
+        let locals = self.locals.split_off(active.locals);
+        let seen_macros = self.seen_macros.split_off(active.seen_macros);
+        let res = self.push_locals(|this| {
+            this.locals.last_mut().unwrap().extend(&caller.args);
+            this.prepare_ws(caller.ws1);
+            let mut body = Vec::new();
+            this.handle(&active.ctx, &caller.nodes, AstLevel::Nested, &mut body)?;
+            this.flush_ws(caller.ws2, &mut body);
+            Ok(body)
+        });
+        self.locals.extend(locals);
+        self.seen_macros.extend(seen_macros);
+        out.push(Op::Caller(args, &caller.args, res?));
+        self.prepare_ws(ws);
+        Ok(())
+    }
+
+    fn write_let_block(
+        &mut self,
+        ctx: &Context<'a>,
+        l: &'a WithSpan<'a, Let<'a>>,
+        block: &'a LetBlock<'a>,
+        out: &mut Vec<Op<'a>>,
+    ) -> Result<'a> {
+        self.flush_ws(block.ws1, out);
+        let body = self.push_locals(|this| {
+            this.prepare_ws(block.ws1);
+            let mut body = Vec::new();
+            this.handle(ctx, &block.nodes, AstLevel::Nested, &mut body)?;
+            this.flush_ws(block.ws2, &mut body);
+            Ok(body)
+        })?;
+        self.add_locals(&l.var);
+        out.push(Op::LetBlock(l, body));
+        self.prepare_ws(block.ws2);
+        Ok(())
+    }
+
+    fn write_filter_block(
+        &mut self,
+        ctx: &Context<'a>,
+        filter: &'a WithSpan<'a, FilterBlock<'a>>,
+        out: &mut Vec<Op<'a>>,
+    ) -> Result<'a> {
+        self.flush_ws(filter.ws1, out);
+        self.is_in_filter_block += 1;
+        let body = self.push_locals(|this| {
+            this.prepare_ws(filter.ws1);
+            let mut body = Vec::new();
+            this.handle(ctx, &filter.nodes, AstLevel::Nested, &mut body)?;
+            this.flush_ws(filter.ws2, &mut body);
+            Ok(body)
+        });
+        self.is_in_filter_block -= 1;
+        out.push(Op::FilterBlock(filter, body?));
+        self.prepare_ws(filter.ws2);
+        Ok(())
+    }
+
+    fn handle_include(
+        &mut self,
+        ctx: &Context<'a>,
+        i: &'a WithSpan<'a, Include<'a>>,
+        out: &mut Vec<Op<'a>>,
+    ) -> Result<'a> {
+        self.flush_ws(i.ws, out);
+        if i.with.is_empty() && !i.only {
+            self.write_include_target(ctx, i, out)?;
+        } else {
+            // With `only`, the included template cannot see anything but the passed variables.
+            let names = i.with.iter().map(|&(name, _)| name).collect();
+            let hidden = i.only.then(|| {
+                (
+                    mem::take(&mut self.locals),
+                    mem::take(&mut self.callers),
+                    mem::replace(&mut self.is_isolated, true),
+                )
+            });
+            self.locals.push(names);
+            let mut body = Vec::new();
+            let res = self.write_include_target(ctx, i, &mut body);
+            self.locals.pop();
+            if let Some((locals, callers, is_isolated)) = hidden {
+                self.locals = locals;
+                self.callers = callers;
+                self.is_isolated = is_isolated;
+            }
+            res?;
+            out.push(Op::IncludeWith(i, body));
+        }
+        self.prepare_ws(i.ws);
+        Ok(())
+    }
+
+    fn write_include_target(
+        &mut self,
+        ctx: &Context<'a>,
+        i: &'a WithSpan<'a, Include<'a>>,
+        out: &mut Vec<Op<'a>>,
+    ) -> Result<'a> {
+        match &i.target {
+            IncludeTarget::Path(_) | IncludeTarget::Fallback(_) => {
+                // Only the first template that was found is included, if any.
+                let found = i
+                    .target
+                    .paths()
+                    .iter()
+                    .find(|path| self.sources.is_resolved(ctx.name, path));
+                if let Some(path) = found {
+                    let body = self.compile_include(ctx, path, i.span())?;
+                    out.push(Op::Scope(body));
+                }
+            }
+            IncludeTarget::Dynamic(expr, paths) => {
+                let candidates = paths
+                    .iter()
+                    .map(|&path| {
+                        // A template that is missing with `ignore missing` renders nothing.
+                        if self.sources.is_resolved(ctx.name, path) {
+                            Ok((path, self.compile_include(ctx, path, i.span())?))
+                        } else {
+                            Ok((path, Vec::new()))
+                        }
+                    })
+                    .collect::<Result<'a, _>>()?;
+                out.push(Op::DynamicInclude(expr, candidates));
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_include(
+        &mut self,
+        ctx: &Context<'a>,
+        path: &'a str,
+        span: Span<'a>,
+    ) -> Result<'a, Vec<Op<'a>>> {
+        let path = self.sources.resolve(ctx.name, path);
+        let mut child_ctx = Context::clone(&self.contexts[path]);
+        child_ctx.inherit(ctx);
+        let child_ctx = Rc::new(child_ctx);
+
+        let heritage = if !child_ctx.blocks.is_empty() || child_ctx.extends.is_some() {
+            Some(Rc::new(Heritage::new(Rc::clone(&child_ctx), self.contexts)))
+        } else {
+            None
+        };
+        let handle_ctx = match &heritage {
+            Some(heritage) => Rc::clone(&heritage.root),
+            None => child_ctx,
+        };
+
+        self.enter(span)?;
+        let res = self.with_child(heritage, |child| {
+            let mut body = Vec::new();
+            child.handle(&handle_ctx, handle_ctx.nodes, AstLevel::Top, &mut body)?;
+            Ok(body)
+        });
+        self.depth -= 1;
+        res
+    }
+
+    /// If `name` is `Some`, this is a call to a block definition, and we have to find the first
+    /// block for that name from the ancestry chain. If name is `None`, this is from a `super()`
+    /// call, and we can get the name from `self.super_block`.
+    fn write_block(
+        &mut self,
+        ctx: &Context<'a>,
+        name: Option<&'a str>,
+        outer: Ws,
+        node: Span<'a>,
+        out: &mut Vec<Op<'a>>,
+    ) -> Result<'a> {
+        if self.is_in_filter_block > 0 {
+            return Err(CompileError::new(
+                "cannot have a block inside a filter block",
+                node,
+            ));
+        }
+        // Flush preceding whitespace according to the outer WS spec
+        self.flush_ws(outer, out);
+
+        let cur = match (name, self.super_block) {
+            // The top-level context contains a block definition
+            (Some(cur_name), None) => (cur_name, 0),
+            // A block definition contains a block definition of the same name
+            (Some(cur_name), Some((prev_name, _))) if cur_name == prev_name => {
+                return Err(CompileError::new(
+                    format_args!("cannot define recursive blocks ({cur_name})"),
+                    node,
+                ));
+            }
+            // A block definition contains a definition of another block
+            (Some(cur_name), Some((_, _))) => (cur_name, 0),
+            // `super()` was called inside a block
+            (None, Some((prev_name, generation))) => (prev_name, generation + 1),
+            // `super()` is called from outside a block
+            (None, None) => {
+                return Err(CompileError::new(
+                    "cannot call 'super()' outside block",
+                    node,
+                ));
+            }
+        };
+
+        // Get the block definition from the heritage chain
+        let heritage = self
+            .heritage
+            .clone()
+            .ok_or_else(|| CompileError::new("no block ancestors available", node))?;
+        let (child_ctx, def) = heritage.blocks[cur.0].get(cur.1).ok_or_else(|| {
+            CompileError::new(
+                match name {
+                    None => format!("no super() block found for block '{}'", cur.0),
+                    Some(name) => format!("no block found for name '{name}'"),
+                },
+                node,
+            )
+        })?;
+        let def = *def;
+        let mut child_ctx = Context::clone(child_ctx);
+        child_ctx.inherit(ctx);
+
+        let body = self.with_child(Some(Rc::clone(&heritage)), |child| {
+            // Handle inner whitespace suppression spec and process block nodes
+            child.prepare_ws(def.ws1);
+            child.super_block = Some(cur);
+            let mut body = Vec::new();
+            child.handle(&child_ctx, &def.nodes, AstLevel::Block, &mut body)?;
+            child.flush_ws(def.ws2, &mut body);
+            Ok(body)
+        })?;
+        out.push(Op::Scope(body));
+
+        // Restore original block context and set whitespace suppression for
+        // succeeding whitespace according to the outer WS spec
+        self.prepare_ws(outer);
+        Ok(())
+    }
+
+    fn write_lit(&mut self, lit: &'a Lit<'a>, out: &mut Vec<Op<'a>>) {
+        let Lit { lws, val, rws } = *lit;
+        if !lws.is_empty() {
+            match self.skip_ws {
+                Whitespace::Suppress => {}
+                _ if val.is_empty() => self.next_ws = Some(lws),
+                Whitespace::Preserve => push_lit(out, lws),
+                Whitespace::Minimize => push_lit(out, minimized(lws)),
+            }
+        }
+
+        if !val.is_empty() {
+            self.skip_ws = Whitespace::Preserve;
+            push_lit(out, val);
+        }
+
+        if !rws.is_empty() {
+            self.next_ws = Some(rws);
+        }
+    }
+
+    /// Combines `flush_ws()` and `prepare_ws()` to handle both trailing whitespace from the
+    /// preceding literal and leading whitespace from the succeeding literal.
+    fn handle_ws(&mut self, ws: Ws, out: &mut Vec<Op<'a>>) {
+        self.flush_ws(ws, out);
+        self.prepare_ws(ws);
+    }
+
+    fn should_trim_ws(&self, ws: Option<Whitespace>) -> Whitespace {
+        ws.unwrap_or(self.whitespace)
+    }
+
+    /// If the previous literal left some trailing whitespace in `next_ws` and the
+    /// prefix whitespace suppressor from the given argument, flush that whitespace.
+    /// In either case, `next_ws` is reset to `None` (no trailing whitespace).
+    fn flush_ws(&mut self, ws: Ws, out: &mut Vec<Op<'a>>) {
+        let Some(val) = self.next_ws.take() else {
+            return;
+        };
+        if val.is_empty() {
+            return;
+        }
+        match self.should_trim_ws(ws.0) {
+            Whitespace::Preserve => push_lit(out, val),
+            Whitespace::Minimize => push_lit(out, minimized(val)),
+            Whitespace::Suppress => {}
+        }
+    }
+
+    /// Sets `skip_ws` to match the suffix whitespace suppressor from the given
+    /// argument, to determine whether to suppress leading whitespace from the
+    /// next literal.
+    fn prepare_ws(&mut self, ws: Ws) {
+        self.skip_ws = self.should_trim_ws(ws.1);
+    }
+}
+
+fn minimized(ws: &str) -> &'static str {
+    match ws.contains('\n') {
+        true => "\n",
+        false => " ",
+    }
+}
+
+/// Append `s` to the output, merging it with a preceding literal
+fn push_lit<'a>(out: &mut Vec<Op<'a>>, s: &'a str) {
+    if let Some(Op::Lit(prev)) = out.last_mut() {
+        prev.to_mut().push_str(s);
+    } else {
+        out.push(Op::Lit(Cow::Borrowed(s)));
+    }
+}
+
+/// Match the arguments of a macro call to the parameters of its definition
+fn bind_macro_args<'a>(
+    call: &'a WithSpan<'a, Call<'a>>,
+    def: &'a Macro<'a>,
+) -> Result<'a, Vec<(&'a str, &'a WithSpan<'a, Expr<'a>>)>> {
+    let args = &call.args;
+    let name = call.name;
+    ensure_arg_count(call, def)?;
+
+    let mut named_arguments = HashMap::new();
+    // Since named arguments can only be passed last, we only need to check if the last argument
+    // is a named one.
+    if let Some(Expr::NamedArgument(_, _)) = args.last().map(|expr| &**expr) {
+        // First we check that all named arguments actually exist in the called item.
+        for (index, arg) in args.iter().enumerate().rev() {
+            let Expr::NamedArgument(arg_name, value) = &**arg else {
+                break;
+            };
+            if !def.args.iter().any(|(arg, _)| arg == arg_name) {
+                return Err(CompileError::new(
+                    format_args!("no argument named `{arg_name}` in macro {name:?}"),
+                    call.span(),
+                ));
+            }
+            named_arguments.insert(*arg_name, (index, &**value));
+        }
+    }
+
+    let mut bindings = Vec::with_capacity(def.args.len());
+    let mut allow_positional = true;
+    let mut used_named_args = vec![false; args.len()];
+    for (index, (arg, default_value)) in def.args.iter().enumerate() {
+        let expr = if let Some((index, expr)) = named_arguments.get(arg) {
+            used_named_args[*index] = true;
+            allow_positional = false;
+            *expr
+        } else {
+            match args.get(index) {
+                Some(arg_expr) if !matches!(**arg_expr, Expr::NamedArgument(_, _)) => {
+                    // If there is already at least one named argument, then it's not allowed
+                    // to use unnamed ones at this point anymore.
+                    if !allow_positional {
+                        return Err(CompileError::new(
+                            format_args!(
+                                "cannot have unnamed argument (`{arg}`) after named argument \
+                                 in call to macro {name:?}"
+                            ),
+                            call.span(),
+                        ));
+                    }
+                    arg_expr
+                }
+                Some(arg_expr) if used_named_args[index] => {
+                    let Expr::NamedArgument(name, _) = **arg_expr else {
+                        unreachable!()
+                    };
+                    return Err(CompileError::new(
+                        format_args!("`{name}` is passed more than once"),
+                        call.span(),
+                    ));
+                }
+                _ => match default_value {
+                    Some(default_value) => default_value,
+                    None => {
+                        return Err(CompileError::new(
+                            format_args!("missing `{arg}` argument"),
+                            call.span(),
+                        ));
+                    }
+                },
+            }
+        };
+        bindings.push((*arg, expr));
+    }
+    Ok(bindings)
+}
+
+/// cf. `macro_call_ensure_arg_count()`
+fn ensure_arg_count<'a>(call: &'a WithSpan<'a, Call<'a>>, def: &Macro<'_>) -> Result<'a> {
+    if call.args.len() > def.args.len() {
+        return Err(CompileError::new(
+            format_args!(
+                "macro `{}` expected {} argument{}, found {}",
+                def.name,
+                def.args.len(),
+                if def.args.len() > 1 { "s" } else { "" },
+                call.args.len(),
+            ),
+            call.span(),
+        ));
+    }
+
+    // First we list of arguments position, then we remove every argument with a value.
+    let mut args: Vec<_> = def.args.iter().map(|&(name, _)| Some(name)).collect();
+    for (pos, arg) in call.args.iter().enumerate() {
+        let pos = match **arg {
+            Expr::NamedArgument(name, ..) => {
+                def.args.iter().position(|(arg_name, _)| *arg_name == name)
+            }
+            _ => Some(pos),
+        };
+        if let Some(pos) = pos {
+            if args[pos].take().is_none() {
+                return Err(CompileError::new(
+                    format_args!(
+                        "argument `{}` was passed more than once when calling macro `{}`",
+                        def.args[pos].0, def.name,
+                    ),
+                    call.span(),
+                ));
+            }
+        }
+    }
+
+    // Now we can check off arguments with a default value, too.
+    for (pos, (_, dflt)) in def.args.iter().enumerate() {
+        if dflt.is_some() {
+            args[pos] = None;
+        }
+    }
+
+    let missing: Vec<&str> = args.into_iter().flatten().collect();
+    let message = match missing.as_slice() {
+        [] => return Ok(()),
+        [arg] => format!(
+            "missing argument when calling macro `{}`: `{arg}`",
+            def.name
+        ),
+        [init @ .., last] => {
+            let mut message = format!("missing arguments when calling macro `{}`: ", def.name);
+            for (idx, arg) in init.iter().enumerate() {
+                if idx > 0 {
+                    message.push_str(", ");
+                }
+                let _ = write!(message, "`{arg}`");
+            }
+            let _ = write!(message, " and `{last}`");
+            message
+        }
+    };
+    Err(CompileError::new(message, call.span()))
+}
+
+/// Reject the built-in filters and functions of compiled templates that the interpreter does
+/// not implement, unless a filter or function of the same name was registered
+///
+/// Named arguments are rejected in all filters, because none of the implemented filters takes
+/// them.
+pub(crate) fn ensure_supported<'a>(
+    nodes: &'a [Node<'a>],
+    has_filter: &dyn Fn(&str) -> bool,
+    has_function: &dyn Fn(&str) -> bool,
+) -> Result<'a> {
+    Support {
+        has_filter,
+        has_function,
+    }
+    .nodes(nodes)
+}
+
+struct Support<'h> {
+    has_filter: &'h dyn Fn(&str) -> bool,
+    has_function: &'h dyn Fn(&str) -> bool,
+}
+
+impl<'a> Support<'_> {
+    fn nodes(&self, nodes: &'a [Node<'a>]) -> Result<'a> {
+        nodes.iter().try_for_each(|node| self.node(node))
+    }
+
+    fn node(&self, node: &'a Node<'a>) -> Result<'a> {
+        match node {
+            Node::Lit(_)
+            | Node::Comment(_)
+            | Node::Extends(_)
+            | Node::Import(_)
+            | Node::Raw(_)
+            | Node::Break(_)
+            | Node::Continue(_) => Ok(()),
+            Node::Expr(_, expr) => self.expr(expr),
+            Node::Include(i) => {
+                if let IncludeTarget::Dynamic(expr, _) = &i.target {
+                    self.expr(expr)?;
+                }
+                i.with.iter().try_for_each(|(_, value)| self.expr(value))
+            }
+            Node::Call(call) => {
+                self.exprs(&call.args)?;
+                match &call.caller {
+                    Some(caller) => self.nodes(&caller.nodes),
+                    None => Ok(()),
+                }
+            }
+            Node::Let(l) => {
+                if let Some(val) = &l.val {
+                    self.expr(val)?;
+                }
+                if let Some(block) = &l.block {
+                    if let Some(filter) = &block.filters {
+                        self.filter(filter, l.span())?;
+                    }
+                    self.nodes(&block.nodes)?;
+                }
+                Ok(())
+            }
+            Node::If(i) => i.branches.iter().try_for_each(|branch| {
+                if let Some(CondTest { expr, .. }) = &branch.cond {
+                    self.expr(expr)?;
+                }
+                self.nodes(&branch.nodes)
+            }),
+            Node::Match(m) => {
+                self.expr(&m.expr)?;
+                m.arms.iter().try_for_each(|arm| {
+                    if let Some(guard) = &arm.guard {
+                        self.expr(guard)?;
+                    }
+                    self.nodes(&arm.nodes)
+                })
+            }
+            Node::Loop(l) => {
+                self.expr(&l.iter)?;
+                if let Some(cond) = &l.cond {
+                    self.expr(cond)?;
+                }
+                self.nodes(&l.body)?;
+                self.nodes(&l.else_nodes)
+            }
+            Node::BlockDef(b) => self.nodes(&b.nodes),
+            Node::Macro(m) => {
+                for (_, default) in &m.args {
+                    if let Some(default) = default {
+                        self.expr(default)?;
+                    }
+                }
+                self.nodes(&m.nodes)
+            }
+            Node::FilterBlock(f) => {
+                self.filter(&f.filters, f.span())?;
+                self.nodes(&f.nodes)
+            }
+        }
+    }
+
+    fn exprs(&self, exprs: &'a [WithSpan<'a, Expr<'a>>]) -> Result<'a> {
+        exprs.iter().try_for_each(|expr| self.expr(expr))
+    }
+
+    fn filter(&self, filter: &'a Filter<'a>, span: Span<'a>) -> Result<'a> {
+        if UNSUPPORTED_FILTERS.contains(&filter.name) && !(self.has_filter)(filter.name) {
+            return Err(CompileError::new(
+                format_args!(
+                    "the `{}` filter is not supported by the interpreter",
+                    filter.name,
+                ),
+                span,
+            ));
+        }
+        if let Some(arg) = filter
+            .arguments
+            .iter()
+            .find(|arg| matches!(***arg, Expr::NamedArgument(..)))
+        {
+            return Err(CompileError::new(
+                format_args!(
+                    "named arguments are not supported by the filter `{}`",
+                    filter.name,
+                ),
+                arg.span(),
+            ));
+        }
+        self.exprs(&filter.arguments)
+    }
+
+    fn expr(&self, expr: &'a WithSpan<'a, Expr<'a>>) -> Result<'a> {
+        match &**expr {
+            Expr::BoolLit(_)
+            | Expr::NumLit(_, _)
+            | Expr::StrLit(_)
+            | Expr::CharLit(_)
+            | Expr::Var(_)
+            | Expr::Path(_)
+            | Expr::RustMacro(_, _)
+            | Expr::FilterSource
+            | Expr::IsDefined(_)
+            | Expr::IsNotDefined(_) => Ok(()),
+            Expr::Array(exprs) | Expr::Tuple(exprs) | Expr::Concat(exprs) => self.exprs(exprs),
+            Expr::Attr(expr, _)
+            | Expr::As(expr, _)
+            | Expr::NamedArgument(_, expr)
+            | Expr::Unary(_, expr)
+            | Expr::Group(expr)
+            | Expr::Try(expr)
+            | Expr::IsNone(expr)
+            | Expr::IsNotNone(expr) => self.expr(expr),
+            Expr::Index(left, right) | Expr::BinOp(_, left, right) => {
+                self.expr(left)?;
+                self.expr(right)
+            }
+            Expr::Range(_, left, right) => {
+                if let Some(left) = left {
+                    self.expr(left)?;
+                }
+                match right {
+                    Some(right) => self.expr(right),
+                    None => Ok(()),
+                }
+            }
+            Expr::Filter(filter) => self.filter(filter, expr.span()),
+            Expr::LetCond(cond) => self.expr(&cond.expr),
+            Expr::Call { path, args, .. } => {
+                if let Expr::Var("t") = ***path {
+                    if !(self.has_function)("t") {
+                        return Err(CompileError::new(
+                            "the translation function `t()` is not supported by the interpreter",
+                            expr.span(),
+                        ));
+                    }
+                }
+                self.expr(path)?;
+                self.exprs(args)
+            }
+        }
+    }
+}
+
+/// Collect all variable names that are bound by a pattern
+pub(crate) fn collect_names<'a>(target: &'a Target<'a>, names: &mut Vec<&'a str>) {
+    match target {
+        Target::Name(name) => names.push(name),
+        Target::Tuple(_, targets) | Target::Array(_, targets) | Target::OrChain(targets) => {
+            for target in targets {
+                collect_names(target, names);
+            }
+        }
+        Target::Struct(_, targets) => {
+            for (_, target) in targets {
+                collect_names(target, names);
+            }
+        }
+        Target::Rest(name) => names.extend(**name),
+        Target::NumLit(..)
+        | Target::StrLit(_)
+        | Target::CharLit(_)
+        | Target::BoolLit(_)
+        | Target::Path(_)
+        | Target::Placeholder(_) => {}
+    }
+}
+
+struct CondInfo<'a> {
+    cond: &'a WithSpan<'a, askama_parser::node::Cond<'a>>,
+    generate_condition: bool,
+    generate_content: bool,
+}
+
+struct Conds<'a> {
+    conds: Vec<CondInfo<'a>>,
+    ws_before: Option<Ws>,
+    ws_after: Option<Ws>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum EvaluatedResult {
+    AlwaysTrue,
+    AlwaysFalse,
+    Unknown,
+}
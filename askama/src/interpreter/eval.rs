@@ -0,0 +1,1561 @@
+//! Evaluation of the [`Op`]s produced by the [`compile`][super::compile] module
+
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::cell::Cell;
+use core::cmp::Ordering;
+use core::fmt::{self, Write};
+use core::mem;
+
+use askama_parser::node::{CondTest, Include, Let};
+use askama_parser::{Attr, Expr, Num, Span, StrLit, Target, WithSpan};
+
+use super::compile::{LoopOp, Op};
+use super::value::f32_to_f64;
+use super::{DynEscaper, Environment, Error, ErrorKind, Value};
+
+/// Error during the evaluation, located at `span` if known
+pub(crate) struct EvalError<'a> {
+    pub(crate) error: Error,
+    pub(crate) span: Option<Span<'a>>,
+}
+
+impl<'a> EvalError<'a> {
+    pub(super) fn new(message: impl fmt::Display, span: Span<'a>) -> Self {
+        Self {
+            error: Error::new(ErrorKind::Render, message),
+            span: Some(span),
+        }
+    }
+}
+
+impl From<Error> for EvalError<'_> {
+    #[inline]
+    fn from(error: Error) -> Self {
+        Self { error, span: None }
+    }
+}
+
+pub(super) type Result<'a, T = ()> = core::result::Result<T, EvalError<'a>>;
+
+/// How a value must be escaped, cf. `DisplayWrap` and [`HtmlSafe`][crate::filters::HtmlSafe]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum Safety {
+    /// The value must be escaped
+    Unsafe,
+    /// The value must be escaped, unless the escaper is [`Html`][crate::filters::Html]
+    HtmlSafe,
+    /// The value must not be escaped
+    Safe,
+}
+
+/// A variable in a scope
+struct Slot {
+    /// `None` if the variable was declared with `{% let name %}`, but not assigned yet
+    value: Option<Value>,
+    safety: Safety,
+}
+
+/// Control flow after executing a list of [`Op`]s
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+}
+
+/// State of a `{% for %}` loop, for the `loop.*` variables
+struct LoopState<'a> {
+    op: &'a LoopOp<'a>,
+    /// Nesting level of a recursive loop, starting at 1
+    depth: usize,
+    index: usize,
+    items: Arc<[Value]>,
+    /// Last values of the `loop.changed(…)` calls, keyed by the address of their arguments
+    changed: Vec<(usize, Value)>,
+}
+
+pub(crate) struct Evaluator<'a, 'e> {
+    pub(super) env: &'e Environment,
+    pub(super) escaper: &'e dyn DynEscaper,
+    root: &'e Value,
+    scopes: Vec<Vec<(&'a str, Slot)>>,
+    loops: Vec<LoopState<'a>>,
+    /// Number of scopes and loops at the call sites of the `{% call(…) %}` blocks
+    callers: Vec<(usize, usize)>,
+    /// Output of the `{% filter %}` blocks that are currently being evaluated
+    pub(super) filter_sources: Vec<String>,
+    /// Set in templates included with `{% include … only %}`, which cannot see the root value
+    isolated: bool,
+    /// Number of bytes that can still be written, cf. [`Environment::set_max_output()`],
+    /// or `None` once the limit was exceeded
+    remaining: Cell<Option<usize>>,
+}
+
+/// A writer that fails once more than `remaining` bytes were written to it
+struct Limited<'w> {
+    out: &'w mut dyn Write,
+    remaining: &'w Cell<Option<usize>>,
+}
+
+impl Write for Limited<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let remaining = self.remaining.get().and_then(|r| r.checked_sub(s.len()));
+        self.remaining.set(remaining);
+        match remaining {
+            Some(_) => self.out.write_str(s),
+            None => Err(fmt::Error),
+        }
+    }
+}
+
+impl<'a, 'e> Evaluator<'a, 'e> {
+    pub(crate) fn new(env: &'e Environment, escaper: &'e dyn DynEscaper, root: &'e Value) -> Self {
+        Self {
+            env,
+            escaper,
+            root,
+            scopes: vec![Vec::new()],
+            loops: Vec::new(),
+            callers: Vec::new(),
+            filter_sources: Vec::new(),
+            isolated: false,
+            remaining: Cell::new(Some(env.max_output.unwrap_or(usize::MAX))),
+        }
+    }
+
+    pub(crate) fn run(&mut self, ops: &'a [Op<'a>], out: &mut dyn Write) -> Result<'a> {
+        self.exec(ops, out)?;
+        Ok(())
+    }
+
+    fn scoped<T>(&mut self, callback: impl FnOnce(&mut Self) -> T) -> T {
+        self.scopes.push(Vec::new());
+        let res = callback(self);
+        self.scopes.pop();
+        res
+    }
+
+    fn exec(&mut self, ops: &'a [Op<'a>], out: &mut dyn Write) -> Result<'a, Flow> {
+        for op in ops {
+            let flow = match op {
+                Op::Lit(lit) => {
+                    let mut out = self.limited(out);
+                    out.write_str(lit)
+                        .map_err(|err| self.write_error(err, None))?;
+                    Flow::Normal
+                }
+                Op::Expr(expr) => {
+                    let (value, safety) = self.eval_safe(expr)?;
+                    self.write_value(out, &value, safety, expr.span())?;
+                    Flow::Normal
+                }
+                Op::Let(l) => {
+                    self.exec_let(l)?;
+                    Flow::Normal
+                }
+                Op::LetBlock(l, body) => {
+                    let mut source = String::new();
+                    let flow = self.scoped(|this| this.exec(body, &mut source))?;
+                    if matches!(flow, Flow::Normal) {
+                        self.exec_let_block(l, source)?;
+                    }
+                    flow
+                }
+                Op::If(branches) => {
+                    let mut flow = Flow::Normal;
+                    for branch in branches {
+                        let taken = self.scoped(|this| match branch.cond {
+                            Some(cond) if !this.test_cond(cond)? => Ok(None),
+                            _ => this.exec(&branch.body, out).map(Some),
+                        })?;
+                        if let Some(taken) = taken {
+                            flow = taken;
+                            break;
+                        }
+                    }
+                    flow
+                }
+                Op::Match(expr, arms) => {
+                    let value = self.eval(expr)?;
+                    let mut flow = Flow::Normal;
+                    for arm in arms {
+                        let taken = self.scoped(|this| {
+                            for target in arm.targets {
+                                if this.bind(target, &value, Safety::Unsafe, expr.span())?
+                                    && arm.guard.map_or(Ok(true), |guard| this.test(guard))?
+                                {
+                                    return this.exec(&arm.body, out).map(Some);
+                                }
+                            }
+                            Ok(None)
+                        })?;
+                        if let Some(taken) = taken {
+                            flow = taken;
+                            break;
+                        }
+                    }
+                    flow
+                }
+                Op::Loop(l) => {
+                    self.exec_loop(l, out)?;
+                    Flow::Normal
+                }
+                Op::Break => return Ok(Flow::Break),
+                Op::Continue => return Ok(Flow::Continue),
+                Op::Scope(body) => self.scoped(|this| this.exec(body, out))?,
+                Op::DynamicInclude(expr, candidates) => {
+                    let name = match self.eval(expr)? {
+                        Value::Str(name) => name,
+                        value => {
+                            return Err(EvalError::new(
+                                format_args!(
+                                    "the template of an `include` must be a string, found {}",
+                                    value.kind(),
+                                ),
+                                expr.span(),
+                            ));
+                        }
+                    };
+                    let Some((_, body)) = candidates.iter().find(|(path, _)| **path == *name)
+                    else {
+                        return Err(EvalError::new(
+                            format_args!("{name:?} is not one of the templates of this `include`"),
+                            expr.span(),
+                        ));
+                    };
+                    self.scoped(|this| this.exec(body, out))?
+                }
+                Op::IncludeWith(i, body) => self.exec_include_with(i, body, out)?,
+                Op::Call(args, body, has_caller) => {
+                    if *has_caller {
+                        self.callers.push((self.scopes.len(), self.loops.len()));
+                    }
+                    let flow = self.scoped(|this| {
+                        for &(name, expr) in args {
+                            let (value, safety) = this.eval_safe(expr)?;
+                            this.declare(name, Some(value), safety);
+                        }
+                        this.exec(body, out)
+                    });
+                    if *has_caller {
+                        self.callers.pop();
+                    }
+                    flow?
+                }
+                Op::Caller(args, names, body) => self.exec_caller(args, names, body, out)?,
+                Op::FilterBlock(filter, body) => {
+                    let mut source = String::new();
+                    let flow = self.scoped(|this| this.exec(body, &mut source))?;
+                    self.filter_sources.push(source);
+                    let result = self.eval_filter(&filter.filters, filter.span());
+                    self.filter_sources.pop();
+                    let (value, safety) = result?;
+                    self.write_value(out, &value, safety, filter.span())?;
+                    flow
+                }
+            };
+            if !matches!(flow, Flow::Normal) {
+                return Ok(flow);
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn exec_let(&mut self, l: &'a WithSpan<'a, Let<'a>>) -> Result<'a> {
+        let Some(val) = &l.val else {
+            let mut names = Vec::new();
+            super::compile::collect_names(&l.var, &mut names);
+            for name in names {
+                self.declare(name, None, Safety::Unsafe);
+            }
+            return Ok(());
+        };
+
+        let (value, safety) = self.eval_safe(val)?;
+        self.assign(l, value, safety)
+    }
+
+    /// Assign the rendered body of `{% set x %}…{% endset %}`, after applying its filters
+    fn exec_let_block(&mut self, l: &'a WithSpan<'a, Let<'a>>, source: String) -> Result<'a> {
+        let filters = l.block.as_ref().and_then(|block| block.filters.as_ref());
+        let Some(filters) = filters else {
+            return self.assign(l, Value::Str(source.into()), Safety::Safe);
+        };
+
+        // The body was already escaped, so the result is not escaped again.
+        self.filter_sources.push(source);
+        let result = self.eval_filter(filters, l.span());
+        self.filter_sources.pop();
+        let (value, _) = result?;
+        let mut text = String::new();
+        self.write_value(&mut text, &value, Safety::Safe, l.span())?;
+        self.assign(l, Value::Str(text.into()), Safety::Safe)
+    }
+
+    fn assign(&mut self, l: &'a WithSpan<'a, Let<'a>>, value: Value, safety: Safety) -> Result<'a> {
+        if let Target::Name(name) = l.var {
+            // Assign a value to a variable that was declared with `{% let name %}`.
+            if let Some(slot) = self.find_slot(name) {
+                if slot.value.is_none() {
+                    slot.value = Some(value);
+                    slot.safety = safety;
+                    return Ok(());
+                }
+            }
+        }
+        if is_literal(&l.var) {
+            return Err(EvalError::new(
+                "literals are not allowed on the left-hand side of an assignment",
+                l.span(),
+            ));
+        }
+        match self.bind(&l.var, &value, safety, l.span())? {
+            true => Ok(()),
+            false => Err(EvalError::new(
+                format_args!("refutable pattern in `let`: {} did not match", value.kind()),
+                l.span(),
+            )),
+        }
+    }
+
+    fn exec_loop(&mut self, l: &'a LoopOp<'a>, out: &mut dyn Write) -> Result<'a> {
+        let items = self.eval(&l.node.iter)?;
+        self.run_loop(l, items, 1, out)
+    }
+
+    /// Implementation of `loop(children)` in a recursive loop
+    fn exec_recursive_loop(
+        &mut self,
+        args: &'a [WithSpan<'a, Expr<'a>>],
+        span: Span<'a>,
+    ) -> Result<'a, Value> {
+        let Some(&LoopState { op, depth, .. }) = self.loops.last() else {
+            return Err(EvalError::new("`loop` used outside of a loop", span));
+        };
+        if !op.node.recursive {
+            return Err(EvalError::new(
+                "`loop(…)` can only be used in a `recursive` for loop",
+                span,
+            ));
+        }
+        let [arg] = args else {
+            return Err(EvalError::new(
+                "`loop(…)` expects exactly one argument",
+                span,
+            ));
+        };
+        if depth >= self.env.max_depth {
+            return Err(EvalError::new(
+                format_args!(
+                    "recursive loop is nested more than {} levels deep",
+                    self.env.max_depth,
+                ),
+                span,
+            ));
+        }
+        let items = self.eval(arg)?;
+        let mut out = String::new();
+        self.run_loop(op, items, depth + 1, &mut out)?;
+        Ok(Value::Str(out.into()))
+    }
+
+    fn run_loop(
+        &mut self,
+        l: &'a LoopOp<'a>,
+        items: Value,
+        depth: usize,
+        out: &mut dyn Write,
+    ) -> Result<'a> {
+        let node = &**l.node;
+        let items = match items {
+            Value::Seq(items) => items,
+            Value::Map(map) => map
+                .iter()
+                .map(|(key, value)| Value::Seq([Value::Str(Arc::clone(key)), value.clone()].into()))
+                .collect(),
+            value => {
+                return Err(EvalError::new(
+                    format_args!("cannot iterate over a {}", value.kind()),
+                    node.iter.span(),
+                ));
+            }
+        };
+
+        let items = match &node.cond {
+            Some(cond) => {
+                let mut filtered = Vec::with_capacity(items.len());
+                for item in items.iter() {
+                    let keep = self.scoped(|this| {
+                        this.bind_loop_var(node, item)?;
+                        this.test(cond)
+                    })?;
+                    if keep {
+                        filtered.push(item.clone());
+                    }
+                }
+                filtered.into()
+            }
+            None => items,
+        };
+
+        if items.is_empty() {
+            if let Some(else_body) = &l.else_body {
+                self.scoped(|this| this.exec(else_body, out))?;
+            }
+            return Ok(());
+        }
+
+        self.loops.push(LoopState {
+            op: l,
+            depth,
+            index: 0,
+            items: Arc::clone(&items),
+            changed: Vec::new(),
+        });
+        let mut result = Ok(());
+        for (index, item) in items.iter().enumerate() {
+            if let Some(state) = self.loops.last_mut() {
+                state.index = index;
+            }
+            let flow = self.scoped(|this| {
+                this.bind_loop_var(node, item)?;
+                this.exec(&l.body, out)
+            });
+            match flow {
+                Ok(Flow::Break) => break,
+                Ok(_) => {}
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                }
+            }
+        }
+        self.loops.pop();
+        result
+    }
+
+    /// Execute the body of a `{% call(…) %}` block in the scope of its call site
+    fn exec_caller(
+        &mut self,
+        args: &'a [WithSpan<'a, Expr<'a>>],
+        names: &'a [&'a str],
+        body: &'a [Op<'a>],
+        out: &mut dyn Write,
+    ) -> Result<'a, Flow> {
+        let values = args
+            .iter()
+            .map(|arg| self.eval_safe(arg))
+            .collect::<Result<'a, Vec<_>>>()?;
+        let Some((scopes, loops)) = self.callers.pop() else {
+            return Ok(Flow::Normal);
+        };
+        let hidden_scopes = self.scopes.split_off(scopes);
+        let hidden_loops = self.loops.split_off(loops);
+        let flow = self.scoped(|this| {
+            for (&name, (value, safety)) in names.iter().zip(values) {
+                this.declare(name, Some(value), safety);
+            }
+            this.exec(body, out)
+        });
+        self.scopes.extend(hidden_scopes);
+        self.loops.extend(hidden_loops);
+        self.callers.push((scopes, loops));
+        flow
+    }
+
+    fn exec_include_with(
+        &mut self,
+        i: &'a Include<'a>,
+        body: &'a [Op<'a>],
+        out: &mut dyn Write,
+    ) -> Result<'a, Flow> {
+        let values = i
+            .with
+            .iter()
+            .map(|(_, value)| self.eval_safe(value))
+            .collect::<Result<'a, Vec<_>>>()?;
+        if !i.only {
+            return self.scoped(|this| {
+                for (&(name, _), (value, safety)) in i.with.iter().zip(values) {
+                    this.declare(name, Some(value), safety);
+                }
+                this.exec(body, out)
+            });
+        }
+
+        let hidden_scopes = mem::replace(&mut self.scopes, vec![Vec::new()]);
+        let hidden_loops = mem::take(&mut self.loops);
+        let hidden_callers = mem::take(&mut self.callers);
+        let isolated = mem::replace(&mut self.isolated, true);
+        for (&(name, _), (value, safety)) in i.with.iter().zip(values) {
+            self.declare(name, Some(value), safety);
+        }
+        let flow = self.exec(body, out);
+        self.scopes = hidden_scopes;
+        self.loops = hidden_loops;
+        self.callers = hidden_callers;
+        self.isolated = isolated;
+        flow
+    }
+
+    fn bind_loop_var(
+        &mut self,
+        node: &'a askama_parser::node::Loop<'a>,
+        item: &Value,
+    ) -> Result<'a> {
+        match self.bind(&node.var, item, Safety::Unsafe, node.iter.span())? {
+            true => Ok(()),
+            false => Err(EvalError::new(
+                format_args!("loop pattern did not match a {}", item.kind()),
+                node.iter.span(),
+            )),
+        }
+    }
+
+    fn write_value(
+        &self,
+        out: &mut dyn Write,
+        value: &Value,
+        safety: Safety,
+        span: Span<'a>,
+    ) -> Result<'a> {
+        if !value.is_displayable() {
+            return Err(EvalError::new(
+                format_args!("cannot render a {} value", value.kind()),
+                span,
+            ));
+        }
+        let safety = safety.max(value_safety(value));
+        let out = &mut self.limited(out);
+        let result =
+            if safety == Safety::Safe || (safety == Safety::HtmlSafe && self.escaper.is_html()) {
+                write!(out, "{value}")
+            } else {
+                match value {
+                    Value::Str(s) => self.escaper.write_escaped(out, s),
+                    value => self.escaper.write_escaped(out, &value.to_string()),
+                }
+            };
+        result.map_err(|err| self.write_error(err, Some(span)))
+    }
+
+    fn limited<'w>(&'w self, out: &'w mut dyn Write) -> Limited<'w> {
+        Limited {
+            out,
+            remaining: &self.remaining,
+        }
+    }
+
+    /// Tell apart an exceeded output limit from an error of the underlying writer
+    fn write_error(&self, err: fmt::Error, span: Option<Span<'a>>) -> EvalError<'a> {
+        if self.remaining.get().is_some() {
+            return fmt_error(err);
+        }
+        let error = Error::new(
+            ErrorKind::Render,
+            format_args!(
+                "the rendered output exceeds the limit of {} bytes",
+                self.env.max_output.unwrap_or_default(),
+            ),
+        );
+        EvalError { error, span }
+    }
+
+    // ---- variables ----
+
+    fn declare(&mut self, name: &'a str, value: Option<Value>, safety: Safety) {
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .push((name, Slot { value, safety }));
+    }
+
+    fn find_slot(&mut self, name: &str) -> Option<&mut Slot> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find_map(|(n, slot)| (*n == name).then_some(slot))
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Slot> {
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find_map(|(n, slot)| (*n == name).then_some(slot))
+    }
+
+    fn is_defined(&self, name: &str) -> bool {
+        self.lookup(name).is_some() || (!self.isolated && self.root.get(name).is_some())
+    }
+
+    fn var(&self, name: &str, span: Span<'a>) -> Result<'a, (Value, Safety)> {
+        match self.lookup(name) {
+            Some(Slot {
+                value: Some(value),
+                safety,
+            }) => Ok((value.clone(), *safety)),
+            Some(Slot { value: None, .. }) => Err(EvalError::new(
+                format_args!("variable `{name}` is used before it was assigned"),
+                span,
+            )),
+            None if self.isolated => Err(EvalError::new(
+                format_args!(
+                    "variable `{name}` was not passed to the template included with `only`"
+                ),
+                span,
+            )),
+            None if name == "self" => Ok((self.root.clone(), Safety::Unsafe)),
+            None => match self.root.get(name) {
+                Some(value) => Ok((value.clone(), Safety::Unsafe)),
+                None => Err(EvalError::new(
+                    format_args!("variable `{name}` not found"),
+                    span,
+                )),
+            },
+        }
+    }
+
+    // ---- conditions ----
+
+    fn test_cond(&mut self, cond: &'a CondTest<'a>) -> Result<'a, bool> {
+        let Some(target) = &cond.target else {
+            return self.test(&cond.expr);
+        };
+        // In `if let Some(x) = a && x > 0`, the target only binds the left-hand side.
+        match &*cond.expr {
+            Expr::BinOp(op @ ("&&" | "||"), left, right) => {
+                let value = self.eval(left)?;
+                let matched = self.bind(target, &value, Safety::Unsafe, left.span())?;
+                match *op {
+                    "&&" => Ok(matched && self.test(right)?),
+                    _ => Ok(matched || self.test(right)?),
+                }
+            }
+            _ => {
+                let (value, safety) = self.eval_safe(&cond.expr)?;
+                self.bind(target, &value, safety, cond.expr.span())
+            }
+        }
+    }
+
+    /// Evaluate a condition, cf. `Generator::visit_condition()`
+    pub(super) fn test(&mut self, expr: &'a WithSpan<'a, Expr<'a>>) -> Result<'a, bool> {
+        match &**expr {
+            Expr::Unary("!", inner) => Ok(!self.test(inner)?),
+            Expr::BinOp("&&", left, right) => Ok(self.test(left)? && self.test(right)?),
+            Expr::BinOp("||", left, right) => Ok(self.test(left)? || self.test(right)?),
+            Expr::Group(inner) => self.test(inner),
+            Expr::LetCond(cond) => self.test_cond(cond),
+            _ => match self.eval(expr)? {
+                Value::Bool(value) => Ok(value),
+                value => Err(EvalError::new(
+                    format_args!("expected a bool, found a {}", value.kind()),
+                    expr.span(),
+                )),
+            },
+        }
+    }
+
+    // ---- patterns ----
+
+    /// Match `value` against `target`, and bind the variables in the current scope on success
+    fn bind(
+        &mut self,
+        target: &'a Target<'a>,
+        value: &Value,
+        safety: Safety,
+        span: Span<'a>,
+    ) -> Result<'a, bool> {
+        let mut bindings = Vec::new();
+        if !matches_target(target, value, &mut bindings).map_err(|msg| EvalError::new(msg, span))? {
+            return Ok(false);
+        }
+        let safety = match target {
+            Target::Name(_) => safety,
+            _ => Safety::Unsafe,
+        };
+        for (name, value) in bindings {
+            self.declare(name, Some(value), safety);
+        }
+        Ok(true)
+    }
+
+    // ---- expressions ----
+
+    /// Evaluate an expression, and determine if its value needs to be escaped
+    pub(super) fn eval_safe(
+        &mut self,
+        expr: &'a WithSpan<'a, Expr<'a>>,
+    ) -> Result<'a, (Value, Safety)> {
+        match &**expr {
+            Expr::Filter(filter) => self.eval_filter(filter, expr.span()),
+            Expr::FilterSource => Ok((self.filter_source(expr.span())?, Safety::Safe)),
+            Expr::Var(name) => self.var(name, expr.span()),
+            Expr::Unary("*" | "&", inner) => self.eval_safe(inner),
+            Expr::Call { path, args, .. }
+                if matches!(***path, Expr::Var("loop")) && self.lookup("loop").is_none() =>
+            {
+                Ok((self.exec_recursive_loop(args, path.span())?, Safety::Safe))
+            }
+            _ => Ok((self.eval(expr)?, Safety::Unsafe)),
+        }
+    }
+
+    fn filter_source(&self, span: Span<'a>) -> Result<'a, Value> {
+        match self.filter_sources.last() {
+            Some(source) => Ok(Value::Str(source.as_str().into())),
+            None => Err(EvalError::new("no filter source available", span)),
+        }
+    }
+
+    pub(super) fn eval(&mut self, expr: &'a WithSpan<'a, Expr<'a>>) -> Result<'a, Value> {
+        let span = expr.span();
+        let err = |msg: String| EvalError::new(msg, span);
+        Ok(match &**expr {
+            Expr::BoolLit(value) => Value::Bool(*value),
+            Expr::NumLit(_, num) => parse_num(num).map_err(err)?,
+            Expr::StrLit(lit) => Value::Str(parse_str(lit).map_err(err)?.into()),
+            Expr::CharLit(lit) => Value::Char(lit.unescape().map_err(err)?),
+            Expr::Var(name) => self.var(name, span)?.0,
+            Expr::Path(path) => match path.as_slice() {
+                ["None"] => Value::None,
+                [.., last] => Value::Str((*last).into()),
+                [] => Value::None,
+            },
+            Expr::Array(items) | Expr::Tuple(items) => items
+                .iter()
+                .map(|item| self.eval(item))
+                .collect::<Result<'a, Value>>()?,
+            Expr::Attr(obj, Attr { name, .. }) => {
+                if let (Expr::Var("loop"), None) = (&***obj, self.lookup("loop")) {
+                    return self.loop_var(name, span);
+                }
+                let obj = self.eval(obj)?;
+                get_attr(&obj, name).map_err(err)?
+            }
+            Expr::Index(obj, index) => {
+                let obj = self.eval(obj)?;
+                if let Expr::Range(op, start, end) = &***index {
+                    let start = self.eval_opt(start.as_deref())?;
+                    let end = self.eval_opt(end.as_deref())?;
+                    slice(&obj, op, start, end).map_err(err)?
+                } else {
+                    let index = self.eval(index)?;
+                    get_index(&obj, &index).map_err(err)?
+                }
+            }
+            Expr::Filter(filter) => self.eval_filter(filter, span)?.0,
+            Expr::As(inner, ty) => {
+                let value = self.eval(inner)?;
+                cast(&value, ty).map_err(err)?
+            }
+            Expr::NamedArgument(_, inner) => self.eval(inner)?,
+            Expr::Unary(op, inner) => {
+                let value = self.eval(inner)?;
+                unary(op, value).map_err(err)?
+            }
+            Expr::BinOp("&&" | "||", _, _) => Value::Bool(self.test(expr)?),
+            Expr::BinOp(op, left, right) => {
+                let left = self.eval(left)?;
+                let right = self.eval(right)?;
+                binop(op, &left, &right).map_err(err)?
+            }
+            Expr::Range(op, start, end) => {
+                let start = self.eval_opt(start.as_deref())?;
+                let end = self.eval_opt(end.as_deref())?;
+                range(op, start, end).map_err(err)?
+            }
+            Expr::Group(inner) => self.eval(inner)?,
+            Expr::Call { path, args, .. } => self.eval_call(path, args, span)?,
+            Expr::RustMacro(path, _) => {
+                return Err(err(format!(
+                    "Rust macros like `{}!()` are not supported by the interpreter",
+                    path.join("::"),
+                )));
+            }
+            Expr::Try(inner) => match self.eval(inner)? {
+                Value::Map(map) if map.len() == 1 && map.contains_key("Ok") => map["Ok"].clone(),
+                Value::Map(map) if map.len() == 1 && map.contains_key("Err") => {
+                    return Err(err(map["Err"].to_string()));
+                }
+                Value::None => return Err(err("`?` applied to `None`".into())),
+                value => value,
+            },
+            Expr::FilterSource => self.filter_source(span)?,
+            Expr::IsDefined(name) => Value::Bool(self.is_defined(name)),
+            Expr::IsNotDefined(name) => Value::Bool(!self.is_defined(name)),
+            Expr::IsNone(inner) => Value::Bool(matches!(self.eval(inner)?, Value::None)),
+            Expr::IsNotNone(inner) => Value::Bool(!matches!(self.eval(inner)?, Value::None)),
+            Expr::Concat(items) => {
+                let mut buf = String::new();
+                for item in items {
+                    let value = self.eval(item)?;
+                    if !value.is_displayable() {
+                        return Err(EvalError::new(
+                            format_args!("cannot concatenate a {} value", value.kind()),
+                            item.span(),
+                        ));
+                    }
+                    let _ = write!(buf, "{value}");
+                }
+                Value::Str(buf.into())
+            }
+            Expr::LetCond(_) => Value::Bool(self.test(expr)?),
+        })
+    }
+
+    fn eval_opt(&mut self, expr: Option<&'a WithSpan<'a, Expr<'a>>>) -> Result<'a, Option<Value>> {
+        expr.map(|expr| self.eval(expr)).transpose()
+    }
+
+    pub(super) fn eval_args(
+        &mut self,
+        args: &'a [WithSpan<'a, Expr<'a>>],
+    ) -> Result<'a, Vec<Value>> {
+        args.iter().map(|arg| self.eval(arg)).collect()
+    }
+
+    fn loop_var(&self, name: &str, span: Span<'a>) -> Result<'a, Value> {
+        let Some(state) = self.loops.last() else {
+            return Err(EvalError::new("`loop` used outside of a loop", span));
+        };
+        Ok(match name {
+            "index" => Value::from(state.index + 1),
+            "index0" => Value::from(state.index),
+            "first" => Value::Bool(state.index == 0),
+            "last" => Value::Bool(state.index + 1 == state.items.len()),
+            "length" => Value::from(state.items.len()),
+            "revindex" => Value::from(state.items.len() - state.index),
+            "revindex0" => Value::from(state.items.len() - state.index - 1),
+            "previtem" => match state.index.checked_sub(1) {
+                Some(index) => state.items[index].clone(),
+                None => Value::None,
+            },
+            "nextitem" => state
+                .items
+                .get(state.index + 1)
+                .cloned()
+                .unwrap_or_default(),
+            "depth" => Value::from(state.depth),
+            "depth0" => Value::from(state.depth - 1),
+            name => {
+                return Err(EvalError::new(
+                    format_args!("unknown loop variable `{name}`"),
+                    span,
+                ));
+            }
+        })
+    }
+
+    fn eval_call(
+        &mut self,
+        path: &'a WithSpan<'a, Expr<'a>>,
+        args: &'a [WithSpan<'a, Expr<'a>>],
+        span: Span<'a>,
+    ) -> Result<'a, Value> {
+        if let Some(arg) = args
+            .iter()
+            .find(|arg| matches!(***arg, Expr::NamedArgument(..)))
+        {
+            return Err(EvalError::new(
+                "named arguments are only supported in macro calls",
+                arg.span(),
+            ));
+        }
+        match &**path {
+            Expr::Var("loop") if self.lookup("loop").is_none() => {
+                self.exec_recursive_loop(args, path.span())
+            }
+            Expr::Attr(obj, Attr { name, .. }) => {
+                if let (Expr::Var("loop"), None) = (&***obj, self.lookup("loop")) {
+                    return self.loop_method(name, args, path.span());
+                }
+                if let (Expr::Var("self"), None) = (&***obj, self.lookup("self")) {
+                    let args = self.eval_args(args)?;
+                    return self.call_function(&[name], &Value::None, &args, span);
+                }
+                let receiver = self.eval(obj)?;
+                let args = self.eval_args(args)?;
+                match call_method(&receiver, name, &args) {
+                    Some(result) => result.map_err(|msg| EvalError::new(msg, span)),
+                    None => self.call_function(&[name], &receiver, &args, span),
+                }
+            }
+            Expr::Var(name) => {
+                let args = self.eval_args(args)?;
+                self.call_function(&[name], &Value::None, &args, span)
+            }
+            Expr::Path(path) => {
+                let mut args = self.eval_args(args)?;
+                match (path.as_slice(), args.len()) {
+                    (["Some"], 1) => Ok(args.pop().unwrap()),
+                    (["Ok" | "Err"], 1) => {
+                        let value = args.pop().unwrap();
+                        Ok(Value::from_iter([(path[0], value)]))
+                    }
+                    (path @ [.., last], _) => {
+                        if self.function(path).is_some() {
+                            self.call_function(path, &Value::None, &args, span)
+                        } else if last.starts_with(|c: char| c.is_ascii_uppercase()) {
+                            // A tuple struct or tuple variant
+                            let data = match args.len() {
+                                1 => args.pop().unwrap(),
+                                _ => Value::from(args),
+                            };
+                            Ok(Value::from_iter([(*last, data)]))
+                        } else {
+                            self.call_function(path, &Value::None, &args, span)
+                        }
+                    }
+                    ([], _) => unreachable!(),
+                }
+            }
+            _ => {
+                let value = self.eval(path)?;
+                Err(EvalError::new(
+                    format_args!("cannot call a {}", value.kind()),
+                    span,
+                ))
+            }
+        }
+    }
+
+    fn function(&self, path: &[&str]) -> Option<&'e super::Callback> {
+        let functions = &self.env.functions;
+        let last = path.last()?;
+        functions
+            .get(&path.join("::"))
+            .or_else(|| functions.get(*last))
+            .map(|f| &**f)
+    }
+
+    fn call_function(
+        &self,
+        path: &[&str],
+        receiver: &Value,
+        args: &[Value],
+        span: Span<'a>,
+    ) -> Result<'a, Value> {
+        match self.function(path) {
+            Some(function) => function(receiver, args).map_err(|error| EvalError {
+                error,
+                span: Some(span),
+            }),
+            None => Err(EvalError::new(
+                match receiver {
+                    Value::None => format!("unknown function `{}`", path.join("::")),
+                    receiver => format!(
+                        "unknown method `{}` for a {}",
+                        path.join("::"),
+                        receiver.kind()
+                    ),
+                },
+                span,
+            )),
+        }
+    }
+
+    fn loop_method(
+        &mut self,
+        name: &str,
+        args: &'a [WithSpan<'a, Expr<'a>>],
+        span: Span<'a>,
+    ) -> Result<'a, Value> {
+        match name {
+            "cycle" => {
+                let [arg] = args else {
+                    return Err(EvalError::new(
+                        "loop.cycle(…) expects exactly one argument",
+                        span,
+                    ));
+                };
+                let index = match self.loops.last() {
+                    Some(state) => state.index,
+                    None => return Err(EvalError::new("`loop` used outside of a loop", span)),
+                };
+                match self.eval(arg)? {
+                    Value::Seq(items) if !items.is_empty() => {
+                        Ok(items[index % items.len()].clone())
+                    }
+                    Value::Seq(_) => Err(EvalError::new(
+                        "loop.cycle(…) cannot use an empty array",
+                        arg.span(),
+                    )),
+                    value => Err(EvalError::new(
+                        format_args!("loop.cycle(…) expects a sequence, found a {}", value.kind()),
+                        arg.span(),
+                    )),
+                }
+            }
+            "changed" => {
+                if args.is_empty() {
+                    return Err(EvalError::new(
+                        "loop.changed(…) expects at least one argument",
+                        span,
+                    ));
+                }
+                let value = match self.eval_args(args)? {
+                    mut values if values.len() == 1 => values.pop().unwrap(),
+                    values => Value::Seq(values.into()),
+                };
+                let Some(state) = self.loops.last_mut() else {
+                    return Err(EvalError::new("`loop` used outside of a loop", span));
+                };
+                let key = args.as_ptr() as usize;
+                let changed = match state.changed.iter_mut().find(|(k, _)| *k == key) {
+                    Some((_, last)) if *last == value => false,
+                    Some((_, last)) => {
+                        *last = value;
+                        true
+                    }
+                    None => {
+                        state.changed.push((key, value));
+                        true
+                    }
+                };
+                Ok(Value::Bool(changed))
+            }
+            name => Err(EvalError::new(
+                format_args!("unknown loop method: {name:?}"),
+                span,
+            )),
+        }
+    }
+}
+
+pub(super) fn fmt_error(_: fmt::Error) -> EvalError<'static> {
+    EvalError::from(Error::with_source(ErrorKind::Render, crate::Error::Fmt))
+}
+
+/// Numbers and booleans are [`HtmlSafe`][crate::filters::HtmlSafe]
+pub(super) fn value_safety(value: &Value) -> Safety {
+    match value {
+        Value::Bool(_) | Value::Int(_) | Value::Float(_) => Safety::HtmlSafe,
+        _ => Safety::Unsafe,
+    }
+}
+
+fn is_literal(target: &Target<'_>) -> bool {
+    matches!(
+        target,
+        Target::NumLit(..) | Target::StrLit(_) | Target::CharLit(_) | Target::BoolLit(_)
+    )
+}
+
+/// Match `value` against `target`, and collect the variables that the pattern binds
+fn matches_target<'a>(
+    target: &'a Target<'a>,
+    value: &Value,
+    bindings: &mut Vec<(&'a str, Value)>,
+) -> core::result::Result<bool, String> {
+    Ok(match target {
+        Target::Name(name) => {
+            bindings.push((name, value.clone()));
+            true
+        }
+        Target::Placeholder(_) | Target::Rest(_) => true,
+        Target::NumLit(_, num) => *value == parse_num(num)?,
+        Target::StrLit(lit) => value.as_str() == Some(&*parse_str(lit)?),
+        Target::CharLit(lit) => *value == Value::Char(lit.unescape()?),
+        Target::BoolLit(lit) => *value == Value::Bool(*lit == "true"),
+        Target::Path(path) => match path.as_slice() {
+            ["None"] => matches!(value, Value::None),
+            [.., last] => value.as_str() == Some(last),
+            [] => false,
+        },
+        Target::OrChain(targets) => {
+            for target in targets {
+                let len = bindings.len();
+                if matches_target(target, value, bindings)? {
+                    return Ok(true);
+                }
+                bindings.truncate(len);
+            }
+            false
+        }
+        Target::Tuple(path, targets) => match path.as_slice() {
+            [] => match value {
+                Value::Seq(items) => matches_seq(targets, items, bindings)?,
+                _ => false,
+            },
+            ["Some"] => match (value, targets.as_slice()) {
+                (Value::None, _) => false,
+                (value, [target]) => matches_target(target, value, bindings)?,
+                _ => false,
+            },
+            [.., last] => match variant_data(value, last) {
+                Some(data) => match (data, targets.as_slice()) {
+                    (data, [target]) => matches_target(target, data, bindings)?,
+                    (Value::Seq(items), targets) => matches_seq(targets, items, bindings)?,
+                    _ => false,
+                },
+                None => false,
+            },
+        },
+        Target::Array(_, targets) => match value {
+            Value::Seq(items) => matches_seq(targets, items, bindings)?,
+            _ => false,
+        },
+        Target::Struct(path, fields) => {
+            let data = match path.last() {
+                Some(last) => variant_data(value, last).unwrap_or(value),
+                None => value,
+            };
+            let Value::Map(map) = data else {
+                return Ok(false);
+            };
+            for (name, target) in fields {
+                if let Target::Rest(_) = target {
+                    continue;
+                }
+                match map.get(*name) {
+                    Some(value) if matches_target(target, value, bindings)? => {}
+                    _ => return Ok(false),
+                }
+            }
+            true
+        }
+    })
+}
+
+/// The data of an enum variant named `name`, i.e. the value of a map with the single key `name`
+fn variant_data<'v>(value: &'v Value, name: &str) -> Option<&'v Value> {
+    match value {
+        Value::Map(map) if map.len() == 1 => map.get(name),
+        _ => None,
+    }
+}
+
+fn matches_seq<'a>(
+    targets: &'a [Target<'a>],
+    items: &[Value],
+    bindings: &mut Vec<(&'a str, Value)>,
+) -> core::result::Result<bool, String> {
+    let rest = targets.iter().position(|t| matches!(t, Target::Rest(_)));
+    let (head, tail, rest) = match rest {
+        Some(pos) => (&targets[..pos], &targets[pos + 1..], Some(&targets[pos])),
+        None => (targets, &[][..], None),
+    };
+    let len_ok = match rest {
+        Some(_) => items.len() >= head.len() + tail.len(),
+        None => items.len() == head.len(),
+    };
+    if !len_ok {
+        return Ok(false);
+    }
+    for (target, item) in head.iter().zip(items) {
+        if !matches_target(target, item, bindings)? {
+            return Ok(false);
+        }
+    }
+    let tail_start = items.len() - tail.len();
+    for (target, item) in tail.iter().zip(&items[tail_start..]) {
+        if !matches_target(target, item, bindings)? {
+            return Ok(false);
+        }
+    }
+    if let Some(Target::Rest(name)) = rest {
+        if let Some(name) = **name {
+            bindings.push((
+                name,
+                items[head.len()..tail_start].iter().cloned().collect(),
+            ));
+        }
+    }
+    Ok(true)
+}
+
+fn get_attr(obj: &Value, name: &str) -> core::result::Result<Value, String> {
+    match obj {
+        Value::Map(map) => match map.get(name) {
+            Some(value) => Ok(value.clone()),
+            None => Err(format!("no field `{name}` found")),
+        },
+        Value::Seq(items) => match name.parse::<usize>().ok().and_then(|idx| items.get(idx)) {
+            Some(value) => Ok(value.clone()),
+            None => Err(format!("no field `{name}` found in a sequence")),
+        },
+        obj => Err(format!("cannot access field `{name}` of a {}", obj.kind())),
+    }
+}
+
+fn get_index(obj: &Value, index: &Value) -> core::result::Result<Value, String> {
+    match (obj, index) {
+        (Value::Seq(items), Value::Int(idx)) => usize::try_from(*idx)
+            .ok()
+            .and_then(|idx| items.get(idx))
+            .cloned()
+            .ok_or_else(|| {
+                format!(
+                    "index out of bounds: the len is {} but the index is {idx}",
+                    items.len()
+                )
+            }),
+        (Value::Map(map), Value::Str(key)) => map
+            .get(key)
+            .cloned()
+            .ok_or_else(|| format!("key {key:?} not found")),
+        (obj, index) => Err(format!(
+            "cannot index a {} with a {}",
+            obj.kind(),
+            index.kind()
+        )),
+    }
+}
+
+fn slice(
+    obj: &Value,
+    op: &str,
+    start: Option<Value>,
+    end: Option<Value>,
+) -> core::result::Result<Value, String> {
+    let len = match obj {
+        Value::Seq(items) => items.len(),
+        Value::Str(s) => s.len(),
+        obj => return Err(format!("cannot slice a {}", obj.kind())),
+    };
+    let bound = |value: Option<Value>, default: usize| match value {
+        None => Ok(default),
+        Some(Value::Int(idx)) => usize::try_from(idx).map_err(|_| format!("invalid index {idx}")),
+        Some(value) => Err(format!("cannot index with a {}", value.kind())),
+    };
+    let start = bound(start, 0)?;
+    let mut end = bound(end, len)?;
+    if op == "..=" {
+        end += 1;
+    }
+    if start > end || end > len {
+        return Err(format!(
+            "range {start}..{end} out of bounds for a length of {len}"
+        ));
+    }
+    match obj {
+        Value::Seq(items) => Ok(items[start..end].iter().cloned().collect()),
+        Value::Str(s) => match s.get(start..end) {
+            Some(s) => Ok(Value::Str(s.into())),
+            None => Err(format!("byte range {start}..{end} is not a char boundary")),
+        },
+        _ => unreachable!(),
+    }
+}
+
+fn range(
+    op: &str,
+    start: Option<Value>,
+    end: Option<Value>,
+) -> core::result::Result<Value, String> {
+    let start = match start {
+        None => 0,
+        Some(Value::Int(start)) => start,
+        Some(value) => return Err(format!("cannot use a {} in a range", value.kind())),
+    };
+    let end = match end {
+        None => return Err("unbounded ranges are not supported by the interpreter".into()),
+        Some(Value::Int(end)) => end,
+        Some(value) => return Err(format!("cannot use a {} in a range", value.kind())),
+    };
+    Ok(match op {
+        "..=" => (start..=end).map(Value::Int).collect(),
+        _ => (start..end).map(Value::Int).collect(),
+    })
+}
+
+fn unary(op: &str, value: Value) -> core::result::Result<Value, String> {
+    Ok(match (op, value) {
+        ("!", Value::Bool(value)) => Value::Bool(!value),
+        ("!", Value::Int(value)) => Value::Int(!value),
+        ("-", Value::Int(value)) => match value.checked_neg() {
+            Some(value) => Value::Int(value),
+            None => return Err("attempt to negate with overflow".into()),
+        },
+        ("-", Value::Float(value)) => Value::Float(-value),
+        ("*" | "&", value) => value,
+        (op, value) => {
+            return Err(format!(
+                "cannot apply unary operator `{op}` to a {}",
+                value.kind()
+            ));
+        }
+    })
+}
+
+fn binop(op: &str, left: &Value, right: &Value) -> core::result::Result<Value, String> {
+    let ordering = || {
+        left.partial_cmp(right)
+            .ok_or_else(|| format!("cannot compare a {} with a {}", left.kind(), right.kind()))
+    };
+    let overflow = |name: &str| format!("attempt to {name} with overflow");
+    Ok(match op {
+        "==" => Value::Bool(left == right),
+        "!=" => Value::Bool(left != right),
+        "<" => Value::Bool(ordering()? == Ordering::Less),
+        "<=" => Value::Bool(ordering()? != Ordering::Greater),
+        ">" => Value::Bool(ordering()? == Ordering::Greater),
+        ">=" => Value::Bool(ordering()? != Ordering::Less),
+        _ => match (left, right) {
+            (Value::Int(l), Value::Int(r)) => Value::Int(match op {
+                "+" => l.checked_add(*r).ok_or_else(|| overflow("add"))?,
+                "-" => l.checked_sub(*r).ok_or_else(|| overflow("subtract"))?,
+                "*" => l.checked_mul(*r).ok_or_else(|| overflow("multiply"))?,
+                "/" => l
+                    .checked_div(*r)
+                    .ok_or("attempt to divide by zero or with overflow")?,
+                "%" => l.checked_rem(*r).ok_or(
+                    "attempt to calculate the remainder with a divisor of zero or with overflow",
+                )?,
+                "&" => l & r,
+                "|" => l | r,
+                "^" => l ^ r,
+                "<<" => u32::try_from(*r)
+                    .ok()
+                    .and_then(|r| l.checked_shl(r))
+                    .ok_or_else(|| overflow("shift left"))?,
+                ">>" => u32::try_from(*r)
+                    .ok()
+                    .and_then(|r| l.checked_shr(r))
+                    .ok_or_else(|| overflow("shift right"))?,
+                op => return Err(format!("unknown operator `{op}`")),
+            }),
+            (Value::Bool(l), Value::Bool(r)) => Value::Bool(match op {
+                "&" => l & r,
+                "|" => l | r,
+                "^" => l ^ r,
+                op => return Err(format!("cannot apply operator `{op}` to bools")),
+            }),
+            (Value::Int(_) | Value::Float(_), Value::Int(_) | Value::Float(_)) => {
+                let l = as_f64(left);
+                let r = as_f64(right);
+                Value::Float(match op {
+                    "+" => l + r,
+                    "-" => l - r,
+                    "*" => l * r,
+                    "/" => l / r,
+                    "%" => l % r,
+                    op => return Err(format!("cannot apply operator `{op}` to floats")),
+                })
+            }
+            (Value::Str(l), Value::Str(r)) if op == "+" => {
+                let mut s = String::with_capacity(l.len() + r.len());
+                s.push_str(l);
+                s.push_str(r);
+                Value::Str(s.into())
+            }
+            (left, right) => {
+                return Err(format!(
+                    "cannot apply operator `{op}` to a {} and a {}",
+                    left.kind(),
+                    right.kind()
+                ));
+            }
+        },
+    })
+}
+
+pub(super) fn as_f64(value: &Value) -> f64 {
+    match value {
+        Value::Int(i) => *i as f64,
+        Value::Float(f) => *f,
+        _ => f64::NAN,
+    }
+}
+
+/// Rust's `as` casts between primitive types
+fn cast(value: &Value, ty: &str) -> core::result::Result<Value, String> {
+    macro_rules! cast {
+        ($($ity:ident)*; $($fty:ident)*) => {
+            match (ty, value) {
+                $(
+                    (stringify!($ity), Value::Int(v)) => Value::Int(*v as $ity as i128),
+                    (stringify!($ity), Value::Float(v)) => Value::Int(*v as $ity as i128),
+                    (stringify!($ity), Value::Bool(v)) => Value::Int(*v as $ity as i128),
+                    (stringify!($ity), Value::Char(v)) => Value::Int(*v as u32 as $ity as i128),
+                )*
+                $(
+                    (stringify!($fty), Value::Int(v)) => Value::Float(*v as $fty as f64),
+                    (stringify!($fty), Value::Float(v)) => Value::Float(*v as $fty as f64),
+                )*
+                ("bool", Value::Bool(v)) => Value::Bool(*v),
+                ("char", Value::Char(v)) => Value::Char(*v),
+                ("char", Value::Int(v)) if (0..=255).contains(v) => Value::Char(*v as u8 as char),
+                (ty, value) => return Err(format!("cannot cast a {} as `{ty}`", value.kind())),
+            }
+        };
+    }
+
+    let value = cast!(i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize; f64);
+    Ok(match (ty, value) {
+        ("f32", Value::Float(v)) => Value::Float(f32_to_f64(v as f32)),
+        (_, value) => value,
+    })
+}
+
+fn parse_num(num: &Num<'_>) -> core::result::Result<Value, String> {
+    match *num {
+        Num::Int(s, _) => {
+            let s = s.replace('_', "");
+            let (neg, digits) = match s.strip_prefix('-') {
+                Some(digits) => (true, digits),
+                None => (false, s.as_str()),
+            };
+            let (radix, digits) = match digits.get(..2) {
+                Some("0x") => (16, &digits[2..]),
+                Some("0o") => (8, &digits[2..]),
+                Some("0b") => (2, &digits[2..]),
+                _ => (10, digits),
+            };
+            let value = i128::from_str_radix(digits, radix)
+                .map_err(|err| format!("invalid integer literal {s:?}: {err}"))?;
+            Ok(Value::Int(if neg { -value } else { value }))
+        }
+        Num::Float(s, _) => s
+            .replace('_', "")
+            .parse()
+            .map(Value::Float)
+            .map_err(|err| format!("invalid float literal {s:?}: {err}")),
+    }
+}
+
+pub(super) fn parse_str<'a>(lit: &StrLit<'a>) -> core::result::Result<Cow<'a, str>, String> {
+    if lit.prefix.is_some() {
+        return Err("byte and C string literals are not supported by the interpreter".into());
+    }
+    lit.unescape()
+}
+
+fn option(value: Option<&Value>) -> Value {
+    value.cloned().unwrap_or_default()
+}
+
+/// Call a method of the standard library, returns `None` if the method is unknown
+fn call_method(
+    receiver: &Value,
+    name: &str,
+    args: &[Value],
+) -> Option<core::result::Result<Value, String>> {
+    let arg = |idx: usize| {
+        args.get(idx)
+            .ok_or_else(|| format!("missing argument {} of method `{name}`", idx + 1))
+    };
+    let int_arg = |idx: usize| match arg(idx)? {
+        Value::Int(v) => usize::try_from(*v).map_err(|_| format!("invalid argument {v}")),
+        value => Err(format!("expected an integer, found a {}", value.kind())),
+    };
+    let str_arg = |idx: usize| match arg(idx)? {
+        Value::Str(s) => Ok(s.to_string()),
+        Value::Char(c) => Ok(c.to_string()),
+        value => Err(format!("expected a string, found a {}", value.kind())),
+    };
+
+    let result = (|| {
+        Ok(Some(match (receiver, name) {
+            // generic methods
+            (
+                receiver,
+                "clone" | "to_owned" | "as_ref" | "as_deref" | "borrow" | "deref" | "iter"
+                | "into_iter" | "as_str" | "as_slice" | "to_vec" | "copied" | "cloned",
+            ) => receiver.clone(),
+            (receiver, "to_string") if receiver.is_displayable() => {
+                Value::Str(receiver.to_string().into())
+            }
+            (Value::None, "is_none") => Value::Bool(true),
+            (_, "is_none") => Value::Bool(false),
+            (Value::None, "is_some") => Value::Bool(false),
+            (_, "is_some") => Value::Bool(true),
+            (Value::None, "unwrap" | "expect") => {
+                return Err("called `unwrap()` on a `None` value".into());
+            }
+            (Value::None, "unwrap_or") => arg(0)?.clone(),
+            (Value::None, "unwrap_or_default") => Value::Str("".into()),
+            (receiver, "unwrap" | "expect" | "unwrap_or" | "unwrap_or_default") => receiver.clone(),
+
+            // strings
+            (Value::Str(s), "len") => Value::from(s.len()),
+            (Value::Str(s), "is_empty") => Value::Bool(s.is_empty()),
+            (Value::Str(s), "contains") => Value::Bool(s.contains(&*str_arg(0)?)),
+            (Value::Str(s), "starts_with") => Value::Bool(s.starts_with(&*str_arg(0)?)),
+            (Value::Str(s), "ends_with") => Value::Bool(s.ends_with(&*str_arg(0)?)),
+            (Value::Str(s), "to_uppercase") => Value::from(s.to_uppercase()),
+            (Value::Str(s), "to_lowercase") => Value::from(s.to_lowercase()),
+            (Value::Str(s), "to_ascii_uppercase") => Value::from(s.to_ascii_uppercase()),
+            (Value::Str(s), "to_ascii_lowercase") => Value::from(s.to_ascii_lowercase()),
+            (Value::Str(s), "trim") => Value::from(s.trim()),
+            (Value::Str(s), "trim_start") => Value::from(s.trim_start()),
+            (Value::Str(s), "trim_end") => Value::from(s.trim_end()),
+            (Value::Str(s), "replace") => Value::from(s.replace(&*str_arg(0)?, &str_arg(1)?)),
+            (Value::Str(s), "repeat") => Value::from(s.repeat(int_arg(0)?)),
+            (Value::Str(s), "chars") => s.chars().map(Value::Char).collect(),
+            (Value::Str(s), "lines") => s.lines().map(Value::from).collect(),
+            (Value::Str(s), "split") => s.split(&*str_arg(0)?).map(Value::from).collect(),
+            (Value::Str(s), "split_whitespace") => s.split_whitespace().map(Value::from).collect(),
+
+            // sequences
+            (Value::Seq(items), "len") => Value::from(items.len()),
+            (Value::Seq(items), "is_empty") => Value::Bool(items.is_empty()),
+            (Value::Seq(items), "first") => option(items.first()),
+            (Value::Seq(items), "last") => option(items.last()),
+            (Value::Seq(items), "get") => option(items.get(int_arg(0)?)),
+            (Value::Seq(items), "contains") => Value::Bool(items.contains(arg(0)?)),
+            (Value::Seq(items), "rev") => items.iter().rev().cloned().collect(),
+            (Value::Seq(items), "skip") => items.iter().skip(int_arg(0)?).cloned().collect(),
+            (Value::Seq(items), "take") => items.iter().take(int_arg(0)?).cloned().collect(),
+            (Value::Seq(items), "enumerate") => items
+                .iter()
+                .enumerate()
+                .map(|(idx, item)| Value::Seq([Value::from(idx), item.clone()].into()))
+                .collect(),
+            (Value::Seq(items), "join") => {
+                let sep = str_arg(0)?;
+                let mut s = String::new();
+                for (idx, item) in items.iter().enumerate() {
+                    if idx > 0 {
+                        s.push_str(&sep);
+                    }
+                    let _ = write!(s, "{item}");
+                }
+                Value::from(s)
+            }
+
+            // maps
+            (Value::Map(map), "len") => Value::from(map.len()),
+            (Value::Map(map), "is_empty") => Value::Bool(map.is_empty()),
+            (Value::Map(map), "get") => option(map.get(&*str_arg(0)?)),
+            (Value::Map(map), "contains_key") => Value::Bool(map.contains_key(&*str_arg(0)?)),
+            (Value::Map(map), "keys") => {
+                map.keys().map(|key| Value::Str(Arc::clone(key))).collect()
+            }
+            (Value::Map(map), "values") => map.values().cloned().collect(),
+
+            // numbers
+            (Value::Int(v), "abs") => Value::Int(v.abs()),
+            (Value::Int(v), "pow") => match u32::try_from(int_arg(0)?)
+                .ok()
+                .and_then(|exp| v.checked_pow(exp))
+            {
+                Some(v) => Value::Int(v),
+                None => return Err("attempt to multiply with overflow".into()),
+            },
+            (Value::Int(_) | Value::Float(_), "min" | "max") => {
+                let other = arg(0)?;
+                let less = receiver.partial_cmp(other) == Some(Ordering::Less);
+                match (name, less) {
+                    ("min", true) | ("max", false) => receiver.clone(),
+                    _ => other.clone(),
+                }
+            }
+            (Value::Float(v), "abs") => Value::Float(v.abs()),
+            (Value::Float(v), "round") => Value::Float(v.round()),
+            (Value::Float(v), "floor") => Value::Float(v.floor()),
+            (Value::Float(v), "ceil") => Value::Float(v.ceil()),
+            (Value::Float(v), "trunc") => Value::Float(v.trunc()),
+            (Value::Float(v), "sqrt") => Value::Float(v.sqrt()),
+
+            _ => return Ok(None),
+        }))
+    })();
+    result.transpose()
+}
@@ -0,0 +1,609 @@
+//! Built-in filters, cf. `Generator::visit_filter()`
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::convert::Infallible;
+use core::fmt::Write;
+use core::iter;
+
+use askama_parser::{Expr, Filter, Span, StrLit};
+
+use super::eval::{
+    EvalError, Evaluator, Result, Safety, as_f64, fmt_error, parse_str, value_safety,
+};
+use super::value::DebugValue;
+use super::{DynEscaper, Value};
+use crate::filters::{self, Escaper};
+
+/// Filters that simply call the function of the same name in [`crate::filters`]
+const BUILTIN_FILTERS: &[&str] = &[
+    "capitalize",
+    "center",
+    "indent",
+    "lower",
+    "lowercase",
+    "title",
+    "trim",
+    "truncate",
+    "upper",
+    "uppercase",
+    "wordcount",
+];
+
+/// Built-in filters of compiled templates that the interpreter does not implement
+///
+/// `Environment::compile()` rejects templates that use them, unless a custom filter with the
+/// same name was registered.
+pub(super) const UNSUPPORTED_FILTERS: &[&str] = &[
+    "batch",
+    "currency",
+    "date",
+    "datetime",
+    "default",
+    "first",
+    "groupby",
+    "intcomma",
+    "last",
+    "length",
+    "map",
+    "max",
+    "min",
+    "number",
+    "percent",
+    "reject",
+    "rejectattr",
+    "reverse",
+    "select",
+    "selectattr",
+    "slice",
+    "sort",
+    "sum",
+    "time",
+    "timesince",
+    "timeuntil",
+    "unique",
+    "value",
+];
+
+impl<'a> Evaluator<'a, '_> {
+    /// Apply a filter, and determine if its result needs to be escaped
+    pub(super) fn eval_filter(
+        &mut self,
+        filter: &'a Filter<'a>,
+        span: Span<'a>,
+    ) -> Result<'a, (Value, Safety)> {
+        let name = filter.name;
+        let args = &*filter.arguments;
+        let err = |msg: String| EvalError::new(msg, span);
+        let unexpected_args = || err(format!("unexpected argument(s) in `{name}` filter"));
+
+        Ok(match name {
+            "deref" | "ref" => match args {
+                [arg] => self.eval_safe(arg)?,
+                _ => return Err(unexpected_args()),
+            },
+            "safe" => match args {
+                [arg] => (self.eval(arg)?, Safety::Safe),
+                _ => return Err(unexpected_args()),
+            },
+            "escape" | "e" => {
+                let escaper = match args {
+                    [_] => None,
+                    [_, escaper] => match &**escaper {
+                        Expr::StrLit(StrLit {
+                            prefix: None,
+                            content,
+                        }) => Some(*content),
+                        _ => return Err(err("invalid escaper type for escape filter".into())),
+                    },
+                    _ => return Err(err("only two arguments allowed to escape filter".into())),
+                };
+                let named_escaper;
+                let escaper: &dyn DynEscaper = match escaper {
+                    Some(escaper) => {
+                        named_escaper = self.env.find_escaper(escaper).ok_or_else(|| {
+                            let mut exts = self
+                                .env
+                                .escapers
+                                .iter()
+                                .flat_map(|(exts, _)| exts)
+                                .map(|ext| format!("{ext:?}"))
+                                .collect::<Vec<_>>();
+                            exts.sort();
+                            err(format!(
+                                "invalid escaper '{escaper}' for `escape` filter. The available \
+                             extensions are: {}",
+                                exts.join(", "),
+                            ))
+                        })?;
+                        &*named_escaper
+                    }
+                    None => self.escaper,
+                };
+                let value = self.eval(&args[0])?;
+                let mut buf = String::new();
+                escaper
+                    .write_escaped(&mut buf, &display(&value, span)?)
+                    .map_err(fmt_error)?;
+                (Value::from(buf), Safety::Safe)
+            }
+            "pluralize" => {
+                let (count, sg, pl) = match args {
+                    [count] => (count, None, None),
+                    [count, sg] => (count, Some(sg), None),
+                    [count, sg, pl] => (count, Some(sg), Some(pl)),
+                    _ => return Err(unexpected_args()),
+                };
+                let is_singular = match self.eval(count)? {
+                    Value::Int(count) => count == 1 || count == -1,
+                    value => {
+                        return Err(err(format!(
+                            "`pluralize` expects an integer, found a {}",
+                            value.kind()
+                        )));
+                    }
+                };
+                let (arg, default) = match is_singular {
+                    true => (sg, ""),
+                    false => (pl, "s"),
+                };
+                match arg {
+                    Some(arg) => {
+                        let (value, safety) = self.eval_safe(arg)?;
+                        (self.auto_escape(&value, safety, span)?, Safety::Safe)
+                    }
+                    None => (Value::from(default), Safety::Safe),
+                }
+            }
+            "linebreaks" | "linebreaksbr" | "paragraphbreaks" => {
+                let [arg] = args else {
+                    return Err(unexpected_args());
+                };
+                // The input is always HTML escaped, regardless of the selected escaper.
+                let (value, safety) = self.eval_safe(arg)?;
+                let safety = safety.max(value_safety(&value));
+                let input = display(&value, span)?;
+                let input = match safety {
+                    Safety::Unsafe => {
+                        let mut buf = String::new();
+                        let _ = filters::Html.write_escaped_str(&mut buf, &input);
+                        buf
+                    }
+                    _ => input,
+                };
+                let output = match name {
+                    "linebreaks" => filters::linebreaks(input),
+                    "linebreaksbr" => filters::linebreaksbr(input),
+                    _ => filters::paragraphbreaks(input),
+                };
+                let output = output.map_err(fmt_error)?;
+                (Value::from(output.0), Safety::HtmlSafe)
+            }
+            "urlencode" | "urlencode_strict" => {
+                let [arg] = args else {
+                    return Err(unexpected_args());
+                };
+                #[cfg(feature = "urlencode")]
+                {
+                    let value = display(&self.eval(arg)?, span)?;
+                    let output = match name {
+                        "urlencode" => filters::urlencode(value),
+                        _ => filters::urlencode_strict(value),
+                    };
+                    let output = infallible(output);
+                    (Value::from(output.to_string()), Safety::HtmlSafe)
+                }
+                #[cfg(not(feature = "urlencode"))]
+                {
+                    let _ = arg;
+                    return Err(err(format!(
+                        "the `{name}` filter requires the `urlencode` feature to be enabled"
+                    )));
+                }
+            }
+            "filesizeformat" => {
+                let [arg] = args else {
+                    return Err(unexpected_args());
+                };
+                let value = match self.eval(arg)? {
+                    value @ (Value::Int(_) | Value::Float(_)) => as_f64(&value) as f32,
+                    value => {
+                        return Err(err(format!(
+                            "`filesizeformat` expects a number, found a {}",
+                            value.kind()
+                        )));
+                    }
+                };
+                let output = infallible(filters::filesizeformat(value));
+                (Value::from(output.to_string()), Safety::HtmlSafe)
+            }
+            "json" | "tojson" => {
+                #[cfg(feature = "serde_json")]
+                {
+                    let output = match args {
+                        [arg] => {
+                            let output = infallible(filters::json(self.eval(arg)?));
+                            output.to_string()
+                        }
+                        [arg, indent] => {
+                            let value = self.eval(arg)?;
+                            let output = match self.eval(indent)? {
+                                Value::Int(indent) => {
+                                    let indent = usize::try_from(indent).unwrap_or_default();
+                                    let output = infallible(filters::json_pretty(value, indent));
+                                    output.to_string()
+                                }
+                                Value::Str(indent) => {
+                                    let output = infallible(filters::json_pretty(value, &*indent));
+                                    output.to_string()
+                                }
+                                indent => {
+                                    return Err(err(format!(
+                                        "invalid indentation for `json` filter: {}",
+                                        indent.kind()
+                                    )));
+                                }
+                            };
+                            output
+                        }
+                        _ => return Err(err("unexpected argument(s) in `json` filter".into())),
+                    };
+                    (Value::from(output), Safety::Unsafe)
+                }
+                #[cfg(not(feature = "serde_json"))]
+                {
+                    return Err(err(
+                        "the `json` filter requires the `serde_json` feature to be enabled".into(),
+                    ));
+                }
+            }
+            "fmt" => match args {
+                [arg, fmt] => {
+                    let Expr::StrLit(fmt) = &**fmt else {
+                        return Err(err(r#"use filter fmt like `value|fmt("{:?}")`"#.into()));
+                    };
+                    let fmt = parse_str(fmt).map_err(err)?;
+                    let value = self.eval(arg)?;
+                    (
+                        Value::from(format_value(&fmt, &[value]).map_err(err)?),
+                        Safety::Unsafe,
+                    )
+                }
+                _ => return Err(err(r#"use filter fmt like `value|fmt("{:?}")`"#.into())),
+            },
+            "format" => match args {
+                [fmt, args @ ..] if matches!(**fmt, Expr::StrLit(_)) => {
+                    let Expr::StrLit(fmt) = &**fmt else {
+                        unreachable!()
+                    };
+                    let fmt = parse_str(fmt).map_err(err)?;
+                    let args = self.eval_args(args)?;
+                    (
+                        Value::from(format_value(&fmt, &args).map_err(err)?),
+                        Safety::Unsafe,
+                    )
+                }
+                _ => {
+                    return Err(err(
+                        r#"use filter format like `"a={} b={}"|format(a, b)`"#.into()
+                    ));
+                }
+            },
+            "join" => {
+                let [input, sep] = args else {
+                    return Err(unexpected_args());
+                };
+                let items = match self.eval(input)? {
+                    Value::Seq(items) => items,
+                    value => {
+                        return Err(err(format!(
+                            "`join` expects a sequence, found a {}",
+                            value.kind()
+                        )));
+                    }
+                };
+                let sep = display(&self.eval(sep)?, span)?;
+                let mut output = String::new();
+                for (idx, item) in items.iter().enumerate() {
+                    if idx > 0 {
+                        output.push_str(&sep);
+                    }
+                    output.push_str(&display(item, span)?);
+                }
+                (Value::from(output), Safety::Unsafe)
+            }
+            name if BUILTIN_FILTERS.contains(&name) => {
+                let args = self.eval_args(args)?;
+                (self.builtin_filter(name, &args, span)?, Safety::Unsafe)
+            }
+            name => {
+                let Some(filter) = self.env.filters.get(name) else {
+                    return Err(err(format!("unknown filter `{name}`")));
+                };
+                let args = self.eval_args(args)?;
+                let (input, args) = args.split_first().unwrap_or((&Value::None, &[]));
+                let value = filter(input, args).map_err(|error| EvalError {
+                    error,
+                    span: Some(span),
+                })?;
+                (value, Safety::Unsafe)
+            }
+        })
+    }
+
+    fn builtin_filter(&self, name: &str, args: &[Value], span: Span<'a>) -> Result<'a, Value> {
+        let err = |msg: String| EvalError::new(msg, span);
+        let (input, args) = args.split_first().unwrap_or((&Value::None, &[]));
+        let input = display(input, span)?;
+        let width = || match args {
+            [Value::Int(width)] => usize::try_from(*width)
+                .map_err(|_| err(format!("invalid argument for `{name}` filter: {width}"))),
+            _ => Err(err(format!("unexpected argument(s) in `{name}` filter"))),
+        };
+        if !matches!(name, "center" | "indent" | "truncate") && !args.is_empty() {
+            return Err(err(format!("unexpected argument(s) in `{name}` filter")));
+        }
+
+        let output = match name {
+            "capitalize" => filters::capitalize(input),
+            "center" => {
+                let output = infallible(filters::center(input, width()?));
+                Ok(output.to_string())
+            }
+            "indent" => filters::indent(input, width()?),
+            "lower" | "lowercase" => filters::lower(input),
+            "title" => filters::title(input),
+            "trim" => Ok(input.trim().to_string()),
+            "truncate" => {
+                let output = infallible(filters::truncate(input, width()?));
+                Ok(output.to_string())
+            }
+            "upper" | "uppercase" => filters::upper(input),
+            "wordcount" => {
+                let count = filters::wordcount(input).map_err(fmt_error)?;
+                return Ok(Value::from(count));
+            }
+            _ => unreachable!(),
+        };
+        Ok(Value::from(output.map_err(fmt_error)?))
+    }
+
+    /// Escape `value` with the template's escaper, unless it is safe
+    fn auto_escape(&self, value: &Value, safety: Safety, span: Span<'a>) -> Result<'a, Value> {
+        let safety = safety.max(value_safety(value));
+        let value = display(value, span)?;
+        if safety == Safety::Safe || (safety == Safety::HtmlSafe && self.escaper.is_html()) {
+            return Ok(Value::from(value));
+        }
+        let mut buf = String::new();
+        self.escaper
+            .write_escaped(&mut buf, &value)
+            .map_err(fmt_error)?;
+        Ok(Value::from(buf))
+    }
+}
+
+fn infallible<T>(result: core::result::Result<T, Infallible>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(err) => match err {},
+    }
+}
+
+/// The string representation of a value, if it can be displayed
+fn display<'a>(value: &Value, span: Span<'a>) -> Result<'a, String> {
+    match value {
+        Value::Str(s) => Ok(s.to_string()),
+        value if value.is_displayable() => Ok(value.to_string()),
+        value => Err(EvalError::new(
+            format_args!("cannot display a {} value", value.kind()),
+            span,
+        )),
+    }
+}
+
+/// A runtime implementation of the most common parts of [`format!()`][alloc::format]
+fn format_value(fmt: &str, args: &[Value]) -> core::result::Result<String, String> {
+    let mut output = String::new();
+    let mut next_arg = 0;
+    let mut rest = fmt;
+    while let Some(pos) = rest.find(['{', '}']) {
+        output.push_str(&rest[..pos]);
+        let brace = rest.as_bytes()[pos];
+        rest = &rest[pos + 1..];
+        if rest.as_bytes().first() == Some(&brace) {
+            output.push(brace as char);
+            rest = &rest[1..];
+            continue;
+        } else if brace == b'}' {
+            return Err(format!(
+                "invalid format string {fmt:?}: unmatched `}}` found"
+            ));
+        }
+
+        let end = rest
+            .find('}')
+            .ok_or_else(|| format!("invalid format string {fmt:?}: expected `}}`"))?;
+        let (arg, spec) = rest[..end].split_once(':').unwrap_or((&rest[..end], ""));
+        rest = &rest[end + 1..];
+
+        let arg = match arg.trim() {
+            "" => {
+                next_arg += 1;
+                next_arg - 1
+            }
+            arg => arg.parse::<usize>().map_err(|_| {
+                format!("named argument `{arg}` is not supported in format strings")
+            })?,
+        };
+        let value = args.get(arg).ok_or_else(|| {
+            format!(
+                "invalid reference to positional argument {arg} ({} argument(s) given)",
+                args.len()
+            )
+        })?;
+        Spec::parse(spec)
+            .ok_or_else(|| format!("unsupported format specification {spec:?}"))?
+            .write(&mut output, value)?;
+    }
+    output.push_str(rest);
+    if next_arg < args.len() {
+        return Err(format!(
+            "{} argument(s) never used in format string {fmt:?}",
+            args.len() - next_arg
+        ));
+    }
+    Ok(output)
+}
+
+/// A parsed format specification, i.e. the part after the `:` in `{:>8.2}`
+#[derive(Default)]
+struct Spec {
+    fill: Option<char>,
+    align: Option<char>,
+    plus: bool,
+    alternate: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    kind: char,
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Option<Self> {
+        let mut result = Self::default();
+        let mut chars = spec;
+
+        let mut iter = chars.chars();
+        match (iter.next(), iter.next()) {
+            (Some(fill), Some(align @ ('<' | '^' | '>'))) => {
+                result.fill = Some(fill);
+                result.align = Some(align);
+                chars = iter.as_str();
+            }
+            (Some(align @ ('<' | '^' | '>')), _) => {
+                result.align = Some(align);
+                chars = &chars[1..];
+            }
+            _ => {}
+        }
+        if let Some(tail) = chars.strip_prefix('+') {
+            result.plus = true;
+            chars = tail;
+        }
+        if let Some(tail) = chars.strip_prefix('#') {
+            result.alternate = true;
+            chars = tail;
+        }
+        if let Some(tail) = chars.strip_prefix('0') {
+            result.zero = true;
+            chars = tail;
+        }
+        let digits = chars
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(chars.len());
+        if digits > 0 {
+            result.width = chars[..digits].parse().ok()?;
+            chars = &chars[digits..];
+        }
+        if let Some(tail) = chars.strip_prefix('.') {
+            let digits = tail
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(tail.len());
+            result.precision = Some(tail[..digits].parse().ok()?);
+            chars = &tail[digits..];
+        }
+        result.kind = match chars {
+            "" => ' ',
+            "?" | "x" | "X" | "o" | "b" | "e" | "E" => chars.chars().next()?,
+            _ => return None,
+        };
+        Some(result)
+    }
+
+    fn write(&self, output: &mut String, value: &Value) -> core::result::Result<(), String> {
+        let is_number = matches!(value, Value::Int(_) | Value::Float(_));
+        let mut prefix = "";
+        let body = match (self.kind, value) {
+            (' ', Value::Float(v)) => match self.precision {
+                Some(precision) => format!("{v:.precision$}"),
+                None => v.to_string(),
+            },
+            (' ', Value::Str(s)) => match self.precision {
+                Some(precision) => s.chars().take(precision).collect(),
+                None => s.to_string(),
+            },
+            (' ', value) if value.is_displayable() => value.to_string(),
+            ('?', Value::Float(v)) => match self.precision {
+                Some(precision) => format!("{v:.precision$?}"),
+                None => format!("{v:?}"),
+            },
+            ('?', Value::Str(s)) => format!("{s:?}"),
+            ('?', Value::Char(c)) => format!("{c:?}"),
+            ('?', value) => DebugValue(value).to_string(),
+            ('x', Value::Int(v)) => {
+                prefix = "0x";
+                format!("{v:x}")
+            }
+            ('X', Value::Int(v)) => {
+                prefix = "0x";
+                format!("{v:X}")
+            }
+            ('o', Value::Int(v)) => {
+                prefix = "0o";
+                format!("{v:o}")
+            }
+            ('b', Value::Int(v)) => {
+                prefix = "0b";
+                format!("{v:b}")
+            }
+            ('e' | 'E', Value::Int(_) | Value::Float(_)) => {
+                let v = as_f64(value);
+                let s = match self.precision {
+                    Some(precision) => format!("{v:.precision$e}"),
+                    None => format!("{v:e}"),
+                };
+                match self.kind {
+                    'E' => s.to_uppercase(),
+                    _ => s,
+                }
+            }
+            (kind, value) => {
+                return Err(format!(
+                    "cannot format a {} with `{{:{kind}}}`",
+                    value.kind()
+                ));
+            }
+        };
+
+        let (sign, body) = match body.strip_prefix('-') {
+            Some(body) if is_number => ("-", body),
+            _ if is_number && self.plus => ("+", body.as_str()),
+            _ => ("", body.as_str()),
+        };
+        let prefix = if self.alternate { prefix } else { "" };
+        let len = sign.len() + prefix.len() + body.chars().count();
+        let padding = self.width.saturating_sub(len);
+
+        if self.zero && is_number {
+            output.push_str(sign);
+            output.push_str(prefix);
+            output.extend(iter::repeat('0').take(padding));
+            output.push_str(body);
+            return Ok(());
+        }
+
+        let align = self.align.unwrap_or(if is_number { '>' } else { '<' });
+        let (before, after) = match align {
+            '<' => (0, padding),
+            '^' => (padding / 2, padding - padding / 2),
+            _ => (padding, 0),
+        };
+        let fill = self.fill.unwrap_or(' ');
+        output.extend(iter::repeat(fill).take(before));
+        let _ = write!(output, "{sign}{prefix}{body}");
+        output.extend(iter::repeat(fill).take(after));
+        Ok(())
+    }
+}
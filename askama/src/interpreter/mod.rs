@@ -0,0 +1,900 @@
+//! Rendering templates at runtime, without compiling them first
+//!
+//! The [`#[derive(Template)]`][crate::Template] macro needs to know the template when your
+//! program is compiled. If your templates are stored in a database, are edited by your customers,
+//! or should be replaceable without rebuilding your program, you can use the interpreter instead.
+//! It understands the same template language, and it evaluates the same AST that the derive macro
+//! uses, with the same whitespace control, inheritance, includes, macros and filters.
+//! Instead of a struct, any [`serde::Serialize`] value can be used as template context, or a
+//! [`Value`] you built yourself.
+//!
+//! ```
+//! # use askama::interpreter::Environment;
+//! # use std::collections::HashMap;
+//! let mut env = Environment::new();
+//! env.add_template("base.html", "<h1>{% block title %}{% endblock %}</h1>")?;
+//! env.add_template(
+//!     "hello.html",
+//!     r#"{% extends "base.html" %}{% block title %}Hello, {{ name }}!{% endblock %}"#,
+//! )?;
+//!
+//! let context = HashMap::from([("name", "<world>")]);
+//! assert_eq!(env.render("hello.html", &context)?, "<h1>Hello, &#60;world&#62;!</h1>");
+//! # Ok::<(), askama::interpreter::Error>(())
+//! ```
+//!
+//! Because there are no Rust types at runtime, a few things work differently than in compiled
+//! templates:
+//!
+//! * Calls of methods and functions are limited to a set of common methods of the standard
+//!   library like `len()`, `is_empty()` or `contains()`, and to functions that you registered
+//!   with [`Environment::add_function()`]. A call `{{ name(…) }}` or `{{ self.name(…) }}` calls
+//!   the registered function `name`, too.
+//! * Custom filters must be registered with [`Environment::add_filter()`].
+//! * Rust macros like `{{ format!(…) }}` are not supported.
+//! * The `|value` filter and `askama::get_value()` are not supported.
+//! * The filters that need a feature or a group in the `builtin_filters` of the configuration
+//!   file, e.g. `|default`, `|sort`, `|date` or `|number`, and the translation function `t()`
+//!   are not supported. A template that uses them is rejected, unless you registered a filter or
+//!   function with the same name.
+//! * [Context-aware escaping](crate::filters::Attr) is not supported.
+//!
+//! If the templates come from untrusted users, limit the resources a template can use with
+//! [`Environment::set_max_depth()`] and [`Environment::set_max_output()`].
+
+mod compile;
+mod eval;
+mod filters;
+mod value;
+
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::any::TypeId;
+use core::cell::RefCell;
+use core::{fmt, slice};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::SystemTime;
+
+use askama_parser::node::IncludeTarget;
+use askama_parser::{Node, Parsed, Span, SyntaxBuilder};
+use serde::Serialize;
+
+use self::compile::Op;
+pub use self::value::Value;
+use crate::filters::{Escaper, Html, Js, Text};
+
+/// Parsed template files, and their modification time
+type FileCache = HashMap<PathBuf, (Option<SystemTime>, Arc<Parsed>)>;
+
+/// The last compiled version of each rendered template
+type CompiledCache = HashMap<String, Arc<Compiled>>;
+
+/// File name extensions, and the escaper to use for them
+type Escapers = Vec<(Vec<Cow<'static, str>>, Arc<dyn DynEscaper>)>;
+
+/// Signature of a custom filter or function, see [`Environment::add_filter()`]
+type Callback = dyn Fn(&Value, &[Value]) -> Result<Value, Error> + Send + Sync;
+
+/// A set of templates, and the settings to render them
+///
+/// Templates are either added directly with [`Environment::add_template()`], or they are read
+/// from the directories registered with [`Environment::add_dir()`]. Template names are resolved
+/// just like the `path` argument of `#[template(path = "…")]`, `{% include %}` and `{% extends %}`:
+/// a path is first tried relative to the including template, then relative to the roots.
+pub struct Environment {
+    /// Templates that were added with [`Environment::add_template()`]
+    templates: HashMap<String, Arc<Parsed>>,
+    /// Directories to read templates from
+    dirs: Vec<PathBuf>,
+    files: Mutex<FileCache>,
+    compiled: Mutex<CompiledCache>,
+    syntax: Syntax,
+    whitespace: Whitespace,
+    escapers: Escapers,
+    filters: HashMap<String, Arc<Callback>>,
+    functions: HashMap<String, Arc<Callback>>,
+    max_depth: usize,
+    max_output: Option<usize>,
+}
+
+impl Environment {
+    /// Default value of [`Environment::set_max_depth()`]
+    pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+    /// Create a new environment without templates and with askama's default settings
+    pub fn new() -> Self {
+        let escapers: [(&[&'static str], Arc<dyn DynEscaper>); 3] = [
+            (
+                &[
+                    "askama", "html", "htm", "j2", "jinja", "jinja2", "rinja", "svg", "xml",
+                ],
+                Arc::new(Html),
+            ),
+            (&["js", "mjs"], Arc::new(Js)),
+            (&["md", "none", "txt", "yml", ""], Arc::new(Text)),
+        ];
+        Self {
+            templates: HashMap::new(),
+            dirs: Vec::new(),
+            files: Mutex::default(),
+            compiled: Mutex::default(),
+            syntax: Syntax::default(),
+            whitespace: Whitespace::default(),
+            escapers: escapers
+                .into_iter()
+                .map(|(exts, escaper)| (exts.iter().map(|&ext| ext.into()).collect(), escaper))
+                .collect(),
+            filters: HashMap::new(),
+            functions: HashMap::new(),
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            max_output: None,
+        }
+    }
+
+    /// Add a template named `name`, replacing any template of the same name
+    ///
+    /// The template is parsed immediately, so syntax errors are reported by this function.
+    pub fn add_template(
+        &mut self,
+        name: impl Into<String>,
+        source: impl Into<Arc<str>>,
+    ) -> Result<(), Error> {
+        let name = name.into();
+        let parsed = self.parse(&name, source.into(), None)?;
+        self.templates.insert(name, Arc::new(parsed));
+        Ok(())
+    }
+
+    /// Read templates that were not added with [`Environment::add_template()`] from `dir`
+    ///
+    /// The files are read when they are needed. A file is parsed again if it was modified since
+    /// it was last used. Template names that are absolute, or that would leave `dir` with `..`,
+    /// are rejected.
+    pub fn add_dir(&mut self, dir: impl Into<PathBuf>) {
+        self.dirs.push(dir.into());
+    }
+
+    /// Use a custom syntax, like the `[[syntax]]` sections of the configuration file
+    ///
+    /// The templates that were already added are parsed again. If the syntax is invalid, or if a
+    /// template cannot be parsed with the new syntax, the previous syntax is kept.
+    pub fn set_syntax(&mut self, syntax: Syntax) -> Result<(), Error> {
+        syntax.to_parser_syntax()?;
+        let syntax = core::mem::replace(&mut self.syntax, syntax);
+        let templates = self
+            .templates
+            .iter()
+            .map(|(name, parsed)| {
+                let parsed = self.parse(name, parsed.source().into(), None)?;
+                Ok((name.clone(), Arc::new(parsed)))
+            })
+            .collect::<Result<_, Error>>();
+        match templates {
+            Ok(templates) => {
+                self.templates = templates;
+                self.files
+                    .get_mut()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .clear();
+                self.clear_compiled();
+                Ok(())
+            }
+            Err(err) => {
+                self.syntax = syntax;
+                Err(err)
+            }
+        }
+    }
+
+    /// Set the default whitespace handling, like the `whitespace` setting of the configuration
+    /// file
+    pub fn set_whitespace(&mut self, whitespace: Whitespace) {
+        self.whitespace = whitespace;
+        self.clear_compiled();
+    }
+
+    /// Use `escaper` for templates with one of the file name `extensions`, like the
+    /// `[[escaper]]` sections of the configuration file
+    pub fn set_escaper<I, S, E>(&mut self, extensions: I, escaper: E)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<Cow<'static, str>>,
+        E: Escaper + Send + Sync + 'static,
+    {
+        let extensions = extensions.into_iter().map(Into::into).collect::<Vec<_>>();
+        for (exts, _) in &mut self.escapers {
+            exts.retain(|ext| !extensions.contains(ext));
+        }
+        self.escapers.insert(0, (extensions, Arc::new(escaper)));
+    }
+
+    /// Limit how deeply includes, macro calls and recursive loops can be nested
+    ///
+    /// Includes and macro calls are counted while the template is compiled, so a template that
+    /// includes itself is rejected with an [`ErrorKind::Compile`] error. A recursive loop that
+    /// goes deeper than `depth` levels fails with an [`ErrorKind::Render`] error.
+    /// The default is [`Environment::DEFAULT_MAX_DEPTH`].
+    pub fn set_max_depth(&mut self, depth: usize) {
+        self.max_depth = depth;
+        self.clear_compiled();
+    }
+
+    /// Limit the size of the rendered output to `len` bytes, or remove the limit with `None`
+    ///
+    /// Rendering fails with an [`ErrorKind::Render`] error once the limit is exceeded. The text
+    /// that is captured by `{% filter %}` and `{% set %}` blocks or by `loop(…)` counts against
+    /// the limit, too, so a template that captures its output can fail before it wrote `len`
+    /// bytes. The output is not limited by default.
+    pub fn set_max_output(&mut self, len: Option<usize>) {
+        self.max_output = len;
+    }
+
+    /// Register a custom filter
+    ///
+    /// The filter is called with the filtered value and the list of arguments, so
+    /// `{{ a|name(b, c) }}` calls `filter(&a, &[b, c])`. Like in compiled templates, the result
+    /// is escaped.
+    pub fn add_filter<F>(&mut self, name: impl Into<String>, filter: F)
+    where
+        F: Fn(&Value, &[Value]) -> Result<Value, Error> + Send + Sync + 'static,
+    {
+        self.filters.insert(name.into(), Arc::new(filter));
+    }
+
+    /// Register a function that can be called in a template
+    ///
+    /// The function is called with [`Value::None`] and the list of arguments, so
+    /// `{{ name(a, b) }}` calls `function(&Value::None, &[a, b])`. A call of a path like
+    /// `{{ module::name(a, b) }}` looks for a function named `"module::name"` first,
+    /// then for a function named `"name"`.
+    pub fn add_function<F>(&mut self, name: impl Into<String>, function: F)
+    where
+        F: Fn(&Value, &[Value]) -> Result<Value, Error> + Send + Sync + 'static,
+    {
+        self.functions.insert(name.into(), Arc::new(function));
+    }
+
+    /// Render the template `name` with the serializable `context` into a new string
+    pub fn render<T: Serialize + ?Sized>(&self, name: &str, context: &T) -> Result<String, Error> {
+        let mut buf = String::new();
+        self.render_into(&mut buf, name, context)?;
+        Ok(buf)
+    }
+
+    /// Render the template `name` with the serializable `context` into `writer`
+    pub fn render_into<T: Serialize + ?Sized>(
+        &self,
+        writer: &mut (impl fmt::Write + ?Sized),
+        name: &str,
+        context: &T,
+    ) -> Result<(), Error> {
+        self.render_value_into(writer, name, &Value::from_serialize(context)?)
+    }
+
+    /// Render the template `name` with the `context` into `writer`
+    ///
+    /// The fields of a [`Value::Map`] context are the variables of the template.
+    pub fn render_value_into(
+        &self,
+        mut writer: &mut (impl fmt::Write + ?Sized),
+        name: &str,
+        context: &Value,
+    ) -> Result<(), Error> {
+        let compiled = self.compile(name, context)?;
+        let sources = &compiled.sources;
+        let escaper = self.escaper_for(&sources.root)?;
+        eval::Evaluator::new(self, &*escaper, context)
+            .run(compiled.ops(), &mut writer)
+            .map_err(|err| sources.render_error(err))
+    }
+
+    /// Compile the template `name`, or reuse the last compiled version if it is still current
+    fn compile(&self, name: &str, context: &Value) -> Result<Arc<Compiled>, Error> {
+        let sources = self.load(name)?;
+        let cached = self
+            .compiled
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(name)
+            .cloned();
+        if let Some(compiled) = cached {
+            if compiled.is_current(&sources, context) {
+                return Ok(compiled);
+            }
+        }
+
+        let sources = Arc::new(sources);
+        let contexts = sources
+            .templates
+            .keys()
+            .map(|name| {
+                compile::ensure_supported(
+                    sources.nodes(name),
+                    &|name| self.filters.contains_key(name),
+                    &|name| self.functions.contains_key(name),
+                )
+                .map_err(|err| sources.compile_error(err))?;
+                let ctx = compile::Context::new(&sources, name)
+                    .map_err(|err| sources.compile_error(err))?;
+                Ok((name.as_str(), Rc::new(ctx)))
+            })
+            .collect::<Result<HashMap<_, _>, Error>>()?;
+
+        // `{% if x is defined %}` is decided while compiling, so the compiled template can only
+        // be reused for contexts that define the same of these variables.
+        let defined = RefCell::new(Vec::new());
+        let is_field = |name: &str| {
+            let is_field = context.get(name).is_some();
+            defined.borrow_mut().push((name.to_string(), is_field));
+            is_field
+        };
+        let ops = compile::Compiler::new(
+            &sources,
+            &contexts,
+            self.whitespace.into(),
+            self.max_depth,
+            &is_field,
+        )
+        .compile(&sources.root)
+        .map_err(|err| sources.compile_error(err))?;
+        drop(contexts);
+
+        // SAFETY: `ops` only borrows from the heap allocations of `sources`, which is kept alive
+        // and is never modified. `Compiled::ops()` limits the lifetime to `Compiled`.
+        let ops = unsafe { core::mem::transmute::<Vec<Op<'_>>, Vec<Op<'static>>>(ops) };
+        let compiled = Arc::new(Compiled {
+            ops,
+            sources,
+            defined: defined.into_inner(),
+        });
+        self.compiled
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(name.to_string(), Arc::clone(&compiled));
+        Ok(compiled)
+    }
+
+    fn clear_compiled(&mut self) {
+        self.compiled
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    /// Find the escaper for the file name extension of the template `name`
+    fn escaper_for(&self, name: &str) -> Result<Arc<dyn DynEscaper>, Error> {
+        let ext = Path::new(name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default();
+        self.find_escaper(ext).ok_or_else(|| {
+            Error::new(
+                ErrorKind::Compile,
+                format_args!("no escaper defined for extension '{ext}'"),
+            )
+        })
+    }
+
+    fn find_escaper(&self, ext: &str) -> Option<Arc<dyn DynEscaper>> {
+        self.escapers
+            .iter()
+            .find(|(exts, _)| exts.iter().any(|e| e == ext))
+            .map(|(_, escaper)| Arc::clone(escaper))
+    }
+
+    fn parse(
+        &self,
+        name: &str,
+        source: Arc<str>,
+        file_path: Option<Arc<Path>>,
+    ) -> Result<Parsed, Error> {
+        let syntax = self.syntax.to_parser_syntax()?;
+        Parsed::new(Arc::clone(&source), file_path, &syntax).map_err(|err| Error {
+            kind: ErrorKind::Syntax,
+            message: match err.message {
+                Some(message) => message.into_owned(),
+                None => "failed to parse template source".into(),
+            },
+            location: Some(Location::new(name, &source, err.offset)),
+            source: None,
+        })
+    }
+
+    /// Get the template `name`, if it exists
+    ///
+    /// Names that are absolute or that leave the template directories with `..` are rejected.
+    fn get(&self, name: &str) -> Result<Option<Arc<Parsed>>, Error> {
+        if let Some(parsed) = self.templates.get(name) {
+            return Ok(Some(Arc::clone(parsed)));
+        }
+        if self.dirs.is_empty() {
+            return Ok(None);
+        }
+        let Some(relative) = normalize_name(name) else {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format_args!("template name {name:?} is not inside the template directories"),
+            ));
+        };
+        for dir in &self.dirs {
+            let path = dir.join(&relative);
+            let Ok(metadata) = path.metadata() else {
+                continue;
+            };
+            if !metadata.is_file() {
+                continue;
+            }
+            let modified = metadata.modified().ok();
+
+            let files = self.files.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Some((time, parsed)) = files.get(&path) {
+                if modified.is_some() && *time == modified {
+                    return Ok(Some(Arc::clone(parsed)));
+                }
+            }
+            drop(files);
+
+            let mut source = std::fs::read_to_string(&path).map_err(|err| Error {
+                kind: ErrorKind::Io,
+                message: format!("could not read template file {}", path.display()),
+                location: None,
+                source: Some(Box::new(err)),
+            })?;
+            // like the derive macro, drop a single trailing newline of template files
+            if source.ends_with('\n') {
+                let _ = source.pop();
+            }
+            let parsed = Arc::new(self.parse(name, source.into(), Some(path.as_path().into()))?);
+            self.files
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert(path, (modified, Arc::clone(&parsed)));
+            return Ok(Some(parsed));
+        }
+        Ok(None)
+    }
+
+    /// Load the template `name` and all templates it depends on
+    fn load(&self, name: &str) -> Result<Sources, Error> {
+        let parsed = self.get(name)?.ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format_args!("template {name:?} not found"),
+            )
+        })?;
+        let mut sources = Sources {
+            root: name.to_string(),
+            templates: HashMap::from([(name.to_string(), parsed)]),
+            resolved: HashMap::new(),
+        };
+
+        let mut queue = vec![name.to_string()];
+        while let Some(includer) = queue.pop() {
+            let parsed = Arc::clone(&sources.templates[&includer]);
+            let mut paths = Vec::new();
+            collect_paths(parsed.nodes(), &mut paths);
+            for (alternatives, ignore_missing) in paths {
+                let resolved = sources.resolved.entry(includer.clone()).or_default();
+                if alternatives.iter().any(|path| resolved.contains_key(*path)) {
+                    continue;
+                }
+
+                // Like `Config::find_include()`: the first of the alternatives that exists is
+                // used, and the path is tried relative to the includer first.
+                let mut found = None;
+                'find: for &path in alternatives {
+                    let relative = Path::new(&includer).with_file_name(path);
+                    let candidates = relative.to_str().into_iter().chain([path]);
+                    // Paths that would leave the template directories are skipped.
+                    for candidate in candidates.filter_map(normalize_name) {
+                        if let Some(parsed) = sources.templates.get(&candidate) {
+                            found = Some((path, candidate, Arc::clone(parsed)));
+                            break 'find;
+                        } else if let Some(parsed) = self.get(&candidate)? {
+                            found = Some((path, candidate, parsed));
+                            break 'find;
+                        }
+                    }
+                }
+                let Some((path, target, parsed)) = found else {
+                    if ignore_missing {
+                        continue;
+                    }
+                    let message = match alternatives {
+                        [path] => format!("template {path:?} not found"),
+                        _ => format!("none of the templates {alternatives:?} was found"),
+                    };
+                    return Err(Error {
+                        kind: ErrorKind::NotFound,
+                        message,
+                        location: sources.locate(Span::from(alternatives[0])),
+                        source: None,
+                    });
+                };
+
+                resolved.insert(path.to_string(), target.clone());
+                if !sources.templates.contains_key(&target) {
+                    sources.templates.insert(target.clone(), parsed);
+                    queue.push(target);
+                }
+            }
+        }
+        Ok(sources)
+    }
+}
+
+impl Default for Environment {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut templates = self.templates.keys().collect::<Vec<_>>();
+        templates.sort();
+        f.debug_struct("Environment")
+            .field("templates", &templates)
+            .field("dirs", &self.dirs)
+            .field("syntax", &self.syntax)
+            .field("whitespace", &self.whitespace)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Resolve `.` and `..` in a relative template name
+///
+/// Returns `None` if the name is absolute, or if it would leave its root with `..`.
+fn normalize_name(name: &str) -> Option<String> {
+    let mut parts = Vec::new();
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_str()?),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                parts.pop()?;
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// Collect the paths of all `{% extends %}`, `{% include %}` and `{% import %}` nodes
+fn collect_paths<'a>(nodes: &'a [Node<'a>], paths: &mut Vec<(&'a [&'a str], bool)>) {
+    for node in nodes {
+        match node {
+            Node::Extends(e) => paths.push((slice::from_ref(&e.path), false)),
+            Node::Include(i) => match &i.target {
+                IncludeTarget::Dynamic(_, candidates) => paths.extend(
+                    candidates
+                        .iter()
+                        .map(|path| (slice::from_ref(path), i.ignore_missing)),
+                ),
+                target => paths.push((target.paths(), i.ignore_missing)),
+            },
+            Node::Import(i) => paths.push((slice::from_ref(&i.path), false)),
+            Node::If(i) => {
+                for cond in &i.branches {
+                    collect_paths(&cond.nodes, paths);
+                }
+            }
+            Node::Loop(l) => {
+                collect_paths(&l.body, paths);
+                collect_paths(&l.else_nodes, paths);
+            }
+            Node::Match(m) => {
+                for arm in &m.arms {
+                    collect_paths(&arm.nodes, paths);
+                }
+            }
+            Node::BlockDef(b) => collect_paths(&b.nodes, paths),
+            Node::Macro(m) => collect_paths(&m.nodes, paths),
+            Node::FilterBlock(f) => collect_paths(&f.nodes, paths),
+            Node::Call(c) => {
+                if let Some(caller) = &c.caller {
+                    collect_paths(&caller.nodes, paths);
+                }
+            }
+            Node::Let(l) => {
+                if let Some(block) = &l.block {
+                    collect_paths(&block.nodes, paths);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A compiled template, and the sources it borrows from
+struct Compiled {
+    // `ops` borrows from `sources`, so `ops` must be declared before `sources`
+    ops: Vec<Op<'static>>,
+    sources: Arc<Sources>,
+    /// The answers of the context to the `is defined` tests that were decided while compiling
+    defined: Vec<(String, bool)>,
+}
+
+impl Compiled {
+    // The return value's lifetime must be limited to `self` to uphold the unsafe invariant.
+    fn ops(&self) -> &[Op<'_>] {
+        &self.ops
+    }
+
+    /// Returns `true` if the template can be used for `sources` and `context`, i.e. if no
+    /// template file was modified, and if the `is defined` tests give the same results
+    fn is_current(&self, sources: &Sources, context: &Value) -> bool {
+        let old = &self.sources;
+        old.root == sources.root
+            && old.resolved == sources.resolved
+            && old.templates.len() == sources.templates.len()
+            && old.templates.iter().all(|(name, parsed)| {
+                sources
+                    .templates
+                    .get(name)
+                    .is_some_and(|new| Arc::ptr_eq(parsed, new))
+            })
+            && self
+                .defined
+                .iter()
+                .all(|(name, is_field)| context.get(name).is_some() == *is_field)
+    }
+}
+
+/// The templates that are needed to render a template
+pub(crate) struct Sources {
+    /// Name of the rendered template
+    root: String,
+    templates: HashMap<String, Arc<Parsed>>,
+    /// `resolved[includer][path]` is the name of the template that `includer` refers to as `path`
+    resolved: HashMap<String, HashMap<String, String>>,
+}
+
+impl Sources {
+    pub(crate) fn nodes(&self, name: &str) -> &[Node<'_>] {
+        self.templates[name].nodes()
+    }
+
+    /// The name of the template that `includer` refers to as `path`
+    pub(crate) fn resolve(&self, includer: &str, path: &str) -> &str {
+        &self.resolved[includer][path]
+    }
+
+    /// Returns `false` for templates of `{% include … ignore missing %}` that were not found
+    pub(crate) fn is_resolved(&self, includer: &str, path: &str) -> bool {
+        self.resolved
+            .get(includer)
+            .is_some_and(|resolved| resolved.contains_key(path))
+    }
+
+    /// Find the template that contains `span`
+    fn locate(&self, span: Span<'_>) -> Option<Location> {
+        self.templates.iter().find_map(|(name, parsed)| {
+            let offset = span.offset_from(parsed.source())?;
+            Some(Location::new(name, parsed.source(), offset))
+        })
+    }
+
+    fn compile_error(&self, err: compile::CompileError<'_>) -> Error {
+        Error {
+            kind: ErrorKind::Compile,
+            message: err.message,
+            location: self.locate(err.span),
+            source: None,
+        }
+    }
+
+    fn render_error(&self, err: eval::EvalError<'_>) -> Error {
+        let location = err.span.and_then(|span| self.locate(span));
+        let mut err = err.error;
+        if err.location.is_none() {
+            err.location = location;
+        }
+        err
+    }
+}
+
+/// How whitespace around blocks is handled, like the `whitespace` setting of the configuration
+/// file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Whitespace {
+    /// Keep all whitespace, unless a block uses `-` or `~`
+    #[default]
+    Preserve,
+    /// Remove all whitespace around blocks, unless a block uses `+` or `~`
+    Suppress,
+    /// Collapse whitespace around blocks into a single space or newline, unless a block uses `+`
+    /// or `-`
+    Minimize,
+}
+
+impl From<Whitespace> for askama_parser::node::Whitespace {
+    fn from(value: Whitespace) -> Self {
+        match value {
+            Whitespace::Preserve => Self::Preserve,
+            Whitespace::Suppress => Self::Suppress,
+            Whitespace::Minimize => Self::Minimize,
+        }
+    }
+}
+
+/// Custom delimiters, like a `[[syntax]]` section of the configuration file
+///
+/// All delimiters that are not set use askama's default delimiters.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Syntax {
+    /// Defaults to `"{%"`
+    pub block_start: Option<String>,
+    /// Defaults to `"%}"`
+    pub block_end: Option<String>,
+    /// Defaults to `"{{"`
+    pub expr_start: Option<String>,
+    /// Defaults to `"}}"`
+    pub expr_end: Option<String>,
+    /// Defaults to `"{#"`
+    pub comment_start: Option<String>,
+    /// Defaults to `"#}"`
+    pub comment_end: Option<String>,
+}
+
+impl Syntax {
+    fn to_parser_syntax(&self) -> Result<askama_parser::Syntax<'_>, Error> {
+        SyntaxBuilder {
+            name: "interpreter",
+            block_start: self.block_start.as_deref(),
+            block_end: self.block_end.as_deref(),
+            expr_start: self.expr_start.as_deref(),
+            expr_end: self.expr_end.as_deref(),
+            comment_start: self.comment_start.as_deref(),
+            comment_end: self.comment_end.as_deref(),
+        }
+        .to_syntax()
+        .map_err(|message| Error::new(ErrorKind::Syntax, message))
+    }
+}
+
+/// Object safe version of [`Escaper`]
+pub(crate) trait DynEscaper: Send + Sync {
+    fn write_escaped(&self, dest: &mut dyn fmt::Write, string: &str) -> fmt::Result;
+
+    /// Values that are [`HtmlSafe`][crate::filters::HtmlSafe] are not escaped by this escaper
+    fn is_html(&self) -> bool;
+}
+
+impl<E: Escaper + Send + Sync + 'static> DynEscaper for E {
+    #[inline]
+    fn write_escaped(&self, dest: &mut dyn fmt::Write, string: &str) -> fmt::Result {
+        self.write_escaped_str(dest, string)
+    }
+
+    #[inline]
+    fn is_html(&self) -> bool {
+        TypeId::of::<E>() == TypeId::of::<Html>()
+    }
+}
+
+/// An error that occurred while loading or rendering a template
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    message: String,
+    location: Option<Location>,
+    source: Option<Box<dyn std::error::Error + Send + Sync>>,
+}
+
+/// The category of an [`Error`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A template could not be found
+    NotFound,
+    /// A template file could not be read
+    Io,
+    /// A template could not be parsed
+    Syntax,
+    /// A template is invalid, e.g. because it calls an unknown macro
+    Compile,
+    /// An expression could not be evaluated, or a filter or function failed
+    Render,
+    /// The template context could not be serialized
+    Serialize,
+}
+
+impl Error {
+    /// Create a new error, e.g. to be returned by a custom filter
+    pub fn new(kind: ErrorKind, message: impl fmt::Display) -> Self {
+        Self {
+            kind,
+            message: message.to_string(),
+            location: None,
+            source: None,
+        }
+    }
+
+    /// Wrap an error, e.g. to be returned by a custom filter
+    pub fn with_source(
+        kind: ErrorKind,
+        source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        let source = source.into();
+        Self {
+            kind,
+            message: source.to_string(),
+            location: None,
+            source: Some(source),
+        }
+    }
+
+    /// The category of this error
+    #[inline]
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// The name of the template that caused this error, if known
+    #[inline]
+    pub fn template(&self) -> Option<&str> {
+        self.location.as_ref().map(|loc| loc.name.as_str())
+    }
+
+    /// The 1-based line and column in the template that caused this error, if known
+    #[inline]
+    pub fn line_column(&self) -> Option<(usize, usize)> {
+        self.location.as_ref().map(|loc| (loc.line, loc.column))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        if let Some(Location { name, line, column }) = &self.location {
+            write!(f, "\n --> {name}:{line}:{column}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.source {
+            Some(err) => Some(&**err),
+            None => None,
+        }
+    }
+}
+
+impl From<Error> for crate::Error {
+    #[inline]
+    fn from(err: Error) -> Self {
+        crate::Error::Custom(Box::new(err))
+    }
+}
+
+/// Position in a template
+#[derive(Debug, Clone)]
+struct Location {
+    name: String,
+    line: usize,
+    column: usize,
+}
+
+impl Location {
+    fn new(name: &str, source: &str, offset: usize) -> Self {
+        let before = source.get(..offset).unwrap_or(source);
+        let line_start = before.rfind('\n').map_or(0, |pos| pos + 1);
+        Self {
+            name: name.to_string(),
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
@@ -0,0 +1,585 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt;
+use std::collections::HashMap;
+
+use serde::ser::{self, Serialize};
+
+/// A dynamically typed value, as seen by the template [`interpreter`][super]
+///
+/// The interpreter accepts any [`Serialize`] type as template context, see
+/// [`Value::from_serialize()`]. Its data model follows the one of [`serde_json`](https://docs.rs/serde_json):
+///
+/// * `None` and `()` become [`Value::None`], `Some(value)` becomes `value`,
+/// * structs and maps become a [`Value::Map`],
+/// * tuples, arrays and sequences become a [`Value::Seq`],
+/// * unit enum variants become a [`Value::Str`] containing the variant name,
+/// * other enum variants become a [`Value::Map`] with the variant name as single key.
+///
+/// Cloning a value is cheap, because strings, sequences and maps are reference counted.
+#[derive(Debug, Clone, Default)]
+pub enum Value {
+    /// `None`, `()` or a missing value
+    #[default]
+    None,
+    /// A boolean
+    Bool(bool),
+    /// Any integer
+    Int(i128),
+    /// Any floating point number
+    Float(f64),
+    /// A single character
+    Char(char),
+    /// A string
+    Str(Arc<str>),
+    /// A sequence, e.g. a [`Vec`], an array, a tuple or a range
+    Seq(Arc<[Value]>),
+    /// A mapping with string keys, e.g. a struct or a [`HashMap`]
+    Map(Arc<BTreeMap<Arc<str>, Value>>),
+}
+
+impl Value {
+    /// Convert any serializable value into a [`Value`]
+    pub fn from_serialize<T: Serialize + ?Sized>(value: &T) -> Result<Self, super::Error> {
+        value.serialize(ValueSerializer)
+    }
+
+    /// Short description of the type of this value, used in error messages
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            Value::None => "none",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "integer",
+            Value::Float(_) => "float",
+            Value::Char(_) => "char",
+            Value::Str(_) => "string",
+            Value::Seq(_) => "sequence",
+            Value::Map(_) => "map",
+        }
+    }
+
+    /// Returns the string content, if this value is a [`Value::Str`]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Look up a field of a [`Value::Map`]
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Map(map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if this value can be written into the output
+    pub(crate) fn is_displayable(&self) -> bool {
+        matches!(
+            self,
+            Value::Bool(_) | Value::Int(_) | Value::Float(_) | Value::Char(_) | Value::Str(_)
+        )
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::None => f.write_str("None"),
+            Value::Bool(b) => b.fmt(f),
+            Value::Int(i) => i.fmt(f),
+            Value::Float(v) => v.fmt(f),
+            Value::Char(c) => c.fmt(f),
+            Value::Str(s) => s.fmt(f),
+            Value::Seq(items) => {
+                f.write_str("[")?;
+                for (idx, item) in items.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", DebugValue(item))?;
+                }
+                f.write_str("]")
+            }
+            Value::Map(map) => {
+                f.write_str("{")?;
+                for (idx, (key, value)) in map.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{key:?}: {}", DebugValue(value))?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+/// Displays a [`Value`] like [`fmt::Debug`] would display the original Rust value
+pub(crate) struct DebugValue<'a>(pub(crate) &'a Value);
+
+impl fmt::Display for DebugValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Value::Float(v) => write!(f, "{v:?}"),
+            Value::Char(c) => write!(f, "{c:?}"),
+            Value::Str(s) => write!(f, "{s:?}"),
+            value => value.fmt(f),
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::None, Value::None) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a == b,
+            (Value::Int(a), Value::Float(b)) | (Value::Float(b), Value::Int(a)) => *a as f64 == *b,
+            (Value::Char(a), Value::Char(b)) => a == b,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Seq(a), Value::Seq(b)) => a == b,
+            (Value::Map(a), Value::Map(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Value::None, Value::None) => Some(Ordering::Equal),
+            (Value::Bool(a), Value::Bool(b)) => a.partial_cmp(b),
+            (Value::Int(a), Value::Int(b)) => a.partial_cmp(b),
+            (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+            (Value::Int(a), Value::Float(b)) => (*a as f64).partial_cmp(b),
+            (Value::Float(a), Value::Int(b)) => a.partial_cmp(&(*b as f64)),
+            (Value::Char(a), Value::Char(b)) => a.partial_cmp(b),
+            (Value::Str(a), Value::Str(b)) => a.partial_cmp(b),
+            (Value::Seq(a), Value::Seq(b)) => a.iter().partial_cmp(b.iter()),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::None => serializer.serialize_none(),
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::Int(i) => match i64::try_from(*i) {
+                Ok(i) => serializer.serialize_i64(i),
+                Err(_) => match u64::try_from(*i) {
+                    Ok(i) => serializer.serialize_u64(i),
+                    Err(_) => serializer.serialize_i128(*i),
+                },
+            },
+            Value::Float(v) => serializer.serialize_f64(*v),
+            Value::Char(c) => serializer.serialize_char(*c),
+            Value::Str(s) => serializer.serialize_str(s),
+            Value::Seq(items) => serializer.collect_seq(items.iter()),
+            Value::Map(map) => {
+                serializer.collect_map(map.iter().map(|(key, value)| (&**key, value)))
+            }
+        }
+    }
+}
+
+macro_rules! impl_from {
+    ($($ty:ty => |$v:ident| $conv:expr),* $(,)?) => { $(
+        impl From<$ty> for Value {
+            #[inline]
+            fn from($v: $ty) -> Self {
+                $conv
+            }
+        }
+    )* };
+}
+
+impl_from! {
+    () => |_v| Value::None,
+    bool => |v| Value::Bool(v),
+    i8 => |v| Value::Int(v.into()),
+    i16 => |v| Value::Int(v.into()),
+    i32 => |v| Value::Int(v.into()),
+    i64 => |v| Value::Int(v.into()),
+    i128 => |v| Value::Int(v),
+    isize => |v| Value::Int(v as i128),
+    u8 => |v| Value::Int(v.into()),
+    u16 => |v| Value::Int(v.into()),
+    u32 => |v| Value::Int(v.into()),
+    u64 => |v| Value::Int(v.into()),
+    usize => |v| Value::Int(v as i128),
+    f32 => |v| Value::Float(f32_to_f64(v)),
+    f64 => |v| Value::Float(v),
+    char => |v| Value::Char(v),
+    &str => |v| Value::Str(v.into()),
+    String => |v| Value::Str(v.into()),
+    Arc<str> => |v| Value::Str(v),
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    #[inline]
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::None, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    #[inline]
+    fn from(value: Vec<T>) -> Self {
+        value.into_iter().map(Into::into).collect()
+    }
+}
+
+impl FromIterator<Value> for Value {
+    fn from_iter<I: IntoIterator<Item = Value>>(iter: I) -> Self {
+        Value::Seq(iter.into_iter().collect())
+    }
+}
+
+impl<K: Into<Arc<str>>, V: Into<Value>> FromIterator<(K, V)> for Value {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Value::Map(Arc::new(
+            iter.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        ))
+    }
+}
+
+impl<K: Into<Arc<str>>, V: Into<Value>> From<BTreeMap<K, V>> for Value {
+    #[inline]
+    fn from(value: BTreeMap<K, V>) -> Self {
+        value.into_iter().collect()
+    }
+}
+
+impl<K: Into<Arc<str>>, V: Into<Value>, S> From<HashMap<K, V, S>> for Value {
+    #[inline]
+    fn from(value: HashMap<K, V, S>) -> Self {
+        value.into_iter().collect()
+    }
+}
+
+/// Convert an `f32` so that it is displayed like the original value
+pub(super) fn f32_to_f64(v: f32) -> f64 {
+    v.to_string().parse().unwrap_or(v.into())
+}
+
+/// [`ser::Serializer`] that builds a [`Value`]
+struct ValueSerializer;
+
+impl ser::Error for super::Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        super::Error::new(super::ErrorKind::Serialize, msg.to_string())
+    }
+}
+
+type SerResult = Result<Value, super::Error>;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = super::Error;
+
+    type SerializeSeq = SerializeSeq;
+    type SerializeTuple = SerializeSeq;
+    type SerializeTupleStruct = SerializeSeq;
+    type SerializeTupleVariant = SerializeVariant<SerializeSeq>;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeVariant<SerializeMap>;
+
+    fn serialize_bool(self, v: bool) -> SerResult {
+        Ok(v.into())
+    }
+
+    fn serialize_i8(self, v: i8) -> SerResult {
+        Ok(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> SerResult {
+        Ok(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> SerResult {
+        Ok(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> SerResult {
+        Ok(v.into())
+    }
+
+    fn serialize_i128(self, v: i128) -> SerResult {
+        Ok(v.into())
+    }
+
+    fn serialize_u8(self, v: u8) -> SerResult {
+        Ok(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> SerResult {
+        Ok(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> SerResult {
+        Ok(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> SerResult {
+        Ok(v.into())
+    }
+
+    fn serialize_u128(self, v: u128) -> SerResult {
+        match i128::try_from(v) {
+            Ok(v) => Ok(Value::Int(v)),
+            Err(_) => Err(ser::Error::custom("integer value is too large")),
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> SerResult {
+        Ok(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> SerResult {
+        Ok(v.into())
+    }
+
+    fn serialize_char(self, v: char) -> SerResult {
+        Ok(v.into())
+    }
+
+    fn serialize_str(self, v: &str) -> SerResult {
+        Ok(v.into())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> SerResult {
+        Ok(v.iter().copied().map(Value::from).collect())
+    }
+
+    fn serialize_none(self) -> SerResult {
+        Ok(Value::None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> SerResult {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> SerResult {
+        Ok(Value::None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> SerResult {
+        Ok(Value::None)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> SerResult {
+        Ok(variant.into())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> SerResult {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> SerResult {
+        Ok([(variant, value.serialize(self)?)].into_iter().collect())
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Ok(SerializeSeq(Vec::with_capacity(len.unwrap_or_default())))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Ok(SerializeVariant(variant, self.serialize_seq(Some(len))?))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(SerializeMap(BTreeMap::new(), None))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Ok(SerializeVariant(variant, self.serialize_map(Some(len))?))
+    }
+}
+
+struct SerializeSeq(Vec<Value>);
+
+impl ser::SerializeSeq for SerializeSeq {
+    type Ok = Value;
+    type Error = super::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.0.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> SerResult {
+        Ok(Value::Seq(self.0.into()))
+    }
+}
+
+impl ser::SerializeTuple for SerializeSeq {
+    type Ok = Value;
+    type Error = super::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> SerResult {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeSeq {
+    type Ok = Value;
+    type Error = super::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> SerResult {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct SerializeMap(BTreeMap<Arc<str>, Value>, Option<Arc<str>>);
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Value;
+    type Error = super::Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Self::Error> {
+        let key = match key.serialize(ValueSerializer)? {
+            Value::Str(key) => key,
+            key if key.is_displayable() => key.to_string().into(),
+            key => {
+                return Err(ser::Error::custom(format_args!(
+                    "a {} cannot be used as map key",
+                    key.kind(),
+                )));
+            }
+        };
+        self.1 = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        let key = self
+            .1
+            .take()
+            .ok_or_else(|| <super::Error as ser::Error>::custom("map value without key"))?;
+        self.0.insert(key, value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> SerResult {
+        Ok(Value::Map(Arc::new(self.0)))
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = Value;
+    type Error = super::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.0.insert(key.into(), value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> SerResult {
+        ser::SerializeMap::end(self)
+    }
+}
+
+/// A tuple or struct variant is serialized as map with the variant name as its only key
+struct SerializeVariant<T>(&'static str, T);
+
+impl ser::SerializeTupleVariant for SerializeVariant<SerializeSeq> {
+    type Ok = Value;
+    type Error = super::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        ser::SerializeSeq::serialize_element(&mut self.1, value)
+    }
+
+    fn end(self) -> SerResult {
+        let value = ser::SerializeSeq::end(self.1)?;
+        Ok([(self.0, value)].into_iter().collect())
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeVariant<SerializeMap> {
+    type Ok = Value;
+    type Error = super::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        ser::SerializeStruct::serialize_field(&mut self.1, key, value)
+    }
+
+    fn end(self) -> SerResult {
+        let value = ser::SerializeMap::end(self.1)?;
+        Ok([(self.0, value)].into_iter().collect())
+    }
+}
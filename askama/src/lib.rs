@@ -66,11 +66,19 @@ extern crate alloc;
 extern crate std;
 
 mod ascii_str;
+#[cfg(any(feature = "futures-io", feature = "tokio"))]
+mod async_write;
+#[cfg(feature = "std")]
+mod chunks;
 mod error;
 pub mod filters;
 #[doc(hidden)]
 pub mod helpers;
 mod html;
+#[cfg(feature = "i18n")]
+pub mod i18n;
+#[cfg(feature = "interpreter")]
+pub mod interpreter;
 mod values;
 
 #[cfg(feature = "alloc")]
@@ -84,6 +92,12 @@ pub use askama_derive::Template;
 
 #[doc(hidden)]
 pub use crate as shared;
+#[cfg(feature = "futures-io")]
+pub use crate::async_write::WriteIntoAsync;
+#[cfg(feature = "tokio")]
+pub use crate::async_write::WriteIntoTokio;
+#[cfg(feature = "std")]
+pub use crate::chunks::RenderChunks;
 pub use crate::error::{Error, Result};
 pub use crate::helpers::PrimitiveType;
 pub use crate::values::{NO_VALUES, Value, Values, get_value};
@@ -179,6 +193,106 @@ pub trait Template: fmt::Display + filters::FastWritable {
         }
     }
 
+    /// Renders the template in a background thread, and returns an iterator over the output
+    /// in chunks of `chunk_size` bytes.
+    ///
+    /// Please see [`RenderChunks`] for more information.
+    #[inline]
+    #[cfg(feature = "std")]
+    fn render_chunks(self, chunk_size: usize) -> RenderChunks
+    where
+        Self: Sized + Send + 'static,
+    {
+        RenderChunks::new(self, chunk_size, ())
+    }
+
+    /// Renders the template in a background thread with provided [`Values`], and returns an
+    /// iterator over the output in chunks of `chunk_size` bytes.
+    ///
+    /// Please see [`RenderChunks`] for more information.
+    #[inline]
+    #[cfg(feature = "std")]
+    fn render_chunks_with_values<V>(self, chunk_size: usize, values: V) -> RenderChunks
+    where
+        Self: Sized + Send + 'static,
+        V: Values + Send + 'static,
+    {
+        RenderChunks::new(self, chunk_size, values)
+    }
+
+    /// Renders the template in a background thread into the given `writer`
+    /// [`futures_io::AsyncWrite`] sink.
+    ///
+    /// The template is evaluated by the same generated code as [`render_into()`], so no further
+    /// code generation is needed. The output is handed to the `writer` in chunks while the
+    /// template is still being evaluated, and the `writer` is flushed at the end. The evaluation
+    /// pauses while the `writer` is busy, so the memory usage is bounded.
+    ///
+    /// Please see [`WriteIntoAsync`] for more information.
+    ///
+    /// [`render_into()`]: Template::render_into
+    #[inline]
+    #[cfg(feature = "futures-io")]
+    fn write_into_async<W>(self, writer: &mut W) -> WriteIntoAsync<'_, W>
+    where
+        Self: Sized + Send + 'static,
+        W: futures_io::AsyncWrite + Unpin + ?Sized,
+    {
+        WriteIntoAsync::new(RenderChunks::new(self, async_write::CHUNK_SIZE, ()), writer)
+    }
+
+    /// Renders the template in a background thread with provided [`Values`] into the given
+    /// `writer` [`futures_io::AsyncWrite`] sink.
+    ///
+    /// The `values` are moved into the background thread, so unlike
+    /// [`write_into_with_values()`][Template::write_into_with_values] they are passed by value.
+    #[inline]
+    #[cfg(feature = "futures-io")]
+    fn write_into_async_with_values<W, V>(self, writer: &mut W, values: V) -> WriteIntoAsync<'_, W>
+    where
+        Self: Sized + Send + 'static,
+        W: futures_io::AsyncWrite + Unpin + ?Sized,
+        V: Values + Send + 'static,
+    {
+        WriteIntoAsync::new(
+            RenderChunks::new(self, async_write::CHUNK_SIZE, values),
+            writer,
+        )
+    }
+
+    /// Renders the template in a background thread into the given `writer`
+    /// [`tokio::io::AsyncWrite`] sink.
+    ///
+    /// This method works like [`write_into_async()`][Template::write_into_async].
+    #[inline]
+    #[cfg(feature = "tokio")]
+    fn write_into_tokio<W>(self, writer: &mut W) -> WriteIntoTokio<'_, W>
+    where
+        Self: Sized + Send + 'static,
+        W: tokio::io::AsyncWrite + Unpin + ?Sized,
+    {
+        WriteIntoTokio::new(RenderChunks::new(self, async_write::CHUNK_SIZE, ()), writer)
+    }
+
+    /// Renders the template in a background thread with provided [`Values`] into the given
+    /// `writer` [`tokio::io::AsyncWrite`] sink.
+    ///
+    /// This method works like
+    /// [`write_into_async_with_values()`][Template::write_into_async_with_values].
+    #[inline]
+    #[cfg(feature = "tokio")]
+    fn write_into_tokio_with_values<W, V>(self, writer: &mut W, values: V) -> WriteIntoTokio<'_, W>
+    where
+        Self: Sized + Send + 'static,
+        W: tokio::io::AsyncWrite + Unpin + ?Sized,
+        V: Values + Send + 'static,
+    {
+        WriteIntoTokio::new(
+            RenderChunks::new(self, async_write::CHUNK_SIZE, values),
+            writer,
+        )
+    }
+
     /// Provides a rough estimate of the expanded length of the rendered template. Larger
     /// values result in higher memory usage but fewer reallocations. Smaller values result in the
     /// opposite. This value only affects [`render`]. It does not take effect when calling
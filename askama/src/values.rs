@@ -157,8 +157,9 @@ where
 
 /// A value in a [`Values`] collection.
 ///
-/// This is <code>[dyn](https://doc.rust-lang.org/stable/std/keyword.dyn.html) [Any]</code>,
-/// <code>[Option]&lt;dyn Any&gt;</code>, or a reference to either.
+/// This is <code>[dyn](https://doc.rust-lang.org/stable/std/keyword.dyn.html) [Any]</code>
+/// (optionally with the bounds [`Send`] and [`Sync`]), <code>[Option]&lt;dyn Any&gt;</code>,
+/// or a reference to either.
 pub trait Value {
     /// Returns a reference to this value unless it is `None`.
     fn ref_any(&self) -> Option<&dyn Any>;
@@ -180,6 +181,20 @@ impl Value for dyn Any {
     }
 }
 
+impl Value for dyn Any + Send {
+    #[inline]
+    fn ref_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+}
+
+impl Value for dyn Any + Send + Sync {
+    #[inline]
+    fn ref_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+}
+
 impl<T: Value> Value for Option<T> {
     #[inline]
     fn ref_any(&self) -> Option<&dyn Any> {
@@ -0,0 +1,203 @@
+//! The error type and the cache of the crate root, which the configuration loader needs, too
+//!
+//! `askama_extract`, `askama_fmt` and `askama_lsp` include this file and `config.rs`, so they
+//! read `askama.toml` exactly like the derive macro.
+
+use std::borrow::Borrow;
+use std::collections::hash_map::{Entry, HashMap};
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::path::Path;
+use std::sync::Mutex;
+
+use parser::{Parsed, strip_common};
+use proc_macro2::Span;
+use rustc_hash::FxBuildHasher;
+
+#[derive(Debug, Clone)]
+pub(crate) struct CompileError {
+    pub(crate) msg: String,
+    pub(crate) span: Option<Span>,
+    /// Further errors that are reported at the same span, e.g. the other syntax errors of a
+    /// template
+    pub(crate) more: Vec<String>,
+}
+
+impl CompileError {
+    pub(crate) fn new<S: fmt::Display>(msg: S, file_info: Option<FileInfo<'_>>) -> Self {
+        Self::new_with_span(msg, file_info, None)
+    }
+
+    pub(crate) fn new_with_span<S: fmt::Display>(
+        msg: S,
+        file_info: Option<FileInfo<'_>>,
+        span: Option<Span>,
+    ) -> Self {
+        let msg = match file_info {
+            Some(file_info) => format!("{msg}{file_info}"),
+            None => msg.to_string(),
+        };
+        Self {
+            msg,
+            span,
+            more: Vec::new(),
+        }
+    }
+
+    pub(crate) fn no_file_info<S: ToString>(msg: S, span: Option<Span>) -> Self {
+        Self {
+            msg: msg.to_string(),
+            span,
+            more: Vec::new(),
+        }
+    }
+
+    /// Appends `errors`, which are emitted as separate `compile_error!()`s at the span of `self`
+    pub(crate) fn with_more(mut self, errors: impl IntoIterator<Item = CompileError>) -> Self {
+        for error in errors {
+            self.more.push(error.msg);
+            self.more.extend(error.more);
+        }
+        self
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+impl fmt::Display for CompileError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(&self.msg)?;
+        for msg in &self.more {
+            write!(fmt, "\n\n{msg}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FileInfo<'a> {
+    path: &'a Path,
+    source: Option<&'a str>,
+    node_source: Option<&'a str>,
+}
+
+impl<'a> FileInfo<'a> {
+    pub(crate) fn new(
+        path: &'a Path,
+        source: Option<&'a str>,
+        node_source: Option<&'a str>,
+    ) -> Self {
+        Self {
+            path,
+            source,
+            node_source,
+        }
+    }
+
+    pub(crate) fn of(node: parser::Span<'a>, path: &'a Path, parsed: &'a Parsed) -> Self {
+        let source = parsed.source();
+        Self {
+            path,
+            source: Some(source),
+            node_source: node.as_suffix_of(source),
+        }
+    }
+}
+
+impl fmt::Display for FileInfo<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let (Some(source), Some(node_source)) = (self.source, self.node_source) {
+            let (error_info, file_path) = generate_error_info(source, node_source, self.path);
+            write!(
+                f,
+                "\n  --> {file_path}:{row}:{column}\n{source_after}",
+                row = error_info.row,
+                column = error_info.column,
+                source_after = error_info.source_after,
+            )
+        } else {
+            match std::env::current_dir() {
+                Ok(cwd) => write!(f, "\n --> {}", strip_common(&cwd, self.path)),
+                Err(_) => write!(f, "\n --> {}", self.path.display()),
+            }
+        }
+    }
+}
+
+pub(crate) struct ErrorInfo {
+    row: usize,
+    column: usize,
+    source_after: String,
+}
+
+fn generate_row_and_column(src: &str, input: &str) -> ErrorInfo {
+    const MAX_LINE_LEN: usize = 80;
+
+    let offset = src.len() - input.len();
+    let (source_before, source_after) = src.split_at(offset);
+
+    let source_after = match source_after
+        .char_indices()
+        .enumerate()
+        .take(MAX_LINE_LEN + 1)
+        .last()
+    {
+        Some((MAX_LINE_LEN, (i, _))) => format!("{:?}...", &source_after[..i]),
+        _ => format!("{source_after:?}"),
+    };
+
+    let (row, last_line) = source_before.lines().enumerate().last().unwrap_or_default();
+    let column = last_line.chars().count();
+    ErrorInfo {
+        row: row + 1,
+        column,
+        source_after,
+    }
+}
+
+/// Return the error related information and its display file path.
+fn generate_error_info(src: &str, input: &str, file_path: &Path) -> (ErrorInfo, String) {
+    let file_path = match std::env::current_dir() {
+        Ok(cwd) => strip_common(&cwd, file_path),
+        Err(_) => file_path.display().to_string(),
+    };
+    let error_info = generate_row_and_column(src, input);
+    (error_info, file_path)
+}
+
+#[derive(Debug)]
+pub(crate) struct OnceMap<K, V>([Mutex<HashMap<K, V, FxBuildHasher>>; 8]);
+
+impl<K, V> Default for OnceMap<K, V> {
+    fn default() -> Self {
+        Self(Default::default())
+    }
+}
+
+impl<K: Hash + Eq, V> OnceMap<K, V> {
+    // The API of this function was copied, and adapted from the `once_map` crate
+    // <https://crates.io/crates/once_map/0.4.18>.
+    pub(crate) fn get_or_try_insert<T, Q, E>(
+        &self,
+        key: &Q,
+        make_key_value: impl FnOnce(&Q) -> Result<(K, V), E>,
+        to_value: impl FnOnce(&V) -> T,
+    ) -> Result<T, E>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        let shard_idx = (FxBuildHasher.hash_one(key) % self.0.len() as u64) as usize;
+        let mut shard = self.0[shard_idx].lock().unwrap();
+        Ok(to_value(if let Some(v) = shard.get(key) {
+            v
+        } else {
+            let (k, v) = make_key_value(key)?;
+            match shard.entry(k) {
+                Entry::Vacant(entry) => entry.insert(v),
+                Entry::Occupied(_) => unreachable!("key in map when it should not have been"),
+            }
+        }))
+    }
+}
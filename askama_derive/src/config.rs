@@ -7,12 +7,12 @@ use std::sync::{Arc, OnceLock};
 use std::{env, fs};
 
 use parser::node::Whitespace;
-use parser::{ParseError, Parsed, Syntax, SyntaxBuilder};
+use parser::{Ast, ParseError, Parsed, Syntax, SyntaxBuilder};
 use proc_macro2::Span;
 #[cfg(feature = "config")]
 use serde_derive::Deserialize;
 
-use crate::{CompileError, FileInfo, OnceMap};
+use crate::common::{CompileError, FileInfo, OnceMap};
 
 #[derive(Debug)]
 pub(crate) struct Config {
@@ -21,6 +21,9 @@ pub(crate) struct Config {
     pub(crate) default_syntax: &'static str,
     pub(crate) escapers: Vec<(Vec<Cow<'static, str>>, Cow<'static, str>)>,
     pub(crate) whitespace: Whitespace,
+    pub(crate) builtin_filters: Vec<FilterGroup>,
+    #[cfg_attr(not(feature = "i18n"), allow(dead_code))]
+    pub(crate) i18n: I18nConfig,
     // `Config` is self referential and `_key` owns it data, so it must come last
     _key: OwnedConfigKey,
 }
@@ -38,6 +41,7 @@ struct OwnedConfigKey(&'static ConfigKey<'static>);
 #[derive(Debug, PartialEq, Eq, Hash)]
 struct ConfigKey<'a> {
     source: Cow<'a, str>,
+    root: Option<Cow<'a, Path>>,
     config_path: Option<Cow<'a, str>>,
     template_whitespace: Option<Whitespace>,
 }
@@ -48,6 +52,10 @@ impl ToOwned for ConfigKey<'_> {
     fn to_owned(&self) -> Self::Owned {
         let owned_key = ConfigKey {
             source: Cow::Owned(self.source.as_ref().to_owned()),
+            root: self
+                .root
+                .as_ref()
+                .map(|s| Cow::Owned(s.as_ref().to_owned())),
             config_path: self
                 .config_path
                 .as_ref()
@@ -66,8 +74,11 @@ impl<'a> Borrow<ConfigKey<'a>> for OwnedConfigKey {
 }
 
 impl Config {
+    /// Relative paths in the configuration are resolved against `root`, the directory of the
+    /// configuration file; by default, it is the crate root `CARGO_MANIFEST_DIR`.
     pub(crate) fn new(
         source: &str,
+        root: Option<&Path>,
         config_path: Option<&str>,
         template_whitespace: Option<Whitespace>,
         config_span: Option<Span>,
@@ -77,6 +88,7 @@ impl Config {
         CACHE.get_or_init(OnceMap::default).get_or_try_insert(
             &ConfigKey {
                 source: source.into(),
+                root: root.map(Cow::Borrowed),
                 config_path: config_path.map(Cow::Borrowed),
                 template_whitespace,
             },
@@ -98,7 +110,11 @@ impl Config {
         let s = key.0.source.as_ref();
         let config_path = key.0.config_path.as_deref();
 
-        let root = manifest_root();
+        let root = key
+            .0
+            .root
+            .as_deref()
+            .map_or_else(manifest_root, Path::to_owned);
         let default_dirs = vec![root.join("templates")];
 
         let mut syntaxes = BTreeMap::new();
@@ -110,19 +126,26 @@ impl Config {
             RawConfig::from_toml_str(s)?
         };
 
-        let (dirs, default_syntax, whitespace) = match raw.general {
+        let (dirs, default_syntax, whitespace, builtin_filters) = match raw.general {
             Some(General {
                 dirs,
                 default_syntax,
                 whitespace,
+                builtin_filters,
             }) => (
                 dirs.map_or(default_dirs, |v| {
                     v.into_iter().map(|dir| root.join(dir)).collect()
                 }),
                 default_syntax.unwrap_or(DEFAULT_SYNTAX_NAME),
                 whitespace,
+                builtin_filters,
+            ),
+            None => (
+                default_dirs,
+                DEFAULT_SYNTAX_NAME,
+                Whitespace::default(),
+                Vec::new(),
             ),
-            None => (default_dirs, DEFAULT_SYNTAX_NAME, Whitespace::default()),
         };
         let file_info = config_path.map(|path| FileInfo::new(Path::new(path), None, None));
         let whitespace = key.0.template_whitespace.unwrap_or(whitespace);
@@ -166,22 +189,93 @@ impl Config {
             ));
         }
 
+        let i18n = match raw.i18n {
+            Some(RawI18n {
+                dir,
+                default_locale,
+                missing,
+            }) => I18nConfig {
+                dir: root.join(dir.unwrap_or(DEFAULT_I18N_DIR)),
+                default_locale: default_locale.unwrap_or(DEFAULT_LOCALE).to_owned(),
+                missing,
+            },
+            None => I18nConfig {
+                dir: root.join(DEFAULT_I18N_DIR),
+                default_locale: DEFAULT_LOCALE.to_owned(),
+                missing: MissingTranslation::default(),
+            },
+        };
+
         Ok(Config {
             dirs,
             syntaxes,
             default_syntax,
             escapers,
             whitespace,
+            builtin_filters,
+            i18n,
             _key: key,
         })
     }
 
+    /// Whether the built-in filters of `group` were enabled in the configuration file
+    pub(crate) fn has_builtin_filters(&self, group: FilterGroup) -> bool {
+        self.builtin_filters.contains(&group)
+    }
+
     pub(crate) fn find_template(
         &self,
         path: &str,
         start_at: Option<&Path>,
         file_info: Option<FileInfo<'_>>,
     ) -> Result<Arc<Path>, CompileError> {
+        match self.try_find_template(path, start_at, file_info)? {
+            Some(path) => Ok(path),
+            None => Err(CompileError::new(
+                format_args!(
+                    "template {:?} not found in directories {:?}",
+                    path, self.dirs,
+                ),
+                file_info,
+            )),
+        }
+    }
+
+    /// Finds the first of `paths` that exists, for `{% include ["a.html", "b.html"] %}`
+    ///
+    /// If none of them exists, `None` is returned for `{% include … ignore missing %}`, and an
+    /// error otherwise.
+    pub(crate) fn find_include(
+        &self,
+        paths: &[&str],
+        ignore_missing: bool,
+        start_at: Option<&Path>,
+        file_info: Option<FileInfo<'_>>,
+    ) -> Result<Option<Arc<Path>>, CompileError> {
+        for path in paths {
+            if let Some(path) = self.try_find_template(path, start_at, file_info)? {
+                return Ok(Some(path));
+            }
+        }
+        match paths {
+            _ if ignore_missing => Ok(None),
+            [path] => self.find_template(path, start_at, file_info).map(Some),
+            _ => Err(CompileError::new(
+                format_args!(
+                    "none of the templates {:?} was found in directories {:?}",
+                    paths, self.dirs,
+                ),
+                file_info,
+            )),
+        }
+    }
+
+    fn try_find_template(
+        &self,
+        path: &str,
+        start_at: Option<&Path>,
+        file_info: Option<FileInfo<'_>>,
+    ) -> Result<Option<Arc<Path>>, CompileError> {
         let path = 'find_path: {
             if let Some(root) = start_at {
                 let relative = root.with_file_name(path);
@@ -195,16 +289,10 @@ impl Config {
                     break 'find_path rooted;
                 }
             }
-            return Err(CompileError::new(
-                format_args!(
-                    "template {:?} not found in directories {:?}",
-                    path, self.dirs,
-                ),
-                file_info,
-            ));
+            return Ok(None);
         };
         match path.canonicalize() {
-            Ok(path) => Ok(path.into()),
+            Ok(path) => Ok(Some(path.into())),
             Err(err) => Err(CompileError::new(
                 format_args!("could not canonicalize path {path:?}: {err}"),
                 file_info,
@@ -213,6 +301,50 @@ impl Config {
     }
 }
 
+/// The `[i18n]` section of the configuration file
+#[derive(Debug)]
+#[cfg_attr(not(feature = "i18n"), allow(dead_code))]
+pub(crate) struct I18nConfig {
+    /// Directory containing a subdirectory with `.ftl` files for every locale
+    pub(crate) dir: PathBuf,
+    pub(crate) default_locale: String,
+    pub(crate) missing: MissingTranslation,
+}
+
+/// A group of built-in filters that must be enabled with `builtin_filters` in the configuration
+/// file, because projects often have custom filters with the same names
+///
+/// If a group is not enabled, its names refer to the custom filters in the `filters` module.
+#[cfg_attr(feature = "config", derive(Deserialize))]
+#[cfg_attr(feature = "config", serde(rename_all = "lowercase"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FilterGroup {
+    /// `date`, `datetime`, `time`, `timesince` and `timeuntil`
+    Datetime,
+    /// `batch`, `first`, `groupby`, `last`, `length`, `map`, `max`, `min`, `reject`,
+    /// `rejectattr`, `reverse`, `select`, `selectattr`, `slice`, `sort`, `sum` and `unique`
+    Collections,
+    /// `default`
+    Default,
+    /// `currency`, `intcomma`, `number` and `percent`
+    Numbers,
+}
+
+/// What to do if a message is missing in a locale other than the default locale
+#[cfg_attr(feature = "config", derive(Deserialize))]
+#[cfg_attr(feature = "config", serde(rename_all = "lowercase"))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(feature = "config"), allow(dead_code))]
+pub(crate) enum MissingTranslation {
+    /// Fail to compile the template
+    #[default]
+    Error,
+    /// Fall back to the default locale at runtime
+    Ignore,
+    /// Like `Ignore`, but report the missing messages as warnings
+    Warn,
+}
+
 #[derive(Debug, Default)]
 pub(crate) struct SyntaxAndCache<'a> {
     syntax: Syntax<'a>,
@@ -258,11 +390,12 @@ impl<'a> SyntaxAndCache<'a> {
         }
     }
 
+    /// Parses a template, or returns all its syntax errors, in source order
     pub(crate) fn parse(
         &self,
         source: Arc<str>,
         source_path: Option<Arc<Path>>,
-    ) -> Result<Arc<Parsed>, ParseError> {
+    ) -> Result<Arc<Parsed>, Vec<ParseError>> {
         self.cache.get_or_try_insert(
             &SyntaxAndCacheKey {
                 source: Cow::Owned(source),
@@ -276,11 +409,19 @@ impl<'a> SyntaxAndCache<'a> {
                         .as_deref()
                         .map(|v| Cow::Owned(Arc::clone(v))),
                 });
+                let source_path = key.source_path.as_deref().map(Arc::clone);
                 let parsed = Parsed::new(
                     Arc::clone(key.source.as_ref()),
-                    key.source_path.as_deref().map(Arc::clone),
+                    source_path.clone(),
                     &self.syntax,
-                )?;
+                )
+                .map_err(|err| {
+                    // Look for the other errors, too, so they can all be fixed at once.
+                    match Ast::from_str_recovering(&key.source, source_path, &self.syntax) {
+                        Err(errors) => errors,
+                        Ok(_) => vec![err],
+                    }
+                })?;
                 Ok((key, Arc::new(parsed)))
             },
             Arc::clone,
@@ -295,6 +436,7 @@ struct RawConfig<'a> {
     general: Option<General<'a>>,
     syntax: Option<Vec<SyntaxBuilder<'a>>>,
     escaper: Option<Vec<RawEscaper<'a>>>,
+    i18n: Option<RawI18n<'a>>,
 }
 
 impl RawConfig<'_> {
@@ -321,6 +463,8 @@ struct General<'a> {
     default_syntax: Option<&'a str>,
     #[cfg_attr(feature = "config", serde(default))]
     whitespace: Whitespace,
+    #[cfg_attr(feature = "config", serde(default))]
+    builtin_filters: Vec<FilterGroup>,
 }
 
 #[cfg_attr(feature = "config", derive(Deserialize))]
@@ -329,11 +473,20 @@ struct RawEscaper<'a> {
     extensions: Vec<&'a str>,
 }
 
+#[cfg_attr(feature = "config", derive(Deserialize))]
+struct RawI18n<'a> {
+    dir: Option<&'a str>,
+    default_locale: Option<&'a str>,
+    #[cfg_attr(feature = "config", serde(default))]
+    missing: MissingTranslation,
+}
+
 pub(crate) fn read_config_file(
+    root: Option<&Path>,
     config_path: Option<&str>,
     span: Option<Span>,
 ) -> Result<String, CompileError> {
-    let root = manifest_root();
+    let root = root.map_or_else(manifest_root, Path::to_owned);
     let filename = match config_path {
         Some(config_path) => root.join(config_path),
         None => root.join(CONFIG_FILE_NAME),
@@ -366,6 +519,8 @@ fn str_set(vals: &[&'static str]) -> Vec<Cow<'static, str>> {
 
 static CONFIG_FILE_NAME: &str = "askama.toml";
 static DEFAULT_SYNTAX_NAME: &str = "default";
+static DEFAULT_I18N_DIR: &str = "i18n";
+static DEFAULT_LOCALE: &str = "en-US";
 static DEFAULT_ESCAPERS: &[(&[&str], &str)] = &[
     (
         &[
@@ -373,6 +528,7 @@ static DEFAULT_ESCAPERS: &[(&[&str], &str)] = &[
         ],
         "Html",
     ),
+    (&["js", "mjs"], "Js"),
     (&["md", "none", "txt", "yml", ""], "Text"),
 ];
 
@@ -387,7 +543,7 @@ mod tests {
     fn test_default_config() {
         let mut root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
         root.push("templates");
-        let config = Config::new("", None, None, None).unwrap();
+        let config = Config::new("", None, None, None, None).unwrap();
         assert_eq!(config.dirs, vec![root]);
     }
 
@@ -396,15 +552,74 @@ mod tests {
     fn test_config_dirs() {
         let mut root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
         root.push("tpl");
-        let config = Config::new("[general]\ndirs = [\"tpl\"]", None, None, None).unwrap();
+        let config = Config::new("[general]\ndirs = [\"tpl\"]", None, None, None, None).unwrap();
         assert_eq!(config.dirs, vec![root]);
+
+        // An explicit root replaces the crate root, e.g. for the command line tools.
+        let root = Path::new("elsewhere");
+        let raw_config = "[general]\ndirs = [\"tpl\"]";
+        let config = Config::new(raw_config, Some(root), None, None, None).unwrap();
+        assert_eq!(config.dirs, vec![root.join("tpl")]);
+    }
+
+    #[test]
+    fn test_default_i18n_config() {
+        let root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+        let config = Config::new("", None, None, None, None).unwrap();
+        assert_eq!(config.i18n.dir, root.join("i18n"));
+        assert_eq!(config.i18n.default_locale, "en-US");
+        assert_eq!(config.i18n.missing, MissingTranslation::Error);
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_config_builtin_filters() {
+        let config = Config::new("", None, None, None, None).unwrap();
+        assert!(!config.has_builtin_filters(FilterGroup::Datetime));
+
+        let raw_config = "[general]\nbuiltin_filters = [\"datetime\"]";
+        let config = Config::new(raw_config, None, None, None, None).unwrap();
+        assert!(config.has_builtin_filters(FilterGroup::Datetime));
+        assert!(!config.has_builtin_filters(FilterGroup::Collections));
+        assert!(!config.has_builtin_filters(FilterGroup::Default));
+        assert!(!config.has_builtin_filters(FilterGroup::Numbers));
+
+        let raw_config = "[general]\nbuiltin_filters = [\"unknown\"]";
+        assert!(Config::new(raw_config, None, None, None, None).is_err());
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_config_i18n() {
+        let root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+        let raw_config = r#"
+        [i18n]
+        dir = "locales"
+        default_locale = "de-CH"
+        missing = "ignore"
+        "#;
+        let config = Config::new(raw_config, None, None, None, None).unwrap();
+        assert_eq!(config.i18n.dir, root.join("locales"));
+        assert_eq!(config.i18n.default_locale, "de-CH");
+        assert_eq!(config.i18n.missing, MissingTranslation::Ignore);
+
+        let raw_config = "[i18n]\nmissing = \"warn\"";
+        let config = Config::new(raw_config, None, None, None, None).unwrap();
+        assert_eq!(config.i18n.missing, MissingTranslation::Warn);
+
+        let raw_config = "[i18n]\nmissing = \"panic\"";
+        assert!(Config::new(raw_config, None, None, None, None).is_err());
     }
 
     fn assert_eq_rooted(actual: &Path, expected: &str) {
         let mut root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap())
             .canonicalize()
             .unwrap();
-        if root.ends_with("askama_derive_standalone") {
+        if root.ends_with("askama_derive_standalone")
+            || root.ends_with("askama_extract")
+            || root.ends_with("askama_fmt")
+            || root.ends_with("askama_lsp")
+        {
             root.pop();
             root.push("askama_derive");
         }
@@ -417,7 +632,7 @@ mod tests {
 
     #[test]
     fn find_absolute() {
-        let config = Config::new("", None, None, None).unwrap();
+        let config = Config::new("", None, None, None, None).unwrap();
         let root = config.find_template("a.html", None, None).unwrap();
         let path = config
             .find_template("sub/b.html", Some(&root), None)
@@ -428,14 +643,14 @@ mod tests {
     #[test]
     #[should_panic]
     fn find_relative_nonexistent() {
-        let config = Config::new("", None, None, None).unwrap();
+        let config = Config::new("", None, None, None, None).unwrap();
         let root = config.find_template("a.html", None, None).unwrap();
         config.find_template("c.html", Some(&root), None).unwrap();
     }
 
     #[test]
     fn find_relative() {
-        let config = Config::new("", None, None, None).unwrap();
+        let config = Config::new("", None, None, None, None).unwrap();
         let root = config.find_template("sub/b.html", None, None).unwrap();
         let path = config.find_template("c.html", Some(&root), None).unwrap();
         assert_eq_rooted(&path, "sub/c.html");
@@ -443,7 +658,7 @@ mod tests {
 
     #[test]
     fn find_relative_sub() {
-        let config = Config::new("", None, None, None).unwrap();
+        let config = Config::new("", None, None, None, None).unwrap();
         let root = config.find_template("sub/b.html", None, None).unwrap();
         let path = config
             .find_template("sub1/d.html", Some(&root), None)
@@ -468,7 +683,7 @@ mod tests {
         "#;
 
         let default_syntax = Syntax::default();
-        let config = Config::new(raw_config, None, None, None).unwrap();
+        let config = Config::new(raw_config, None, None, None, None).unwrap();
         assert_eq!(config.default_syntax, "foo");
 
         let foo = config.syntaxes.get("foo").unwrap();
@@ -500,7 +715,7 @@ mod tests {
         "#;
 
         let default_syntax = Syntax::default();
-        let config = Config::new(raw_config, None, None, None).unwrap();
+        let config = Config::new(raw_config, None, None, None, None).unwrap();
         assert_eq!(config.default_syntax, "foo");
 
         let foo = config.syntaxes.get("foo").unwrap();
@@ -537,7 +752,7 @@ mod tests {
         default_syntax = "emoji"
         "#;
 
-        let config = Config::new(raw_config, None, None, None).unwrap();
+        let config = Config::new(raw_config, None, None, None, None).unwrap();
         assert_eq!(config.default_syntax, "emoji");
 
         let foo = config.syntaxes.get("emoji").unwrap();
@@ -565,7 +780,7 @@ mod tests {
         name = "too_short"
         block_start = "<"
         "#;
-        let config = Config::new(raw_config, None, None, None);
+        let config = Config::new(raw_config, None, None, None, None);
         assert_eq!(
             expect_err(config).msg,
             r#"delimiters must be at least two characters long. The opening block delimiter ("<") is too short"#,
@@ -576,7 +791,7 @@ mod tests {
         name = "contains_ws"
         block_start = " {{ "
         "#;
-        let config = Config::new(raw_config, None, None, None);
+        let config = Config::new(raw_config, None, None, None, None);
         assert_eq!(
             expect_err(config).msg,
             r#"delimiters may not contain white spaces. The opening block delimiter (" {{ ") contains white spaces"#,
@@ -589,7 +804,7 @@ mod tests {
         expr_start = "{{$"
         comment_start = "{{#"
         "#;
-        let config = Config::new(raw_config, None, None, None);
+        let config = Config::new(raw_config, None, None, None, None);
         assert_eq!(
             expect_err(config).msg,
             r#"an opening delimiter may not be the prefix of another delimiter. The block delimiter ("{{") clashes with the expression delimiter ("{{$")"#,
@@ -604,7 +819,7 @@ mod tests {
         syntax = [{ name = "default" }]
         "#;
 
-        let _config = Config::new(raw_config, None, None, None).unwrap();
+        let _config = Config::new(raw_config, None, None, None, None).unwrap();
     }
 
     #[cfg(feature = "config")]
@@ -616,7 +831,7 @@ mod tests {
                   { name = "foo", block_start = "%%" } ]
         "#;
 
-        let _config = Config::new(raw_config, None, None, None).unwrap();
+        let _config = Config::new(raw_config, None, None, None, None).unwrap();
     }
 
     #[cfg(feature = "config")]
@@ -628,7 +843,7 @@ mod tests {
         default_syntax = "foo"
         "#;
 
-        let _config = Config::new(raw_config, None, None, None).unwrap();
+        let _config = Config::new(raw_config, None, None, None, None).unwrap();
     }
 
     #[cfg(feature = "config")]
@@ -643,6 +858,7 @@ mod tests {
             None,
             None,
             None,
+            None,
         )
         .unwrap();
         assert_eq!(
@@ -655,6 +871,7 @@ mod tests {
                     ]),
                     "askama::filters::Html".into()
                 ),
+                (str_set(&["js", "mjs"]), "askama::filters::Js".into()),
                 (
                     str_set(&["md", "none", "txt", "yml", ""]),
                     "askama::filters::Text".into()
@@ -674,11 +891,12 @@ mod tests {
             None,
             None,
             None,
+            None,
         )
         .unwrap();
         assert_eq!(config.whitespace, Whitespace::Suppress);
 
-        let config = Config::new(r#""#, None, None, None).unwrap();
+        let config = Config::new(r#""#, None, None, None, None).unwrap();
         assert_eq!(config.whitespace, Whitespace::Preserve);
 
         let config = Config::new(
@@ -689,6 +907,7 @@ mod tests {
             None,
             None,
             None,
+            None,
         )
         .unwrap();
         assert_eq!(config.whitespace, Whitespace::Preserve);
@@ -701,6 +920,7 @@ mod tests {
             None,
             None,
             None,
+            None,
         )
         .unwrap();
         assert_eq!(config.whitespace, Whitespace::Minimize);
@@ -718,13 +938,14 @@ mod tests {
             whitespace = "suppress"
             "#,
             None,
+            None,
             Some(Whitespace::Minimize),
             None,
         )
         .unwrap();
         assert_eq!(config.whitespace, Whitespace::Minimize);
 
-        let config = Config::new(r#""#, None, Some(Whitespace::Minimize), None).unwrap();
+        let config = Config::new(r#""#, None, None, Some(Whitespace::Minimize), None).unwrap();
         assert_eq!(config.whitespace, Whitespace::Minimize);
     }
 }
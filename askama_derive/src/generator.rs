@@ -1,4 +1,6 @@
 mod expr;
+pub(crate) mod filters;
+mod html_state;
 mod node;
 
 use std::borrow::Cow;
@@ -8,13 +10,16 @@ use std::path::Path;
 use std::str;
 use std::sync::Arc;
 
-use parser::node::{Macro, Whitespace};
+use parser::node::{Caller, Macro, Whitespace};
 use parser::{
-    CharLit, Expr, FloatKind, IntKind, MAX_RUST_KEYWORD_LEN, Num, RUST_KEYWORDS, StrLit, WithSpan,
+    CharLit, Expr, FloatKind, IntKind, MAX_RUST_KEYWORD_LEN, Num, RUST_KEYWORDS, Span, StrLit,
+    WithSpan,
 };
 use rustc_hash::FxBuildHasher;
 
+use self::html_state::HtmlState;
 use crate::ascii_str::{AsciiChar, AsciiStr};
+use crate::config::FilterGroup;
 use crate::heritage::{Context, Heritage};
 use crate::html::write_escaped_str;
 use crate::input::{Source, TemplateInput};
@@ -46,10 +51,58 @@ pub(crate) fn template_to_string(
 
     if tmpl_kind == TmplKind::Struct {
         impl_everything(input.ast, buf);
+        #[cfg(feature = "hot-reload")]
+        if hot_reload_sources(input, contexts).is_some() {
+            crate::integration::impl_reflect(input.ast, buf);
+        }
     }
     Ok(size_hint)
 }
 
+/// Returns the paths and source code of all templates used by `input`, if the template can be
+/// hot-reloaded by the interpreter
+#[cfg(feature = "hot-reload")]
+fn hot_reload_sources<'a>(
+    input: &TemplateInput<'_>,
+    contexts: &'a HashMap<&Arc<Path>, Context<'a>, FxBuildHasher>,
+) -> Option<Vec<(&'a str, &'a str)>> {
+    // Only whole templates that are read from files can be reloaded. The interpreter does not
+    // implement contextual escaping, and it cannot access the fields of enum variants.
+    if !matches!(input.source, Source::Path(_))
+        || input.block.is_some()
+        || input.contextual_escape
+        || input.enum_ast.is_some()
+        || !matches!(input.ast.data, syn::Data::Struct(_))
+    {
+        return None;
+    }
+    hot_reload_name(input)?;
+    let mut sources = contexts
+        .iter()
+        .map(|(path, ctx)| Some((path.to_str()?, ctx.parsed.source())))
+        .collect::<Option<Vec<_>>>()?;
+    sources.sort_unstable();
+    Some(sources)
+}
+
+/// Returns the name of the template relative to the template directory that contains it, which
+/// is how the interpreter finds the template
+#[cfg(feature = "hot-reload")]
+fn hot_reload_name(input: &TemplateInput<'_>) -> Option<String> {
+    input.config.dirs.iter().find_map(|dir| {
+        let dir = dir.canonicalize().ok()?;
+        let name = input.path.strip_prefix(dir).ok()?;
+        let parts = name
+            .components()
+            .map(|part| match part {
+                std::path::Component::Normal(part) => part.to_str(),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(parts.join("/"))
+    })
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum TmplKind<'a> {
     /// [`askama::Template`]
@@ -85,6 +138,52 @@ struct Generator<'a, 'h> {
     is_in_filter_block: usize,
     /// Set of called macros we are currently in. Used to prevent (indirect) recursions.
     seen_macros: Vec<(&'a Macro<'a>, Option<FileInfo<'a>>)>,
+    /// `loop.*` attributes used by the `{% for %}` loops we are currently in
+    loops: Vec<LoopUsage>,
+    /// `{% call(…) %}` bodies of the macro calls we are currently in, used by `{{ caller() }}`
+    callers: Vec<ActiveCaller<'a>>,
+    /// Set in templates included with `{% include … only %}`, which can only use the passed
+    /// variables, and not the fields of `self`
+    is_isolated: bool,
+    /// Usage of the translation function `t()`
+    #[cfg(feature = "i18n")]
+    translations: Translations,
+}
+
+/// Which `loop.*` attributes need extra state in the generated loop
+#[derive(Default)]
+struct LoopUsage {
+    /// Set for `{% for … recursive %}`
+    recursive: bool,
+    /// `loop.length`, `loop.revindex` or `loop.revindex0`
+    length: bool,
+    /// `loop.previtem`
+    previtem: bool,
+    /// `loop.nextitem`
+    nextitem: bool,
+    /// Number of `loop.changed(…)` calls
+    changed: usize,
+}
+
+/// The body of a `{% call(…) %}` block, and the state of its call site
+struct ActiveCaller<'a> {
+    caller: &'a Caller<'a>,
+    ctx: Context<'a>,
+    /// The number of local scopes at the call site: the scopes of the macro are hidden in the body
+    scopes: usize,
+    /// The number of loops at the call site
+    loops: usize,
+    /// The number of macro calls at the call site
+    seen_macros: usize,
+}
+
+#[cfg(feature = "i18n")]
+#[derive(Default)]
+struct Translations {
+    /// Set if `t()` is used, so the Fluent resources must be embedded
+    used: bool,
+    /// Missing translations that are reported as warnings
+    warnings: Vec<String>,
 }
 
 impl<'a, 'h> Generator<'a, 'h> {
@@ -106,10 +205,16 @@ impl<'a, 'h> Generator<'a, 'h> {
             super_block: None,
             buf_writable: WritableBuffer {
                 discard: buf_writable_discard,
+                html: input.contextual_escape.then(HtmlState::default),
                 ..Default::default()
             },
             is_in_filter_block,
             seen_macros: Vec::new(),
+            loops: Vec::new(),
+            callers: Vec::new(),
+            is_isolated: false,
+            #[cfg(feature = "i18n")]
+            translations: Translations::default(),
         }
     }
 
@@ -142,6 +247,18 @@ impl<'a, 'h> Generator<'a, 'h> {
                     helpers::{ResultConverter as _, core::fmt::Write as _},\
                 };",
         );
+        // The lookup of the `default` filter only exists if the filter is enabled.
+        if self.input.config.has_builtin_filters(FilterGroup::Default) {
+            buf.write(
+                "#[allow(unused_imports)]\
+                use askama::filters::DefaultValueLookup as _;",
+            );
+        }
+
+        #[cfg(feature = "hot-reload")]
+        if tmpl_kind == TmplKind::Struct {
+            self.write_hot_reload(buf);
+        }
 
         // Make sure the compiler understands that the generated code depends on the template files.
         let mut paths = self
@@ -167,6 +284,16 @@ impl<'a, 'h> Generator<'a, 'h> {
 
         let size_hint = self.impl_template_inner(ctx, buf)?;
 
+        #[cfg(feature = "i18n")]
+        {
+            if self.translations.used {
+                self.write_translation_resources(buf)?;
+            }
+            for warning in std::mem::take(&mut self.translations.warnings) {
+                buf.warn(warning);
+            }
+        }
+
         buf.write("askama::Result::Ok(()) }");
         if tmpl_kind == TmplKind::Struct {
             buf.write(format_args!(
@@ -184,6 +311,104 @@ impl<'a, 'h> Generator<'a, 'h> {
         Ok(size_hint)
     }
 
+    /// Embed the Fluent resources used by `t()`
+    #[cfg(feature = "i18n")]
+    fn write_translation_resources(&self, buf: &mut Buffer) -> Result<(), CompileError> {
+        let config = &self.input.config.i18n;
+        let catalog = crate::i18n::Catalog::load(config)?;
+        buf.write(format_args!(
+            "static __ASKAMA_I18N: askama::i18n::Resources = \
+                askama::i18n::Resources::new({:?}, &[",
+            config.default_locale,
+        ));
+        for (name, locale) in &catalog.locales {
+            buf.write(format_args!("({name:?}, &["));
+            for path in &locale.files {
+                buf.write(format_args!(
+                    "askama::helpers::core::include_str!({:#?}),",
+                    path.canonicalize().as_deref().unwrap_or(path),
+                ));
+            }
+            buf.write("]),");
+        }
+        buf.write("]);");
+
+        // Without the feature "i18n", `t()` would call the method `self.t()`, so such a method
+        // must not exist if `t()` is the translation function.
+        buf.write(
+            "let _: fn(&Self) -> askama::i18n::TranslationFunction = |__askama_self| {\
+                use askama::i18n::NoMethodT as _;\
+                __askama_self.t(askama::i18n::TemplateMethodTIsShadowedByTranslationFunction)\
+            };",
+        );
+        Ok(())
+    }
+
+    /// In debug builds, render the template with the interpreter if its files were modified
+    #[cfg(feature = "hot-reload")]
+    fn write_hot_reload(&self, buf: &mut Buffer) {
+        let (Some(sources), Some(name)) = (
+            hot_reload_sources(self.input, self.contexts),
+            hot_reload_name(self.input),
+        ) else {
+            return;
+        };
+        let Some(dirs) = self
+            .input
+            .config
+            .dirs
+            .iter()
+            .map(|dir| dir.to_str())
+            .collect::<Option<Vec<_>>>()
+        else {
+            return;
+        };
+        let whitespace = match self.input.config.whitespace {
+            Whitespace::Preserve => "Preserve",
+            Whitespace::Suppress => "Suppress",
+            Whitespace::Minimize => "Minimize",
+        };
+        let syntax = self.input.syntax;
+
+        buf.write(format_args!(
+            "\
+            #[cfg(debug_assertions)]\
+            if let askama::helpers::core::option::Option::Some(result) = \
+                askama::helpers::hot_reload::render_into(\
+                    __askama_writer,\
+                    &askama::helpers::hot_reload::Template {{\
+                        path: {:?},\
+                        sources: &[{}],\
+                        dirs: &[{}],\
+                        syntax: [{:?}, {:?}, {:?}, {:?}, {:?}, {:?}],\
+                        whitespace: askama::interpreter::Whitespace::{whitespace},\
+                    }},\
+                    {},\
+                    self,\
+                )\
+            {{\
+                return result;\
+            }}",
+            name,
+            sources
+                .iter()
+                .map(|(path, source)| format!("({path:?}, {source:?})"))
+                .collect::<Vec<_>>()
+                .join(", "),
+            dirs.iter()
+                .map(|dir| format!("{dir:?}"))
+                .collect::<Vec<_>>()
+                .join(", "),
+            syntax.block_start,
+            syntax.block_end,
+            syntax.expr_start,
+            syntax.expr_end,
+            syntax.comment_start,
+            syntax.comment_end,
+            self.input.escaper,
+        ));
+    }
+
     #[cfg(feature = "blocks")]
     fn impl_block(
         &self,
@@ -329,7 +554,32 @@ impl<'a, 'h> Generator<'a, 'h> {
     }
 
     fn is_var_defined(&self, var_name: &str) -> bool {
-        self.locals.get(var_name).is_some() || self.input.fields.iter().any(|f| f == var_name)
+        self.locals.get(var_name).is_some()
+            || (!self.is_isolated && self.input.fields.iter().any(|f| f == var_name))
+    }
+
+    /// Resolves the variable `name` to a local variable, or to a field of `self`
+    fn resolve_var(
+        &self,
+        ctx: &Context<'_>,
+        name: &str,
+        span: Span<'_>,
+    ) -> Result<String, CompileError> {
+        match self.locals.resolve(name) {
+            Some(resolved) => Ok(resolved),
+            None if self.is_isolated && name == "self" => Err(ctx.generate_error(
+                "`self` is not available in a template included with `only`",
+                span,
+            )),
+            None if self.is_isolated => Err(ctx.generate_error(
+                format_args!(
+                    "`{name}` is not available in a template included with `only`, \
+                     it has to be passed with `with {name} = …`"
+                ),
+                span,
+            )),
+            None => Ok(format!("self.{}", normalize_identifier(name))),
+        }
     }
 }
 
@@ -536,11 +786,6 @@ impl<'a> MapChain<'a> {
             None => name.to_string(),
         })
     }
-
-    fn resolve_or_self(&self, name: &str) -> String {
-        let name = normalize_identifier(name);
-        self.resolve(name).unwrap_or_else(|| format!("self.{name}"))
-    }
 }
 
 impl Default for MapChain<'_> {
@@ -580,6 +825,27 @@ fn is_copyable_within_op(expr: &Expr<'_>, within_op: bool) -> bool {
     }
 }
 
+/// Converts the iterable expression of a `{% for %}` loop, or of a recursive `loop(…)` call,
+/// into an iterator
+fn loop_iter_code(iter: &Expr<'_>, expr_code: &str) -> String {
+    match iter {
+        Expr::Range(_, _, _) => expr_code.to_owned(),
+        Expr::Array(..) => format!("{expr_code}.iter()"),
+        // If `iter` is a call then we assume it's something that returns
+        // an iterator. If not then the user can explicitly add the needed
+        // call without issues.
+        Expr::Call { .. } | Expr::Index(..) => format!("({expr_code}).into_iter()"),
+        // If accessing `self` then it most likely needs to be
+        // borrowed, to prevent an attempt of moving.
+        _ if expr_code.starts_with("self.") => format!("(&{expr_code}).into_iter()"),
+        // If accessing a field then it most likely needs to be
+        // borrowed, to prevent an attempt of moving.
+        Expr::Attr(..) => format!("(&{expr_code}).into_iter()"),
+        // Otherwise, we borrow `iter` assuming that it implements `IntoIterator`.
+        _ => format!("({expr_code}).into_iter()"),
+    }
+}
+
 /// Returns `true` if this is an `Attr` where the `obj` is `"self"`.
 fn is_attr_self(mut expr: &Expr<'_>) -> bool {
     loop {
@@ -603,10 +869,18 @@ enum DisplayWrap {
 struct WritableBuffer<'a> {
     buf: Vec<Writable<'a>>,
     discard: bool,
+    /// The HTML context of the output, if the template uses contextual escaping
+    html: Option<HtmlState>,
 }
 
 impl<'a> WritableBuffer<'a> {
     fn push(&mut self, writable: Writable<'a>) {
+        if let Some(html) = &mut self.html {
+            match &writable {
+                Writable::Lit(s) => html.write_lit(s),
+                Writable::Expr(..) => html.write_expr(),
+            }
+        }
         if !self.discard {
             self.buf.push(writable);
         }
@@ -624,7 +898,8 @@ impl<'a> Deref for WritableBuffer<'a> {
 #[derive(Debug)]
 enum Writable<'a> {
     Lit(Cow<'a, str>),
-    Expr(&'a WithSpan<'a, Expr<'a>>),
+    /// An expression, and the path of its escaper or the reason why no escaper can be used
+    Expr(&'a WithSpan<'a, Expr<'a>>, Result<&'a str, &'static str>),
 }
 
 /// Identifiers to be replaced with raw identifiers, so as to avoid
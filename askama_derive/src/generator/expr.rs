@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::fmt;
 
 use parser::node::CondTest;
 use parser::{
@@ -7,13 +8,21 @@ use parser::{
 };
 use quote::quote;
 
+use super::filters::{
+    BUILTIN_FILTERS, BUILTIN_FILTERS_NEED_ALLOC, FILTERS_WITH_NAMED_ARGUMENTS, FORWARDED_FILTERS,
+};
 use super::{
     DisplayWrap, FILTER_SOURCE, Generator, LocalMeta, TargetIsize, TargetUsize, Writable,
-    compile_time_escape, is_copyable, normalize_identifier,
+    compile_time_escape, is_copyable, loop_iter_code, normalize_identifier,
 };
+use crate::config::FilterGroup;
+#[cfg(feature = "i18n")]
+use crate::config::MissingTranslation;
 use crate::heritage::Context;
+#[cfg(feature = "i18n")]
+use crate::i18n::Catalog;
 use crate::integration::Buffer;
-use crate::{BUILTIN_FILTERS, BUILTIN_FILTERS_NEED_ALLOC, CompileError, MsgValidEscapers};
+use crate::{CompileError, MsgValidEscapers};
 
 impl<'a> Generator<'a, '_> {
     pub(crate) fn visit_expr_root(
@@ -37,7 +46,7 @@ impl<'a> Generator<'a, '_> {
             Expr::NumLit(s, _) => self.visit_num_lit(buf, s),
             Expr::StrLit(ref s) => self.visit_str_lit(buf, s),
             Expr::CharLit(ref s) => self.visit_char_lit(buf, s),
-            Expr::Var(s) => self.visit_var(buf, s),
+            Expr::Var(s) => self.visit_var(ctx, buf, s, expr.span())?,
             Expr::Path(ref path) => self.visit_path(buf, path),
             Expr::Array(ref elements) => self.visit_array(ctx, buf, elements)?,
             Expr::Attr(ref obj, ref attr) => self.visit_attr(ctx, buf, obj, attr)?,
@@ -65,6 +74,8 @@ impl<'a> Generator<'a, '_> {
             Expr::FilterSource => self.visit_filter_source(buf),
             Expr::IsDefined(var_name) => self.visit_is_defined(buf, true, var_name)?,
             Expr::IsNotDefined(var_name) => self.visit_is_defined(buf, false, var_name)?,
+            Expr::IsNone(ref expr) => self.visit_is_none(ctx, buf, true, expr)?,
+            Expr::IsNotNone(ref expr) => self.visit_is_none(ctx, buf, false, expr)?,
             Expr::As(ref expr, target) => self.visit_as(ctx, buf, expr, target)?,
             Expr::Concat(ref exprs) => self.visit_concat(ctx, buf, exprs)?,
             Expr::LetCond(ref cond) => self.visit_let_cond(ctx, buf, cond)?,
@@ -125,7 +136,11 @@ impl<'a> Generator<'a, '_> {
         expr: &WithSpan<'_, Expr<'a>>,
     ) -> Result<(), CompileError> {
         match &**expr {
-            Expr::BoolLit(_) | Expr::IsDefined(_) | Expr::IsNotDefined(_) => {
+            Expr::BoolLit(_)
+            | Expr::IsDefined(_)
+            | Expr::IsNotDefined(_)
+            | Expr::IsNone(_)
+            | Expr::IsNotNone(_) => {
                 self.visit_expr(ctx, buf, expr)?;
             }
             Expr::Unary("!", expr) => {
@@ -167,6 +182,22 @@ impl<'a> Generator<'a, '_> {
         Ok(DisplayWrap::Unwrapped)
     }
 
+    fn visit_is_none(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        is_none: bool,
+        expr: &WithSpan<'_, Expr<'a>>,
+    ) -> Result<DisplayWrap, CompileError> {
+        if !is_none {
+            buf.write('!');
+        }
+        buf.write("askama::helpers::core::matches!(");
+        self.visit_expr(ctx, buf, expr)?;
+        buf.write(", askama::helpers::core::option::Option::None)");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
     fn visit_as(
         &mut self,
         ctx: &Context<'_>,
@@ -251,28 +282,69 @@ impl<'a> Generator<'a, '_> {
         generics: &[WithSpan<'_, TyGenerics<'_>>],
         node: Span<'_>,
     ) -> Result<DisplayWrap, CompileError> {
+        // Only the names of `BUILTIN_FILTERS` are looked up, so the language server, that uses
+        // the same list, offers every filter that is dispatched below.
+        if BUILTIN_FILTERS.binary_search(&name).is_err() {
+            return self._visit_custom_filter(ctx, buf, name, args, generics, node);
+        }
         let filter = match name {
+            "batch" if self.has_collection_filters() => Self::_visit_batch_filter,
+            "currency" if self.has_unstable_number_filters() => Self::_visit_currency_filter,
+            "date" if self.has_datetime_filters() => Self::_visit_date_filter,
+            "datetime" if self.has_datetime_filters() => Self::_visit_datetime_filter,
+            "default" if self.input.config.has_builtin_filters(FilterGroup::Default) => {
+                Self::_visit_default_filter
+            }
             "deref" => Self::_visit_deref_filter,
             "escape" | "e" => Self::_visit_escape_filter,
             "filesizeformat" => Self::_visit_humansize,
+            "first" if self.has_collection_filters() => Self::_visit_first_filter,
             "fmt" => Self::_visit_fmt_filter,
             "format" => Self::_visit_format_filter,
+            "groupby" if self.has_collection_filters() => Self::_visit_groupby_filter,
+            "intcomma" if self.has_number_filters() => Self::_visit_intcomma_filter,
             "join" => Self::_visit_join_filter,
             "json" | "tojson" => Self::_visit_json_filter,
+            "last" if self.has_collection_filters() => Self::_visit_last_filter,
+            "length" if self.has_collection_filters() => Self::_visit_length_filter,
             "linebreaks" => Self::_visit_linebreaks_filter,
             "linebreaksbr" => Self::_visit_linebreaksbr_filter,
+            "map" if self.has_collection_filters() => Self::_visit_map_filter,
+            "max" if self.has_collection_filters() => Self::_visit_max_filter,
+            "min" if self.has_collection_filters() => Self::_visit_min_filter,
+            "number" if self.has_number_filters() => Self::_visit_number_filter,
             "paragraphbreaks" => Self::_visit_paragraphbreaks_filter,
+            "percent" if self.has_unstable_number_filters() => Self::_visit_percent_filter,
             "pluralize" => Self::_visit_pluralize_filter,
             "ref" => Self::_visit_ref_filter,
+            "reject" if self.has_collection_filters() => Self::_visit_reject_filter,
+            "rejectattr" if self.has_collection_filters() => Self::_visit_rejectattr_filter,
+            "reverse" if self.has_collection_filters() => Self::_visit_reverse_filter,
             "safe" => Self::_visit_safe_filter,
+            "select" if self.has_collection_filters() => Self::_visit_select_filter,
+            "selectattr" if self.has_collection_filters() => Self::_visit_selectattr_filter,
+            "slice" if self.has_collection_filters() => Self::_visit_slice_filter,
+            "sort" if self.has_collection_filters() => Self::_visit_sort_filter,
+            "sum" if self.has_collection_filters() => Self::_visit_sum_filter,
+            "time" if self.has_datetime_filters() => Self::_visit_time_filter,
+            "timesince" if self.has_datetime_filters() => Self::_visit_timesince_filter,
+            "timeuntil" if self.has_datetime_filters() => Self::_visit_timeuntil_filter,
+            "unique" if self.has_collection_filters() => Self::_visit_unique_filter,
             "urlencode" => Self::_visit_urlencode_filter,
             "urlencode_strict" => Self::_visit_urlencode_strict_filter,
-            "value" => return self._visit_value(ctx, buf, args, generics, node, "`value` filter"),
-            name if BUILTIN_FILTERS.contains(&name) => {
+            "value" => {
+                ensure_no_named_arguments(ctx, args, format_args!("the filter `{name}`"))?;
+                return self._visit_value(ctx, buf, args, generics, node, "`value` filter");
+            }
+            name if FORWARDED_FILTERS.contains(&name) => {
+                ensure_no_named_arguments(ctx, args, format_args!("the filter `{name}`"))?;
                 return self._visit_builtin_filter(ctx, buf, name, args, generics, node);
             }
             _ => return self._visit_custom_filter(ctx, buf, name, args, generics, node),
         };
+        if !FILTERS_WITH_NAMED_ARGUMENTS.contains(&name) {
+            ensure_no_named_arguments(ctx, args, format_args!("the filter `{name}`"))?;
+        }
         if !generics.is_empty() {
             Err(ctx.generate_error(format_args!("unexpected generics on filter `{name}`"), node))
         } else {
@@ -280,6 +352,24 @@ impl<'a> Generator<'a, '_> {
         }
     }
 
+    fn has_datetime_filters(&self) -> bool {
+        DATETIME_FILTERS && self.input.config.has_builtin_filters(FilterGroup::Datetime)
+    }
+
+    fn has_number_filters(&self) -> bool {
+        cfg!(feature = "icu") && self.input.config.has_builtin_filters(FilterGroup::Numbers)
+    }
+
+    fn has_unstable_number_filters(&self) -> bool {
+        cfg!(feature = "icu-unstable") && self.has_number_filters()
+    }
+
+    fn has_collection_filters(&self) -> bool {
+        self.input
+            .config
+            .has_builtin_filters(FilterGroup::Collections)
+    }
+
     fn _visit_custom_filter(
         &mut self,
         ctx: &Context<'_>,
@@ -292,6 +382,7 @@ impl<'a> Generator<'a, '_> {
         if BUILTIN_FILTERS_NEED_ALLOC.contains(&name) {
             ensure_filter_has_feature_alloc(ctx, name, node)?;
         }
+        ensure_no_named_arguments(ctx, args, format_args!("the custom filter `{name}`"))?;
         buf.write(format_args!("filters::{name}"));
         self.visit_call_generics(buf, generics);
         buf.write('(');
@@ -366,151 +457,895 @@ impl<'a> Generator<'a, '_> {
         Ok(DisplayWrap::Unwrapped)
     }
 
-    fn _visit_humansize(
+    fn _visit_date_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        self._visit_datetime_filter_inner(ctx, buf, "date", "%Y-%m-%d", args, node)
+    }
+
+    fn _visit_datetime_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        self._visit_datetime_filter_inner(ctx, buf, "datetime", "%Y-%m-%d %H:%M:%S", args, node)
+    }
+
+    fn _visit_time_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        self._visit_datetime_filter_inner(ctx, buf, "time", "%H:%M:%S", args, node)
+    }
+
+    fn _visit_datetime_filter_inner(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        name: &str,
+        default_format: &str,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        let (value, format) = match args {
+            [value] => (value, None),
+            [value, format] => (value, Some(format)),
+            _ => {
+                return Err(ctx.generate_error(
+                    format_args!("unexpected argument(s) in `{name}` filter"),
+                    node,
+                ));
+            }
+        };
+        buf.write(format_args!("askama::filters::{name}(&("));
+        self._visit_arg(ctx, buf, value)?;
+        buf.write("),");
+        match format {
+            Some(format) => self._visit_arg(ctx, buf, format)?,
+            None => buf.write(format_args!("{default_format:?}")),
+        }
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_timesince_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        self._visit_timesince_filter_inner(ctx, buf, "timesince", args, node)
+    }
+
+    fn _visit_timeuntil_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        self._visit_timesince_filter_inner(ctx, buf, "timeuntil", args, node)
+    }
+
+    fn _visit_timesince_filter_inner(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        name: &str,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        let (value, now) = match args {
+            [value] => (value, None),
+            [value, now] => (value, Some(now)),
+            _ => {
+                return Err(ctx.generate_error(
+                    format_args!("unexpected argument(s) in `{name}` filter"),
+                    node,
+                ));
+            }
+        };
+        if now.is_none() && !cfg!(feature = "std") {
+            return Err(ctx.generate_error(
+                format_args!(
+                    "the `{name}` filter needs a point in time to compare to \
+                     if the `std` feature is disabled"
+                ),
+                node,
+            ));
+        }
+
+        // The output only consists of numbers and english words, so it is HTML safe.
+        buf.write(format_args!(
+            "askama::filters::HtmlSafeOutput(askama::filters::{name}(&("
+        ));
+        self._visit_arg(ctx, buf, value)?;
+        buf.write("),&(");
+        match now {
+            Some(now) => self._visit_arg(ctx, buf, now)?,
+            None => buf.write("askama::helpers::std::time::SystemTime::now()"),
+        }
+        buf.write("))?)");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_humansize(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        _node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        // All filters return numbers, and any default formatted number is HTML safe.
+        buf.write(format_args!(
+            "askama::filters::HtmlSafeOutput(askama::filters::filesizeformat(\
+                 askama::helpers::get_primitive_value(&("
+        ));
+        self._visit_args(ctx, buf, args)?;
+        buf.write(")) as askama::helpers::core::primitive::f32)?)");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_number_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        let (value, decimals) = match args {
+            [value] => (value, None),
+            [value, decimals] => (value, Some(decimals)),
+            _ => return Err(ctx.generate_error("unexpected argument(s) in `number` filter", node)),
+        };
+        self._visit_number_filter_inner(ctx, buf, "number", value, None, decimals)
+    }
+
+    fn _visit_intcomma_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        let [value] = args else {
+            return Err(ctx.generate_error("unexpected argument(s) in `intcomma` filter", node));
+        };
+        self._visit_number_filter_inner(ctx, buf, "number", value, None, None)
+    }
+
+    fn _visit_percent_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        let (value, decimals) = match args {
+            [value] => (value, None),
+            [value, decimals] => (value, Some(decimals)),
+            _ => {
+                return Err(ctx.generate_error("unexpected argument(s) in `percent` filter", node));
+            }
+        };
+        self._visit_number_filter_inner(ctx, buf, "percent", value, None, decimals)
+    }
+
+    fn _visit_currency_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        let (value, code, decimals) = match args {
+            [value, code] => (value, code, None),
+            [value, code, decimals] => (value, code, Some(decimals)),
+            _ => {
+                return Err(ctx.generate_error(
+                    "the `currency` filter expects a currency code, e.g. `|currency(\"EUR\")`, \
+                     and optionally the number of decimal places",
+                    node,
+                ));
+            }
+        };
+        self._visit_number_filter_inner(ctx, buf, "currency", value, Some(code), decimals)
+    }
+
+    #[cfg_attr(not(feature = "icu"), allow(unused_variables))]
+    fn _visit_number_filter_inner(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        name: &str,
+        value: &WithSpan<'_, Expr<'a>>,
+        currency: Option<&WithSpan<'_, Expr<'a>>>,
+        decimals: Option<&WithSpan<'_, Expr<'a>>>,
+    ) -> Result<DisplayWrap, CompileError> {
+        #[cfg(feature = "icu")]
+        {
+            // Numbers formatted with CLDR data never contain characters that need escaping.
+            buf.write(format_args!(
+                "askama::filters::HtmlSafeOutput(askama::filters::{name}(&("
+            ));
+            self._visit_arg(ctx, buf, value)?;
+            buf.write("),");
+            if let Some(currency) = currency {
+                buf.write("askama::helpers::core::convert::AsRef::<str>::as_ref(&(");
+                self._visit_arg(ctx, buf, currency)?;
+                buf.write(")),");
+            }
+            buf.write("&askama::filters::number_locale(");
+            self.visit_locale(buf);
+            buf.write(",__askama_values),");
+            match decimals {
+                // A literal is checked here, so an invalid value is a compile error.
+                Some(decimals) if is_num_lit(decimals) => {
+                    let Some(decimals) = expr_as_u8_lit(decimals) else {
+                        return Err(ctx.generate_error(
+                            format_args!(
+                                "the number of decimal places of the `{name}` filter must be \
+                                 an integer between 0 and 255",
+                            ),
+                            decimals.span(),
+                        ));
+                    };
+                    buf.write(format_args!(
+                        "askama::helpers::core::option::Option::Some({decimals}_u8)"
+                    ));
+                }
+                Some(decimals) => {
+                    buf.write(
+                        "askama::helpers::core::option::Option::Some(\
+                            askama::filters::number_decimals(\
+                                askama::helpers::get_primitive_value(&(",
+                    );
+                    self._visit_arg(ctx, buf, decimals)?;
+                    buf.write(")))?)");
+                }
+                None => buf.write("askama::helpers::core::option::Option::None"),
+            }
+            buf.write(")?)");
+        }
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_pluralize_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        const SINGULAR: &WithSpan<'static, Expr<'static>> =
+            &WithSpan::new_without_span(Expr::StrLit(StrLit {
+                prefix: None,
+                content: "",
+            }));
+        const PLURAL: &WithSpan<'static, Expr<'static>> =
+            &WithSpan::new_without_span(Expr::StrLit(StrLit {
+                prefix: None,
+                content: "s",
+            }));
+
+        let (count, sg, pl) = match args {
+            [count] => (count, SINGULAR, PLURAL),
+            [count, sg] => (count, sg, PLURAL),
+            [count, sg, pl] => (count, sg, pl),
+            _ => {
+                return Err(
+                    ctx.generate_error("unexpected argument(s) in `pluralize` filter", node)
+                );
+            }
+        };
+        if let Some(is_singular) = expr_is_int_lit_plus_minus_one(count) {
+            let value = if is_singular { sg } else { pl };
+            self._visit_auto_escaped_arg(ctx, buf, value)?;
+        } else {
+            buf.write("askama::filters::pluralize(");
+            self._visit_arg(ctx, buf, count)?;
+            for value in [sg, pl] {
+                buf.write(',');
+                self._visit_auto_escaped_arg(ctx, buf, value)?;
+            }
+            buf.write(")?");
+        }
+        Ok(DisplayWrap::Wrapped)
+    }
+
+    fn _visit_paragraphbreaks_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        self._visit_linebreaks_filters(ctx, buf, "paragraphbreaks", args, node)
+    }
+
+    fn _visit_linebreaksbr_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        self._visit_linebreaks_filters(ctx, buf, "linebreaksbr", args, node)
+    }
+
+    fn _visit_linebreaks_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        self._visit_linebreaks_filters(ctx, buf, "linebreaks", args, node)
+    }
+
+    fn _visit_linebreaks_filters(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        name: &str,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        ensure_filter_has_feature_alloc(ctx, name, node)?;
+        if args.len() != 1 {
+            return Err(ctx.generate_error(
+                format_args!("unexpected argument(s) in `{name}` filter"),
+                node,
+            ));
+        }
+        buf.write(format_args!(
+            "askama::filters::{name}(&(&&askama::filters::AutoEscaper::new(&(",
+        ));
+        self._visit_args(ctx, buf, args)?;
+        // The input is always HTML escaped, regardless of the selected escaper:
+        buf.write("), askama::filters::Html)).askama_auto_escape()?)?");
+        // The output is marked as HTML safe, not safe in all contexts:
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_ref_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        let arg = match args {
+            [arg] => arg,
+            _ => return Err(ctx.generate_error("unexpected argument(s) in `as_ref` filter", node)),
+        };
+        buf.write('&');
+        self.visit_expr(ctx, buf, arg)?;
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_deref_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        let arg = match args {
+            [arg] => arg,
+            _ => return Err(ctx.generate_error("unexpected argument(s) in `deref` filter", node)),
+        };
+        buf.write('*');
+        self.visit_expr(ctx, buf, arg)?;
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_default_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        let (value, fallback, empty_is_missing) = match args {
+            [value, fallback] => (value, fallback, None),
+            [value, fallback, empty_is_missing] => (value, fallback, Some(empty_is_missing)),
+            _ => return Err(ctx.generate_error("unexpected argument(s) in `default` filter", node)),
+        };
+        // `DefaultValueOf` needs a reference to the value itself, not to a reference of it,
+        // so an `Option` can be recognized.
+        buf.write("askama::filters::default_of((&&askama::filters::DefaultValueOf(");
+        self._visit_arg_inner(ctx, buf, value, true)?;
+        buf.write(")).askama_default_value(");
+        match empty_is_missing {
+            Some(empty_is_missing) => {
+                buf.write("askama::helpers::get_primitive_value(&(");
+                self._visit_arg(ctx, buf, empty_is_missing)?;
+                buf.write("))");
+            }
+            None => buf.write("false"),
+        }
+        buf.write("),(&&askama::filters::DefaultValueOf(");
+        self._visit_arg_inner(ctx, buf, fallback, true)?;
+        buf.write(")).askama_default_value(false))?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_batch_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        self._visit_collection_filter(ctx, buf, "batch", args, node)
+    }
+
+    fn _visit_first_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        self._visit_collection_filter(ctx, buf, "first", args, node)
+    }
+
+    fn _visit_groupby_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        self._visit_collection_filter(ctx, buf, "groupby", args, node)
+    }
+
+    fn _visit_last_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        self._visit_collection_filter(ctx, buf, "last", args, node)
+    }
+
+    fn _visit_length_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        self._visit_collection_filter(ctx, buf, "length", args, node)
+    }
+
+    fn _visit_max_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        self._visit_collection_filter(ctx, buf, "max", args, node)
+    }
+
+    fn _visit_min_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        self._visit_collection_filter(ctx, buf, "min", args, node)
+    }
+
+    fn _visit_reverse_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        self._visit_collection_filter(ctx, buf, "reverse", args, node)
+    }
+
+    fn _visit_slice_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        self._visit_collection_filter(ctx, buf, "slice", args, node)
+    }
+
+    fn _visit_sort_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        self._visit_collection_filter(ctx, buf, "sort", args, node)
+    }
+
+    fn _visit_sum_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        self._visit_collection_filter(ctx, buf, "sum", args, node)
+    }
+
+    fn _visit_unique_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        self._visit_collection_filter(ctx, buf, "unique", args, node)
+    }
+
+    fn _visit_collection_filter(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        name: &str,
+        args: &[WithSpan<'_, Expr<'a>>],
+        node: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        let params: &[&str] = match name {
+            "first" | "last" | "length" | "reverse" => &[],
+            "groupby" | "max" | "min" | "unique" => &["attribute"],
+            "sort" => &["reverse", "attribute"],
+            "sum" => &["attribute", "start"],
+            "batch" => &["linecount", "fill_with"],
+            "slice" => &["slices", "fill_with"],
+            _ => unreachable!(),
+        };
+        if matches!(
+            name,
+            "batch" | "groupby" | "reverse" | "slice" | "sort" | "unique"
+        ) {
+            ensure_filter_has_feature_alloc(ctx, name, node)?;
+        }
+        let (input, values) = collection_filter_args(ctx, name, params, args, node)?;
+
+        buf.write(format_args!("askama::filters::{name}("));
+        self._visit_iterable_arg(ctx, buf, input)?;
+        match name {
+            "groupby" | "max" | "min" | "sort" | "sum" | "unique" => {
+                let attribute = match name {
+                    "sort" => values[1],
+                    _ => values[0],
+                };
+                if name == "groupby" && attribute.is_none() {
+                    return Err(ctx.generate_error(
+                        "the `groupby` filter requires an `attribute` argument",
+                        node,
+                    ));
+                }
+                buf.write(',');
+                self._visit_key_fn(ctx, buf, name, attribute)?;
+            }
+            "batch" | "slice" => {
+                let Some(count) = values[0] else {
+                    return Err(ctx.generate_error(
+                        format_args!("the `{name}` filter requires a `{}` argument", params[0]),
+                        node,
+                    ));
+                };
+                buf.write(",askama::helpers::get_primitive_value(&(");
+                self.visit_expr(ctx, buf, count)?;
+                buf.write(")) as askama::helpers::core::primitive::usize");
+            }
+            _ => {}
+        }
+        match name {
+            "sort" => {
+                buf.write(',');
+                match values[0] {
+                    Some(reverse) => {
+                        buf.write("askama::helpers::get_primitive_value(&(");
+                        self.visit_expr(ctx, buf, reverse)?;
+                        buf.write("))");
+                    }
+                    None => buf.write("false"),
+                }
+            }
+            "sum" | "batch" | "slice" => {
+                buf.write(',');
+                match values[1] {
+                    Some(value) => {
+                        buf.write("askama::helpers::core::option::Option::Some(");
+                        self.visit_expr(ctx, buf, value)?;
+                        buf.write(')');
+                    }
+                    None => buf.write("askama::helpers::core::option::Option::None"),
+                }
+            }
+            _ => {}
+        }
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    /// Writes an expression that implements [`IntoIterator`], borrowing places like fields
+    /// or variables, similar to the iterable of a `{% for %}` loop
+    fn _visit_iterable_arg(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        arg: &WithSpan<'_, Expr<'a>>,
+    ) -> Result<(), CompileError> {
+        let mut inner = arg;
+        while let Expr::Group(ref expr) = **inner {
+            inner = expr;
+        }
+        match **inner {
+            Expr::Filter(..) | Expr::Call { .. } | Expr::Range(..) | Expr::Array(..) => {
+                buf.write('(');
+                self.visit_expr(ctx, buf, arg)?;
+                buf.write(')');
+            }
+            _ => {
+                buf.write("(&(");
+                self.visit_expr(ctx, buf, arg)?;
+                buf.write(")).into_iter()");
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a closure that returns a reference to the `attribute` of an item,
+    /// or the item itself
+    fn _visit_key_fn(
         &mut self,
         ctx: &Context<'_>,
         buf: &mut Buffer,
-        args: &[WithSpan<'_, Expr<'a>>],
-        _node: Span<'_>,
-    ) -> Result<DisplayWrap, CompileError> {
-        // All filters return numbers, and any default formatted number is HTML safe.
+        name: &str,
+        attribute: Option<&WithSpan<'_, Expr<'a>>>,
+    ) -> Result<(), CompileError> {
+        let Some(attribute) = attribute else {
+            buf.write("|__askama_item| __askama_item");
+            return Ok(());
+        };
         buf.write(format_args!(
-            "askama::filters::HtmlSafeOutput(askama::filters::filesizeformat(\
-                 askama::helpers::get_primitive_value(&("
+            "|__askama_item| &__askama_item{}",
+            attribute_access(ctx, name, attribute)?
         ));
-        self._visit_args(ctx, buf, args)?;
-        buf.write(")) as askama::helpers::core::primitive::f32)?)");
-        Ok(DisplayWrap::Unwrapped)
+        Ok(())
     }
 
-    fn _visit_pluralize_filter(
+    fn _visit_map_filter(
         &mut self,
         ctx: &Context<'_>,
         buf: &mut Buffer,
         args: &[WithSpan<'_, Expr<'a>>],
         node: Span<'_>,
     ) -> Result<DisplayWrap, CompileError> {
-        const SINGULAR: &WithSpan<'static, Expr<'static>> =
-            &WithSpan::new_without_span(Expr::StrLit(StrLit {
-                prefix: None,
-                content: "",
-            }));
-        const PLURAL: &WithSpan<'static, Expr<'static>> =
-            &WithSpan::new_without_span(Expr::StrLit(StrLit {
-                prefix: None,
-                content: "s",
-            }));
-
-        let (count, sg, pl) = match args {
-            [count] => (count, SINGULAR, PLURAL),
-            [count, sg] => (count, sg, PLURAL),
-            [count, sg, pl] => (count, sg, pl),
-            _ => {
-                return Err(
-                    ctx.generate_error("unexpected argument(s) in `pluralize` filter", node)
-                );
-            }
-        };
-        if let Some(is_singular) = expr_is_int_lit_plus_minus_one(count) {
-            let value = if is_singular { sg } else { pl };
-            self._visit_auto_escaped_arg(ctx, buf, value)?;
-        } else {
-            buf.write("askama::filters::pluralize(");
-            self._visit_arg(ctx, buf, count)?;
-            for value in [sg, pl] {
-                buf.write(',');
-                self._visit_auto_escaped_arg(ctx, buf, value)?;
-            }
-            buf.write(")?");
+        if let Some(arg) = args
+            .iter()
+            .skip(1)
+            .find(|arg| !matches!(***arg, Expr::NamedArgument(..)))
+        {
+            return Err(ctx.generate_error(
+                "the `map` filter only supports the `attribute` argument, \
+                 e.g. `map(attribute=\"name\")`",
+                arg.span(),
+            ));
         }
-        Ok(DisplayWrap::Wrapped)
+        let (input, values) = collection_filter_args(ctx, "map", &["attribute"], args, node)?;
+        let Some(attribute) = values[0] else {
+            return Err(
+                ctx.generate_error("the `map` filter requires an `attribute` argument", node)
+            );
+        };
+        buf.write("askama::filters::map(");
+        self._visit_iterable_arg(ctx, buf, input)?;
+        buf.write(',');
+        self._visit_key_fn(ctx, buf, "map", Some(attribute))?;
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
     }
 
-    fn _visit_paragraphbreaks_filter(
+    fn _visit_select_filter(
         &mut self,
         ctx: &Context<'_>,
         buf: &mut Buffer,
         args: &[WithSpan<'_, Expr<'a>>],
         node: Span<'_>,
     ) -> Result<DisplayWrap, CompileError> {
-        self._visit_linebreaks_filters(ctx, buf, "paragraphbreaks", args, node)
+        self._visit_select_filters(ctx, buf, "select", args, node)
     }
 
-    fn _visit_linebreaksbr_filter(
+    fn _visit_reject_filter(
         &mut self,
         ctx: &Context<'_>,
         buf: &mut Buffer,
         args: &[WithSpan<'_, Expr<'a>>],
         node: Span<'_>,
     ) -> Result<DisplayWrap, CompileError> {
-        self._visit_linebreaks_filters(ctx, buf, "linebreaksbr", args, node)
+        self._visit_select_filters(ctx, buf, "reject", args, node)
     }
 
-    fn _visit_linebreaks_filter(
+    fn _visit_selectattr_filter(
         &mut self,
         ctx: &Context<'_>,
         buf: &mut Buffer,
         args: &[WithSpan<'_, Expr<'a>>],
         node: Span<'_>,
     ) -> Result<DisplayWrap, CompileError> {
-        self._visit_linebreaks_filters(ctx, buf, "linebreaks", args, node)
+        self._visit_select_filters(ctx, buf, "selectattr", args, node)
     }
 
-    fn _visit_linebreaks_filters(
+    fn _visit_rejectattr_filter(
         &mut self,
         ctx: &Context<'_>,
         buf: &mut Buffer,
-        name: &str,
         args: &[WithSpan<'_, Expr<'a>>],
         node: Span<'_>,
     ) -> Result<DisplayWrap, CompileError> {
-        ensure_filter_has_feature_alloc(ctx, name, node)?;
-        if args.len() != 1 {
-            return Err(ctx.generate_error(
-                format_args!("unexpected argument(s) in `{name}` filter"),
-                node,
-            ));
-        }
-        buf.write(format_args!(
-            "askama::filters::{name}(&(&&askama::filters::AutoEscaper::new(&(",
-        ));
-        self._visit_args(ctx, buf, args)?;
-        // The input is always HTML escaped, regardless of the selected escaper:
-        buf.write("), askama::filters::Html)).askama_auto_escape()?)?");
-        // The output is marked as HTML safe, not safe in all contexts:
-        Ok(DisplayWrap::Unwrapped)
+        self._visit_select_filters(ctx, buf, "rejectattr", args, node)
     }
 
-    fn _visit_ref_filter(
+    /// Writes a call to `askama::filters::select()` with a closure that applies the test
+    fn _visit_select_filters(
         &mut self,
         ctx: &Context<'_>,
         buf: &mut Buffer,
+        name: &str,
         args: &[WithSpan<'_, Expr<'a>>],
         node: Span<'_>,
     ) -> Result<DisplayWrap, CompileError> {
-        let arg = match args {
-            [arg] => arg,
-            _ => return Err(ctx.generate_error("unexpected argument(s) in `as_ref` filter", node)),
+        let (input, args) = match args {
+            [input, args @ ..] => (input, args),
+            [] => {
+                return Err(
+                    ctx.generate_error(format_args!("missing input of `{name}` filter"), node)
+                );
+            }
         };
-        buf.write('&');
-        self.visit_expr(ctx, buf, arg)?;
+        let (value, args) = match (name, args) {
+            ("selectattr" | "rejectattr", [attribute, args @ ..]) => (
+                Cow::Owned(format!(
+                    "&__askama_item{}",
+                    attribute_access(ctx, name, attribute)?
+                )),
+                args,
+            ),
+            ("selectattr" | "rejectattr", []) => {
+                return Err(ctx.generate_error(
+                    format_args!("the `{name}` filter requires an attribute argument"),
+                    node,
+                ));
+            }
+            _ => (Cow::Borrowed("__askama_item"), args),
+        };
+
+        buf.write("askama::filters::select(");
+        self._visit_iterable_arg(ctx, buf, input)?;
+        buf.write(",|__askama_item| ");
+        if name.starts_with("reject") {
+            buf.write('!');
+        }
+        buf.write('(');
+        self._visit_test(ctx, buf, name, &value, args, node)?;
+        buf.write("))?");
         Ok(DisplayWrap::Unwrapped)
     }
 
-    fn _visit_deref_filter(
+    /// Writes a boolean expression that applies a Jinja-like test, e.g. `"odd"`, to a value
+    fn _visit_test(
         &mut self,
         ctx: &Context<'_>,
         buf: &mut Buffer,
+        name: &str,
+        value: &str,
         args: &[WithSpan<'_, Expr<'a>>],
         node: Span<'_>,
-    ) -> Result<DisplayWrap, CompileError> {
-        let arg = match args {
-            [arg] => arg,
-            _ => return Err(ctx.generate_error("unexpected argument(s) in `deref` filter", node)),
+    ) -> Result<(), CompileError> {
+        let (test, args) = match args {
+            [] => {
+                buf.write(format_args!("askama::filters::Truthy::is_truthy({value})"));
+                return Ok(());
+            }
+            [test, args @ ..] => match **test {
+                Expr::StrLit(StrLit {
+                    prefix: None,
+                    content,
+                }) => (content, args),
+                _ => {
+                    return Err(ctx.generate_error(
+                        format_args!(
+                            "the test of the `{name}` filter must be a string literal, \
+                             e.g. `\"odd\"`"
+                        ),
+                        test.span(),
+                    ));
+                }
+            },
         };
-        buf.write('*');
-        self.visit_expr(ctx, buf, arg)?;
-        Ok(DisplayWrap::Unwrapped)
+        let op = match test {
+            "eq" | "equalto" | "==" => Some("=="),
+            "ne" | "!=" => Some("!="),
+            "lt" | "lessthan" | "<" => Some("<"),
+            "le" | "<=" => Some("<="),
+            "gt" | "greaterthan" | ">" => Some(">"),
+            "ge" | ">=" => Some(">="),
+            _ => None,
+        };
+        let arg = match (test, args) {
+            ("none" | "odd" | "even" | "true" | "false", []) => None,
+            ("divisibleby", [arg]) => Some(arg),
+            (_, [arg]) if op.is_some() => Some(arg),
+            ("none" | "odd" | "even" | "true" | "false" | "divisibleby", _) => {
+                return Err(ctx.generate_error(
+                    format_args!("unexpected argument(s) for test `{test}` in `{name}` filter"),
+                    node,
+                ));
+            }
+            _ if op.is_some() => {
+                return Err(ctx.generate_error(
+                    format_args!("unexpected argument(s) for test `{test}` in `{name}` filter"),
+                    node,
+                ));
+            }
+            _ => {
+                return Err(ctx.generate_error(
+                    format_args!("unknown test `{test}` in `{name}` filter"),
+                    node,
+                ));
+            }
+        };
+
+        match (test, op, arg) {
+            ("none", ..) => buf.write(format_args!("({value}).is_none()")),
+            ("odd" | "even", ..) => buf.write(format_args!(
+                "askama::helpers::get_primitive_value({value}) % 2 {} 0",
+                if test == "odd" { "!=" } else { "==" },
+            )),
+            ("true" | "false", ..) => buf.write(format_args!(
+                "{}askama::helpers::as_bool({value})",
+                if test == "false" { "!" } else { "" },
+            )),
+            ("divisibleby", _, Some(arg)) => {
+                buf.write(format_args!(
+                    "askama::helpers::get_primitive_value({value}) % ("
+                ));
+                self.visit_expr(ctx, buf, arg)?;
+                buf.write(") == 0");
+            }
+            (_, Some(op), Some(arg)) => {
+                buf.write(format_args!(
+                    "askama::filters::CompareValue::compare_value({value}) {op} "
+                ));
+                if is_literal(arg) {
+                    self.visit_expr(ctx, buf, arg)?;
+                } else {
+                    buf.write("askama::filters::CompareValue::compare_value(&(");
+                    self.visit_expr(ctx, buf, arg)?;
+                    buf.write("))");
+                }
+            }
+            _ => unreachable!(),
+        }
+        Ok(())
     }
 
     fn _visit_json_filter(
@@ -666,14 +1501,13 @@ impl<'a> Generator<'a, '_> {
         args: &[WithSpan<'_, Expr<'a>>],
         _node: Span<'_>,
     ) -> Result<DisplayWrap, CompileError> {
-        buf.write("askama::filters::join((&");
+        buf.write("askama::filters::join(");
         for (i, arg) in args.iter().enumerate() {
             if i > 0 {
                 buf.write(", &");
-            }
-            self.visit_expr(ctx, buf, arg)?;
-            if i == 0 {
-                buf.write(").into_iter()");
+                self.visit_expr(ctx, buf, arg)?;
+            } else {
+                self._visit_iterable_arg(ctx, buf, arg)?;
             }
         }
         buf.write(")?");
@@ -802,21 +1636,46 @@ impl<'a> Generator<'a, '_> {
     ) -> Result<DisplayWrap, CompileError> {
         if let Expr::Var(name) = **obj {
             if name == "loop" {
-                if attr.name == "index" {
-                    buf.write("(_loop_item.index + 1)");
-                    return Ok(DisplayWrap::Unwrapped);
-                } else if attr.name == "index0" {
-                    buf.write("_loop_item.index");
-                    return Ok(DisplayWrap::Unwrapped);
-                } else if attr.name == "first" {
-                    buf.write("_loop_item.first");
-                    return Ok(DisplayWrap::Unwrapped);
-                } else if attr.name == "last" {
-                    buf.write("_loop_item.last");
-                    return Ok(DisplayWrap::Unwrapped);
-                } else {
-                    return Err(ctx.generate_error("unknown loop variable", obj.span()));
-                }
+                let code = match attr.name {
+                    "index" => "(_loop_item.index + 1)",
+                    "index0" => "_loop_item.index",
+                    "first" => "_loop_item.first",
+                    "last" => "_loop_item.last",
+                    "length" | "revindex" | "revindex0" => {
+                        if let Some(usage) = self.loops.last_mut() {
+                            usage.length = true;
+                        }
+                        match attr.name {
+                            "length" => "_loop_item.length",
+                            "revindex" => "(_loop_item.length - _loop_item.index)",
+                            _ => "(_loop_item.length - _loop_item.index - 1)",
+                        }
+                    }
+                    "previtem" => {
+                        if let Some(usage) = self.loops.last_mut() {
+                            usage.previtem = true;
+                        }
+                        "_loop_previtem.as_ref()"
+                    }
+                    "nextitem" => {
+                        if let Some(usage) = self.loops.last_mut() {
+                            usage.nextitem = true;
+                        }
+                        "_loop.next_item()"
+                    }
+                    "depth" | "depth0" => {
+                        let recursive = self.loops.last().is_some_and(|usage| usage.recursive);
+                        match (attr.name, recursive) {
+                            ("depth", true) => "_loop_depth",
+                            ("depth", false) => "1usize",
+                            (_, true) => "(_loop_depth - 1)",
+                            (_, false) => "0usize",
+                        }
+                    }
+                    _ => return Err(ctx.generate_error("unknown loop variable", obj.span())),
+                };
+                buf.write(code);
+                return Ok(DisplayWrap::Unwrapped);
             }
         }
         self.visit_expr(ctx, buf, obj)?;
@@ -920,6 +1779,42 @@ impl<'a> Generator<'a, '_> {
                             }
                         }
                     }
+                    "changed" => {
+                        if let [generic, ..] = generics {
+                            return Err(ctx.generate_error(
+                                "loop.changed(…) doesn't use generics",
+                                generic.span(),
+                            ));
+                        }
+                        if args.is_empty() {
+                            return Err(ctx.generate_error(
+                                "loop.changed(…) expects at least one argument",
+                                left.span(),
+                            ));
+                        }
+                        let Some(usage) = self.loops.last_mut() else {
+                            return Err(ctx.generate_error(
+                                "loop.changed(…) used outside of a loop",
+                                left.span(),
+                            ));
+                        };
+                        let id = usage.changed;
+                        usage.changed += 1;
+                        buf.write(format_args!(
+                            "askama::helpers::loop_changed(&mut _loop_changed_{id}, ("
+                        ));
+                        for arg in args {
+                            // Borrowing a local variable would not outlive the current iteration.
+                            match **arg {
+                                Expr::Var(name) if self.locals.get(name).is_some() => {
+                                    self.visit_expr(ctx, buf, arg)?;
+                                }
+                                _ => self._visit_arg(ctx, buf, arg)?,
+                            }
+                            buf.write(',');
+                        }
+                        buf.write("))");
+                    }
                     s => {
                         return Err(ctx.generate_error(
                             format_args!("unknown loop method: {s:?}"),
@@ -928,6 +1823,38 @@ impl<'a> Generator<'a, '_> {
                     }
                 }
             }
+            Expr::Var("caller")
+                if !self.callers.is_empty() && self.locals.get("caller").is_none() =>
+            {
+                return Err(ctx.generate_error(
+                    "`caller(…)` can only be used on its own, as in `{{ caller() }}`",
+                    left.span(),
+                ));
+            }
+            Expr::Var("loop") if self.locals.get("loop").is_none() => {
+                if !self.loops.last().is_some_and(|usage| usage.recursive) {
+                    return Err(ctx.generate_error(
+                        "`loop(…)` can only be used in a `recursive` for loop",
+                        left.span(),
+                    ));
+                }
+                if let [generic, ..] = generics {
+                    return Err(
+                        ctx.generate_error("`loop(…)` doesn't use generics", generic.span())
+                    );
+                }
+                let [arg] = args else {
+                    return Err(
+                        ctx.generate_error("`loop(…)` expects exactly one argument", left.span())
+                    );
+                };
+                let expr_code = self.visit_expr_root(ctx, arg)?;
+                buf.write(format_args!(
+                    "_loop_recurse.call({}, _loop_depth + 1)",
+                    loop_iter_code(arg, &expr_code),
+                ));
+                return Ok(DisplayWrap::Wrapped);
+            }
             // We special-case "askama::get_value".
             Expr::Path(path) if path == &["askama", "get_value"] => {
                 self._visit_value(
@@ -939,12 +1866,23 @@ impl<'a> Generator<'a, '_> {
                     "`get_value` function",
                 )?;
             }
+            #[cfg(feature = "i18n")]
+            Expr::Var("t") if self.locals.get("t").is_none() => {
+                return self.visit_translation(ctx, buf, left.span(), args, generics);
+            }
             sub_left => {
+                if let Some(arg) = args
+                    .iter()
+                    .find(|arg| matches!(***arg, Expr::NamedArgument(..)))
+                {
+                    return Err(ctx.generate_error(
+                        "named arguments are only supported in macro calls, and by the \
+                        translation function `t()` if the feature \"i18n\" is enabled",
+                        arg.span(),
+                    ));
+                }
                 match sub_left {
-                    Expr::Var(name) => match self.locals.resolve(name) {
-                        Some(resolved) => buf.write(resolved),
-                        None => buf.write(format_args!("self.{}", normalize_identifier(name))),
-                    },
+                    Expr::Var(name) => buf.write(self.resolve_var(ctx, name, left.span())?),
                     _ => {
                         self.visit_expr(ctx, buf, left)?;
                     }
@@ -958,6 +1896,117 @@ impl<'a> Generator<'a, '_> {
         Ok(DisplayWrap::Unwrapped)
     }
 
+    /// The translation function `t("message-id", name = value, …)`
+    #[cfg(feature = "i18n")]
+    fn visit_translation(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        node: Span<'_>,
+        args: &[WithSpan<'_, Expr<'a>>],
+        generics: &[WithSpan<'_, TyGenerics<'_>>],
+    ) -> Result<DisplayWrap, CompileError> {
+        if let [generic, ..] = generics {
+            return Err(ctx.generate_error("`t()` doesn't use generics", generic.span()));
+        }
+        let Some((id_arg, args)) = args.split_first() else {
+            return Err(ctx.generate_error("`t()` expects a message ID as first argument", node));
+        };
+        let Expr::StrLit(ref lit @ StrLit { prefix: None, .. }) = **id_arg else {
+            return Err(ctx.generate_error(
+                "the message ID of `t()` must be a string literal",
+                id_arg.span(),
+            ));
+        };
+        let id = &*lit
+            .unescape()
+            .map_err(|err| ctx.generate_error(err, id_arg.span()))?;
+
+        let config = &self.input.config.i18n;
+        let catalog = Catalog::load(config)?;
+        let Some(default_locale) = catalog.locales.get(&config.default_locale) else {
+            return Err(ctx.generate_error(
+                format_args!(
+                    "default locale {:?} not found in {}",
+                    config.default_locale,
+                    config.dir.display(),
+                ),
+                node,
+            ));
+        };
+        if !default_locale.contains(id) {
+            return Err(ctx.generate_error(
+                format_args!(
+                    "unknown translation message {id:?} in default locale {:?}",
+                    config.default_locale,
+                ),
+                id_arg.span(),
+            ));
+        }
+        let missing = catalog
+            .locales
+            .iter()
+            .filter(|(_, locale)| !locale.contains(id))
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            let err = ctx.generate_error(
+                format_args!(
+                    "translation message {id:?} is missing in locale(s) {}",
+                    missing.join(", "),
+                ),
+                id_arg.span(),
+            );
+            match config.missing {
+                MissingTranslation::Error => return Err(err),
+                MissingTranslation::Ignore => {}
+                MissingTranslation::Warn => self.translations.warnings.push(err.to_string()),
+            }
+        }
+
+        if !self.buf_writable.discard {
+            self.translations.used = true;
+        }
+        buf.write("askama::i18n::translate(&__ASKAMA_I18N,");
+        self.visit_locale(buf);
+        buf.write(format_args!(",__askama_values, {id:?}, &["));
+        for arg in args {
+            let Expr::NamedArgument(name, ref value) = **arg else {
+                return Err(ctx.generate_error(
+                    "`t()` only accepts named arguments after the message ID",
+                    arg.span(),
+                ));
+            };
+            buf.write(format_args!(
+                "({name:?}, askama::i18n::Argument::to_fluent_value(&("
+            ));
+            self.visit_expr(ctx, buf, value)?;
+            buf.write("))),");
+        }
+        buf.write("])?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    /// Writes the template variable or field `locale` as `Option<&dyn Display>`
+    #[cfg(any(feature = "i18n", feature = "icu"))]
+    fn visit_locale(&self, buf: &mut Buffer) {
+        let locale = match self.locals.resolve("locale") {
+            Some(locale) => Some(locale),
+            None if self.input.fields.iter().any(|field| field == "locale") => {
+                Some("self.locale".to_owned())
+            }
+            None => None,
+        };
+        match locale {
+            Some(locale) => buf.write(format_args!(
+                "askama::helpers::core::option::Option::Some(\
+                    &({locale}) as &dyn askama::helpers::core::fmt::Display\
+                )"
+            )),
+            None => buf.write("askama::helpers::core::option::Option::None"),
+        }
+    }
+
     fn visit_unary(
         &mut self,
         ctx: &Context<'_>,
@@ -1077,14 +2126,20 @@ impl<'a> Generator<'a, '_> {
         DisplayWrap::Unwrapped
     }
 
-    fn visit_var(&mut self, buf: &mut Buffer, s: &str) -> DisplayWrap {
-        if s == "self" {
+    fn visit_var(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        s: &str,
+        span: Span<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        if s == "self" && !self.is_isolated {
             buf.write(s);
-            return DisplayWrap::Unwrapped;
+            return Ok(DisplayWrap::Unwrapped);
         }
 
-        buf.write(normalize_identifier(&self.locals.resolve_or_self(s)));
-        DisplayWrap::Unwrapped
+        buf.write(normalize_identifier(&self.resolve_var(ctx, s, span)?));
+        Ok(DisplayWrap::Unwrapped)
     }
 
     fn visit_filter_source(&mut self, buf: &mut Buffer) -> DisplayWrap {
@@ -1228,6 +2283,115 @@ impl<'a> Generator<'a, '_> {
     }
 }
 
+/// The filters `date`, `datetime`, `time`, `timesince` and `timeuntil` are only built-in if a date
+/// and time library is enabled, and if the crate enabled them in its configuration file.
+/// Otherwise they are treated like custom filters.
+const DATETIME_FILTERS: bool = cfg!(any(feature = "chrono", feature = "jiff", feature = "time"));
+
+/// Filters that take named arguments, cf. `Generator::_visit_collection_filter()`
+/// Splits the arguments of a collection filter into the input value and the values of the
+/// `params`, that can be passed by position or by name
+#[allow(clippy::type_complexity)]
+fn collection_filter_args<'b, 'a>(
+    ctx: &Context<'_>,
+    name: &str,
+    params: &[&str],
+    args: &'b [WithSpan<'b, Expr<'a>>],
+    node: Span<'_>,
+) -> Result<
+    (
+        &'b WithSpan<'b, Expr<'a>>,
+        Vec<Option<&'b WithSpan<'b, Expr<'a>>>>,
+    ),
+    CompileError,
+> {
+    let Some((input, args)) = args.split_first() else {
+        return Err(ctx.generate_error(format_args!("missing input of `{name}` filter"), node));
+    };
+    let mut values = vec![None; params.len()];
+    for (idx, arg) in args.iter().enumerate() {
+        let (idx, value) = match **arg {
+            Expr::NamedArgument(arg_name, ref value) => {
+                let Some(idx) = params.iter().position(|&param| param == arg_name) else {
+                    return Err(ctx.generate_error(
+                        format_args!("unknown argument `{arg_name}` in `{name}` filter"),
+                        arg.span(),
+                    ));
+                };
+                (idx, &**value)
+            }
+            _ if idx < params.len() => (idx, arg),
+            _ => {
+                return Err(ctx.generate_error(
+                    format_args!("unexpected argument(s) in `{name}` filter"),
+                    arg.span(),
+                ));
+            }
+        };
+        if values[idx].replace(value).is_some() {
+            return Err(ctx.generate_error(
+                format_args!(
+                    "argument `{}` of `{name}` filter was passed more than once",
+                    params[idx]
+                ),
+                arg.span(),
+            ));
+        }
+    }
+    Ok((input, values))
+}
+
+/// Returns the field access `.path.to.field` for the `attribute` argument `"path.to.field"`
+fn attribute_access(
+    ctx: &Context<'_>,
+    name: &str,
+    attribute: &WithSpan<'_, Expr<'_>>,
+) -> Result<String, CompileError> {
+    let path = match **attribute {
+        Expr::StrLit(StrLit {
+            prefix: None,
+            content,
+        }) if !content.is_empty() => content,
+        _ => {
+            return Err(ctx.generate_error(
+                format_args!(
+                    "the `attribute` argument of the `{name}` filter must be a string literal, \
+                     e.g. `\"field.subfield\"`"
+                ),
+                attribute.span(),
+            ));
+        }
+    };
+    let mut access = String::with_capacity(path.len() + 1);
+    for field in path.split('.') {
+        let is_index = !field.is_empty() && field.bytes().all(|c| c.is_ascii_digit());
+        let is_ident = field.bytes().enumerate().all(|(idx, c)| {
+            c == b'_' || c.is_ascii_alphabetic() || (idx > 0 && c.is_ascii_digit())
+        });
+        if field.is_empty() || !(is_index || is_ident) {
+            return Err(ctx.generate_error(
+                format_args!("invalid attribute {path:?} in `{name}` filter"),
+                attribute.span(),
+            ));
+        }
+        access.push('.');
+        access.push_str(normalize_identifier(field));
+    }
+    Ok(access)
+}
+
+/// Returns `true` for literals whose type is inferred from the value they are compared to
+fn is_literal(expr: &Expr<'_>) -> bool {
+    match expr {
+        Expr::BoolLit(_) | Expr::NumLit(..) => true,
+        Expr::StrLit(StrLit { prefix, .. }) => prefix.is_none(),
+        Expr::CharLit(CharLit { prefix, .. }) => prefix.is_none(),
+        Expr::Unary("-", expr) => matches!(***expr, Expr::NumLit(..)),
+        Expr::Group(expr) => is_literal(expr),
+        _ => false,
+    }
+}
+
 fn ensure_filter_has_feature_alloc(
     ctx: &Context<'_>,
     name: &str,
@@ -1242,6 +2406,43 @@ fn ensure_filter_has_feature_alloc(
     Ok(())
 }
 
+/// Only macros, `t()` and the filters in `FILTERS_WITH_NAMED_ARGUMENTS` take named arguments
+fn ensure_no_named_arguments(
+    ctx: &Context<'_>,
+    args: &[WithSpan<'_, Expr<'_>>],
+    callee: fmt::Arguments<'_>,
+) -> Result<(), CompileError> {
+    match args
+        .iter()
+        .find(|arg| matches!(***arg, Expr::NamedArgument(..)))
+    {
+        Some(arg) => Err(ctx.generate_error(
+            format_args!("named arguments are not supported by {callee}"),
+            arg.span(),
+        )),
+        None => Ok(()),
+    }
+}
+
+#[cfg(feature = "icu")]
+fn is_num_lit(expr: &WithSpan<'_, Expr<'_>>) -> bool {
+    match **expr {
+        Expr::NumLit(..) => true,
+        Expr::Unary("-", ref expr) => matches!(***expr, Expr::NumLit(..)),
+        _ => false,
+    }
+}
+
+#[cfg(feature = "icu")]
+fn expr_as_u8_lit(expr: &WithSpan<'_, Expr<'_>>) -> Option<u8> {
+    match **expr {
+        Expr::NumLit(_, Num::Int(value, None | Some(IntKind::U8))) => {
+            value.replace('_', "").parse().ok()
+        }
+        _ => None,
+    }
+}
+
 fn expr_is_int_lit_plus_minus_one(expr: &WithSpan<'_, Expr<'_>>) -> Option<bool> {
     fn is_signed_singular<T: Eq + Default, E>(
         from_str_radix: impl Fn(&str, u32) -> Result<T, E>,
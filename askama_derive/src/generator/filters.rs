@@ -0,0 +1,87 @@
+//! The names of the built-in filters
+//!
+//! This file has no dependencies, so `askama_lsp` includes it, too, to complete filter names.
+
+/// The sorted names of all built-in filters, including the ones that need a feature, or that
+/// have to be enabled in the configuration
+pub(crate) const BUILTIN_FILTERS: &[&str] = &[
+    "batch",
+    "capitalize",
+    "center",
+    "currency",
+    "date",
+    "datetime",
+    "default",
+    "deref",
+    "e",
+    "escape",
+    "filesizeformat",
+    "first",
+    "fmt",
+    "format",
+    "groupby",
+    "indent",
+    "intcomma",
+    "join",
+    "json",
+    "last",
+    "length",
+    "linebreaks",
+    "linebreaksbr",
+    "lower",
+    "lowercase",
+    "map",
+    "max",
+    "min",
+    "number",
+    "paragraphbreaks",
+    "percent",
+    "pluralize",
+    "ref",
+    "reject",
+    "rejectattr",
+    "reverse",
+    "safe",
+    "select",
+    "selectattr",
+    "slice",
+    "sort",
+    "sum",
+    "time",
+    "timesince",
+    "timeuntil",
+    "title",
+    "tojson",
+    "trim",
+    "truncate",
+    "unique",
+    "upper",
+    "uppercase",
+    "urlencode",
+    "urlencode_strict",
+    "value",
+    "wordcount",
+];
+
+// The built-in filters that the code generator forwards to `askama::filters` as they are.
+pub(crate) const FORWARDED_FILTERS: &[&str] = &[
+    "capitalize",
+    "center",
+    "indent",
+    "lower",
+    "lowercase",
+    "title",
+    "trim",
+    "truncate",
+    "upper",
+    "uppercase",
+    "wordcount",
+];
+
+// The built-in filters that accept named arguments, e.g. `sort(attribute = "name")`.
+pub(crate) const FILTERS_WITH_NAMED_ARGUMENTS: &[&str] = &[
+    "batch", "groupby", "map", "max", "min", "slice", "sort", "sum", "unique",
+];
+
+// Built-in filters that need the `alloc` feature.
+pub(crate) const BUILTIN_FILTERS_NEED_ALLOC: &[&str] = &["center", "truncate"];
@@ -0,0 +1,623 @@
+//! Tracks where in an HTML document the generated output currently is, so that
+//! `{{ expr }}` nodes can be escaped according to their context when the template uses
+//! `contextual_escape = true`.
+//!
+//! The tracker only sees the template literals, never the values of the expressions. It is
+//! not a complete HTML tokenizer, but it knows enough about tags, attributes, comments and the
+//! raw text elements `<script>` and `<style>` to select a safe escaper, or to reject positions
+//! where no escaper can make the output safe.
+
+/// The HTML parser state after all literals written so far.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct HtmlState(State);
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+enum State {
+    /// Text content, e.g. `<p>…</p>`
+    #[default]
+    Text,
+    /// Directly after `<`
+    TagOpen,
+    /// Directly after `</`
+    EndTagOpen,
+    /// Inside a tag name, e.g. `<di…`
+    TagName { name: String, end: bool },
+    /// Inside a tag, where an attribute name or `>` is expected
+    InTag { name: String, end: bool },
+    /// Inside an attribute name
+    AttrName { tag: String, attr: String },
+    /// After an attribute name, where `=` is expected
+    AfterAttrName { tag: String, attr: String },
+    /// After `=`, where the attribute value is expected
+    BeforeAttrValue { tag: String, kind: AttrKind },
+    /// Inside an attribute value
+    AttrValue {
+        tag: String,
+        kind: AttrKind,
+        quote: Option<u8>,
+    },
+    /// Directly after `<!`
+    MarkupDecl,
+    /// Directly after `<!-`
+    MarkupDeclDash,
+    /// Inside `<!-- … -->`, counting the trailing dashes
+    Comment { dashes: u8 },
+    /// Inside a doctype, processing instruction, or other bogus markup
+    Bogus,
+    /// Inside a `<script>` element, `end` counts the matched bytes of `</script`
+    Script { js: JsState, end: usize },
+    /// Inside a `<style>` element, `end` counts the matched bytes of `</style`
+    Style { end: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum AttrKind {
+    Normal,
+    /// `query` is set after a `?` or `#` in the attribute value
+    Url {
+        query: bool,
+    },
+    Css,
+    Js(JsState),
+}
+
+impl AttrKind {
+    fn new(attr: &str) -> Self {
+        if attr.starts_with("on") {
+            Self::Js(JsState::default())
+        } else if attr == "style" {
+            Self::Css
+        } else if URL_ATTRS.contains(&attr) {
+            Self::Url { query: false }
+        } else {
+            Self::Normal
+        }
+    }
+}
+
+/// Attributes that contain a URL.
+const URL_ATTRS: &[&str] = &[
+    "action",
+    "background",
+    "cite",
+    "codebase",
+    "data",
+    "formaction",
+    "href",
+    "icon",
+    "longdesc",
+    "manifest",
+    "ping",
+    "poster",
+    "src",
+    "srcset",
+    "xlink:href",
+];
+
+/// The JavaScript tokenizer state inside of a `<script>` element or an event handler attribute.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct JsState {
+    mode: JsMode,
+    /// For every `${` of the template literals around the current position, the number of
+    /// braces that are open inside of it
+    substitutions: Vec<usize>,
+    /// The identifier or keyword that ends at the current position
+    word: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JsMode {
+    /// JavaScript code, `regex` is set if a `/` would start a regular expression literal
+    Code { regex: bool },
+    /// Directly after a `/` in code
+    Slash { regex: bool },
+    /// Inside a `'…'` or `"…"` string literal
+    Str { quote: u8, escaped: bool },
+    /// Inside the text of a `` `…` `` template literal, `dollar` is set after a `$`
+    Template { escaped: bool, dollar: bool },
+    /// Inside a `/…/` regular expression literal, `class` is set inside of `[…]`
+    Regex { escaped: bool, class: bool },
+    /// Inside a `// …` comment
+    LineComment,
+    /// Inside a `/* … */` comment, `star` is set if the previous character was a `*`
+    BlockComment { star: bool },
+}
+
+impl Default for JsMode {
+    fn default() -> Self {
+        Self::Code { regex: true }
+    }
+}
+
+/// Keywords after which a `/` starts a regular expression literal, not a division
+const REGEX_KEYWORDS: &[&str] = &[
+    "await",
+    "case",
+    "delete",
+    "do",
+    "else",
+    "in",
+    "instanceof",
+    "new",
+    "of",
+    "return",
+    "throw",
+    "typeof",
+    "void",
+    "yield",
+];
+
+impl JsState {
+    fn next(&mut self, c: u8) {
+        self.mode = match self.mode {
+            JsMode::Code { regex } => self.code(c, regex),
+            JsMode::Slash { .. } if c == b'/' => JsMode::LineComment,
+            JsMode::Slash { .. } if c == b'*' => JsMode::BlockComment { star: false },
+            JsMode::Slash { regex } => {
+                self.mode = match regex {
+                    true => JsMode::Regex {
+                        escaped: false,
+                        class: false,
+                    },
+                    // A division operator, which can be followed by a regular expression.
+                    false => JsMode::Code { regex: true },
+                };
+                return self.next(c);
+            }
+            JsMode::Str {
+                quote,
+                escaped: true,
+            } => JsMode::Str {
+                quote,
+                escaped: false,
+            },
+            JsMode::Str { quote, .. } => match c {
+                b'\\' => JsMode::Str {
+                    quote,
+                    escaped: true,
+                },
+                b'\n' => JsMode::Code { regex: false },
+                _ if c == quote => JsMode::Code { regex: false },
+                _ => self.mode,
+            },
+            JsMode::Template { escaped: true, .. } => JsMode::Template {
+                escaped: false,
+                dollar: false,
+            },
+            JsMode::Template { dollar, .. } => match c {
+                b'`' => JsMode::Code { regex: false },
+                b'{' if dollar => {
+                    self.substitutions.push(0);
+                    JsMode::Code { regex: true }
+                }
+                _ => JsMode::Template {
+                    escaped: c == b'\\',
+                    dollar: c == b'$',
+                },
+            },
+            JsMode::Regex {
+                escaped: true,
+                class,
+            } => JsMode::Regex {
+                escaped: false,
+                class,
+            },
+            JsMode::Regex { class, .. } => match c {
+                b'/' if !class => JsMode::Code { regex: false },
+                b'\n' => JsMode::Code { regex: false },
+                _ => JsMode::Regex {
+                    escaped: c == b'\\',
+                    class: match c {
+                        b'[' => true,
+                        b']' => false,
+                        _ => class,
+                    },
+                },
+            },
+            JsMode::LineComment => match c {
+                b'\n' => JsMode::Code { regex: true },
+                _ => self.mode,
+            },
+            JsMode::BlockComment { star } => match c {
+                b'/' if star => JsMode::Code { regex: true },
+                _ => JsMode::BlockComment { star: c == b'*' },
+            },
+        };
+    }
+
+    fn code(&mut self, c: u8, regex: bool) -> JsMode {
+        if c.is_ascii_alphanumeric() || matches!(c, b'_' | b'$' | 0x80..) {
+            self.word.push(char::from(c));
+            return JsMode::Code { regex: false };
+        }
+        let regex = match std::mem::take(&mut self.word) {
+            word if word.is_empty() => regex,
+            word => REGEX_KEYWORDS.contains(&word.as_str()),
+        };
+        match c {
+            b'"' | b'\'' => JsMode::Str {
+                quote: c,
+                escaped: false,
+            },
+            b'`' => JsMode::Template {
+                escaped: false,
+                dollar: false,
+            },
+            b'/' => JsMode::Slash { regex },
+            b'{' => {
+                if let Some(open) = self.substitutions.last_mut() {
+                    *open += 1;
+                }
+                JsMode::Code { regex: true }
+            }
+            b'}' => match self.substitutions.last_mut() {
+                Some(0) => {
+                    self.substitutions.pop();
+                    JsMode::Template {
+                        escaped: false,
+                        dollar: false,
+                    }
+                }
+                Some(open) => {
+                    *open -= 1;
+                    JsMode::Code { regex: true }
+                }
+                None => JsMode::Code { regex: true },
+            },
+            b')' | b']' => JsMode::Code { regex: false },
+            c if c.is_ascii_whitespace() => JsMode::Code { regex },
+            _ => JsMode::Code { regex: true },
+        }
+    }
+
+    fn escaper(&self) -> Result<&'static str, &'static str> {
+        match self.mode {
+            JsMode::Code { .. } | JsMode::Slash { .. } => {
+                Err("in JavaScript code outside of a string literal")
+            }
+            JsMode::Regex { .. } => Err("inside a JavaScript regular expression literal"),
+            JsMode::Str { .. }
+            | JsMode::Template { .. }
+            | JsMode::LineComment
+            | JsMode::BlockComment { .. } => Ok("askama::filters::Js"),
+        }
+    }
+}
+
+impl HtmlState {
+    /// Advances the state over a literal that is written verbatim.
+    pub(crate) fn write_lit(&mut self, lit: &str) {
+        for c in lit.bytes() {
+            self.0 = std::mem::take(&mut self.0).next(c);
+        }
+    }
+
+    /// Advances the state over an interpolated expression.
+    pub(crate) fn write_expr(&mut self) {
+        if let State::BeforeAttrValue { tag, kind } = &mut self.0 {
+            self.0 = State::AttrValue {
+                tag: std::mem::take(tag),
+                kind: std::mem::replace(kind, AttrKind::Normal),
+                quote: None,
+            };
+        }
+    }
+
+    /// Returns the path of the escaper to use for an expression at the current position, or
+    /// a description of the position if there is no escaper that could make it safe.
+    ///
+    /// `default` is the escaper for text content, i.e. the template's normal escaper.
+    pub(crate) fn escaper<'a>(&self, default: &'a str) -> Result<&'a str, &'static str> {
+        match &self.0 {
+            State::Text | State::Comment { .. } => Ok(default),
+            State::TagOpen | State::EndTagOpen | State::TagName { .. } => Err("inside a tag name"),
+            State::InTag { .. } | State::AttrName { .. } | State::AfterAttrName { .. } => {
+                Err("inside a tag, where an attribute name is expected")
+            }
+            State::MarkupDecl | State::MarkupDeclDash | State::Bogus => {
+                Err("inside a markup declaration")
+            }
+            State::BeforeAttrValue { kind, .. } => attr_escaper(kind, None, default),
+            State::AttrValue { kind, quote, .. } => attr_escaper(kind, *quote, default),
+            State::Script { js, .. } => js.escaper(),
+            State::Style { .. } => Ok("askama::filters::Css"),
+        }
+    }
+}
+
+fn attr_escaper<'a>(
+    kind: &AttrKind,
+    quote: Option<u8>,
+    default: &'a str,
+) -> Result<&'a str, &'static str> {
+    match (kind, quote) {
+        (AttrKind::Normal, Some(_)) => Ok(default),
+        (AttrKind::Normal, None) => Ok("askama::filters::Attr"),
+        (AttrKind::Url { query: false }, _) => Ok("askama::filters::Url"),
+        (AttrKind::Url { query: true }, _) => Ok("askama::filters::UrlComponent"),
+        // The escape sequences of `Css` end in a space, which ends an unquoted value.
+        (AttrKind::Css, None) => Err("inside an unquoted style attribute"),
+        (AttrKind::Css, Some(_)) => Ok("askama::filters::Css"),
+        (AttrKind::Js(_), None) => Err("inside an unquoted event handler attribute"),
+        (AttrKind::Js(js), Some(_)) => js.escaper(),
+    }
+}
+
+impl State {
+    fn next(self, c: u8) -> Self {
+        match self {
+            Self::Text => match c {
+                b'<' => Self::TagOpen,
+                _ => Self::Text,
+            },
+            Self::TagOpen => match c {
+                b'/' => Self::EndTagOpen,
+                b'!' => Self::MarkupDecl,
+                b'?' => Self::Bogus,
+                c if c.is_ascii_alphabetic() => Self::TagName {
+                    name: char::from(c.to_ascii_lowercase()).into(),
+                    end: false,
+                },
+                _ => Self::Text.next(c),
+            },
+            Self::EndTagOpen => match c {
+                c if c.is_ascii_alphabetic() => Self::TagName {
+                    name: char::from(c.to_ascii_lowercase()).into(),
+                    end: true,
+                },
+                _ => Self::Bogus.next(c),
+            },
+            Self::TagName { mut name, end } => match c {
+                b'>' => close_tag(&name, end),
+                b'/' => Self::InTag { name, end },
+                c if c.is_ascii_whitespace() => Self::InTag { name, end },
+                c => {
+                    name.push(char::from(c.to_ascii_lowercase()));
+                    Self::TagName { name, end }
+                }
+            },
+            Self::InTag { name, end } => match c {
+                b'>' => close_tag(&name, end),
+                b'/' => Self::InTag { name, end },
+                c if c.is_ascii_whitespace() => Self::InTag { name, end },
+                c => Self::AttrName {
+                    tag: name,
+                    attr: char::from(c.to_ascii_lowercase()).into(),
+                },
+            },
+            Self::AttrName { tag, mut attr } => match c {
+                b'>' => close_tag(&tag, false),
+                b'/' => Self::InTag {
+                    name: tag,
+                    end: false,
+                },
+                b'=' => Self::BeforeAttrValue {
+                    kind: AttrKind::new(&attr),
+                    tag,
+                },
+                c if c.is_ascii_whitespace() => Self::AfterAttrName { tag, attr },
+                c => {
+                    attr.push(char::from(c.to_ascii_lowercase()));
+                    Self::AttrName { tag, attr }
+                }
+            },
+            Self::AfterAttrName { tag, attr } => match c {
+                b'=' => Self::BeforeAttrValue {
+                    kind: AttrKind::new(&attr),
+                    tag,
+                },
+                c if c.is_ascii_whitespace() => Self::AfterAttrName { tag, attr },
+                c => Self::InTag {
+                    name: tag,
+                    end: false,
+                }
+                .next(c),
+            },
+            Self::BeforeAttrValue { tag, kind } => match c {
+                b'"' | b'\'' => Self::AttrValue {
+                    tag,
+                    kind,
+                    quote: Some(c),
+                },
+                b'>' => close_tag(&tag, false),
+                c if c.is_ascii_whitespace() => Self::BeforeAttrValue { tag, kind },
+                c => Self::AttrValue {
+                    tag,
+                    kind,
+                    quote: None,
+                }
+                .next(c),
+            },
+            Self::AttrValue { tag, kind, quote } => match (quote, c) {
+                (Some(quote), c) if c == quote => Self::InTag {
+                    name: tag,
+                    end: false,
+                },
+                (None, b'>') => close_tag(&tag, false),
+                (None, c) if c.is_ascii_whitespace() => Self::InTag {
+                    name: tag,
+                    end: false,
+                },
+                (_, c) => {
+                    let mut kind = kind;
+                    match &mut kind {
+                        AttrKind::Js(js) => js.next(c),
+                        AttrKind::Url { query } if matches!(c, b'?' | b'#') => *query = true,
+                        _ => {}
+                    }
+                    Self::AttrValue { tag, kind, quote }
+                }
+            },
+            Self::MarkupDecl => match c {
+                b'-' => Self::MarkupDeclDash,
+                _ => Self::Bogus.next(c),
+            },
+            Self::MarkupDeclDash => match c {
+                b'-' => Self::Comment { dashes: 0 },
+                _ => Self::Bogus.next(c),
+            },
+            Self::Comment { dashes } => match c {
+                b'>' if dashes >= 2 => Self::Text,
+                b'-' => Self::Comment {
+                    dashes: (dashes + 1).min(2),
+                },
+                _ => Self::Comment { dashes: 0 },
+            },
+            Self::Bogus => match c {
+                b'>' => Self::Text,
+                _ => Self::Bogus,
+            },
+            Self::Script { mut js, end } => match match_end_tag(b"</script", end, c) {
+                Some(end) => {
+                    js.next(c);
+                    Self::Script { js, end }
+                }
+                None => Self::InTag {
+                    name: "script".into(),
+                    end: true,
+                },
+            },
+            Self::Style { end } => match match_end_tag(b"</style", end, c) {
+                Some(end) => Self::Style { end },
+                None => Self::InTag {
+                    name: "style".into(),
+                    end: true,
+                },
+            },
+        }
+    }
+}
+
+fn close_tag(name: &str, end: bool) -> State {
+    match (name, end) {
+        ("script", false) => State::Script {
+            js: JsState::default(),
+            end: 0,
+        },
+        ("style", false) => State::Style { end: 0 },
+        _ => State::Text,
+    }
+}
+
+/// Returns the new number of matched bytes of the end tag `tag`, or `None` if it was matched
+/// completely.
+fn match_end_tag(tag: &[u8], matched: usize, c: u8) -> Option<usize> {
+    let matched = if c.to_ascii_lowercase() == tag[matched] {
+        matched + 1
+    } else if c == b'<' {
+        1
+    } else {
+        0
+    };
+    (matched < tag.len()).then_some(matched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HtmlState;
+
+    const HTML: &str = "askama::filters::Html";
+
+    fn escaper(lit: &str) -> Result<&'static str, &'static str> {
+        let mut state = HtmlState::default();
+        state.write_lit(lit);
+        state.escaper(HTML)
+    }
+
+    #[test]
+    fn test_text_and_attrs() {
+        assert_eq!(escaper(""), Ok(HTML));
+        assert_eq!(escaper("<p class=\"a\">"), Ok(HTML));
+        assert_eq!(escaper("<p class=\"a "), Ok(HTML));
+        assert_eq!(escaper("<p class='"), Ok(HTML));
+        assert_eq!(escaper("<p class="), Ok("askama::filters::Attr"));
+        assert_eq!(escaper("<a href=\""), Ok("askama::filters::Url"));
+        assert_eq!(escaper("<IMG SRC='/img/"), Ok("askama::filters::Url"));
+        assert_eq!(escaper("<p style=\"color: "), Ok("askama::filters::Css"));
+        assert_eq!(escaper("<!-- "), Ok(HTML));
+        assert_eq!(escaper("<!-- a --> <b>"), Ok(HTML));
+        assert_eq!(escaper("1 < 2 "), Ok(HTML));
+    }
+
+    #[test]
+    fn test_ambiguous() {
+        assert!(escaper("<").is_err());
+        assert!(escaper("</").is_err());
+        assert!(escaper("<di").is_err());
+        assert!(escaper("<div ").is_err());
+        assert!(escaper("<div class=\"a\" ").is_err());
+        assert!(escaper("<div data-").is_err());
+        assert!(escaper("<!DOCTYPE ").is_err());
+        assert!(escaper("<button onclick=").is_err());
+        assert!(escaper("<button onclick=\"f(").is_err());
+    }
+
+    #[test]
+    fn test_js() {
+        assert_eq!(escaper("<button onclick=\"f('"), Ok("askama::filters::Js"));
+        assert_eq!(escaper("<script>var a = \""), Ok("askama::filters::Js"));
+        assert_eq!(escaper("<script>var a = '\\'"), Ok("askama::filters::Js"));
+        assert_eq!(escaper("<script>var a = `"), Ok("askama::filters::Js"));
+        assert_eq!(escaper("<script>// "), Ok("askama::filters::Js"));
+        assert_eq!(escaper("<script>/* "), Ok("askama::filters::Js"));
+        assert!(escaper("<script>var a = ").is_err());
+        assert!(escaper("<script>var a = \"b\"; var c = ").is_err());
+        assert_eq!(escaper("<script>var a = \"</script> "), Ok(HTML));
+        assert_eq!(escaper("<script></SCRIPT><p>"), Ok(HTML));
+    }
+
+    #[test]
+    fn test_js_template_literals() {
+        assert_eq!(escaper("<script>`a ${b} "), Ok("askama::filters::Js"));
+        assert!(escaper("<script>`${ ").is_err());
+        assert!(escaper("<script>`$${ ").is_err());
+        assert!(escaper("<script>`${ {a: 1}[").is_err());
+        assert_eq!(
+            escaper("<script>`${ {a: '}'}.a } "),
+            Ok("askama::filters::Js")
+        );
+        assert_eq!(escaper("<script>`${ `${ ` "), Ok("askama::filters::Js"));
+        assert!(escaper("<script>`${ `${ `}` ").is_err());
+        assert!(escaper("<script>`\\${ `; f(").is_err());
+        assert_eq!(escaper("<script>`\\${ "), Ok("askama::filters::Js"));
+        assert!(escaper("<script>`a`; f(").is_err());
+    }
+
+    #[test]
+    fn test_js_regex_literals() {
+        assert!(escaper("<script>var r = /\"/; var a = ").is_err());
+        assert!(escaper("<script>if (/[/'\\]]/.test(a)) f(").is_err());
+        assert!(escaper("<script>return /'/.test(").is_err());
+        assert!(escaper("<script>var r = /a").is_err());
+        assert_eq!(
+            escaper("<script>var a = b / 2 + \""),
+            Ok("askama::filters::Js")
+        );
+        assert_eq!(
+            escaper("<script>var a = (b) / c[0] / '"),
+            Ok("askama::filters::Js")
+        );
+        assert_eq!(
+            escaper("<script>var a = /b/g + '"),
+            Ok("askama::filters::Js")
+        );
+    }
+
+    #[test]
+    fn test_css() {
+        assert_eq!(escaper("<style>p { color: "), Ok("askama::filters::Css"));
+        assert_eq!(escaper("<style></style>"), Ok(HTML));
+    }
+
+    #[test]
+    fn test_expr_starts_unquoted_value() {
+        let mut state = HtmlState::default();
+        state.write_lit("<input value=");
+        assert_eq!(state.escaper(HTML), Ok("askama::filters::Attr"));
+        state.write_expr();
+        state.write_lit(" ");
+        assert!(state.escaper(HTML).is_err());
+        state.write_lit(">");
+        assert_eq!(state.escaper(HTML), Ok(HTML));
+    }
+}
@@ -2,17 +2,20 @@ use std::borrow::Cow;
 use std::collections::hash_map::{Entry, HashMap};
 use std::fmt::{self, Write};
 use std::mem;
+use std::path::Path;
+use std::sync::Arc;
 
 use parser::node::{
-    Call, Comment, Cond, CondTest, FilterBlock, If, Include, Let, Lit, Loop, Macro, Match,
-    Whitespace, Ws,
+    Call, Comment, Cond, CondTest, FilterBlock, If, Include, IncludeTarget, Let, LetBlock, Lit,
+    Loop, Macro, Match, Whitespace, Ws,
 };
 use parser::{Expr, Filter, Node, Span, Target, WithSpan};
 use rustc_hash::FxBuildHasher;
 
+use super::html_state::HtmlState;
 use super::{
-    DisplayWrap, FILTER_SOURCE, Generator, LocalMeta, MapChain, compile_time_escape, is_copyable,
-    normalize_identifier,
+    ActiveCaller, DisplayWrap, FILTER_SOURCE, Generator, LocalMeta, LoopUsage, MapChain,
+    compile_time_escape, is_copyable, loop_iter_code, normalize_identifier,
 };
 use crate::generator::Writable;
 use crate::heritage::{Context, Heritage};
@@ -68,10 +71,21 @@ impl<'a> Generator<'a, '_> {
             self.is_in_filter_block,
         );
         child.buf_writable = buf_writable;
+        child.loops = mem::take(&mut self.loops);
+        child.callers = mem::take(&mut self.callers);
+        child.is_isolated = self.is_isolated;
+        #[cfg(feature = "i18n")]
+        {
+            child.translations = mem::take(&mut self.translations);
+        }
         let res = callback(&mut child);
         Generator {
             locals: self.locals,
             buf_writable: self.buf_writable,
+            loops: self.loops,
+            callers: self.callers,
+            #[cfg(feature = "i18n")]
+            translations: self.translations,
             ..
         } = child;
 
@@ -95,9 +109,10 @@ impl<'a> Generator<'a, '_> {
                 Node::Comment(ref comment) => {
                     self.write_comment(comment);
                 }
-                Node::Expr(ws, ref val) => {
-                    self.write_expr(ws, val);
-                }
+                Node::Expr(ws, ref val) => match self.caller_call_args(val) {
+                    Some(args) => size_hint += self.write_caller(ctx, buf, ws, args, val.span())?,
+                    None => self.write_expr(ws, val),
+                },
                 Node::Let(ref l) => {
                     self.write_let(ctx, buf, l)?;
                 }
@@ -207,6 +222,8 @@ impl<'a> Generator<'a, '_> {
             | Expr::FilterSource
             | Expr::As(_, _)
             | Expr::Concat(_)
+            | Expr::IsNone(_)
+            | Expr::IsNotNone(_)
             | Expr::LetCond(_) => {
                 *only_contains_is_defined = false;
                 (EvaluatedResult::Unknown, WithSpan::new(expr, span))
@@ -338,11 +355,13 @@ impl<'a> Generator<'a, '_> {
         &mut self,
         ctx: &Context<'a>,
         buf: &mut Buffer,
-        if_: &'a If<'_>,
+        if_: &'a WithSpan<'_, If<'_>>,
     ) -> Result<usize, CompileError> {
         let mut flushed = 0;
         let mut arm_sizes = Vec::new();
         let mut has_else = false;
+        let mut html_start = None;
+        let mut html_ends = Vec::new();
 
         let conds = Conds::compute_branches(self, if_);
 
@@ -357,10 +376,12 @@ impl<'a> Generator<'a, '_> {
             if pos == 0 {
                 self.handle_ws(cond.ws);
                 flushed += self.write_buf_writable(ctx, buf)?;
+                html_start.clone_from(&self.buf_writable.html);
             }
 
             self.push_locals(|this| {
                 let mut arm_size = 0;
+                this.buf_writable.html.clone_from(&html_start);
 
                 if let Some(CondTest { target, expr, .. }) = &cond.cond {
                     let expr = cond_info.cond_expr.as_ref().unwrap_or(expr);
@@ -429,6 +450,7 @@ impl<'a> Generator<'a, '_> {
                     this.handle_ws(if_.ws);
                     flushed += this.write_buf_writable(ctx, buf)?;
                 }
+                html_ends.push(this.buf_writable.html.clone());
                 Ok(0)
             })?;
         }
@@ -439,7 +461,9 @@ impl<'a> Generator<'a, '_> {
 
         if !has_else && !conds.conds.is_empty() {
             arm_sizes.push(0);
+            html_ends.push(html_start);
         }
+        self.ensure_same_html_state(ctx, html_ends, "if", if_.span())?;
         Ok(flushed + median(&mut arm_sizes))
     }
 
@@ -448,14 +472,14 @@ impl<'a> Generator<'a, '_> {
         &mut self,
         ctx: &Context<'a>,
         buf: &mut Buffer,
-        m: &'a Match<'a>,
+        m: &'a WithSpan<'a, Match<'a>>,
     ) -> Result<usize, CompileError> {
         let Match {
             ws1,
             ref expr,
             ref arms,
             ws2,
-        } = *m;
+        } = **m;
 
         self.flush_ws(ws1);
         let flushed = self.write_buf_writable(ctx, buf)?;
@@ -465,19 +489,27 @@ impl<'a> Generator<'a, '_> {
         buf.write(format_args!("match &{expr_code} {{"));
 
         let mut arm_size = 0;
+        let mut html_start = None;
+        let mut html_ends = Vec::new();
         let mut iter = arms.iter().enumerate().peekable();
         while let Some((i, arm)) = iter.next() {
             if i == 0 {
                 self.handle_ws(arm.ws);
+                html_start.clone_from(&self.buf_writable.html);
             }
 
             self.push_locals(|this| {
+                this.buf_writable.html.clone_from(&html_start);
                 for (index, target) in arm.target.iter().enumerate() {
                     if index != 0 {
                         buf.write('|');
                     }
                     this.visit_target(buf, true, true, target);
                 }
+                if let Some(guard) = &arm.guard {
+                    buf.write(" if ");
+                    this.visit_condition(ctx, buf, guard)?;
+                }
                 buf.write(" => {");
 
                 arm_size = this.handle(ctx, &arm.nodes, buf, AstLevel::Nested)?;
@@ -492,11 +524,13 @@ impl<'a> Generator<'a, '_> {
                     arm_sizes.push(arm_size + this.write_buf_writable(ctx, buf)?);
                     buf.write('}');
                 }
+                html_ends.push(this.buf_writable.html.clone());
                 Ok(0)
             })?;
         }
 
         buf.write('}');
+        self.ensure_same_html_state(ctx, html_ends, "match", m.span())?;
 
         Ok(flushed + median(&mut arm_sizes))
     }
@@ -515,32 +549,25 @@ impl<'a> Generator<'a, '_> {
             let has_else_nodes = !loop_block.else_nodes.is_empty();
 
             let flushed = this.write_buf_writable(ctx, buf)?;
-            buf.write('{');
+            let html_start = this.buf_writable.html.clone();
+            buf.write(format_args!(
+                "{{let _iter = {};",
+                loop_iter_code(&loop_block.iter, &expr_code)
+            ));
+            if loop_block.recursive {
+                // The body of a recursive loop is a closure, that gets called again for
+                // `loop(children)`. All children must have the same type as `_iter`.
+                buf.write(
+                    "askama::helpers::RecursiveLoop::run(\
+                        __askama_writer,\
+                        _iter,\
+                        &|_loop_recurse, __askama_writer, _iter, _loop_depth| \
+                        -> askama::Result<()> {",
+                );
+            }
             if has_else_nodes {
                 buf.write("let mut _did_loop = false;");
             }
-            match &*loop_block.iter {
-                Expr::Range(_, _, _) => buf.write(format_args!("let _iter = {expr_code};")),
-                Expr::Array(..) => buf.write(format_args!("let _iter = {expr_code}.iter();")),
-                // If `iter` is a call then we assume it's something that returns
-                // an iterator. If not then the user can explicitly add the needed
-                // call without issues.
-                Expr::Call { .. } | Expr::Index(..) => {
-                    buf.write(format_args!("let _iter = ({expr_code}).into_iter();"));
-                }
-                // If accessing `self` then it most likely needs to be
-                // borrowed, to prevent an attempt of moving.
-                _ if expr_code.starts_with("self.") => {
-                    buf.write(format_args!("let _iter = (&{expr_code}).into_iter();"));
-                }
-                // If accessing a field then it most likely needs to be
-                // borrowed, to prevent an attempt of moving.
-                Expr::Attr(..) => {
-                    buf.write(format_args!("let _iter = (&{expr_code}).into_iter();"));
-                }
-                // Otherwise, we borrow `iter` assuming that it implements `IntoIterator`.
-                _ => buf.write(format_args!("let _iter = ({expr_code}).into_iter();")),
-            }
             if let Some(cond) = &loop_block.cond {
                 this.push_locals(|this| {
                     buf.write("let _iter = _iter.filter(|");
@@ -553,19 +580,80 @@ impl<'a> Generator<'a, '_> {
             }
 
             let size_hint1 = this.push_locals(|this| {
-                buf.write("for (");
-                this.visit_target(buf, true, true, &loop_block.var);
-                buf.write(", _loop_item) in askama::helpers::TemplateLoop::new(_iter) {");
-
+                let mut target = Buffer::new();
+                this.visit_target(&mut target, true, true, &loop_block.var);
+                let target = target.into_string();
+
+                // The body is generated first, so we know which `loop.*` attributes it uses.
+                let mut body = Buffer::new();
+                body.set_discard(buf.is_discard());
+                this.loops.push(LoopUsage {
+                    recursive: loop_block.recursive,
+                    ..LoopUsage::default()
+                });
+                let size_hint1 = this
+                    .handle(ctx, &loop_block.body, &mut body, AstLevel::Nested)
+                    .and_then(|mut size_hint1| {
+                        this.handle_ws(loop_block.ws2);
+                        size_hint1 += this.write_buf_writable(ctx, &mut body)?;
+                        Ok(size_hint1)
+                    });
+                let usage = this.loops.pop().unwrap();
+                let size_hint1 = size_hint1?;
+
+                for id in 0..usage.changed {
+                    buf.write(format_args!(
+                        "let mut _loop_changed_{id} = askama::helpers::core::option::Option::None;"
+                    ));
+                }
+                if usage.previtem {
+                    buf.write(
+                        "let mut _loop_last_item = askama::helpers::core::option::Option::None;",
+                    );
+                }
+                let template_loop = if usage.length {
+                    "askama::helpers::TemplateLoop::with_length(_iter)?"
+                } else {
+                    "askama::helpers::TemplateLoop::new(_iter)"
+                };
+                let pattern = if usage.previtem {
+                    "_loop_value"
+                } else {
+                    &target
+                };
+                if usage.nextitem {
+                    buf.write(format_args!(
+                        "let mut _loop = {template_loop};\
+                        while let askama::helpers::core::option::Option::Some(({pattern}, _loop_item)) = \
+                            askama::helpers::core::iter::Iterator::next(&mut _loop) {{"
+                    ));
+                } else {
+                    buf.write(format_args!(
+                        "for ({pattern}, _loop_item) in {template_loop} {{"
+                    ));
+                }
+                if usage.previtem {
+                    buf.write(format_args!(
+                        "let (_loop_previtem, _loop_value) = askama::helpers::loop_previtem(\
+                            &mut _loop_last_item, _loop_value\
+                        );\
+                        let {target} = _loop_value;"
+                    ));
+                }
                 if has_else_nodes {
                     buf.write("_did_loop = true;");
                 }
-                let mut size_hint1 = this.handle(ctx, &loop_block.body, buf, AstLevel::Nested)?;
-                this.handle_ws(loop_block.ws2);
-                size_hint1 += this.write_buf_writable(ctx, buf)?;
+                buf.write(body.into_string());
                 Ok(size_hint1)
             })?;
             buf.write('}');
+            let html_body_end = this.buf_writable.html.clone();
+            this.ensure_same_html_state(
+                ctx,
+                vec![html_start.clone(), html_body_end],
+                "for",
+                loop_block.span(),
+            )?;
 
             let size_hint2;
             if has_else_nodes {
@@ -578,11 +666,24 @@ impl<'a> Generator<'a, '_> {
                     Ok(size_hint)
                 })?;
                 buf.write('}');
+                let html_else_end = this.buf_writable.html.clone();
+                this.ensure_same_html_state(
+                    ctx,
+                    vec![html_start, html_else_end],
+                    "for",
+                    loop_block.span(),
+                )?;
             } else {
                 this.handle_ws(loop_block.ws3);
                 size_hint2 = this.write_buf_writable(ctx, buf)?;
             }
 
+            if loop_block.recursive {
+                buf.write(
+                    "askama::Result::Ok(())\
+                    })?;",
+                );
+            }
             buf.write('}');
             Ok(flushed + ((size_hint1 * 3) + size_hint2) / 2)
         })
@@ -592,15 +693,22 @@ impl<'a> Generator<'a, '_> {
         &mut self,
         ctx: &Context<'a>,
         buf: &mut Buffer,
-        call: &'a WithSpan<'_, Call<'_>>,
+        call: &'a WithSpan<'a, Call<'a>>,
     ) -> Result<usize, CompileError> {
         let Call {
             ws,
             scope,
             name,
             ref args,
+            ref caller,
         } = **call;
         if name == "super" {
+            if caller.is_some() {
+                return Err(ctx.generate_error(
+                    "`super()` cannot be used in a `{% call(…) %}` block",
+                    call.span(),
+                ));
+            }
             return self.write_block(ctx, buf, None, ws, call.span());
         }
 
@@ -625,6 +733,14 @@ impl<'a> Generator<'a, '_> {
             (*def, ctx)
         };
 
+        let active_caller = caller.as_ref().map(|caller| ActiveCaller {
+            caller,
+            ctx: ctx.clone(),
+            scopes: self.locals.scopes.len(),
+            loops: self.loops.len(),
+            seen_macros: self.seen_macros.len(),
+        });
+
         if self.seen_macros.iter().any(|(s, _)| std::ptr::eq(*s, def)) {
             let mut message = "Found recursion in macro calls:".to_owned();
             for (m, f) in &self.seen_macros {
@@ -719,7 +835,7 @@ impl<'a> Generator<'a, '_> {
                     // don't reintroduce a new variable. This is
                     // to avoid moving non-copyable values.
                     Expr::Var(name) if *name != "self" => {
-                        let var = this.locals.resolve_or_self(name);
+                        let var = this.resolve_var(ctx, name, expr.span())?;
                         this.locals
                             .insert(Cow::Borrowed(arg), LocalMeta::with_ref(var));
                     }
@@ -753,10 +869,19 @@ impl<'a> Generator<'a, '_> {
                 }
             }
 
-            let mut size_hint = this.handle(own_ctx, &def.nodes, buf, AstLevel::Nested)?;
-
-            this.flush_ws(def.ws2);
-            size_hint += this.write_buf_writable(ctx, buf)?;
+            let has_caller = active_caller.is_some();
+            this.callers.extend(active_caller);
+            let size_hint = this
+                .handle(own_ctx, &def.nodes, buf, AstLevel::Nested)
+                .and_then(|mut size_hint| {
+                    this.flush_ws(def.ws2);
+                    size_hint += this.write_buf_writable(ctx, buf)?;
+                    Ok(size_hint)
+                });
+            if has_caller {
+                this.callers.pop();
+            }
+            let size_hint = size_hint?;
             buf.write('}');
             Ok(size_hint)
         })?;
@@ -765,6 +890,121 @@ impl<'a> Generator<'a, '_> {
         Ok(size_hint)
     }
 
+    /// Returns the arguments if `expr` is a `caller(…)` call in a macro called with a
+    /// `{% call(…) %}` block
+    fn caller_call_args(
+        &self,
+        expr: &'a WithSpan<'a, Expr<'a>>,
+    ) -> Option<&'a [WithSpan<'a, Expr<'a>>]> {
+        match &**expr {
+            Expr::Call { path, args, .. }
+                if matches!(***path, Expr::Var("caller"))
+                    && !self.callers.is_empty()
+                    && self.locals.get("caller").is_none() =>
+            {
+                Some(args)
+            }
+            _ => None,
+        }
+    }
+
+    /// Renders the body of the `{% call(…) %}` block for `{{ caller(…) }}`, in the scope of
+    /// the call site
+    fn write_caller(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        ws: Ws,
+        args: &'a [WithSpan<'a, Expr<'a>>],
+        span: Span<'_>,
+    ) -> Result<usize, CompileError> {
+        let active = self.callers.pop().unwrap();
+        let result = self.write_caller_inner(ctx, buf, ws, args, span, &active);
+        self.callers.push(active);
+        result
+    }
+
+    fn write_caller_inner(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        ws: Ws,
+        args: &'a [WithSpan<'a, Expr<'a>>],
+        span: Span<'_>,
+        active: &ActiveCaller<'a>,
+    ) -> Result<usize, CompileError> {
+        let caller = active.caller;
+        if let Some(arg) = args
+            .iter()
+            .find(|arg| matches!(***arg, Expr::NamedArgument(..)))
+        {
+            return Err(ctx.generate_error(
+                "named arguments are not supported by `caller(…)`",
+                arg.span(),
+            ));
+        }
+        if args.len() != caller.args.len() {
+            return Err(ctx.generate_error(
+                format_args!(
+                    "`caller(…)` expects {} argument{}, found {}",
+                    caller.args.len(),
+                    if caller.args.len() == 1 { "" } else { "s" },
+                    args.len(),
+                ),
+                span,
+            ));
+        }
+
+        self.flush_ws(ws);
+        self.write_buf_writable(ctx, buf)?;
+        buf.write('{');
+
+        // The arguments are evaluated in the scope of the macro.
+        if !args.is_empty() {
+            let mut values = Buffer::new();
+            for arg in args {
+                let (before, after) = if is_copyable(arg) {
+                    ("", "")
+                } else {
+                    ("&(", ")")
+                };
+                values.write(format_args!(
+                    "{before}{}{after},",
+                    self.visit_expr_root(ctx, arg)?
+                ));
+            }
+            buf.write("let (");
+            for arg in &caller.args {
+                buf.write(format_args!("{},", normalize_identifier(arg)));
+            }
+            buf.write(format_args!(") = ({values});"));
+        }
+
+        // The body only sees the locals of the call site.
+        let scopes = self.locals.scopes.split_off(active.scopes);
+        let loops = self.loops.split_off(active.loops);
+        let seen_macros = self.seen_macros.split_off(active.seen_macros);
+        let size_hint = self.push_locals(|this| {
+            for arg in &caller.args {
+                this.locals
+                    .insert_with_default(Cow::Borrowed(normalize_identifier(arg)));
+            }
+            this.prepare_ws(caller.ws1);
+            let mut size_hint = this.handle(&active.ctx, &caller.nodes, buf, AstLevel::Nested)?;
+            this.flush_ws(caller.ws2);
+            size_hint += this.write_buf_writable(&active.ctx, buf)?;
+            Ok(size_hint)
+        });
+        self.locals.scopes.extend(scopes);
+        self.loops.extend(loops);
+        self.seen_macros.extend(seen_macros);
+        let size_hint = size_hint?;
+
+        buf.write('}');
+        self.prepare_ws(ws);
+        Ok(size_hint)
+    }
+
     fn write_filter_block(
         &mut self,
         ctx: &Context<'a>,
@@ -832,18 +1072,96 @@ impl<'a> Generator<'a, '_> {
     ) -> Result<usize, CompileError> {
         self.flush_ws(i.ws);
         self.write_buf_writable(ctx, buf)?;
+
+        // The values of `with` are evaluated in the scope of the including template.
+        if !i.with.is_empty() {
+            let mut values = Buffer::new();
+            for (_, value) in &i.with {
+                let (before, after) = if is_copyable(value) {
+                    ("", "")
+                } else {
+                    ("&(", ")")
+                };
+                values.write(format_args!(
+                    "{before}{}{after},",
+                    self.visit_expr_root(ctx, value)?
+                ));
+            }
+            buf.write("{let (");
+            for (name, _) in &i.with {
+                buf.write(format_args!("{},", normalize_identifier(name)));
+            }
+            buf.write(format_args!(") = ({values});"));
+        }
+
+        let size_hint = match &i.target {
+            IncludeTarget::Path(_) | IncludeTarget::Fallback(_) => {
+                // The first template that exists is included, or nothing with `ignore missing`.
+                match self.find_include(ctx, i, i.target.paths())? {
+                    Some(path) => self.write_include(ctx, buf, i, &path)?,
+                    None => 0,
+                }
+            }
+            IncludeTarget::Dynamic(expr, paths) => {
+                // Every candidate is compiled, and the template is selected with a `match`.
+                let expr_code = self.visit_expr_root(ctx, expr)?;
+                buf.write(format_args!(
+                    "match askama::helpers::core::convert::AsRef::<str>::as_ref(&({expr_code})) {{"
+                ));
+                let html_start = self.buf_writable.html.clone();
+                let mut html_ends = Vec::with_capacity(paths.len());
+                let mut sizes = Vec::with_capacity(paths.len());
+                for path in paths {
+                    self.buf_writable.html.clone_from(&html_start);
+                    buf.write(format_args!("\"{path}\" => {{"));
+                    sizes.push(
+                        match self.find_include(ctx, i, std::slice::from_ref(path))? {
+                            Some(path) => self.write_include(ctx, buf, i, &path)?,
+                            None => 0,
+                        },
+                    );
+                    buf.write('}');
+                    html_ends.push(self.buf_writable.html.clone());
+                }
+                buf.write("_ => return askama::Result::Err(askama::Error::UnknownTemplate),}");
+                self.ensure_same_html_state(ctx, html_ends, "include", i.span())?;
+                median(&mut sizes)
+            }
+        };
+        if !i.with.is_empty() {
+            buf.write('}');
+        }
+        self.prepare_ws(i.ws);
+        Ok(size_hint)
+    }
+
+    /// Finds the first of the templates `paths` that exists, cf. `Config::find_include()`
+    fn find_include(
+        &self,
+        ctx: &Context<'a>,
+        i: &'a WithSpan<'_, Include<'_>>,
+        paths: &[&str],
+    ) -> Result<Option<Arc<Path>>, CompileError> {
         let file_info = ctx
             .path
             .map(|path| FileInfo::of(i.span(), path, ctx.parsed));
-        let path = self
-            .input
+        self.input
             .config
-            .find_template(i.path, Some(&self.input.path), file_info)?;
+            .find_include(paths, i.ignore_missing, Some(&self.input.path), file_info)
+    }
 
+    /// Renders the included template `path` in place
+    fn write_include(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        i: &'a WithSpan<'_, Include<'_>>,
+        path: &Arc<Path>,
+    ) -> Result<usize, CompileError> {
         // We clone the context of the child in order to preserve their macros and imports.
         // But also add all the imports and macros from this template that don't override the
         // child's ones to preserve this template's context.
-        let child_ctx = &mut self.contexts[&path].clone();
+        let child_ctx = &mut self.contexts[path].clone();
         for (name, mac) in &ctx.macros {
             child_ctx.macros.entry(name).or_insert(mac);
         }
@@ -867,16 +1185,36 @@ impl<'a> Generator<'a, '_> {
             None => child_ctx,
         };
 
-        let size_hint = self.with_child(heritage.as_ref(), |child| {
+        // With `only`, the included template cannot see anything but the passed variables.
+        let hidden = i.only.then(|| {
+            (
+                mem::replace(&mut self.locals.scopes, vec![HashMap::default()]),
+                mem::take(&mut self.loops),
+                mem::take(&mut self.callers),
+                mem::replace(&mut self.is_isolated, true),
+            )
+        });
+
+        let res = self.with_child(heritage.as_ref(), |child| {
+            for (name, _) in &i.with {
+                child.locals.insert(
+                    Cow::Borrowed(normalize_identifier(name)),
+                    LocalMeta::initialized(),
+                );
+            }
             let mut size_hint = 0;
             size_hint += child.handle(handle_ctx, handle_ctx.nodes, buf, AstLevel::Top)?;
             size_hint += child.write_buf_writable(handle_ctx, buf)?;
             Ok(size_hint)
-        })?;
-
-        self.prepare_ws(i.ws);
+        });
 
-        Ok(size_hint)
+        if let Some((scopes, loops, callers, is_isolated)) = hidden {
+            self.locals.scopes = scopes;
+            self.loops = loops;
+            self.callers = callers;
+            self.is_isolated = is_isolated;
+        }
+        res
     }
 
     fn is_shadowing_variable(
@@ -934,10 +1272,13 @@ impl<'a> Generator<'a, '_> {
 
     fn write_let(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         l: &'a WithSpan<'_, Let<'_>>,
     ) -> Result<(), CompileError> {
+        if let Some(block) = &l.block {
+            return self.write_let_block(ctx, buf, l, block);
+        }
         self.handle_ws(l.ws);
 
         let Some(val) = &l.val else {
@@ -974,6 +1315,76 @@ impl<'a> Generator<'a, '_> {
         Ok(())
     }
 
+    /// Renders the body of `{% set x %}…{% endset %}` into a string, and binds it to `x`
+    fn write_let_block(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        l: &'a WithSpan<'_, Let<'_>>,
+        block: &'a LetBlock<'_>,
+    ) -> Result<(), CompileError> {
+        if !cfg!(feature = "alloc") {
+            return Err(ctx.generate_error(
+                "block assignments require the `alloc` feature to be enabled",
+                l.span(),
+            ));
+        }
+        self.flush_ws(block.ws1);
+        self.write_buf_writable(ctx, buf)?;
+
+        // The body is generated first, because it must not see the assigned variable.
+        let mut body = Buffer::new();
+        body.set_discard(buf.is_discard());
+        self.push_locals(|this| {
+            this.prepare_ws(block.ws1);
+            this.handle(ctx, &block.nodes, &mut body, AstLevel::Nested)?;
+            this.flush_ws(block.ws2);
+            this.write_buf_writable(ctx, &mut body)
+        })?;
+        // The filters are applied to the rendered body, which is bound to `FILTER_SOURCE`.
+        // The body was already escaped, so the result is not escaped again.
+        let filtered = match &block.filters {
+            Some(filters) => {
+                let mut filter_buf = Buffer::new();
+                self.visit_filter(
+                    ctx,
+                    &mut filter_buf,
+                    filters.name,
+                    &filters.arguments,
+                    &filters.generics,
+                    l.span(),
+                )?;
+                format!(
+                    "let {FILTER_SOURCE} = __askama_block;\
+                    let mut __askama_block = askama::helpers::alloc::string::String::new();\
+                    askama::helpers::core::write!(__askama_block, \"{{}}\", {filter_buf})?;"
+                )
+            }
+            None => String::new(),
+        };
+
+        if self.is_shadowing_variable(ctx, &l.var, l.span())?
+            || matches!(&l.var, Target::Name(name) if self.locals.get(name).is_none())
+        {
+            buf.write("let ");
+        }
+        self.visit_target(buf, true, true, &l.var);
+        buf.write(format_args!(
+            " = {{\
+                let mut __askama_block = askama::helpers::alloc::string::String::new();\
+                {{\
+                    let __askama_writer = &mut __askama_block;\
+                    {}\
+                }}\
+                {filtered}\
+                askama::filters::Safe(__askama_block)\
+            }};",
+            body.into_string(),
+        ));
+        self.prepare_ws(block.ws2);
+        Ok(())
+    }
+
     // If `name` is `Some`, this is a call to a block definition, and we have to find
     // the first block for that name from the ancestry chain. If name is `None`, this
     // is from a `super()` call, and we can get the name from `self.super_block`.
@@ -1094,8 +1505,15 @@ impl<'a> Generator<'a, '_> {
             std::slice::from_ref(s)
         };
         for s in items {
-            self.buf_writable
-                .push(compile_time_escape(s, self.input.escaper).unwrap_or(Writable::Expr(s)));
+            let escaper = match &self.buf_writable.html {
+                Some(html) => html.escaper(self.input.escaper),
+                None => Ok(self.input.escaper),
+            };
+            let writable = escaper
+                .ok()
+                .and_then(|escaper| compile_time_escape(s, escaper))
+                .unwrap_or(Writable::Expr(s, escaper));
+            self.buf_writable.push(writable);
         }
     }
 
@@ -1141,17 +1559,25 @@ impl<'a> Generator<'a, '_> {
                         break;
                     }
                 }
-                Writable::Expr(s) => {
+                Writable::Expr(s, escaper) => {
                     size_hint += 3;
 
                     let mut expr_buf = Buffer::new();
-                    let expr = match self.visit_expr(ctx, &mut expr_buf, s)? {
-                        DisplayWrap::Wrapped => expr_buf.into_string(),
-                        DisplayWrap::Unwrapped => format!(
-                            "(&&askama::filters::AutoEscaper::new(&({expr_buf}), {})).\
+                    let expr = match (self.visit_expr(ctx, &mut expr_buf, s)?, escaper) {
+                        (DisplayWrap::Wrapped, _) => expr_buf.into_string(),
+                        (DisplayWrap::Unwrapped, Ok(escaper)) => format!(
+                            "(&&askama::filters::AutoEscaper::new(&({expr_buf}), {escaper})).\
                                 askama_auto_escape()?",
-                            self.input.escaper,
                         ),
+                        (DisplayWrap::Unwrapped, Err(position)) => {
+                            return Err(ctx.generate_error(
+                                format_args!(
+                                    "cannot escape expression {position}; use the `safe` or \
+                                    `escape` filter to write it anyway"
+                                ),
+                                s.span(),
+                            ));
+                        }
                     };
                     let idx = if is_cacheable(s) {
                         match expr_cache.entry(expr) {
@@ -1228,6 +1654,32 @@ impl<'a> Generator<'a, '_> {
         }
     }
 
+    /// With contextual escaping, the output after a branching node like `{% if %}` must be in the
+    /// same HTML context no matter which branch was taken.
+    fn ensure_same_html_state(
+        &mut self,
+        ctx: &Context<'_>,
+        states: Vec<Option<HtmlState>>,
+        kind: &str,
+        span: Span<'_>,
+    ) -> Result<(), CompileError> {
+        let mut states = states.into_iter();
+        let Some(first) = states.next() else {
+            return Ok(());
+        };
+        if states.any(|state| state != first) {
+            return Err(ctx.generate_error(
+                format_args!(
+                    "the HTML context at the end of this `{kind}` block depends on the path taken \
+                    through it, but `contextual_escape` needs every path to end in the same context"
+                ),
+                span,
+            ));
+        }
+        self.buf_writable.html = first;
+        Ok(())
+    }
+
     // Helper methods for dealing with whitespace nodes
 
     // Combines `flush_ws()` and `prepare_ws()` to handle both trailing whitespace from the
@@ -1546,6 +1998,7 @@ fn is_cacheable(expr: &WithSpan<'_, Expr<'_>>) -> bool {
         Expr::NamedArgument(_, expr) => is_cacheable(expr),
         Expr::As(expr, _) => is_cacheable(expr),
         Expr::Try(expr) => is_cacheable(expr),
+        Expr::IsNone(expr) | Expr::IsNotNone(expr) => is_cacheable(expr),
         Expr::Concat(args) => args.iter().all(is_cacheable),
         // Doesn't make sense in this context.
         Expr::LetCond(_) => false,
@@ -124,6 +124,16 @@ impl<'a> Context<'a> {
                             nested.push(&arm.nodes);
                         }
                     }
+                    Node::Call(c) => {
+                        if let Some(caller) = &c.caller {
+                            nested.push(&caller.nodes);
+                        }
+                    }
+                    Node::Let(l) => {
+                        if let Some(block) = &l.block {
+                            nested.push(&block.nodes);
+                        }
+                    }
                     _ => {}
                 }
             }
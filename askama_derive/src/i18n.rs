@@ -0,0 +1,125 @@
+//! Compile time support for the translation function `t()`
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+use fluent_syntax::ast::Entry;
+
+use crate::config::I18nConfig;
+use crate::{CompileError, FileInfo, OnceMap};
+
+/// All Fluent resources found in the configured `[i18n]` directory
+#[derive(Debug)]
+pub(crate) struct Catalog {
+    /// Keyed by the name of the locale, i.e. the name of its subdirectory
+    pub(crate) locales: BTreeMap<String, Locale>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Locale {
+    /// The `.ftl` files of this locale, sorted by path
+    pub(crate) files: Vec<PathBuf>,
+    /// Message IDs, and the names of their attributes
+    messages: HashMap<String, HashSet<String>>,
+}
+
+impl Locale {
+    /// Returns `true` if the message `id`, or the attribute `"message.attribute"`, is defined
+    pub(crate) fn contains(&self, id: &str) -> bool {
+        let (message, attribute) = match id.split_once('.') {
+            Some((message, attribute)) => (message, Some(attribute)),
+            None => (id, None),
+        };
+        match (self.messages.get(message), attribute) {
+            (Some(_), None) => true,
+            (Some(attributes), Some(attribute)) => attributes.contains(attribute),
+            (None, _) => false,
+        }
+    }
+}
+
+impl Catalog {
+    pub(crate) fn load(config: &I18nConfig) -> Result<Arc<Catalog>, CompileError> {
+        static CACHE: OnceLock<OnceMap<PathBuf, Arc<Catalog>>> = OnceLock::new();
+
+        CACHE.get_or_init(OnceMap::default).get_or_try_insert(
+            &config.dir,
+            |dir| Ok((dir.clone(), Arc::new(Self::load_uncached(dir)?))),
+            Arc::clone,
+        )
+    }
+
+    fn load_uncached(dir: &Path) -> Result<Self, CompileError> {
+        let mut locales = BTreeMap::new();
+        for locale_dir in read_dir(dir)? {
+            if !locale_dir.is_dir() {
+                continue;
+            }
+            let Some(name) = locale_dir.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+
+            let mut files = Vec::new();
+            let mut messages = HashMap::new();
+            for path in read_dir(&locale_dir)? {
+                if path.extension() != Some("ftl".as_ref()) || !path.is_file() {
+                    continue;
+                }
+                let source = fs::read_to_string(&path).map_err(|err| {
+                    CompileError::no_file_info(
+                        format_args!("unable to read {}: {err}", path.display()),
+                        None,
+                    )
+                })?;
+                let resource = match fluent_syntax::parser::parse(source.as_str()) {
+                    Ok(resource) => resource,
+                    Err((_, errors)) => {
+                        let err = &errors[0];
+                        return Err(CompileError::new(
+                            format_args!("invalid Fluent resource: {err}"),
+                            Some(FileInfo::new(
+                                &path,
+                                Some(&source),
+                                source.get(err.pos.start..),
+                            )),
+                        ));
+                    }
+                };
+                for entry in resource.body {
+                    if let Entry::Message(message) = entry {
+                        let attributes = message
+                            .attributes
+                            .iter()
+                            .map(|attr| attr.id.name.to_owned())
+                            .collect();
+                        messages.insert(message.id.name.to_owned(), attributes);
+                    }
+                }
+                files.push(path);
+            }
+            locales.insert(name.to_owned(), Locale { files, messages });
+        }
+        Ok(Self { locales })
+    }
+}
+
+/// Returns the sorted paths in `dir`
+fn read_dir(dir: &Path) -> Result<Vec<PathBuf>, CompileError> {
+    let entries = fs::read_dir(dir).and_then(|entries| {
+        entries
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()
+    });
+    match entries {
+        Ok(mut entries) => {
+            entries.sort();
+            Ok(entries)
+        }
+        Err(err) => Err(CompileError::no_file_info(
+            format_args!("unable to read i18n directory {}: {err}", dir.display()),
+            None,
+        )),
+    }
+}
@@ -6,7 +6,7 @@ use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::{Arc, OnceLock};
 
-use parser::node::Whitespace;
+use parser::node::{IncludeTarget, Whitespace};
 use parser::{Node, Parsed};
 use proc_macro2::Span;
 use rustc_hash::FxBuildHasher;
@@ -30,6 +30,7 @@ pub(crate) struct TemplateInput<'a> {
     pub(crate) blocks: &'a [Block],
     pub(crate) print: Print,
     pub(crate) escaper: &'a str,
+    pub(crate) contextual_escape: bool,
     pub(crate) path: Arc<Path>,
     pub(crate) fields: Arc<[String]>,
 }
@@ -51,6 +52,7 @@ impl TemplateInput<'_> {
             blocks,
             print,
             escaping,
+            contextual_escape,
             ext,
             ext_span,
             syntax,
@@ -143,6 +145,7 @@ impl TemplateInput<'_> {
             blocks: blocks.as_slice(),
             print: *print,
             escaper,
+            contextual_escape: *contextual_escape,
             path,
             fields: fields.into(),
         })
@@ -165,17 +168,22 @@ impl TemplateInput<'_> {
         while let Some((path, source, source_path)) = check.pop() {
             let parsed = match self.syntax.parse(Arc::clone(&source), source_path) {
                 Ok(parsed) => parsed,
-                Err(err) => {
-                    let msg = err
-                        .message
-                        .unwrap_or_else(|| "failed to parse template source".into());
-                    let file_path = err
-                        .file_path
-                        .as_deref()
-                        .unwrap_or(Path::new("<source attribute>"));
-                    let file_info =
-                        FileInfo::new(file_path, Some(&source), Some(&source[err.offset..]));
-                    return Err(CompileError::new(msg, Some(file_info)));
+                Err(errors) => {
+                    let mut errors = errors.into_iter().map(|err| {
+                        let msg = err
+                            .message
+                            .unwrap_or_else(|| "failed to parse template source".into());
+                        let file_path = err
+                            .file_path
+                            .as_deref()
+                            .unwrap_or(Path::new("<source attribute>"));
+                        let file_info =
+                            FileInfo::new(file_path, Some(&source), Some(&source[err.offset..]));
+                        CompileError::new(msg, Some(file_info))
+                    });
+                    // `parse()` returns at least one error.
+                    let first = errors.next().unwrap();
+                    return Err(first.with_more(errors));
                 }
             };
 
@@ -236,12 +244,25 @@ impl TemplateInput<'_> {
                             nested.push(&f.nodes);
                         }
                         Node::Include(include) => {
-                            let include = self.config.find_template(
-                                include.path,
-                                Some(&path),
-                                Some(FileInfo::of(include.span(), &path, &parsed)),
-                            )?;
-                            add_to_check(include)?;
+                            // A dynamic include needs all of its candidates, the other ones only
+                            // the first one that exists.
+                            let candidates = match &include.target {
+                                IncludeTarget::Dynamic(_, paths) => {
+                                    paths.iter().map(std::slice::from_ref).collect()
+                                }
+                                target => vec![target.paths()],
+                            };
+                            for paths in candidates {
+                                let found = self.config.find_include(
+                                    paths,
+                                    include.ignore_missing,
+                                    Some(&path),
+                                    Some(FileInfo::of(include.span(), &path, &parsed)),
+                                )?;
+                                if let Some(include) = found {
+                                    add_to_check(include)?;
+                                }
+                            }
                         }
                         Node::BlockDef(b) => {
                             nested.push(&b.nodes);
@@ -260,12 +281,20 @@ impl TemplateInput<'_> {
                                 nested.push(&arm.nodes);
                             }
                         }
+                        Node::Call(c) => {
+                            if let Some(caller) = &c.caller {
+                                nested.push(&caller.nodes);
+                            }
+                        }
+                        Node::Let(l) => {
+                            if let Some(block) = &l.block {
+                                nested.push(&block.nodes);
+                            }
+                        }
                         Node::Lit(_)
                         | Node::Comment(_)
                         | Node::Expr(_, _)
-                        | Node::Call(_)
                         | Node::Extends(_)
-                        | Node::Let(_)
                         | Node::Import(_)
                         | Node::Macro(_)
                         | Node::Raw(_)
@@ -364,6 +393,7 @@ pub(crate) struct TemplateArgs {
     blocks: Vec<Block>,
     print: Print,
     escaping: Option<String>,
+    contextual_escape: bool,
     ext: Option<String>,
     ext_span: Option<Span>,
     syntax: Option<String>,
@@ -423,6 +453,7 @@ impl TemplateArgs {
                 .collect(),
             print: args.print.unwrap_or_default(),
             escaping: args.escape.map(|value| value.value()),
+            contextual_escape: args.contextual_escape.is_some_and(|value| value.value()),
             ext: args.ext.as_ref().map(|value| value.value()),
             ext_span: args.ext.as_ref().map(|value| value.span()),
             syntax: args.syntax.map(|value| value.value()),
@@ -442,6 +473,7 @@ impl TemplateArgs {
             blocks: vec![],
             print: Print::default(),
             escaping: None,
+            contextual_escape: false,
             ext: Some("txt".to_string()),
             ext_span: None,
             syntax: None,
@@ -714,6 +746,7 @@ pub(crate) struct PartialTemplateArgs {
     pub(crate) block: Option<LitStr>,
     pub(crate) print: Option<Print>,
     pub(crate) escape: Option<LitStr>,
+    pub(crate) contextual_escape: Option<LitBool>,
     pub(crate) ext: Option<LitStr>,
     pub(crate) syntax: Option<LitStr>,
     pub(crate) config: Option<LitStr>,
@@ -778,6 +811,7 @@ const _: () = {
             block: None,
             print: None,
             escape: None,
+            contextual_escape: None,
             ext: None,
             syntax: None,
             config: None,
@@ -900,6 +934,9 @@ const _: () = {
                     set_parseable_string(ident, value, &mut this.print)?;
                 } else if ident == "escape" {
                     set_strlit_pair(ident, value, &mut this.escape)?;
+                } else if ident == "contextual_escape" {
+                    ensure_only_once(ident, &mut this.contextual_escape)?;
+                    this.contextual_escape = Some(get_boollit(ident, value)?);
                 } else if ident == "ext" {
                     set_strlit_pair(ident, value, &mut this.ext)?;
                 } else if ident == "syntax" {
@@ -1059,7 +1096,7 @@ const JINJA_EXTENSIONS: &[&str] = &["askama", "j2", "jinja", "jinja2", "rinja"];
 
 #[test]
 fn get_source() {
-    let path = Config::new("", None, None, None)
+    let path = Config::new("", None, None, None, None)
         .and_then(|config| config.find_template("b.html", None, None))
         .unwrap();
     assert_eq!(get_template_source(&path, None).unwrap(), "bar".into());
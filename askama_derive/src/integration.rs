@@ -74,12 +74,52 @@ fn impl_fast_writable(ast: &DeriveInput, buf: &mut Buffer) {
     );
 }
 
+/// Implement `askama::helpers::hot_reload::Reflect` for the given struct.
+#[cfg(feature = "hot-reload")]
+pub(crate) fn impl_reflect(ast: &DeriveInput, buf: &mut Buffer) {
+    let Data::Struct(data) = &ast.data else {
+        return;
+    };
+
+    buf.write("#[cfg(debug_assertions)]");
+    write_header(ast, buf, "askama::helpers::hot_reload::Reflect");
+    buf.write(
+        "\
+            fn reflect(&self) -> askama::interpreter::Value {\
+                #[allow(unused_imports)]\
+                use askama::helpers::hot_reload::{\
+                    ReflectDisplay as _, ReflectOpaque as _, ReflectSerialize as _,\
+                };\
+                askama::helpers::hot_reload::fields([",
+    );
+    for (idx, field) in data.fields.iter().enumerate() {
+        let (name, member) = match &field.ident {
+            Some(ident) => {
+                let name = ident.to_string();
+                let name = name.strip_prefix("r#").unwrap_or(&name).to_owned();
+                (name, quote!(#ident))
+            }
+            None => {
+                let idx = syn::Index::from(idx);
+                (idx.index.to_string(), quote!(#idx))
+            }
+        };
+        buf.write(format_args!(
+            "({name:?}, (&&&askama::helpers::hot_reload::Field(&self.{member})).askama_reflect()),"
+        ));
+    }
+    buf.write("]) } }");
+}
+
 #[derive(Debug)]
 pub(crate) struct Buffer {
     // The buffer to generate the code into
     buf: String,
     discard: bool,
     last_was_write_str: bool,
+    // Messages that are reported as warnings in the generated code
+    #[cfg(feature = "i18n")]
+    warnings: Vec<String>,
 }
 
 impl Display for Buffer {
@@ -94,6 +134,8 @@ impl Buffer {
             buf: String::new(),
             discard: false,
             last_was_write_str: false,
+            #[cfg(feature = "i18n")]
+            warnings: Vec::new(),
         }
     }
 
@@ -101,6 +143,18 @@ impl Buffer {
         self.buf
     }
 
+    #[cfg(feature = "i18n")]
+    pub(crate) fn warn(&mut self, msg: String) {
+        if !self.warnings.contains(&msg) {
+            self.warnings.push(msg);
+        }
+    }
+
+    #[cfg(feature = "i18n")]
+    pub(crate) fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+
     pub(crate) fn is_discard(&self) -> bool {
         self.discard
     }
@@ -282,6 +336,7 @@ pub(crate) fn build_template_enum(
         if let Some(enum_args) = &mut enum_args {
             set_default(&mut var_args, enum_args, |v| &mut v.source);
             set_default(&mut var_args, enum_args, |v| &mut v.escape);
+            set_default(&mut var_args, enum_args, |v| &mut v.contextual_escape);
             set_default(&mut var_args, enum_args, |v| &mut v.ext);
             set_default(&mut var_args, enum_args, |v| &mut v.syntax);
             set_default(&mut var_args, enum_args, |v| &mut v.config);
@@ -2,31 +2,31 @@
 #![deny(elided_lifetimes_in_paths)]
 #![deny(unreachable_pub)]
 
+mod common;
 mod config;
 mod generator;
 mod heritage;
 mod html;
+#[cfg(feature = "i18n")]
+mod i18n;
 mod input;
 mod integration;
 #[cfg(test)]
 mod tests;
 
-use std::borrow::{Borrow, Cow};
-use std::collections::hash_map::{Entry, HashMap};
+use std::borrow::Cow;
+use std::collections::hash_map::HashMap;
 use std::fmt;
-use std::hash::{BuildHasher, Hash};
-use std::path::Path;
-use std::sync::Mutex;
 
-use parser::{Parsed, ascii_str, strip_common};
+use parser::ascii_str;
 #[cfg(not(feature = "__standalone"))]
 use proc_macro::TokenStream as TokenStream12;
 #[cfg(feature = "__standalone")]
 use proc_macro2::TokenStream as TokenStream12;
-use proc_macro2::{Delimiter, Group, Span, TokenStream, TokenTree};
+use proc_macro2::{Delimiter, Group, TokenStream, TokenTree};
 use quote::{quote, quote_spanned};
-use rustc_hash::FxBuildHasher;
 
+use crate::common::{CompileError, FileInfo, OnceMap};
 use crate::config::{Config, read_config_file};
 use crate::generator::{TmplKind, template_to_string};
 use crate::heritage::{Context, Heritage};
@@ -158,6 +158,15 @@ use crate::integration::{Buffer, build_template_enum};
 /// Override the template's extension used for the purpose of determining the escaper for
 /// this template. See the section on configuring custom escapers for more information.
 ///
+/// ### contextual_escape
+///
+/// E.g. `contextual_escape = true`
+///
+/// Track the HTML context of every expression, and pick a fitting escaper for it: HTML text,
+/// attribute values, URL attributes, `<script>` string literals, and CSS. Expressions that
+/// appear where no escaping is safe, e.g. inside a tag name, are rejected at compile time
+/// unless they are marked with `|safe` or `|escape`. Defaults to `false`.
+///
 /// ### syntax
 ///
 /// E.g. `syntax = "foo"`
@@ -224,10 +233,14 @@ pub fn derive_template(input: TokenStream12) -> TokenStream12 {
         .unwrap_or_default();
 
     let result = args.and_then(|args| build_template(&mut buf, &ast, args));
-    let ts = if let Err(CompileError { msg, span }) = result {
+    #[cfg(feature = "i18n")]
+    let warnings = buf.take_warnings();
+    #[cfg_attr(not(feature = "i18n"), allow(unused_mut))]
+    let mut ts = if let Err(CompileError { msg, span, more }) = result {
+        let msgs = std::iter::once(msg).chain(more);
         let mut ts = quote_spanned! {
             span.unwrap_or(ast.ident.span()) =>
-            askama::helpers::core::compile_error!(#msg);
+            #(askama::helpers::core::compile_error!(#msgs);)*
         };
         buf.clear();
         if build_skeleton(&mut buf, &ast).is_ok() {
@@ -238,6 +251,10 @@ pub fn derive_template(input: TokenStream12) -> TokenStream12 {
     } else {
         buf.into_string().parse().unwrap()
     };
+    #[cfg(feature = "i18n")]
+    for msg in warnings {
+        ts.extend(emit_warning(&msg, ast.ident.span()));
+    }
 
     let ts = TokenTree::Group(Group::new(Delimiter::None, ts));
     let ts = if let Some(crate_name) = crate_name {
@@ -258,9 +275,22 @@ pub fn derive_template(input: TokenStream12) -> TokenStream12 {
     ts.into()
 }
 
+/// There is no stable API to emit warnings from a proc-macro, so we use a deprecated item
+#[cfg(feature = "i18n")]
+fn emit_warning(msg: &str, span: proc_macro2::Span) -> TokenStream {
+    quote_spanned! {
+        span =>
+        const _: () = {
+            #[deprecated(note = #msg)]
+            struct AskamaWarning;
+            let _ = AskamaWarning;
+        };
+    }
+}
+
 fn build_skeleton(buf: &mut Buffer, ast: &syn::DeriveInput) -> Result<usize, CompileError> {
     let template_args = TemplateArgs::fallback();
-    let config = Config::new("", None, None, None)?;
+    let config = Config::new("", None, None, None, None)?;
     let input = TemplateInput::new(ast, None, config, &template_args)?;
     let mut contexts = HashMap::default();
     let parsed = parser::Parsed::default();
@@ -315,9 +345,10 @@ fn build_template_item(
     tmpl_kind: TmplKind<'_>,
 ) -> Result<usize, CompileError> {
     let config_path = template_args.config_path();
-    let s = read_config_file(config_path, template_args.config_span)?;
+    let s = read_config_file(None, config_path, template_args.config_span)?;
     let config = Config::new(
         &s,
+        None,
         config_path,
         template_args.whitespace,
         template_args.config_span,
@@ -364,137 +395,6 @@ fn build_template_item(
     Ok(size_hint)
 }
 
-#[derive(Debug, Clone)]
-struct CompileError {
-    msg: String,
-    span: Option<Span>,
-}
-
-impl CompileError {
-    fn new<S: fmt::Display>(msg: S, file_info: Option<FileInfo<'_>>) -> Self {
-        Self::new_with_span(msg, file_info, None)
-    }
-
-    fn new_with_span<S: fmt::Display>(
-        msg: S,
-        file_info: Option<FileInfo<'_>>,
-        span: Option<Span>,
-    ) -> Self {
-        let msg = match file_info {
-            Some(file_info) => format!("{msg}{file_info}"),
-            None => msg.to_string(),
-        };
-        Self { msg, span }
-    }
-
-    fn no_file_info<S: ToString>(msg: S, span: Option<Span>) -> Self {
-        Self {
-            msg: msg.to_string(),
-            span,
-        }
-    }
-}
-
-impl std::error::Error for CompileError {}
-
-impl fmt::Display for CompileError {
-    #[inline]
-    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt.write_str(&self.msg)
-    }
-}
-
-#[derive(Debug, Clone, Copy)]
-struct FileInfo<'a> {
-    path: &'a Path,
-    source: Option<&'a str>,
-    node_source: Option<&'a str>,
-}
-
-impl<'a> FileInfo<'a> {
-    fn new(path: &'a Path, source: Option<&'a str>, node_source: Option<&'a str>) -> Self {
-        Self {
-            path,
-            source,
-            node_source,
-        }
-    }
-
-    fn of(node: parser::Span<'a>, path: &'a Path, parsed: &'a Parsed) -> Self {
-        let source = parsed.source();
-        Self {
-            path,
-            source: Some(source),
-            node_source: node.as_suffix_of(source),
-        }
-    }
-}
-
-impl fmt::Display for FileInfo<'_> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if let (Some(source), Some(node_source)) = (self.source, self.node_source) {
-            let (error_info, file_path) = generate_error_info(source, node_source, self.path);
-            write!(
-                f,
-                "\n  --> {file_path}:{row}:{column}\n{source_after}",
-                row = error_info.row,
-                column = error_info.column,
-                source_after = error_info.source_after,
-            )
-        } else {
-            write!(
-                f,
-                "\n --> {}",
-                match std::env::current_dir() {
-                    Ok(cwd) => fmt_left!(move "{}", strip_common(&cwd, self.path)),
-                    Err(_) => fmt_right!("{}", self.path.display()),
-                }
-            )
-        }
-    }
-}
-
-struct ErrorInfo {
-    row: usize,
-    column: usize,
-    source_after: String,
-}
-
-fn generate_row_and_column(src: &str, input: &str) -> ErrorInfo {
-    const MAX_LINE_LEN: usize = 80;
-
-    let offset = src.len() - input.len();
-    let (source_before, source_after) = src.split_at(offset);
-
-    let source_after = match source_after
-        .char_indices()
-        .enumerate()
-        .take(MAX_LINE_LEN + 1)
-        .last()
-    {
-        Some((MAX_LINE_LEN, (i, _))) => format!("{:?}...", &source_after[..i]),
-        _ => format!("{source_after:?}"),
-    };
-
-    let (row, last_line) = source_before.lines().enumerate().last().unwrap_or_default();
-    let column = last_line.chars().count();
-    ErrorInfo {
-        row: row + 1,
-        column,
-        source_after,
-    }
-}
-
-/// Return the error related information and its display file path.
-fn generate_error_info(src: &str, input: &str, file_path: &Path) -> (ErrorInfo, String) {
-    let file_path = match std::env::current_dir() {
-        Ok(cwd) => strip_common(&cwd, file_path),
-        Err(_) => file_path.display().to_string(),
-    };
-    let error_info = generate_row_and_column(src, input);
-    (error_info, file_path)
-}
-
 struct MsgValidEscapers<'a>(&'a [(Vec<Cow<'a, str>>, Cow<'a, str>)]);
 
 impl fmt::Display for MsgValidEscapers<'_> {
@@ -510,42 +410,6 @@ impl fmt::Display for MsgValidEscapers<'_> {
     }
 }
 
-#[derive(Debug)]
-struct OnceMap<K, V>([Mutex<HashMap<K, V, FxBuildHasher>>; 8]);
-
-impl<K, V> Default for OnceMap<K, V> {
-    fn default() -> Self {
-        Self(Default::default())
-    }
-}
-
-impl<K: Hash + Eq, V> OnceMap<K, V> {
-    // The API of this function was copied, and adapted from the `once_map` crate
-    // <https://crates.io/crates/once_map/0.4.18>.
-    fn get_or_try_insert<T, Q, E>(
-        &self,
-        key: &Q,
-        make_key_value: impl FnOnce(&Q) -> Result<(K, V), E>,
-        to_value: impl FnOnce(&V) -> T,
-    ) -> Result<T, E>
-    where
-        K: Borrow<Q>,
-        Q: Hash + Eq,
-    {
-        let shard_idx = (FxBuildHasher.hash_one(key) % self.0.len() as u64) as usize;
-        let mut shard = self.0[shard_idx].lock().unwrap();
-        Ok(to_value(if let Some(v) = shard.get(key) {
-            v
-        } else {
-            let (k, v) = make_key_value(key)?;
-            match shard.entry(k) {
-                Entry::Vacant(entry) => entry.insert(v),
-                Entry::Occupied(_) => unreachable!("key in map when it should not have been"),
-            }
-        }))
-    }
-}
-
 enum EitherFormat<L, R>
 where
     L: for<'a, 'b> Fn(&'a mut fmt::Formatter<'b>) -> fmt::Result,
@@ -596,22 +460,3 @@ macro_rules! fmt_right {
 }
 
 pub(crate) use {fmt_left, fmt_right};
-
-// This is used by the code generator to decide whether a named filter is part of
-// Askama or should refer to a local `filters` module.
-const BUILTIN_FILTERS: &[&str] = &[
-    "capitalize",
-    "center",
-    "indent",
-    "lower",
-    "lowercase",
-    "title",
-    "trim",
-    "truncate",
-    "upper",
-    "uppercase",
-    "wordcount",
-];
-
-// Built-in filters that need the `alloc` feature.
-const BUILTIN_FILTERS_NEED_ALLOC: &[&str] = &["center", "truncate"];
@@ -1134,3 +1134,102 @@ fn extends_with_whitespace_control() {
         }
     }
 }
+
+#[cfg(all(feature = "config", feature = "icu"))]
+#[test]
+fn test_number_decimals() {
+    for (source, valid) in [
+        ("{{ 1|number(2) }}", true),
+        ("{{ 1|intcomma }}", true),
+        #[cfg(feature = "icu-unstable")]
+        ("{{ 1|currency(\"EUR\", 255u8) }}", true),
+        #[cfg(feature = "icu-unstable")]
+        ("{{ 1|percent(256) }}", false),
+        ("{{ 1|number(-1) }}", false),
+        ("{{ 1|number(1.5) }}", false),
+        ("{{ 1|number(2i32) }}", false),
+    ] {
+        // `askama/askama.toml` enables the group `"numbers"`.
+        let ts = format!(
+            "#[template(ext = \"txt\", source = {source:?}, config = \"../askama/askama.toml\")] \
+             struct Tmpl;"
+        );
+        let ast = syn::parse_str(&ts).unwrap();
+        match build_template(&ast) {
+            Ok(generated) => assert!(valid, "{source}: {generated}"),
+            Err(err) => {
+                assert!(!valid, "{source}: {err}");
+                assert!(
+                    err.to_string()
+                        .contains("must be an integer between 0 and 255"),
+                    "{source}: {err}",
+                );
+            }
+        }
+    }
+}
+
+#[cfg(feature = "icu")]
+#[test]
+fn test_number_filters_need_group() {
+    // Without the group `"numbers"` in the configuration, the names refer to custom filters.
+    let ts = "#[template(ext = \"txt\", source = \"{{ 1|number }}\")] struct Tmpl;";
+    let generated = build_template(&syn::parse_str(ts).unwrap()).unwrap();
+    assert!(
+        generated.contains("filters::number(") && !generated.contains("askama::filters::number("),
+        "{generated}",
+    );
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn test_default_value_lookup_needs_group() {
+    // The trait of the `default` filter is only imported if the filter is enabled.
+    for (config, imported) in [("", false), (", config = \"../askama/askama.toml\"", true)] {
+        let ts = format!("#[template(ext = \"txt\", source = \"\"{config})] struct Tmpl;");
+        let generated = build_template(&syn::parse_str(&ts).unwrap()).unwrap();
+        assert_eq!(
+            generated.contains("DefaultValueLookup"),
+            imported,
+            "{generated}"
+        );
+    }
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn test_builtin_filters_list() {
+    use crate::generator::filters::BUILTIN_FILTERS;
+
+    assert!(
+        BUILTIN_FILTERS.windows(2).all(|w| w[0] < w[1]),
+        "`BUILTIN_FILTERS` must be sorted"
+    );
+    for name in BUILTIN_FILTERS.iter().chain(&["not_builtin"]) {
+        let needs_feature = match *name {
+            "currency" | "percent" => !cfg!(feature = "icu-unstable"),
+            "intcomma" | "number" => !cfg!(feature = "icu"),
+            "date" | "datetime" | "time" | "timesince" | "timeuntil" => {
+                !cfg!(any(feature = "chrono", feature = "jiff", feature = "time"))
+            }
+            _ => false,
+        };
+        if needs_feature {
+            continue;
+        }
+        // `askama/askama.toml` enables all groups of built-in filters.
+        let ts = format!(
+            "#[template(ext = \"txt\", source = \"{{{{ a|{name} }}}}\", \
+             config = \"../askama/askama.toml\")] struct Tmpl {{ a: u32 }}"
+        );
+        let ast = syn::parse_str(&ts).unwrap();
+        // Some filters need arguments, then the error is fine, too.
+        let Ok(generated) = build_template(&ast) else {
+            continue;
+        };
+        let is_custom = generated
+            .replace("askama::filters::", "")
+            .contains(&format!("filters::{name}("));
+        assert_eq!(is_custom, *name == "not_builtin", "{name}: {generated}");
+    }
+}
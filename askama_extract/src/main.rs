@@ -0,0 +1,285 @@
+//! `askama-extract`: write the translatable strings of Askama templates into a catalog
+//!
+//! The tool reads the template directories and syntaxes from `askama.toml`, with the same code
+//! that `askama_derive` uses, parses every template file, and collects the message IDs of all
+//! calls of the translation function `t()`.
+
+#![deny(elided_lifetimes_in_paths)]
+#![deny(unreachable_pub)]
+
+// Not everything of the configuration is needed by the extractor.
+#[allow(dead_code)]
+#[path = "../derive/common.rs"]
+mod common;
+#[allow(dead_code)]
+#[path = "../derive/config.rs"]
+mod config;
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::{env, fs, io};
+
+use parser::i18n::extract_translations;
+use parser::{Parsed, Syntax};
+use serde_derive::Serialize;
+
+use crate::config::{Config, read_config_file};
+
+const USAGE: &str = "\
+Usage: askama-extract [OPTIONS]
+
+Write the translatable strings of Askama templates into a gettext `.pot` or JSON catalog.
+
+Options:
+  --config <PATH>      configuration file [default: askama.toml, if it exists]
+  --syntax <NAME>      syntax of the templates [default: the configured default syntax]
+  --format <FORMAT>    `pot` or `json` [default: pot]
+  --output <PATH>      write the catalog into a file instead of stdout
+  --help               print this help
+";
+
+fn main() -> ExitCode {
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Args {
+    config: Option<PathBuf>,
+    syntax: Option<String>,
+    format: Format,
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    #[default]
+    Pot,
+    Json,
+}
+
+impl Args {
+    /// Returns `None` if the help was requested
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut result = Self::default();
+        while let Some(arg) = args.next() {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name.to_owned(), Some(value.to_owned())),
+                None => (arg, None),
+            };
+            if name == "--help" || name == "-h" {
+                return Ok(None);
+            }
+            let Some(value) = value.or_else(|| args.next()) else {
+                return Err(format!("missing value for argument `{name}`"));
+            };
+            match name.as_str() {
+                "--config" => result.config = Some(value.into()),
+                "--syntax" => result.syntax = Some(value),
+                "--output" => result.output = Some(value.into()),
+                "--format" => {
+                    result.format = match value.as_str() {
+                        "pot" => Format::Pot,
+                        "json" => Format::Json,
+                        _ => return Err(format!("unknown format {value:?}")),
+                    }
+                }
+                _ => return Err(format!("unknown argument `{name}`")),
+            }
+        }
+        Ok(Some(result))
+    }
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    // Like in the derive macro, the template directories are relative to the crate root, i.e. the
+    // directory of the configuration file.
+    let (root, config_file) = match &args.config {
+        Some(path) => {
+            let root = match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_owned(),
+                _ => PathBuf::from("."),
+            };
+            let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+                return Err(format!("invalid configuration file {}", path.display()));
+            };
+            (root, Some(file_name))
+        }
+        None => (PathBuf::from("."), None),
+    };
+    let source = read_config_file(Some(&root), config_file, None).map_err(|err| err.to_string())?;
+    let config = Config::new(&source, Some(&root), config_file, None, None)
+        .map_err(|err| err.to_string())?;
+
+    let syntax_name = args.syntax.as_deref().unwrap_or(config.default_syntax);
+    let Some(syntax) = config.syntaxes.get(syntax_name) else {
+        return Err(format!("syntax {syntax_name:?} is not defined"));
+    };
+
+    let mut files = Vec::new();
+    for dir in &config.dirs {
+        collect_files(dir, &mut files)
+            .map_err(|err| format!("unable to read template directory {}: {err}", dir.display()))?;
+    }
+
+    let catalog = Catalog::extract(&root, &files, syntax)?;
+    let output = match args.format {
+        Format::Pot => catalog.to_pot(),
+        Format::Json => {
+            let mut json = serde_json::to_string_pretty(&catalog.messages)
+                .map_err(|err| format!("unable to serialize catalog: {err}"))?;
+            json.push('\n');
+            json
+        }
+    };
+    match &args.output {
+        Some(path) => fs::write(path, output)
+            .map_err(|err| format!("unable to write {}: {err}", path.display())),
+        None => {
+            print!("{output}");
+            Ok(())
+        }
+    }
+}
+
+/// Recursively collects all files in `dir`, sorted by path
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct Catalog {
+    messages: Vec<Message>,
+}
+
+#[derive(Debug, Serialize)]
+struct Message {
+    id: String,
+    arguments: Vec<String>,
+    locations: Vec<Location>,
+}
+
+#[derive(Debug, Serialize)]
+struct Location {
+    file: String,
+    line: usize,
+    column: usize,
+}
+
+impl Catalog {
+    /// Collects the messages of all `files`, in the order of their first use
+    fn extract(root: &Path, files: &[PathBuf], syntax: &Syntax<'_>) -> Result<Self, String> {
+        let mut catalog = Self::default();
+        let mut indices = HashMap::new();
+        let mut errors = Vec::new();
+        for path in files {
+            // Skip files that cannot be templates, e.g. images.
+            let Ok(source) = fs::read_to_string(path) else {
+                continue;
+            };
+            let parsed = match Parsed::new(source.into(), Some(Arc::from(path.as_path())), syntax) {
+                Ok(parsed) => parsed,
+                Err(err) => {
+                    errors.push(err.to_string());
+                    continue;
+                }
+            };
+
+            let file = path.strip_prefix(root).unwrap_or(path);
+            let file = file
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            for translation in extract_translations(parsed.source(), parsed.nodes()) {
+                let index = *indices
+                    .entry(translation.message_id.clone().into_owned())
+                    .or_insert_with_key(|id| {
+                        catalog.messages.push(Message {
+                            id: id.clone(),
+                            arguments: Vec::new(),
+                            locations: Vec::new(),
+                        });
+                        catalog.messages.len() - 1
+                    });
+                let message = &mut catalog.messages[index];
+                for argument in translation.arguments {
+                    if !message.arguments.iter().any(|arg| arg == argument) {
+                        message.arguments.push(argument.to_owned());
+                    }
+                }
+                message.locations.push(Location {
+                    file: file.clone(),
+                    line: translation.line,
+                    column: translation.column,
+                });
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(catalog)
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    fn to_pot(&self) -> String {
+        let mut pot = String::from(
+            "# Translatable strings of the Askama templates, extracted by askama-extract\n\
+            msgid \"\"\n\
+            msgstr \"\"\n\
+            \"Content-Type: text/plain; charset=UTF-8\\n\"\n",
+        );
+        for message in &self.messages {
+            pot.push('\n');
+            if !message.arguments.is_empty() {
+                let _ = writeln!(pot, "#. arguments: {}", message.arguments.join(", "));
+            }
+            for location in &message.locations {
+                let _ = writeln!(pot, "#: {}:{}", location.file, location.line);
+            }
+            pot.push_str("msgid \"");
+            for c in message.id.chars() {
+                match c {
+                    '"' => pot.push_str("\\\""),
+                    '\\' => pot.push_str("\\\\"),
+                    '\n' => pot.push_str("\\n"),
+                    '\t' => pot.push_str("\\t"),
+                    c => pot.push(c),
+                }
+            }
+            pot.push_str("\"\nmsgstr \"\"\n");
+        }
+        pot
+    }
+}
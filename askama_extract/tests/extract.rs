@@ -0,0 +1,82 @@
+use std::path::Path;
+use std::process::Command;
+
+fn extract(format: &str) -> String {
+    let config = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixture/askama.toml");
+    let output = Command::new(env!("CARGO_BIN_EXE_askama-extract"))
+        .arg("--config")
+        .arg(config)
+        .args(["--format", format])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_pot() {
+    assert_eq!(
+        extract("pot"),
+        r#"# Translatable strings of the Askama templates, extracted by askama-extract
+msgid ""
+msgstr ""
+"Content-Type: text/plain; charset=UTF-8\n"
+
+#. arguments: name, title
+#: templates/index.html:1
+#: templates/pages/about.html:4
+msgid "welcome-user"
+msgstr ""
+
+#. arguments: count
+#: templates/index.html:4
+msgid "unread-emails"
+msgstr ""
+
+#: templates/pages/about.html:3
+msgid "about-title"
+msgstr ""
+
+#: templates/pages/about.html:5
+msgid "quoted-\"name\""
+msgstr ""
+"#
+    );
+}
+
+#[test]
+fn test_json() {
+    let json: serde_json::Value = serde_json::from_str(&extract("json")).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!([
+            {
+                "id": "welcome-user",
+                "arguments": ["name", "title"],
+                "locations": [
+                    { "file": "templates/index.html", "line": 1, "column": 8 },
+                    { "file": "templates/pages/about.html", "line": 4, "column": 6 },
+                ],
+            },
+            {
+                "id": "unread-emails",
+                "arguments": ["count"],
+                "locations": [{ "file": "templates/index.html", "line": 4, "column": 9 }],
+            },
+            {
+                "id": "about-title",
+                "arguments": [],
+                "locations": [{ "file": "templates/pages/about.html", "line": 3, "column": 6 }],
+            },
+            {
+                "id": "quoted-\"name\"",
+                "arguments": [],
+                "locations": [{ "file": "templates/pages/about.html", "line": 5, "column": 6 }],
+            },
+        ]),
+    );
+}
@@ -0,0 +1,867 @@
+//! Formatter for Askama templates
+//!
+//! [`format()`] parses a template, and prints it back with normalized spacing inside the
+//! `{{ … }}` and `{% … %}` tags, around the operators of expressions and in filter chains.
+//! The text of the template, comments and whitespace control markers like `{%-` are kept as they
+//! are. If [`Options::indent`] is set, block tags that start a line are indented according to
+//! their nesting, too.
+//!
+//! ```
+//! # use askama_fmt::{Options, format};
+//! # use parser::Syntax;
+//! let source = "{%if a+b>1%}\n  {{x|upper}}\n{%endif%}";
+//! let formatted = format(source, &Syntax::default(), &Options::default()).unwrap();
+//! assert_eq!(formatted, "{% if a + b > 1 %}\n  {{ x|upper }}\n{% endif %}");
+//! ```
+
+#![deny(elided_lifetimes_in_paths)]
+#![deny(unreachable_pub)]
+
+use parser::node::{
+    BlockDef, Call, Comment, CondTest, FilterBlock, If, Import, Include, IncludeTarget, Let, Lit,
+    Loop, Macro, Match, Raw, Whitespace, Ws,
+};
+use parser::{
+    Ast, CharLit, CharPrefix, Expr, Filter, Node, ParseError, Span, StrLit, Syntax, Target,
+    TyGenerics, WithSpan,
+};
+
+/// Settings of the formatter
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Options {
+    /// Width of one level of indentation of nested block tags, or `None` (the default) to keep
+    /// the indentation of all tags
+    pub indent: Option<usize>,
+}
+
+/// Formats the template `source`, which uses the delimiters of `syntax`
+///
+/// By default, the indentation of all tags is kept, because it is part of the rendered text,
+/// unless it is trimmed with whitespace control. If [`Options::indent`] is set, tags that start a
+/// line inside of a block are indented one level deeper than the line of the block's opening tag,
+/// and top-level tags keep their indentation.
+///
+/// The AST does not record every spelling, so a few things are normalized: `{% endblock name %}`
+/// and `{% endmacro name %}` lose their optional name, and macros and macro calls are always
+/// written with parentheses.
+pub fn format(source: &str, syntax: &Syntax<'_>, options: &Options) -> Result<String, ParseError> {
+    let ast = Ast::from_str(source, None, syntax)?;
+    let mut formatter = Formatter {
+        source,
+        syntax,
+        unit: options.indent.map(|width| " ".repeat(width)),
+        out: String::with_capacity(source.len()),
+        indent: None,
+    };
+    formatter.nodes(ast.nodes());
+    Ok(formatter.out)
+}
+
+struct Formatter<'a, 's> {
+    source: &'a str,
+    syntax: &'s Syntax<'s>,
+    /// One level of indentation, `None` if tags are not re-indented
+    unit: Option<String>,
+    out: String,
+    /// Indentation of the tags of the current block, `None` at the top level
+    indent: Option<String>,
+}
+
+impl<'a> Formatter<'a, '_> {
+    fn nodes(&mut self, nodes: &[Node<'_>]) {
+        for node in nodes {
+            self.node(node);
+        }
+    }
+
+    fn node(&mut self, node: &Node<'_>) {
+        match node {
+            Node::Lit(lit) => self.lit(lit),
+            Node::Comment(comment) => self.comment(comment),
+            Node::Expr(ws, expr) => {
+                self.out.push_str(self.syntax.expr_start);
+                self.ws_start(ws.0);
+                self.expr(expr);
+                self.ws_end(ws.1);
+                self.out.push_str(self.syntax.expr_end);
+            }
+            Node::Call(call) => self.call(call),
+            Node::Let(l) => self.r#let(l),
+            Node::If(i) => self.r#if(i),
+            Node::Match(m) => self.r#match(m),
+            Node::Loop(l) => self.r#loop(l),
+            Node::Extends(e) => {
+                // `Extends` does not keep its whitespace control, so it is read from the source.
+                let ws = self.tag_ws(self.tag_at(e.span()));
+                self.tag(ws, |f| {
+                    f.out.push_str("extends ");
+                    f.path_lit(e.path);
+                });
+            }
+            Node::BlockDef(b) => self.block_def(b),
+            Node::Include(i) => self.include(i),
+            Node::Import(i) => self.import(i),
+            Node::Macro(m) => self.r#macro(m),
+            Node::Raw(r) => self.raw(r),
+            Node::Break(ws) => self.tag(**ws, |f| f.out.push_str("break")),
+            Node::Continue(ws) => self.tag(**ws, |f| f.out.push_str("continue")),
+            Node::FilterBlock(filter) => self.filter_block(filter),
+        }
+    }
+
+    fn lit(&mut self, lit: &Lit<'_>) {
+        self.out.push_str(lit.lws);
+        self.out.push_str(lit.val);
+        self.out.push_str(lit.rws);
+    }
+
+    fn comment(&mut self, comment: &Comment<'_>) {
+        self.out.push_str(self.syntax.comment_start);
+        self.out.push_str(comment.content);
+        self.out.push_str(self.syntax.comment_end);
+    }
+
+    fn call(&mut self, call: &WithSpan<'_, Call<'_>>) {
+        let target = |f: &mut Self| {
+            if let Some(scope) = call.scope {
+                f.out.push_str(scope);
+                f.out.push_str("::");
+            }
+            f.out.push_str(call.name);
+            f.args(&call.args);
+        };
+        let Some(caller) = &call.caller else {
+            self.tag(call.ws, |f| {
+                f.out.push_str("call ");
+                target(f);
+            });
+            return;
+        };
+        let base = self.block_start(caller.ws1, |f| {
+            f.out.push_str("call(");
+            f.list(&caller.args, |f, arg| f.out.push_str(arg));
+            f.out.push_str(") ");
+            target(f);
+        });
+        self.body(&base, &caller.nodes);
+        self.block_tag(&base, caller.ws2, "endcall");
+    }
+
+    fn r#let(&mut self, l: &WithSpan<'_, Let<'_>>) {
+        let keyword = self.keyword_at(l.span());
+        let Some(block) = &l.block else {
+            self.tag(l.ws, |f| {
+                f.out.push_str(keyword);
+                f.out.push(' ');
+                f.target(&l.var);
+                if let Some(val) = &l.val {
+                    f.out.push_str(" = ");
+                    f.expr(val);
+                }
+            });
+            return;
+        };
+        let base = self.block_start(block.ws1, |f| {
+            f.out.push_str(keyword);
+            f.out.push(' ');
+            f.target(&l.var);
+            if let Some(filters) = &block.filters {
+                f.out.push('|');
+                f.filter(filters);
+            }
+        });
+        self.body(&base, &block.nodes);
+        self.block_tag(&base, block.ws2, &format!("end{keyword}"));
+    }
+
+    fn r#if(&mut self, i: &WithSpan<'_, If<'_>>) {
+        let mut base = String::new();
+        for (index, branch) in i.branches.iter().enumerate() {
+            if index == 0 {
+                base = self.block_start(branch.ws, |f| {
+                    if let Some(cond) = &branch.cond {
+                        f.out.push_str("if ");
+                        f.cond(cond);
+                    }
+                });
+            } else {
+                let keyword = self.keyword_at(branch.span());
+                self.reindent(Some(&base));
+                self.write_tag(branch.ws, |f| match &branch.cond {
+                    Some(cond) if keyword == "elif" => {
+                        f.out.push_str("elif ");
+                        f.cond(cond);
+                    }
+                    Some(cond) => {
+                        f.out.push_str("else if ");
+                        f.cond(cond);
+                    }
+                    None => f.out.push_str("else"),
+                });
+            }
+            self.body(&base, &branch.nodes);
+        }
+        self.block_tag(&base, i.ws, "endif");
+    }
+
+    fn r#match(&mut self, m: &WithSpan<'_, Match<'_>>) {
+        let base = self.block_start(m.ws1, |f| {
+            f.out.push_str("match ");
+            f.expr(&m.expr);
+        });
+        let arm_indent = self.deeper(&base);
+
+        // The whitespace between the `match` tag and the first arm, and after an `endwhen` tag,
+        // is not rendered, and the parser only drops it. The comments in it are kept.
+        let mut skipped = Some(self.after_tag(self.tag_at(m.span())));
+        for arm in &m.arms {
+            match skipped.take() {
+                Some(rest) => self.skipped(rest, &arm_indent),
+                None => self.reindent(Some(&arm_indent)),
+            }
+            let keyword = self.keyword_at(arm.span());
+            self.write_tag(arm.ws, |f| {
+                if keyword == "else" {
+                    f.out.push_str("else");
+                    return;
+                }
+                f.out.push_str("when ");
+                for (index, target) in arm.target.iter().enumerate() {
+                    if index > 0 {
+                        f.out.push_str(" | ");
+                    }
+                    f.target(target);
+                }
+                if let Some(guard) = &arm.guard {
+                    f.out.push_str(" if ");
+                    f.expr(guard);
+                }
+            });
+
+            // An `{% endwhen %}` tag is stored as an empty comment at the end of the arm.
+            let (nodes, endwhen) = match arm.nodes.split_last() {
+                Some((Node::Comment(comment), nodes)) if is_endwhen(comment) => {
+                    (nodes, Some(comment))
+                }
+                _ => (&arm.nodes[..], None),
+            };
+            self.body(&arm_indent, nodes);
+            if let Some(endwhen) = endwhen {
+                let tag = self.tag_at(endwhen.span());
+                let ws = self.tag_ws(tag);
+                self.block_tag(&arm_indent, ws, "endwhen");
+                skipped = Some(self.after_tag(tag));
+            }
+        }
+        match skipped {
+            Some(rest) => self.skipped(rest, &base),
+            None => self.reindent(Some(&base)),
+        }
+        self.write_tag(m.ws2, |f| f.out.push_str("endmatch"));
+    }
+
+    fn r#loop(&mut self, l: &WithSpan<'_, Loop<'_>>) {
+        let base = self.block_start(l.ws1, |f| {
+            f.out.push_str("for ");
+            f.target(&l.var);
+            f.out.push_str(" in ");
+            f.expr(&l.iter);
+            if let Some(cond) = &l.cond {
+                f.out.push_str(" if ");
+                f.expr(cond);
+            }
+            if l.recursive {
+                f.out.push_str(" recursive");
+            }
+        });
+        self.body(&base, &l.body);
+        // An empty `{% else %}` block without whitespace control does not change anything.
+        if !l.else_nodes.is_empty() || l.ws2.1.is_some() || l.ws3.0.is_some() {
+            self.block_tag(&base, l.ws2, "else");
+            self.body(&base, &l.else_nodes);
+            self.block_tag(&base, l.ws3, "endfor");
+        } else {
+            self.block_tag(&base, Ws(l.ws2.0, l.ws3.1), "endfor");
+        }
+    }
+
+    fn block_def(&mut self, b: &WithSpan<'_, BlockDef<'_>>) {
+        let base = self.block_start(b.ws1, |f| {
+            f.out.push_str("block ");
+            f.out.push_str(b.name);
+        });
+        self.body(&base, &b.nodes);
+        self.block_tag(&base, b.ws2, "endblock");
+    }
+
+    fn include(&mut self, i: &WithSpan<'_, Include<'_>>) {
+        self.tag(i.ws, |f| {
+            f.out.push_str("include ");
+            match &i.target {
+                IncludeTarget::Path(path) => f.path_lit(path),
+                IncludeTarget::Fallback(paths) => f.path_list(paths),
+                IncludeTarget::Dynamic(expr, paths) => {
+                    f.expr(expr);
+                    f.out.push_str(" from ");
+                    f.path_list(paths);
+                }
+            }
+            if i.ignore_missing {
+                f.out.push_str(" ignore missing");
+            }
+            if !i.with.is_empty() {
+                f.out.push_str(" with ");
+                f.list(&i.with, |f, (name, value)| {
+                    f.out.push_str(name);
+                    f.out.push_str(" = ");
+                    f.expr(value);
+                });
+            }
+            if i.only {
+                f.out.push_str(" only");
+            }
+        });
+    }
+
+    fn import(&mut self, i: &WithSpan<'_, Import<'_>>) {
+        self.tag(i.ws, |f| {
+            f.out.push_str("import ");
+            f.path_lit(i.path);
+            f.out.push_str(" as ");
+            f.out.push_str(i.scope);
+        });
+    }
+
+    fn r#macro(&mut self, m: &WithSpan<'_, Macro<'_>>) {
+        let base = self.block_start(m.ws1, |f| {
+            f.out.push_str("macro ");
+            f.out.push_str(m.name);
+            f.out.push('(');
+            f.list(&m.args, |f, (name, default)| {
+                f.out.push_str(name);
+                if let Some(default) = default {
+                    f.out.push_str(" = ");
+                    f.expr(default);
+                }
+            });
+            f.out.push(')');
+        });
+        self.body(&base, &m.nodes);
+        self.block_tag(&base, m.ws2, "endmacro");
+    }
+
+    fn raw(&mut self, r: &WithSpan<'_, Raw<'_>>) {
+        self.block_start(r.ws1, |f| f.out.push_str("raw"));
+        // The content of a raw block, including the indentation of `endraw`, is kept verbatim.
+        self.lit(&r.lit);
+        self.write_tag(r.ws2, |f| f.out.push_str("endraw"));
+    }
+
+    fn filter_block(&mut self, filter: &WithSpan<'_, FilterBlock<'_>>) {
+        let base = self.block_start(filter.ws1, |f| {
+            f.out.push_str("filter ");
+            f.filter(&filter.filters);
+        });
+        self.body(&base, &filter.nodes);
+        self.block_tag(&base, filter.ws2, "endfilter");
+    }
+
+    // ---- tags and indentation ----
+
+    /// Writes a tag that does not open a block
+    fn tag(&mut self, ws: Ws, content: impl FnOnce(&mut Self)) {
+        let indent = self.indent.clone();
+        self.reindent(indent.as_deref());
+        self.write_tag(ws, content);
+    }
+
+    /// Writes the opening tag of a block, and returns the indentation of the block's other tags
+    fn block_start(&mut self, ws: Ws, content: impl FnOnce(&mut Self)) -> String {
+        let indent = self.indent.clone();
+        self.reindent(indent.as_deref());
+        let base = self.line_indent().to_owned();
+        self.write_tag(ws, content);
+        base
+    }
+
+    /// Writes an intermediate or closing tag of the block with the indentation `base`
+    fn block_tag(&mut self, base: &str, ws: Ws, keyword: &str) {
+        self.reindent(Some(base));
+        self.write_tag(ws, |f| f.out.push_str(keyword));
+    }
+
+    /// Writes the content of the block with the indentation `base`
+    fn body(&mut self, base: &str, nodes: &[Node<'_>]) {
+        let outer = self.indent.take();
+        self.indent = Some(self.deeper(base));
+        self.nodes(nodes);
+        self.indent = outer;
+    }
+
+    fn write_tag(&mut self, ws: Ws, content: impl FnOnce(&mut Self)) {
+        self.out.push_str(self.syntax.block_start);
+        self.ws_start(ws.0);
+        content(self);
+        self.ws_end(ws.1);
+        self.out.push_str(self.syntax.block_end);
+    }
+
+    fn ws_start(&mut self, ws: Option<Whitespace>) {
+        if let Some(ws) = ws {
+            self.out.push(ws_char(ws));
+        }
+        self.out.push(' ');
+    }
+
+    fn ws_end(&mut self, ws: Option<Whitespace>) {
+        self.out.push(' ');
+        if let Some(ws) = ws {
+            self.out.push(ws_char(ws));
+        }
+    }
+
+    fn deeper(&self, base: &str) -> String {
+        match &self.unit {
+            Some(unit) => format!("{base}{unit}"),
+            None => base.to_owned(),
+        }
+    }
+
+    /// Replaces the indentation of the current line with `indent`, if the line is empty so far
+    fn reindent(&mut self, indent: Option<&str>) {
+        let (Some(indent), Some(_)) = (indent, &self.unit) else {
+            return;
+        };
+        let Some(line_start) = self.out.rfind('\n').map(|i| i + 1) else {
+            return;
+        };
+        if self.out[line_start..]
+            .bytes()
+            .all(|b| matches!(b, b' ' | b'\t'))
+        {
+            self.out.truncate(line_start);
+            self.out.push_str(indent);
+        }
+    }
+
+    /// The indentation of the current line
+    fn line_indent(&self) -> &str {
+        let line = &self.out[self.out.rfind('\n').map_or(0, |i| i + 1)..];
+        &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+    }
+
+    /// Writes the comments in the unrendered whitespace `rest`, until the next tag
+    fn skipped(&mut self, mut rest: &str, indent: &str) {
+        loop {
+            let trimmed = rest.trim_start();
+            let space = &rest[..rest.len() - trimmed.len()];
+            if self.unit.is_none() {
+                self.out.push_str(space);
+            } else if space.contains('\n') {
+                self.out.push('\n');
+                self.out.push_str(indent);
+            } else if !space.is_empty() {
+                self.out.push(' ');
+            }
+            let Some(len) = self.comment_len(trimmed) else {
+                return;
+            };
+            self.out.push_str(&trimmed[..len]);
+            rest = &trimmed[len..];
+        }
+    }
+
+    // ---- reading the source ----
+
+    /// The source of the tag at `span`, starting with its opening delimiter
+    fn tag_at(&self, span: Span<'_>) -> &'a str {
+        let offset = span.offset_from(self.source).unwrap_or_default();
+        let rest = self.source[offset..].trim_start();
+        if rest.starts_with(self.syntax.block_start) {
+            rest
+        } else {
+            // Most nodes start right after the delimiter.
+            &self.source[offset.saturating_sub(self.syntax.block_start.len())..]
+        }
+    }
+
+    /// The keyword of the tag at `span`, e.g. `let` or `set`
+    fn keyword_at(&self, span: Span<'_>) -> &'a str {
+        let tag = &self.tag_at(span)[self.syntax.block_start.len()..];
+        let tag = tag
+            .strip_prefix(['+', '-', '~'])
+            .unwrap_or(tag)
+            .trim_start();
+        let len = tag
+            .find(|c: char| !c.is_alphanumeric() && c != '_')
+            .unwrap_or(tag.len());
+        &tag[..len]
+    }
+
+    /// Offset of the closing delimiter of `tag`, skipping string literals
+    fn tag_end(&self, tag: &str) -> usize {
+        let bytes = tag.as_bytes();
+        let end = self.syntax.block_end.as_bytes();
+        let mut in_str = false;
+        let mut i = self.syntax.block_start.len();
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\\' if in_str => i += 1,
+                b'"' => in_str = !in_str,
+                _ if !in_str && bytes[i..].starts_with(end) => return i,
+                _ => {}
+            }
+            i += 1;
+        }
+        tag.len()
+    }
+
+    /// The source after `tag`
+    fn after_tag(&self, tag: &'a str) -> &'a str {
+        let end = self.tag_end(tag);
+        &tag[(end + self.syntax.block_end.len()).min(tag.len())..]
+    }
+
+    /// The whitespace control of `tag`
+    fn tag_ws(&self, tag: &str) -> Ws {
+        let inner = &tag[self.syntax.block_start.len()..self.tag_end(tag)];
+        Ws(
+            inner.chars().next().and_then(parse_ws),
+            inner.chars().next_back().and_then(parse_ws),
+        )
+    }
+
+    /// The length of the (possibly nested) comment at the start of `s`
+    fn comment_len(&self, s: &str) -> Option<usize> {
+        let (start, end) = (self.syntax.comment_start, self.syntax.comment_end);
+        if !s.starts_with(start) {
+            return None;
+        }
+        let mut depth = 0usize;
+        let mut i = start.len();
+        loop {
+            let next_start = s[i..].find(start).map(|pos| i + pos);
+            let next_end = s[i..].find(end).map(|pos| i + pos)?;
+            match next_start {
+                Some(pos) if pos < next_end => {
+                    depth += 1;
+                    i = pos + start.len();
+                }
+                _ if depth == 0 => return Some(next_end + end.len()),
+                _ => {
+                    depth -= 1;
+                    i = next_end + end.len();
+                }
+            }
+        }
+    }
+
+    // ---- expressions ----
+
+    fn expr(&mut self, expr: &Expr<'_>) {
+        match expr {
+            Expr::BoolLit(true) => self.out.push_str("true"),
+            Expr::BoolLit(false) => self.out.push_str("false"),
+            Expr::NumLit(num, _) => self.out.push_str(num),
+            Expr::StrLit(s) => self.str_lit(s),
+            Expr::CharLit(c) => self.char_lit(c),
+            Expr::Var(name) => self.out.push_str(name),
+            Expr::Path(path) => self.path(path),
+            Expr::Array(items) => {
+                self.out.push('[');
+                self.list(items, |f, item| f.expr(item));
+                self.out.push(']');
+            }
+            Expr::Attr(obj, attr) => {
+                self.expr(obj);
+                self.out.push('.');
+                self.out.push_str(attr.name);
+                self.generics(&attr.generics);
+            }
+            Expr::Index(obj, index) => {
+                self.expr(obj);
+                self.out.push('[');
+                self.expr(index);
+                self.out.push(']');
+            }
+            Expr::Filter(filter) => self.filter(filter),
+            Expr::As(expr, ty) => {
+                self.expr(expr);
+                self.out.push_str(" as ");
+                self.out.push_str(ty);
+            }
+            Expr::NamedArgument(name, value) => {
+                self.out.push_str(name);
+                self.out.push_str(" = ");
+                self.expr(value);
+            }
+            Expr::Unary(op, expr) => {
+                self.out.push_str(op);
+                self.expr(expr);
+            }
+            Expr::BinOp(op, left, right) => {
+                self.expr(left);
+                self.out.push(' ');
+                // Some operators have a different spelling in templates.
+                self.out.push_str(match *op {
+                    "|" => "bitor",
+                    "^" => "xor",
+                    "&" => "bitand",
+                    op => op,
+                });
+                self.out.push(' ');
+                self.expr(right);
+            }
+            Expr::Range(op, left, right) => {
+                if let Some(left) = left {
+                    self.expr(left);
+                }
+                self.out.push_str(op);
+                if let Some(right) = right {
+                    self.expr(right);
+                }
+            }
+            Expr::Group(expr) => {
+                self.out.push('(');
+                self.expr(expr);
+                self.out.push(')');
+            }
+            Expr::Tuple(items) => {
+                self.out.push('(');
+                self.list(items, |f, item| f.expr(item));
+                if items.len() == 1 {
+                    self.out.push(',');
+                }
+                self.out.push(')');
+            }
+            Expr::Call {
+                path,
+                args,
+                generics,
+            } => {
+                self.expr(path);
+                self.generics(generics);
+                self.args(args);
+            }
+            Expr::RustMacro(path, args) => {
+                self.path(path);
+                self.out.push_str("!(");
+                self.out.push_str(args);
+                self.out.push(')');
+            }
+            Expr::Try(expr) => {
+                self.expr(expr);
+                self.out.push('?');
+            }
+            Expr::FilterSource => {}
+            Expr::IsDefined(name) => {
+                self.out.push_str(name);
+                self.out.push_str(" is defined");
+            }
+            Expr::IsNotDefined(name) => {
+                self.out.push_str(name);
+                self.out.push_str(" is not defined");
+            }
+            Expr::IsNone(expr) => {
+                self.expr(expr);
+                self.out.push_str(" is none");
+            }
+            Expr::IsNotNone(expr) => {
+                self.expr(expr);
+                self.out.push_str(" is not none");
+            }
+            Expr::Concat(items) => {
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        self.out.push_str(" ~ ");
+                    }
+                    self.expr(item);
+                }
+            }
+            Expr::LetCond(cond) => self.cond(cond),
+        }
+    }
+
+    fn cond(&mut self, cond: &CondTest<'_>) {
+        if let Some(target) = &cond.target {
+            self.out.push_str("let ");
+            self.target(target);
+            self.out.push_str(" = ");
+        }
+        self.expr(&cond.expr);
+    }
+
+    /// Writes a filter chain, which has no input in filter blocks and block assignments
+    fn filter(&mut self, filter: &Filter<'_>) {
+        let Some((input, args)) = filter.arguments.split_first() else {
+            return;
+        };
+        if !matches!(**input, Expr::FilterSource) {
+            self.expr(input);
+            self.out.push('|');
+        }
+        self.out.push_str(filter.name);
+        self.generics(&filter.generics);
+        if !args.is_empty() {
+            self.args(args);
+        }
+    }
+
+    fn args(&mut self, args: &[WithSpan<'_, Expr<'_>>]) {
+        self.out.push('(');
+        self.list(args, |f, arg| f.expr(arg));
+        self.out.push(')');
+    }
+
+    fn generics(&mut self, generics: &[WithSpan<'_, TyGenerics<'_>>]) {
+        if !generics.is_empty() {
+            self.out.push_str("::<");
+            self.list(generics, |f, ty| f.ty(ty));
+            self.out.push('>');
+        }
+    }
+
+    fn ty(&mut self, ty: &TyGenerics<'_>) {
+        for _ in 0..ty.refs {
+            self.out.push('&');
+        }
+        self.path(&ty.path);
+        if !ty.args.is_empty() {
+            self.out.push('<');
+            self.list(&ty.args, |f, ty| f.ty(ty));
+            self.out.push('>');
+        }
+    }
+
+    fn target(&mut self, target: &Target<'_>) {
+        match target {
+            Target::Name(name) => self.out.push_str(name),
+            Target::Tuple(path, targets) => {
+                self.path(path);
+                self.out.push('(');
+                self.list(targets, |f, target| f.target(target));
+                if path.is_empty() && targets.len() == 1 {
+                    self.out.push(',');
+                }
+                self.out.push(')');
+            }
+            Target::Array(path, targets) => {
+                self.path(path);
+                self.out.push('[');
+                self.list(targets, |f, target| f.target(target));
+                if targets.len() == 1 {
+                    self.out.push(',');
+                }
+                self.out.push(']');
+            }
+            Target::Struct(path, fields) => {
+                self.path(path);
+                if fields.is_empty() {
+                    self.out.push_str(" {}");
+                    return;
+                }
+                self.out.push_str(" { ");
+                self.list(fields, |f, (name, target)| match target {
+                    Target::Rest(_) => f.out.push_str(".."),
+                    Target::Name(var) if var == name => f.out.push_str(name),
+                    target => {
+                        f.out.push_str(name);
+                        f.out.push_str(": ");
+                        f.target(target);
+                    }
+                });
+                self.out.push_str(" }");
+            }
+            Target::NumLit(num, _) => self.out.push_str(num),
+            Target::StrLit(s) => self.str_lit(s),
+            Target::CharLit(c) => self.char_lit(c),
+            Target::BoolLit(b) => self.out.push_str(b),
+            Target::Path(path) => self.path(path),
+            Target::OrChain(targets) => {
+                for (index, target) in targets.iter().enumerate() {
+                    if index > 0 {
+                        self.out.push_str(" or ");
+                    }
+                    self.target(target);
+                }
+            }
+            Target::Placeholder(_) => self.out.push('_'),
+            Target::Rest(name) => {
+                if let Some(name) = **name {
+                    self.out.push_str(name);
+                    self.out.push_str(" @ ");
+                }
+                self.out.push_str("..");
+            }
+        }
+    }
+
+    fn path(&mut self, path: &[&str]) {
+        for (index, part) in path.iter().enumerate() {
+            if index > 0 {
+                self.out.push_str("::");
+            }
+            self.out.push_str(part);
+        }
+    }
+
+    fn str_lit(&mut self, s: &StrLit<'_>) {
+        if let Some(prefix) = s.prefix {
+            self.out.push(prefix.to_char());
+        }
+        self.path_lit(s.content);
+    }
+
+    fn char_lit(&mut self, c: &CharLit<'_>) {
+        if let Some(CharPrefix::Binary) = c.prefix {
+            self.out.push('b');
+        }
+        self.out.push('\'');
+        self.out.push_str(c.content);
+        self.out.push('\'');
+    }
+
+    /// Writes a string literal, whose content is still escaped
+    fn path_lit(&mut self, content: &str) {
+        self.out.push('"');
+        self.out.push_str(content);
+        self.out.push('"');
+    }
+
+    fn path_list(&mut self, paths: &[&str]) {
+        self.out.push('[');
+        self.list(paths, |f, path| f.path_lit(path));
+        self.out.push(']');
+    }
+
+    fn list<T>(&mut self, items: &[T], mut each: impl FnMut(&mut Self, &T)) {
+        for (index, item) in items.iter().enumerate() {
+            if index > 0 {
+                self.out.push_str(", ");
+            }
+            each(self, item);
+        }
+    }
+}
+
+/// The parser stores `{% endwhen %}` as an empty comment that suppresses the following whitespace
+fn is_endwhen(comment: &Comment<'_>) -> bool {
+    comment.content.is_empty() && comment.ws.1 == Some(Whitespace::Suppress)
+}
+
+fn ws_char(ws: Whitespace) -> char {
+    match ws {
+        Whitespace::Preserve => '+',
+        Whitespace::Suppress => '-',
+        Whitespace::Minimize => '~',
+    }
+}
+
+fn parse_ws(c: char) -> Option<Whitespace> {
+    match c {
+        '+' => Some(Whitespace::Preserve),
+        '-' => Some(Whitespace::Suppress),
+        '~' => Some(Whitespace::Minimize),
+        _ => None,
+    }
+}
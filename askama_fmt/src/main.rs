@@ -0,0 +1,208 @@
+//! `askama-fmt`: format Askama templates
+//!
+//! The tool reads the template directories and syntaxes from `askama.toml`, with the same code
+//! that `askama_derive` uses, and formats every template file in place. With `--check`, it only
+//! lists the files that are not formatted.
+
+#![deny(elided_lifetimes_in_paths)]
+#![deny(unreachable_pub)]
+
+// Not everything of the configuration is needed by the formatter.
+#[allow(dead_code)]
+#[path = "../derive/common.rs"]
+mod common;
+#[allow(dead_code)]
+#[path = "../derive/config.rs"]
+mod config;
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::{env, fs, io};
+
+use askama_fmt::{Options, format};
+
+use crate::config::{Config, read_config_file};
+
+const USAGE: &str = "\
+Usage: askama-fmt [OPTIONS] [PATHS]...
+
+Format Askama templates. Without PATHS, all files in the configured template directories are
+formatted.
+
+Options:
+  --config <PATH>      configuration file [default: askama.toml, if it exists]
+  --syntax <NAME>      syntax of the templates [default: the configured default syntax]
+  --indent <WIDTH>     spaces per nesting level of block tags, or `keep` [default: keep]
+  --check              do not write files, fail if any file is not formatted
+  --help               print this help
+";
+
+fn main() -> ExitCode {
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Args {
+    config: Option<PathBuf>,
+    syntax: Option<String>,
+    options: Options,
+    check: bool,
+    paths: Vec<PathBuf>,
+}
+
+impl Args {
+    /// Returns `None` if the help was requested
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut result = Self::default();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with('-') {
+                result.paths.push(arg.into());
+                continue;
+            }
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name.to_owned(), Some(value.to_owned())),
+                None => (arg, None),
+            };
+            match name.as_str() {
+                "--help" | "-h" => return Ok(None),
+                "--check" => {
+                    result.check = true;
+                    continue;
+                }
+                _ => {}
+            }
+            let Some(value) = value.or_else(|| args.next()) else {
+                return Err(format!("missing value for argument `{name}`"));
+            };
+            match name.as_str() {
+                "--config" => result.config = Some(value.into()),
+                "--syntax" => result.syntax = Some(value),
+                "--indent" => {
+                    result.options.indent = match value.as_str() {
+                        "keep" => None,
+                        width => Some(
+                            width
+                                .parse()
+                                .map_err(|_| format!("invalid indentation width {value:?}"))?,
+                        ),
+                    }
+                }
+                _ => return Err(format!("unknown argument `{name}`")),
+            }
+        }
+        Ok(Some(result))
+    }
+}
+
+/// Returns `false` if `--check` found unformatted files
+fn run(args: &Args) -> Result<bool, String> {
+    // Like in the derive macro, the template directories are relative to the crate root, i.e. the
+    // directory of the configuration file.
+    let (root, config_file) = match &args.config {
+        Some(path) => {
+            let root = match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_owned(),
+                _ => PathBuf::from("."),
+            };
+            let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+                return Err(format!("invalid configuration file {}", path.display()));
+            };
+            (root, Some(file_name))
+        }
+        None => (PathBuf::from("."), None),
+    };
+    let source = read_config_file(Some(&root), config_file, None).map_err(|err| err.to_string())?;
+    let config = Config::new(&source, Some(&root), config_file, None, None)
+        .map_err(|err| err.to_string())?;
+
+    let syntax_name = args.syntax.as_deref().unwrap_or(config.default_syntax);
+    let Some(syntax) = config.syntaxes.get(syntax_name) else {
+        return Err(format!("syntax {syntax_name:?} is not defined"));
+    };
+
+    let mut files = Vec::new();
+    if args.paths.is_empty() {
+        for dir in &config.dirs {
+            collect_files(dir, &mut files).map_err(|err| {
+                format!("unable to read template directory {}: {err}", dir.display())
+            })?;
+        }
+    } else {
+        for path in &args.paths {
+            if path.is_dir() {
+                collect_files(path, &mut files)
+                    .map_err(|err| format!("unable to read {}: {err}", path.display()))?;
+            } else {
+                files.push(path.clone());
+            }
+        }
+    }
+
+    let mut formatted = true;
+    let mut errors = Vec::new();
+    for path in &files {
+        // Skip files that cannot be templates, e.g. images.
+        let Ok(source) = fs::read_to_string(path) else {
+            continue;
+        };
+        let output = match format(&source, syntax, &args.options) {
+            Ok(output) => output,
+            Err(mut err) => {
+                err.file_path = Some(Arc::from(path.as_path()));
+                errors.push(err.to_string());
+                continue;
+            }
+        };
+        if output == source {
+            continue;
+        }
+        if args.check {
+            println!("{}", path.display());
+            formatted = false;
+        } else {
+            fs::write(path, output)
+                .map_err(|err| format!("unable to write {}: {err}", path.display()))?;
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(formatted)
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+/// Recursively collects all files in `dir`, sorted by path
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use askama_fmt::{Options, format};
+use parser::{Syntax, SyntaxBuilder};
+
+#[track_caller]
+fn assert_fmt(source: &str, expected: &str) {
+    assert_fmt_with(&Options::default(), source, expected);
+}
+
+#[track_caller]
+fn assert_fmt_with(options: &Options, source: &str, expected: &str) {
+    let formatted = format(source, &Syntax::default(), options).unwrap();
+    assert_eq!(formatted, expected);
+    // Formatting a formatted template must not change it.
+    assert_eq!(
+        format(&formatted, &Syntax::default(), options).unwrap(),
+        formatted
+    );
+}
+
+#[test]
+fn test_expressions() {
+    assert_fmt("{{a+b*c}}", "{{ a + b * c }}");
+    assert_fmt("{{  (a||b)&&!c  }}", "{{ (a || b) && !c }}");
+    assert_fmt("{{a bitor b}}{{a xor b}}", "{{ a bitor b }}{{ a xor b }}");
+    assert_fmt("{{x|upper|truncate( 10 )}}", "{{ x|upper|truncate(10) }}");
+    assert_fmt("{{ x | trim }}{{x|escape()}}", "{{ x|trim }}{{ x|escape }}");
+    assert_fmt("{{f( a,b )[0].c::<u8>()?}}", "{{ f(a, b)[0].c::<u8>()? }}");
+    assert_fmt(
+        "{{[1,2]}}{{(1,)}}{{1..=2}}",
+        "{{ [1, 2] }}{{ (1,) }}{{ 1..=2 }}",
+    );
+    assert_fmt("{{a ~ 'b' ~ b\"c\"}}", "{{ a ~ 'b' ~ b\"c\" }}");
+    assert_fmt(
+        "{{x as i32}}{{format!(\"{}\",  x)}}",
+        "{{ x as i32 }}{{ format!(\"{}\",  x) }}",
+    );
+    assert_fmt(
+        "{{x  is   not none}}{{x.y is none}}",
+        "{{ x is not none }}{{ x.y is none }}",
+    );
+}
+
+#[test]
+fn test_indentation() {
+    // By default, the indentation is kept, because it is part of the rendered text.
+    assert_fmt(
+        "{%if a%}\n  {%let b=1%}\n{%endif%}",
+        "{% if a %}\n  {% let b = 1 %}\n{% endif %}",
+    );
+    assert_fmt(
+        "{%match x%}\n{%when Some(y)%}\n{{y}}\n{%endmatch%}",
+        "{% match x %}\n{% when Some(y) %}\n{{ y }}\n{% endmatch %}",
+    );
+
+    let indent = Options { indent: Some(4) };
+    assert_fmt_with(
+        &indent,
+        "<ul>\n{%for x in xs if x>1%}\n<li>{{x}}</li>\n{%if loop.last%}\n  {%break%}\n{%elif \
+         x==2%}\n{%else if x==3%}\n{%else%}\n{%endif%}\n{%endfor%}\n</ul>",
+        "<ul>\n{% for x in xs if x > 1 %}\n<li>{{ x }}</li>\n    {% if loop.last %}\n        {% \
+         break %}\n    {% elif x == 2 %}\n    {% else if x == 3 %}\n    {% else %}\n    {% endif \
+         %}\n{% endfor %}\n</ul>",
+    );
+    assert_fmt_with(
+        &indent,
+        "  {%block a%}\n{%call m()%}\n{%endblock a%}",
+        "  {% block a %}\n      {% call m() %}\n  {% endblock %}",
+    );
+    assert_fmt_with(
+        &indent,
+        "{%match x%}\n{%when Some(y) if y>0%}\n{{y}}\n{%when None%}\n{%endmatch%}",
+        "{% match x %}\n    {% when Some(y) if y > 0 %}\n{{ y }}\n    {% when None %}\n{% endmatch \
+         %}",
+    );
+}
+
+#[test]
+fn test_tags() {
+    assert_fmt(
+        "{%extends \"base.html\"%}{%import \"m.html\" as m%}",
+        "{% extends \"base.html\" %}{% import \"m.html\" as m %}",
+    );
+    assert_fmt(
+        "{%include \"a.html\" with a=1,b=x only%}{%include [\"b\",\"c\"] ignore missing%}",
+        "{% include \"a.html\" with a = 1, b = x only %}{% include [\"b\", \"c\"] ignore missing \
+         %}",
+    );
+    assert_fmt(
+        "{%macro m(a,b=2)%}{{a}}{%endmacro m%}{%call(x) m(1)%}{{x}}{%endcall%}",
+        "{% macro m(a, b = 2) %}{{ a }}{% endmacro %}{% call(x) m(1) %}{{ x }}{% endcall %}",
+    );
+    assert_fmt(
+        "{%set x=1%}{%let (a,)=b%}{%let S{a,b:c,..}=s%}{%set y|upper%}y{%endset%}",
+        "{% set x = 1 %}{% let (a,) = b %}{% let S { a, b: c, .. } = s %}{% set y|upper %}y{% \
+         endset %}",
+    );
+    assert_fmt(
+        "{%filter lower|trim%}A{%endfilter%}{%if let Some(x)=y%}{%endif%}",
+        "{% filter lower|trim %}A{% endfilter %}{% if let Some(x) = y %}{% endif %}",
+    );
+    assert_fmt(
+        "{%match x%}{%when 1|2%}{%else%}{%endmatch%}",
+        "{% match x %}{% when 1 | 2 %}{% else %}{% endmatch %}",
+    );
+}
+
+#[test]
+fn test_preserve() {
+    // Text, comments and the content of raw blocks are kept verbatim.
+    assert_fmt(
+        "  a  {#  b   #}\n {#- {# c #} -#}{%raw%} {%x%} {{y}} {%endraw%}",
+        "  a  {#  b   #}\n {#- {# c #} -#}{% raw %} {%x%} {{y}} {% endraw %}",
+    );
+    // Whitespace control is kept for all tags.
+    assert_fmt(
+        "{%-if a+%}{{~b-}}{%~else-%}{%+endif~%}{%-extends \"a\"+%}",
+        "{%- if a +%}{{~ b -}}{%~ else -%}{%+ endif ~%}{%- extends \"a\" +%}",
+    );
+    assert_fmt(
+        "{%-for x in y~%}{%+else-%}{%~endfor%}",
+        "{%- for x in y ~%}{%+ else -%}{%~ endfor %}",
+    );
+    // Comments and `endwhen` tags in the unrendered parts of `match` blocks are kept, too.
+    assert_fmt_with(
+        &Options { indent: Some(4) },
+        "{%match x%} {#a#}\n{%when 1%}1{%-endwhen%}{#b#}\n{%endmatch%}",
+        "{% match x %} {#a#}\n    {% when 1 %}1{%- endwhen %}{#b#}\n{% endmatch %}",
+    );
+}
+
+#[test]
+fn test_custom_syntax() {
+    let syntax = SyntaxBuilder {
+        name: "custom",
+        block_start: Some("<%"),
+        block_end: Some("%>"),
+        expr_start: Some("[["),
+        expr_end: Some("]]"),
+        ..SyntaxBuilder::default()
+    }
+    .to_syntax()
+    .unwrap();
+    assert_eq!(
+        format("<%if x%>[[x+1]]<%-endif%>", &syntax, &Options::default()).unwrap(),
+        "<% if x %>[[ x + 1 ]]<%- endif %>",
+    );
+}
+
+#[test]
+fn test_parse_error() {
+    assert!(format("{% if x %}", &Syntax::default(), &Options::default()).is_err());
+}
+
+fn fmt(args: &[&str]) -> Output {
+    let config = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixture/askama.toml");
+    Command::new(env!("CARGO_BIN_EXE_askama-fmt"))
+        .arg("--config")
+        .arg(config)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_check() {
+    let templates = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixture/templates");
+    let before = fs::read_to_string(templates.join("unformatted.html")).unwrap();
+
+    let output = fmt(&["--check"]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 1, "{stdout}");
+    assert!(stdout.trim_end().ends_with("unformatted.html"), "{stdout}");
+    assert_eq!(
+        fs::read_to_string(templates.join("unformatted.html")).unwrap(),
+        before
+    );
+
+    let output = fmt(&[
+        "--check",
+        templates.join("formatted.html").to_str().unwrap(),
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
+
+#[test]
+fn test_write() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("askama-fmt");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("unformatted.html");
+    fs::copy(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixture/templates/unformatted.html"),
+        &path,
+    )
+    .unwrap();
+
+    let output = fmt(&[path.to_str().unwrap()]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "{% if user.is_admin && !hidden %}\n<p>[[ user.name|truncate(10) ]]</p>\n{%- endif %}\n",
+    );
+}
@@ -0,0 +1,38 @@
+//! `askama-lsp`: a language server for Askama templates
+//!
+//! The server talks JSON-RPC over stdin and stdout. It reads the template directories and the
+//! default syntax from the `askama.toml` of the crate that contains a template, with the same
+//! code that `askama_derive` uses.
+
+#![deny(elided_lifetimes_in_paths)]
+#![deny(unreachable_pub)]
+
+// Not everything of the configuration is needed by the language server.
+#[allow(dead_code)]
+#[path = "../derive/common.rs"]
+mod common;
+#[allow(dead_code)]
+#[path = "../derive/config.rs"]
+mod config;
+// Only the names of all built-in filters are needed.
+#[allow(dead_code)]
+#[path = "../derive/generator/filters.rs"]
+mod filters;
+mod lsp;
+mod server;
+
+use std::io::{self, BufReader};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let mut reader = BufReader::new(io::stdin().lock());
+    let mut writer = io::stdout().lock();
+    match server::run(&mut reader, &mut writer) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
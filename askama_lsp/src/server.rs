@@ -0,0 +1,388 @@
+//! Handling of the requests and notifications of the client
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use parser::cst::{Block, Cst, Element, Tag, TokenKind};
+use parser::{Ast, ParseError, Syntax};
+use serde_json::{Value, json};
+
+use crate::common::CompileError;
+use crate::config::{Config, read_config_file};
+use crate::{filters, lsp};
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_REQUEST: i64 = -32600;
+
+const SEVERITY_ERROR: u64 = 1;
+const SYMBOL_FUNCTION: u64 = 12;
+const SYMBOL_MODULE: u64 = 2;
+const COMPLETION_FUNCTION: u64 = 3;
+
+/// Serves the client until it sends `exit`
+///
+/// Returns `true` if the client sent `shutdown` before, as the protocol requires.
+pub(crate) fn run(reader: &mut impl BufRead, writer: &mut impl Write) -> io::Result<bool> {
+    let mut server = Server::default();
+    while let Some(message) = lsp::read_message(reader)? {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let Some(id) = message.get("id") else {
+            if method == "exit" {
+                return Ok(server.shut_down);
+            }
+            for notification in server.notification(method, params) {
+                lsp::write_message(writer, &notification)?;
+            }
+            continue;
+        };
+        let response = match server.request(method, params) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+        lsp::write_message(writer, &response)?;
+    }
+    Ok(false)
+}
+
+#[derive(Default)]
+struct Server {
+    /// The root of the workspace, used for templates outside of any crate
+    root: Option<PathBuf>,
+    /// The text of the open documents, by URI
+    documents: HashMap<String, String>,
+    shut_down: bool,
+}
+
+impl Server {
+    fn request(&mut self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        if self.shut_down {
+            return Err((INVALID_REQUEST, "the server was shut down".into()));
+        }
+        match method {
+            "initialize" => Ok(self.initialize(params)),
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(Value::Null)
+            }
+            "textDocument/definition" => Ok(self.definition(params).unwrap_or_default()),
+            "textDocument/documentSymbol" => Ok(self.document_symbols(params).unwrap_or_default()),
+            "textDocument/completion" => Ok(self.completion(params).unwrap_or_default()),
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method `{method}`"))),
+        }
+    }
+
+    /// Handles a notification, and returns the notifications to send back
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.to_owned(), text.to_owned());
+                vec![self.diagnostics(uri)]
+            }
+            "textDocument/didChange" => {
+                // The server asks for full syncs, so the last change contains the whole text.
+                let changes = params["contentChanges"].as_array();
+                let Some(text) = changes.and_then(|c| c.last()?["text"].as_str()) else {
+                    return Vec::new();
+                };
+                self.documents.insert(uri.to_owned(), text.to_owned());
+                vec![self.diagnostics(uri)]
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                vec![publish_diagnostics(uri, Vec::new())]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn initialize(&mut self, params: &Value) -> Value {
+        let root_uri = match params["rootUri"].as_str() {
+            Some(uri) => Some(uri),
+            None => params["workspaceFolders"][0]["uri"].as_str(),
+        };
+        self.root = match root_uri {
+            Some(uri) => lsp::uri_to_path(uri),
+            None => params["rootPath"].as_str().map(PathBuf::from),
+        };
+        json!({
+            "capabilities": {
+                "textDocumentSync": 1,
+                "definitionProvider": true,
+                "documentSymbolProvider": true,
+                "completionProvider": { "triggerCharacters": ["|"] },
+            },
+            "serverInfo": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+        })
+    }
+
+    fn diagnostics(&self, uri: &str) -> Value {
+        let text = &self.documents[uri];
+        let diagnostics = match self.config(uri) {
+            Ok((config, _)) => {
+                let syntax = syntax(config);
+                match Ast::from_str_recovering(text, None, syntax) {
+                    Ok(_) => Vec::new(),
+                    Err(errors) => {
+                        let cst = Cst::new(text, syntax);
+                        errors
+                            .iter()
+                            .map(|err| parse_error_diagnostic(&cst, err))
+                            .collect()
+                    }
+                }
+            }
+            Err(err) => vec![diagnostic(lsp::range(text, 0, 0), &err.msg)],
+        };
+        publish_diagnostics(uri, diagnostics)
+    }
+
+    /// Resolves the template paths of `extends`, `include` and `import` tags
+    fn definition(&self, params: &Value) -> Option<Value> {
+        let (uri, text, offset) = self.document_position(params)?;
+        let (config, path) = self.config(uri).ok()?;
+        let cst = Cst::new(text, syntax(config));
+        let tag = tags(cst.elements())
+            .into_iter()
+            .find(|tag| contains(tag, offset, text.len()))?;
+        if !matches!(tag.keyword(), Some("extends" | "include" | "import")) {
+            return None;
+        }
+        let strings = tag
+            .tokens
+            .iter()
+            .filter(|token| token.kind == TokenKind::Str);
+        let under_cursor = strings
+            .clone()
+            .find(|token| (token.offset..=token.offset + token.text.len()).contains(&offset));
+        let locations = match under_cursor {
+            Some(token) => vec![token],
+            None => strings.collect(),
+        }
+        .into_iter()
+        .filter_map(|token| {
+            let name = token.text.strip_prefix('"')?.strip_suffix('"')?;
+            let target = config.find_template(name, Some(&path), None).ok()?;
+            Some(json!({
+                "uri": lsp::path_to_uri(&target),
+                "range": lsp::range("", 0, 0),
+            }))
+        })
+        .collect();
+        Some(Value::Array(locations))
+    }
+
+    /// Lists the blocks and macros of a template
+    fn document_symbols(&self, params: &Value) -> Option<Value> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let text = self.documents.get(uri)?;
+        let (config, _) = self.config(uri).ok()?;
+        let cst = Cst::new(text, syntax(config));
+        Some(Value::Array(symbols(text, cst.elements())))
+    }
+
+    /// Offers the built-in filters after a `|`, and after the keyword of a `{% filter %}` block
+    fn completion(&self, params: &Value) -> Option<Value> {
+        let (uri, text, offset) = self.document_position(params)?;
+        let (config, _) = self.config(uri).ok()?;
+        let cst = Cst::new(text, syntax(config));
+        let tag = tags(cst.elements())
+            .into_iter()
+            .find(|tag| contains(tag, offset, text.len()))?;
+
+        let before = &text[tag.tokens[0].offset..offset];
+        let before = before.trim_end_matches(|c: char| c.is_alphanumeric() || c == '_');
+        let trimmed = before.trim_end();
+        let after_pipe = trimmed.ends_with('|') && !trimmed.ends_with("||");
+        let after_keyword = tag.keyword() == Some("filter")
+            && trimmed.ends_with("filter")
+            && trimmed.len() < before.len();
+        if !after_pipe && !after_keyword {
+            return None;
+        }
+
+        let items = filters::BUILTIN_FILTERS
+            .iter()
+            .map(|name| {
+                json!({
+                    "label": name,
+                    "kind": COMPLETION_FUNCTION,
+                    "detail": "built-in filter",
+                })
+            })
+            .collect();
+        Some(Value::Array(items))
+    }
+
+    /// The URI, the text and the byte offset of a `TextDocumentPositionParams`
+    fn document_position<'a>(&'a self, params: &'a Value) -> Option<(&'a str, &'a str, usize)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let text = self.documents.get(uri)?;
+        let offset = lsp::offset(text, &params["position"])?;
+        Some((uri, text, offset))
+    }
+
+    /// Loads the configuration of the crate that contains the document
+    ///
+    /// Like the derive macro, the template directories are relative to the crate root, which
+    /// is the closest directory with an `askama.toml` or a `Cargo.toml` file.
+    fn config(&self, uri: &str) -> Result<(&'static Config, PathBuf), CompileError> {
+        let Some(path) = lsp::uri_to_path(uri) else {
+            return Err(CompileError::no_file_info(
+                format_args!("unsupported URI `{uri}`"),
+                None,
+            ));
+        };
+        let root = find_ancestor(&path, "askama.toml")
+            .or_else(|| find_ancestor(&path, "Cargo.toml"))
+            .or_else(|| self.root.clone())
+            .or_else(|| path.parent().map(Path::to_path_buf))
+            .unwrap_or_default();
+
+        let source = read_config_file(Some(&root), None, None)?;
+        let config = Config::new(&source, Some(&root), None, None, None)?;
+        Ok((config, path))
+    }
+}
+
+fn syntax(config: &Config) -> &Syntax<'static> {
+    &config.syntaxes[config.default_syntax]
+}
+
+fn find_ancestor(path: &Path, file_name: &str) -> Option<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .find(|dir| dir.join(file_name).is_file())
+        .map(Path::to_path_buf)
+}
+
+fn parse_error_diagnostic(cst: &Cst<'_>, err: &ParseError) -> Value {
+    // Underline the token at the position of the error.
+    let (start, end) = match cst.token_at(err.offset) {
+        Some(token) if token.kind != TokenKind::Text => {
+            (token.offset, token.offset + token.text.len())
+        }
+        _ => (err.offset, err.offset),
+    };
+    let message = err
+        .message
+        .as_deref()
+        .unwrap_or("failed to parse template source");
+    diagnostic(lsp::range(cst.source(), start, end), message)
+}
+
+fn diagnostic(range: Value, message: &str) -> Value {
+    json!({
+        "range": range,
+        "severity": SEVERITY_ERROR,
+        "source": "askama",
+        "message": message,
+    })
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+/// All tags of `elements`, in source order
+fn tags<'t, 'a>(elements: &'t [Element<'a>]) -> Vec<&'t Tag<'a>> {
+    fn collect<'t, 'a>(elements: &'t [Element<'a>], tags: &mut Vec<&'t Tag<'a>>) {
+        for element in elements {
+            match element {
+                Element::Token(_) => {}
+                Element::Tag(tag) => tags.push(tag),
+                Element::Block(block) => {
+                    for section in &block.sections {
+                        tags.push(&section.tag);
+                        collect(&section.children, tags);
+                    }
+                    tags.extend(&block.end);
+                }
+            }
+        }
+    }
+
+    let mut tags = Vec::new();
+    collect(elements, &mut tags);
+    tags
+}
+
+/// Returns `true` if `offset` is after the opening delimiter of `tag`, and not after its end
+fn contains(tag: &Tag<'_>, offset: usize, text_len: usize) -> bool {
+    let (Some(first), Some(last)) = (tag.tokens.first(), tag.tokens.last()) else {
+        return false;
+    };
+    let end = match tag.is_closed() {
+        true => last.offset,
+        false => text_len,
+    };
+    first.offset + first.text.len() <= offset && offset <= end
+}
+
+fn symbols(text: &str, elements: &[Element<'_>]) -> Vec<Value> {
+    let mut found = Vec::new();
+    for element in elements {
+        let Element::Block(block) = element else {
+            continue;
+        };
+        let children = block
+            .sections
+            .iter()
+            .flat_map(|section| symbols(text, &section.children))
+            .collect::<Vec<_>>();
+        match block_symbol(text, block) {
+            Some(mut symbol) => {
+                symbol["children"] = Value::Array(children);
+                found.push(symbol);
+            }
+            None => found.extend(children),
+        }
+    }
+    found
+}
+
+/// The symbol of a `{% block %}` or a `{% macro %}`
+fn block_symbol(text: &str, block: &Block<'_>) -> Option<Value> {
+    let tag = &block.sections.first()?.tag;
+    let kind = match tag.keyword()? {
+        "block" => SYMBOL_MODULE,
+        "macro" => SYMBOL_FUNCTION,
+        _ => return None,
+    };
+    let name = tag
+        .tokens
+        .iter()
+        .filter(|token| token.kind == TokenKind::Ident)
+        .nth(1)?;
+
+    let last_tag = match &block.end {
+        Some(end) => end,
+        None => &block.sections.last()?.tag,
+    };
+    let start = tag.tokens.first()?.offset;
+    let end = match (&block.end, last_tag.tokens.last()) {
+        (Some(_), Some(token)) => token.offset + token.text.len(),
+        // An unclosed block extends to the end of the template.
+        _ => text.len(),
+    };
+    Some(json!({
+        "name": name.text,
+        "kind": kind,
+        "range": lsp::range(text, start, end),
+        "selectionRange": lsp::range(text, name.offset, name.offset + name.text.len()),
+    }))
+}
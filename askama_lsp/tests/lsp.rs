@@ -0,0 +1,207 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use serde_json::{Value, json};
+
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_askama-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut client = Self {
+            child,
+            stdin,
+            stdout,
+            next_id: 1,
+        };
+        let result = client.request("initialize", json!({ "rootUri": null }));
+        assert_eq!(result["capabilities"]["definitionProvider"], true);
+        client.notify("initialized", json!({}));
+        client
+    }
+
+    fn send(&mut self, message: &Value) {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            self.stdout.read_line(&mut line).unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("Content-Length:") {
+                length = value.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        let response = self.receive();
+        assert_eq!(response["id"], id);
+        response["result"].clone()
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(&json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    /// Opens a document, and returns the published diagnostics
+    fn open(&mut self, uri: &str, text: &str) -> Value {
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": uri, "languageId": "html", "version": 1, "text": text },
+            }),
+        );
+        let notification = self.receive();
+        assert_eq!(notification["method"], "textDocument/publishDiagnostics");
+        notification["params"]["diagnostics"].clone()
+    }
+}
+
+fn uri(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixture")
+        .join("templates")
+        .join(name);
+    format!("file://{}", path.display())
+}
+
+fn position(uri: &str, line: u64, character: u64) -> Value {
+    json!({
+        "textDocument": { "uri": uri },
+        "position": { "line": line, "character": character },
+    })
+}
+
+#[test]
+fn test_diagnostics() {
+    let mut client = Client::start();
+    let uri = uri("page.html");
+
+    let diagnostics = client.open(&uri, "<p>\n{{ a + }}</p>\n{{ b( }}");
+    assert_eq!(diagnostics.as_array().unwrap().len(), 2);
+    assert_eq!(diagnostics[0]["severity"], 1);
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], 1);
+    assert_eq!(diagnostics[1]["range"]["start"]["line"], 2);
+
+    client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": uri, "version": 2 },
+            "contentChanges": [{ "text": "<p>{{ a + b }}</p>" }],
+        }),
+    );
+    let notification = client.receive();
+    assert_eq!(notification["params"]["diagnostics"], json!([]));
+}
+
+#[test]
+fn test_definition() {
+    let mut client = Client::start();
+    let uri = uri("page.html");
+    client.open(
+        &uri,
+        "{% extends \"base.html\" %}\n{% include \"missing.html\" %}",
+    );
+
+    let locations = client.request("textDocument/definition", position(&uri, 0, 14));
+    let locations = locations.as_array().unwrap();
+    assert_eq!(locations.len(), 1);
+    let target = locations[0]["uri"].as_str().unwrap();
+    assert!(
+        target.ends_with("/tests/fixture/templates/base.html"),
+        "{target}"
+    );
+
+    let locations = client.request("textDocument/definition", position(&uri, 1, 14));
+    assert_eq!(locations, json!([]));
+}
+
+#[test]
+fn test_document_symbols() {
+    let mut client = Client::start();
+    let uri = uri("page.html");
+    client.open(
+        &uri,
+        "{% block content %}\n{% if x %}{% macro m(a) %}{% endmacro %}{% endif %}\n{% endblock %}",
+    );
+
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": uri } }),
+    );
+    assert_eq!(symbols.as_array().unwrap().len(), 1);
+    assert_eq!(symbols[0]["name"], "content");
+    assert_eq!(symbols[0]["range"]["end"]["line"], 2);
+    assert_eq!(symbols[0]["selectionRange"]["start"]["character"], 9);
+    let children = symbols[0]["children"].as_array().unwrap();
+    assert_eq!(children.len(), 1);
+    assert_eq!(children[0]["name"], "m");
+    assert_eq!(children[0]["kind"], 12);
+}
+
+#[test]
+fn test_completion() {
+    let mut client = Client::start();
+    let uri = uri("page.html");
+    client.open(
+        &uri,
+        "{{ name|up }}{{ a || b }}{% filter  %}{% endfilter %}",
+    );
+
+    let items = client.request("textDocument/completion", position(&uri, 0, 10));
+    let labels = items
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    for name in ["upper", "escape", "e", "truncate", "json", "default"] {
+        assert!(labels.contains(&name), "{name} missing in {labels:?}");
+    }
+
+    assert_eq!(
+        client.request("textDocument/completion", position(&uri, 0, 22)),
+        Value::Null,
+    );
+    let items = client.request("textDocument/completion", position(&uri, 0, 35));
+    assert!(!items.as_array().unwrap().is_empty());
+}
+
+#[test]
+fn test_shutdown() {
+    let mut client = Client::start();
+    assert_eq!(client.request("shutdown", Value::Null), Value::Null);
+    client.notify("exit", Value::Null);
+    assert!(client.child.wait().unwrap().success());
+
+    let mut client = Client::start();
+    client.notify("exit", Value::Null);
+    assert!(!client.child.wait().unwrap().success());
+}
@@ -0,0 +1,689 @@
+//! Lossless concrete syntax tree of templates
+//!
+//! The [`Ast`] drops everything that does not change the meaning of a template, e.g. the
+//! whitespace inside of tags, or the spelling of delimiters. Tools that rewrite templates need
+//! that information, so [`Cst`] splits the source into [`Token`]s, where every byte of the source
+//! belongs to exactly one token. The tokens are grouped into [`Tag`]s, and the tags into
+//! [`Block`]s, but nothing is interpreted beyond that.
+//!
+//! Building a CST never fails: the tokens of invalid or incomplete templates are kept, too, so
+//! it can be used in editors while a template is being written.
+//!
+//! The CST is not an input of the parser. [`Cst::ast()`] does not build the AST from the tokens,
+//! but parses the source a second time. Both point into the same source, so each [`Span`] of the
+//! AST can be mapped to the token it points into with [`Cst::token_at()`].
+
+use std::ops::Range;
+use std::{fmt, mem};
+
+use crate::memchr_splitter::{Splitter1, Splitter3};
+use crate::node::{Lit, Whitespace, Ws};
+use crate::{Ast, ParseError, Span, Syntax, char_lit, identifier, num_lit, str_lit};
+
+/// A lossless concrete syntax tree of a template
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cst<'a> {
+    source: &'a str,
+    elements: Vec<Element<'a>>,
+    /// All tokens of `elements`, in source order
+    tokens: Vec<Token<'a>>,
+}
+
+impl<'a> Cst<'a> {
+    /// Splits `source`, which uses the delimiters of `syntax`, into its tokens
+    pub fn new(source: &'a str, syntax: &Syntax<'_>) -> Self {
+        let mut lexer = Lexer {
+            source,
+            syntax,
+            pos: 0,
+            builder: Builder::default(),
+        };
+        while lexer.pos < source.len() {
+            lexer.next();
+        }
+        let elements = lexer.builder.finish();
+        let mut tokens = Vec::new();
+        for element in &elements {
+            element.collect_tokens(&mut tokens);
+        }
+        Self {
+            source,
+            elements,
+            tokens,
+        }
+    }
+
+    #[must_use]
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    #[must_use]
+    pub fn elements(&self) -> &[Element<'a>] {
+        &self.elements
+    }
+
+    /// All tokens, in source order
+    #[must_use]
+    pub fn tokens(&self) -> &[Token<'a>] {
+        &self.tokens
+    }
+
+    /// The token that contains the byte at `offset`
+    #[must_use]
+    pub fn token_at(&self, offset: usize) -> Option<&Token<'a>> {
+        let index = self
+            .tokens
+            .partition_point(|token| token.offset + token.text.len() <= offset);
+        self.tokens.get(index)
+    }
+
+    /// Parses the source into an [`Ast`]
+    ///
+    /// The AST is not built from the tokens, but the source is parsed again. `syntax` has to be
+    /// the syntax that the CST was built with, then the spans of the AST point into the same
+    /// source as the tokens, and every node starts at the start of a token.
+    pub fn ast(&self, syntax: &Syntax<'_>) -> Result<Ast<'a>, ParseError> {
+        Ast::from_str(self.source, None, syntax)
+    }
+
+    /// The ranges to blank out, so the parser can skip an error at `offset`
+    ///
+    /// That is the innermost tag or comment that contains `offset`. If the tag belongs to a
+    /// block, the ranges are all tags of the block, but not the content between them.
+    pub(crate) fn recovery_ranges(&self, offset: usize) -> Vec<Range<usize>> {
+        recovery_ranges(&self.elements, offset).unwrap_or_default()
+    }
+}
+
+fn recovery_ranges(elements: &[Element<'_>], offset: usize) -> Option<Vec<Range<usize>>> {
+    for element in elements {
+        match element {
+            Element::Token(token) => {
+                let range = token.offset..token.offset + token.text.len();
+                if token.kind == TokenKind::Comment && range.contains(&offset) {
+                    return Some(vec![range]);
+                }
+            }
+            Element::Tag(tag) => {
+                if tag.contains(offset) {
+                    return Some(vec![tag.range()]);
+                }
+            }
+            Element::Block(block) => {
+                let tags = block
+                    .sections
+                    .iter()
+                    .map(|section| &section.tag)
+                    .chain(&block.end);
+                // The wrong end tag of an unclosed block, e.g. `{% if … %}{% endfor %}`, is
+                // blanked out together with the block.
+                let children = block.sections.iter().flat_map(|section| &section.children);
+                for child in children.filter(|_| block.end.is_none()) {
+                    match child {
+                        Element::Tag(tag)
+                            if tag.contains(offset)
+                                && tag.keyword().is_some_and(|k| k.starts_with("end")) =>
+                        {
+                            return Some(tags.chain([tag]).map(Tag::range).collect());
+                        }
+                        _ => {}
+                    }
+                }
+                for section in &block.sections {
+                    if let Some(ranges) = recovery_ranges(&section.children, offset) {
+                        return Some(ranges);
+                    }
+                }
+                if tags.clone().any(|tag| tag.contains(offset)) {
+                    return Some(tags.map(Tag::range).collect());
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Writes the source back, which is simply the concatenation of all tokens
+impl fmt::Display for Cst<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in self.tokens() {
+            f.write_str(token.text)?;
+        }
+        Ok(())
+    }
+}
+
+/// An element of a template or of a section of a block
+#[derive(Debug, Clone, PartialEq)]
+pub enum Element<'a> {
+    /// Text, whitespace between tags, or a comment
+    Token(Token<'a>),
+    /// An expression tag `{{ … }}`, or a block tag `{% … %}` that does not open a block
+    Tag(Tag<'a>),
+    Block(Block<'a>),
+}
+
+impl<'a> Element<'a> {
+    fn collect_tokens(&self, tokens: &mut Vec<Token<'a>>) {
+        match self {
+            Self::Token(token) => tokens.push(*token),
+            Self::Tag(tag) => tokens.extend(&tag.tokens),
+            Self::Block(block) => {
+                for section in &block.sections {
+                    tokens.extend(&section.tag.tokens);
+                    for element in &section.children {
+                        element.collect_tokens(tokens);
+                    }
+                }
+                if let Some(end) = &block.end {
+                    tokens.extend(&end.tokens);
+                }
+            }
+        }
+    }
+}
+
+/// A block like `{% if … %}…{% else %}…{% endif %}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block<'a> {
+    /// The opening tag and its content, followed by the intermediate tags like `{% else %}`
+    /// and their content
+    pub sections: Vec<Section<'a>>,
+    /// The closing tag, `None` if the block is not closed
+    pub end: Option<Tag<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section<'a> {
+    pub tag: Tag<'a>,
+    pub children: Vec<Element<'a>>,
+}
+
+/// An expression tag `{{ … }}` or a block tag `{% … %}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tag<'a> {
+    pub kind: TagKind,
+    /// All tokens of the tag, starting with its opening delimiter
+    pub tokens: Vec<Token<'a>>,
+}
+
+impl<'a> Tag<'a> {
+    /// The keyword of a block tag, e.g. `if`
+    #[must_use]
+    pub fn keyword(&self) -> Option<&'a str> {
+        if self.kind != TagKind::Block {
+            return None;
+        }
+        self.significant_tokens()
+            .next()
+            .filter(|token| token.kind == TokenKind::Ident)
+            .map(|token| token.text)
+    }
+
+    /// The whitespace control of the tag, e.g. `{%- … +%}`
+    #[must_use]
+    pub fn ws(&self) -> Ws {
+        let control = |token: Option<&Token<'_>>| {
+            token
+                .filter(|token| token.kind == TokenKind::WhitespaceControl)
+                .and_then(|token| Whitespace::parse_char(token.text.chars().next()?))
+        };
+        let nws = match self.tokens.len() {
+            len if len > 2 && self.is_closed() => control(self.tokens.get(len - 2)),
+            _ => None,
+        };
+        Ws(control(self.tokens.get(1)), nws)
+    }
+
+    /// Returns `false` if the source ended before the closing delimiter
+    #[must_use]
+    pub fn is_closed(&self) -> bool {
+        self.tokens
+            .last()
+            .is_some_and(|token| matches!(token.kind, TokenKind::ExprEnd | TokenKind::BlockEnd))
+    }
+
+    #[must_use]
+    pub fn span(&self) -> Span<'a> {
+        match self.tokens.first() {
+            Some(token) => token.span(),
+            None => Span::empty(),
+        }
+    }
+
+    fn range(&self) -> Range<usize> {
+        match (self.tokens.first(), self.tokens.last()) {
+            (Some(first), Some(last)) => first.offset..last.offset + last.text.len(),
+            _ => 0..0,
+        }
+    }
+
+    /// Returns `true` if `offset` is inside of the tag, or at the end of an unclosed tag
+    fn contains(&self, offset: usize) -> bool {
+        let range = self.range();
+        range.contains(&offset) || (!self.is_closed() && offset == range.end)
+    }
+
+    /// The tokens between the delimiters, without whitespace and whitespace control
+    fn significant_tokens(&self) -> impl Iterator<Item = &Token<'a>> + '_ {
+        self.tokens.iter().skip(1).filter(|token| {
+            !matches!(
+                token.kind,
+                TokenKind::Whitespace
+                    | TokenKind::WhitespaceControl
+                    | TokenKind::ExprEnd
+                    | TokenKind::BlockEnd
+            )
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagKind {
+    /// `{{ … }}`
+    Expr,
+    /// `{% … %}`
+    Block,
+}
+
+/// A piece of the source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    /// The offset of `text` in the source
+    pub offset: usize,
+}
+
+impl<'a> Token<'a> {
+    #[must_use]
+    pub fn span(&self) -> Span<'a> {
+        Span::from(self.text)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Text outside of tags, without its leading and trailing whitespace
+    Text,
+    /// Whitespace outside of tags or between the tokens inside of a tag
+    Whitespace,
+    /// A comment, including its delimiters
+    Comment,
+    ExprStart,
+    ExprEnd,
+    BlockStart,
+    BlockEnd,
+    /// `+`, `-` or `~` directly inside of a delimiter
+    WhitespaceControl,
+    /// An identifier or keyword
+    Ident,
+    Str,
+    Char,
+    Num,
+    /// An operator or punctuation, e.g. `==` or `(`
+    Punct,
+    /// A character that cannot start any token
+    Unknown,
+}
+
+/// Operators that consist of more than one character, longest first
+const PUNCTS: &[&str] = &[
+    "..=", "::", "..", "==", "!=", "<=", ">=", "&&", "||", "=>", "->",
+];
+
+struct Lexer<'a, 's> {
+    source: &'a str,
+    syntax: &'s Syntax<'s>,
+    pos: usize,
+    builder: Builder<'a>,
+}
+
+impl<'a> Lexer<'a, '_> {
+    fn next(&mut self) {
+        let rest = self.rest();
+        if rest.starts_with(self.syntax.comment_start) {
+            let token = self.token(TokenKind::Comment, self.comment_len(rest));
+            self.builder.push(Element::Token(token));
+        } else if rest.starts_with(self.syntax.expr_start) {
+            let tag = self.tag(TagKind::Expr);
+            self.builder.push(Element::Tag(tag));
+        } else if rest.starts_with(self.syntax.block_start) {
+            let tag = self.tag(TagKind::Block);
+            let is_raw = tag.keyword() == Some("raw") && tag.is_closed();
+            self.builder.tag(tag);
+            if is_raw {
+                self.raw();
+            }
+        } else {
+            let len = self.text_len(rest);
+            self.text(len);
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.pos..]
+    }
+
+    fn token(&mut self, kind: TokenKind, len: usize) -> Token<'a> {
+        let token = Token {
+            kind,
+            text: &self.source[self.pos..self.pos + len],
+            offset: self.pos,
+        };
+        self.pos += len;
+        token
+    }
+
+    /// Adds the text of the next `len` bytes, split like a [`Lit`]
+    fn text(&mut self, len: usize) {
+        let Lit { lws, val, rws } = Lit::split_ws_parts(&self.rest()[..len]);
+        for (kind, part) in [
+            (TokenKind::Whitespace, lws),
+            (TokenKind::Text, val),
+            (TokenKind::Whitespace, rws),
+        ] {
+            if !part.is_empty() {
+                let token = self.token(kind, part.len());
+                self.builder.push(Element::Token(token));
+            }
+        }
+    }
+
+    /// The length of the text until the next tag or comment
+    fn text_len(&self, rest: &str) -> usize {
+        let syntax = self.syntax;
+        let splitter = Splitter3::new(syntax.block_start, syntax.comment_start, syntax.expr_start);
+        let mut len = 0;
+        // The text is never empty, because `next()` is only called for text at the start.
+        len += rest.chars().next().map_or(0, char::len_utf8);
+        while let Some((before, candidate)) = splitter.split(&rest[len..]) {
+            len += before.len();
+            if [syntax.block_start, syntax.comment_start, syntax.expr_start]
+                .iter()
+                .any(|start| candidate.starts_with(start))
+            {
+                return len;
+            }
+            len += candidate.chars().next().map_or(0, char::len_utf8);
+        }
+        rest.len()
+    }
+
+    /// The length of the (possibly nested) comment at the start of `rest`
+    fn comment_len(&self, rest: &str) -> usize {
+        let (start, end) = (self.syntax.comment_start, self.syntax.comment_end);
+        let mut depth = 0usize;
+        let mut i = start.len();
+        loop {
+            let Some(next_end) = rest[i..].find(end).map(|pos| i + pos) else {
+                return rest.len();
+            };
+            match rest[i..].find(start).map(|pos| i + pos) {
+                Some(next_start) if next_start < next_end => {
+                    depth += 1;
+                    i = next_start + start.len();
+                }
+                _ if depth == 0 => return next_end + end.len(),
+                _ => {
+                    depth -= 1;
+                    i = next_end + end.len();
+                }
+            }
+        }
+    }
+
+    fn tag(&mut self, kind: TagKind) -> Tag<'a> {
+        let (start, end, start_kind, end_kind) = match kind {
+            TagKind::Expr => (
+                self.syntax.expr_start,
+                self.syntax.expr_end,
+                TokenKind::ExprStart,
+                TokenKind::ExprEnd,
+            ),
+            TagKind::Block => (
+                self.syntax.block_start,
+                self.syntax.block_end,
+                TokenKind::BlockStart,
+                TokenKind::BlockEnd,
+            ),
+        };
+        let mut tokens = vec![self.token(start_kind, start.len())];
+        if self.rest().starts_with(['+', '-', '~']) {
+            tokens.push(self.token(TokenKind::WhitespaceControl, 1));
+        }
+        loop {
+            let rest = self.rest();
+            if rest.is_empty() {
+                break;
+            } else if rest.starts_with(end) {
+                tokens.push(self.token(end_kind, end.len()));
+                break;
+            } else if rest.starts_with(['+', '-', '~']) && rest[1..].starts_with(end) {
+                tokens.push(self.token(TokenKind::WhitespaceControl, 1));
+                tokens.push(self.token(end_kind, end.len()));
+                break;
+            }
+            let (kind, len) = inner_token(rest);
+            tokens.push(self.token(kind, len));
+        }
+        Tag { kind, tokens }
+    }
+
+    /// Adds the content of a `{% raw %}` block and its closing tag
+    fn raw(&mut self) {
+        let splitter = Splitter1::new(self.syntax.block_start);
+        let mut len = 0;
+        while let Some((before, candidate)) = splitter.split(&self.rest()[len..]) {
+            len += before.len();
+            if candidate.starts_with(self.syntax.block_start) {
+                let mut lexer = Lexer {
+                    source: self.source,
+                    syntax: self.syntax,
+                    pos: self.pos + len,
+                    builder: Builder::default(),
+                };
+                let tag = lexer.tag(TagKind::Block);
+                if tag.keyword() == Some("endraw") && tag.is_closed() {
+                    if len > 0 {
+                        self.text(len);
+                    }
+                    self.pos = lexer.pos;
+                    self.builder.tag(tag);
+                    return;
+                }
+            }
+            len += candidate.chars().next().map_or(0, char::len_utf8);
+        }
+        if !self.rest().is_empty() {
+            self.text(self.rest().len());
+        }
+    }
+}
+
+/// The kind and length of the token at the start of `rest`, inside of a tag
+fn inner_token(rest: &str) -> (TokenKind, usize) {
+    let Some(c) = rest.chars().next() else {
+        return (TokenKind::Unknown, 0);
+    };
+    let taken = |remaining: &str| rest.len() - remaining.len();
+
+    if c.is_ascii_whitespace() {
+        return (TokenKind::Whitespace, taken(rest.trim_ascii_start()));
+    }
+    let literal = rest.strip_prefix(['b', 'c']).unwrap_or(rest);
+    if literal.starts_with('"') {
+        let mut i = rest;
+        if str_lit(&mut i).is_ok() {
+            return (TokenKind::Str, taken(i));
+        }
+    } else if literal.starts_with('\'') && !rest.starts_with('c') {
+        let mut i = rest;
+        if char_lit(&mut i).is_ok() {
+            return (TokenKind::Char, taken(i));
+        }
+    }
+    if c.is_ascii_digit() {
+        let mut i = rest;
+        return match num_lit(&mut i) {
+            Ok(_) => (TokenKind::Num, taken(i)),
+            Err(_) => (
+                TokenKind::Num,
+                taken(rest.trim_start_matches(|c: char| c.is_alphanumeric() || c == '_')),
+            ),
+        };
+    }
+    let mut i = rest;
+    if identifier(&mut i).is_ok() {
+        return (TokenKind::Ident, taken(i));
+    }
+    if let Some(punct) = PUNCTS.iter().find(|punct| rest.starts_with(**punct)) {
+        return (TokenKind::Punct, punct.len());
+    }
+    match c.is_ascii_punctuation() {
+        true => (TokenKind::Punct, 1),
+        false => (TokenKind::Unknown, c.len_utf8()),
+    }
+}
+
+/// Groups the tags into blocks
+#[derive(Default)]
+struct Builder<'a> {
+    root: Vec<Element<'a>>,
+    stack: Vec<OpenBlock<'a>>,
+}
+
+struct OpenBlock<'a> {
+    keyword: &'a str,
+    /// Set for `{% set x %}`, which is only a block if it is closed by `{% endset %}`
+    tentative: bool,
+    sections: Vec<Section<'a>>,
+    current: Section<'a>,
+}
+
+impl<'a> Builder<'a> {
+    fn push(&mut self, element: Element<'a>) {
+        match self.stack.last_mut() {
+            Some(open) => open.current.children.push(element),
+            None => self.root.push(element),
+        }
+    }
+
+    fn tag(&mut self, tag: Tag<'a>) {
+        let Some(keyword) = tag.keyword() else {
+            self.push(Element::Tag(tag));
+            return;
+        };
+
+        // Like the parser, end a tentative block at a tag that cannot be part of its content.
+        let is_delimiting =
+            keyword.starts_with("end") || matches!(keyword, "elif" | "else" | "when");
+        while let Some(open) = self.stack.last() {
+            if !open.tentative
+                || !is_delimiting
+                || keyword.strip_prefix("end") == Some(open.keyword)
+            {
+                break;
+            }
+            self.close(None);
+        }
+
+        if let Some(open) = self.stack.last_mut() {
+            let intermediates: &[&str] = match open.keyword {
+                "if" => &["elif", "else"],
+                "for" => &["else"],
+                "match" => &["when", "else", "endwhen"],
+                _ => &[],
+            };
+            if intermediates.contains(&keyword) {
+                let section = Section {
+                    tag,
+                    children: Vec::new(),
+                };
+                let previous = mem::replace(&mut open.current, section);
+                open.sections.push(previous);
+                return;
+            }
+        }
+
+        if let Some(index) = self
+            .stack
+            .iter()
+            .rposition(|open| keyword.strip_prefix("end") == Some(open.keyword))
+        {
+            while self.stack.len() > index + 1 {
+                self.close(None);
+            }
+            self.close(Some(tag));
+            return;
+        }
+
+        let Some(tentative) = opens_block(&tag, keyword) else {
+            self.push(Element::Tag(tag));
+            return;
+        };
+        self.stack.push(OpenBlock {
+            keyword,
+            tentative,
+            sections: Vec::new(),
+            current: Section {
+                tag,
+                children: Vec::new(),
+            },
+        });
+    }
+
+    /// Closes the innermost block with the tag `end`
+    fn close(&mut self, end: Option<Tag<'a>>) {
+        let Some(open) = self.stack.pop() else {
+            return;
+        };
+        if open.tentative && end.is_none() {
+            let Section { tag, children } = open.current;
+            self.push(Element::Tag(tag));
+            for child in children {
+                self.push(child);
+            }
+        } else {
+            let mut sections = open.sections;
+            sections.push(open.current);
+            self.push(Element::Block(Block { sections, end }));
+        }
+    }
+
+    fn finish(mut self) -> Vec<Element<'a>> {
+        while !self.stack.is_empty() {
+            self.close(None);
+        }
+        self.root
+    }
+}
+
+/// Returns if the block tag `tag` opens a block, and if that block is tentative
+fn opens_block(tag: &Tag<'_>, keyword: &str) -> Option<bool> {
+    match keyword {
+        "block" | "if" | "for" | "match" | "macro" | "filter" | "raw" => Some(false),
+        // Only `{% call(args) … %}` has a body.
+        "call" => match tag.significant_tokens().nth(1) {
+            Some(token) if token.text == "(" => Some(false),
+            _ => None,
+        },
+        "let" | "set" => {
+            let mut puncts = tag
+                .significant_tokens()
+                .filter(|token| token.kind == TokenKind::Punct)
+                .map(|token| token.text);
+            match puncts.find(|&punct| punct == "=" || punct == "|") {
+                // `{% let x = value %}` is a plain assignment.
+                Some("=") => None,
+                // `{% let x|filter %}` is always a block.
+                Some(_) => Some(false),
+                // `{% set x %}` is only a block if it is closed by `{% endset %}`.
+                None if keyword == "set" => Some(true),
+                None => None,
+            }
+        }
+        _ => None,
+    }
+}
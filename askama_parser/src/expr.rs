@@ -85,7 +85,11 @@ fn check_expr<'a>(
             }
             Ok(())
         }
-        Expr::As(elem, _) | Expr::Unary(_, elem) | Expr::Group(elem) => check_expr(elem, false),
+        Expr::As(elem, _)
+        | Expr::Unary(_, elem)
+        | Expr::Group(elem)
+        | Expr::IsNone(elem)
+        | Expr::IsNotNone(elem) => check_expr(elem, false),
         Expr::Call { path, args, .. } => {
             check_expr(path, false)?;
             for arg in args {
@@ -134,6 +138,9 @@ pub enum Expr<'a> {
     FilterSource,
     IsDefined(&'a str),
     IsNotDefined(&'a str),
+    /// `x is none`, which tests if an `Option` is `None`
+    IsNone(Box<WithSpan<'a, Expr<'a>>>),
+    IsNotNone(Box<WithSpan<'a, Expr<'a>>>),
     Concat(Vec<WithSpan<'a, Expr<'a>>>),
     /// If you have `&& let Some(y)`, this variant handles it.
     LetCond(Box<WithSpan<'a, CondTest<'a>>>),
@@ -143,7 +150,6 @@ impl<'a> Expr<'a> {
     pub(super) fn arguments(
         i: &mut &'a str,
         level: Level<'_>,
-        is_template_macro: bool,
     ) -> ParseResult<'a, Vec<WithSpan<'a, Self>>> {
         let _level_guard = level.nest(i)?;
         let mut named_arguments = HashSet::new();
@@ -161,15 +167,7 @@ impl<'a> Expr<'a> {
                         let has_named_arguments = !named_arguments.is_empty();
 
                         let expr = alt((
-                            move |i: &mut _| {
-                                Self::named_argument(
-                                    i,
-                                    level,
-                                    named_arguments,
-                                    start,
-                                    is_template_macro,
-                                )
-                            },
+                            move |i: &mut _| Self::named_argument(i, level, named_arguments, start),
                             move |i: &mut _| Self::parse(i, level, false),
                         ))
                         .parse_next(i)?;
@@ -195,14 +193,7 @@ impl<'a> Expr<'a> {
         level: Level<'_>,
         named_arguments: &mut HashSet<&'a str>,
         start: &'a str,
-        is_template_macro: bool,
     ) -> ParseResult<'a, WithSpan<'a, Self>> {
-        if !is_template_macro {
-            // If this is not a template macro, we don't want to parse named arguments so
-            // we instead return an error which will allow to continue the parsing.
-            return fail.parse_next(i);
-        }
-
         let (argument, _, value) = (identifier, ws('='), move |i: &mut _| {
             Self::parse(i, level, false)
         })
@@ -333,17 +324,25 @@ impl<'a> Expr<'a> {
             }
         }
 
-        let rhs = opt(terminated(opt(keyword("not")), ws(keyword("defined")))).parse_next(i)?;
+        let rhs = opt((
+            opt(keyword("not")),
+            ws(alt((keyword("defined"), keyword("none")))),
+        ))
+        .parse_next(i)?;
         let ctor = match rhs {
             None => {
                 return Err(winnow::error::ErrMode::Cut(ErrorContext::new(
-                    "expected `defined` or `not defined` after `is`",
+                    "expected `defined`, `not defined`, `none` or `not none` after `is`",
                     // We use `start` to show the whole `var is` thing instead of the current token.
                     start,
                 )));
             }
-            Some(None) => Self::IsDefined,
-            Some(Some(_)) => Self::IsNotDefined,
+            Some((None, "none")) => return Ok(WithSpan::new(Self::IsNone(Box::new(lhs)), start)),
+            Some((Some(_), "none")) => {
+                return Ok(WithSpan::new(Self::IsNotNone(Box::new(lhs)), start));
+            }
+            Some((None, _)) => Self::IsDefined,
+            Some((Some(_), _)) => Self::IsNotDefined,
         };
         let var_name = match *lhs {
             Self::Var(var_name) => var_name,
@@ -517,6 +516,8 @@ impl<'a> Expr<'a> {
             | Self::Call { .. }
             | Self::Range(_, _, _)
             | Self::Try(_)
+            | Self::IsNone(_)
+            | Self::IsNotNone(_)
             | Self::NamedArgument(_, _)
             | Self::Filter(_)
             | Self::Attr(_, _)
@@ -720,8 +721,10 @@ impl<'a> Suffix<'a> {
     }
 
     fn call(i: &mut &'a str, level: Level<'_>) -> ParseResult<'a, Self> {
+        // Named arguments are accepted by the parser for every call, so the code generator
+        // can report a useful error if the called function does not support them.
         (opt(|i: &mut _| call_generics(i, level)), |i: &mut _| {
-            Expr::arguments(i, level, false)
+            Expr::arguments(i, level)
         })
             .map(|(generics, args)| Self::Call {
                 args,
@@ -0,0 +1,210 @@
+//! Find the translatable strings of a template
+
+use std::borrow::Cow;
+
+use crate::expr::Filter;
+use crate::node::{Cond, CondTest, IncludeTarget, Node};
+use crate::{Expr, StrLit, WithSpan};
+
+/// A call of the translation function `t("message-id", name = value, …)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Translation<'a> {
+    /// The message ID, i.e. the value of the string literal that is the first argument of `t()`
+    pub message_id: Cow<'a, str>,
+    /// The names of the named arguments
+    pub arguments: Vec<&'a str>,
+    /// The 1-based line of the call in the template source
+    pub line: usize,
+    /// The 1-based column (counted in characters) of the call in the template source
+    pub column: usize,
+}
+
+/// Returns all calls of the translation function `t()` in `nodes`, in source order
+///
+/// `source` is the template source that `nodes` were parsed from, e.g. [`Parsed::source()`].
+/// Calls whose message ID is not a valid string literal are skipped, because their message cannot
+/// be known without rendering the template.
+///
+/// [`Parsed::source()`]: crate::Parsed::source
+pub fn extract_translations<'a>(source: &'a str, nodes: &[Node<'a>]) -> Vec<Translation<'a>> {
+    let mut extractor = Extractor {
+        source,
+        translations: Vec::new(),
+    };
+    extractor.nodes(nodes);
+    extractor.translations
+}
+
+struct Extractor<'a> {
+    source: &'a str,
+    translations: Vec<Translation<'a>>,
+}
+
+impl<'a> Extractor<'a> {
+    fn nodes(&mut self, nodes: &[Node<'a>]) {
+        for node in nodes {
+            self.node(node);
+        }
+    }
+
+    fn node(&mut self, node: &Node<'a>) {
+        match node {
+            Node::Lit(_)
+            | Node::Comment(_)
+            | Node::Extends(_)
+            | Node::Import(_)
+            | Node::Raw(_)
+            | Node::Break(_)
+            | Node::Continue(_) => {}
+            Node::Expr(_, expr) => self.expr(expr),
+            Node::Include(i) => {
+                if let IncludeTarget::Dynamic(expr, _) = &i.target {
+                    self.expr(expr);
+                }
+                for (_, value) in &i.with {
+                    self.expr(value);
+                }
+            }
+            Node::Call(call) => {
+                self.exprs(&call.args);
+                if let Some(caller) = &call.caller {
+                    self.nodes(&caller.nodes);
+                }
+            }
+            Node::Let(l) => {
+                if let Some(val) = &l.val {
+                    self.expr(val);
+                }
+                if let Some(block) = &l.block {
+                    if let Some(filters) = &block.filters {
+                        self.filter(filters);
+                    }
+                    self.nodes(&block.nodes);
+                }
+            }
+            Node::If(i) => {
+                for branch in &i.branches {
+                    let Cond { cond, nodes, .. } = &**branch;
+                    if let Some(CondTest { expr, .. }) = cond {
+                        self.expr(expr);
+                    }
+                    self.nodes(nodes);
+                }
+            }
+            Node::Match(m) => {
+                self.expr(&m.expr);
+                for arm in &m.arms {
+                    if let Some(guard) = &arm.guard {
+                        self.expr(guard);
+                    }
+                    self.nodes(&arm.nodes);
+                }
+            }
+            Node::Loop(l) => {
+                self.expr(&l.iter);
+                if let Some(cond) = &l.cond {
+                    self.expr(cond);
+                }
+                self.nodes(&l.body);
+                self.nodes(&l.else_nodes);
+            }
+            Node::BlockDef(b) => self.nodes(&b.nodes),
+            Node::Macro(m) => {
+                for (_, default) in &m.args {
+                    if let Some(default) = default {
+                        self.expr(default);
+                    }
+                }
+                self.nodes(&m.nodes);
+            }
+            Node::FilterBlock(f) => {
+                self.filter(&f.filters);
+                self.nodes(&f.nodes);
+            }
+        }
+    }
+
+    fn exprs(&mut self, exprs: &[WithSpan<'a, Expr<'a>>]) {
+        for expr in exprs {
+            self.expr(expr);
+        }
+    }
+
+    fn filter(&mut self, filter: &Filter<'a>) {
+        self.exprs(&filter.arguments);
+    }
+
+    fn expr(&mut self, expr: &WithSpan<'a, Expr<'a>>) {
+        match &**expr {
+            Expr::BoolLit(_)
+            | Expr::NumLit(_, _)
+            | Expr::StrLit(_)
+            | Expr::CharLit(_)
+            | Expr::Var(_)
+            | Expr::Path(_)
+            | Expr::RustMacro(_, _)
+            | Expr::FilterSource
+            | Expr::IsDefined(_)
+            | Expr::IsNotDefined(_) => {}
+            Expr::Array(exprs) | Expr::Tuple(exprs) | Expr::Concat(exprs) => self.exprs(exprs),
+            Expr::Attr(expr, _)
+            | Expr::As(expr, _)
+            | Expr::NamedArgument(_, expr)
+            | Expr::Unary(_, expr)
+            | Expr::Group(expr)
+            | Expr::Try(expr)
+            | Expr::IsNone(expr)
+            | Expr::IsNotNone(expr) => self.expr(expr),
+            Expr::Index(left, right) | Expr::BinOp(_, left, right) => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::Range(_, left, right) => {
+                if let Some(left) = left {
+                    self.expr(left);
+                }
+                if let Some(right) = right {
+                    self.expr(right);
+                }
+            }
+            Expr::Filter(filter) => self.filter(filter),
+            Expr::LetCond(cond) => self.expr(&cond.expr),
+            Expr::Call { path, args, .. } => {
+                if let Expr::Var("t") = ***path {
+                    self.translation(path, args);
+                }
+                self.expr(path);
+                self.exprs(args);
+            }
+        }
+    }
+
+    fn translation(&mut self, path: &WithSpan<'a, Expr<'a>>, args: &[WithSpan<'a, Expr<'a>>]) {
+        let Some((id, args)) = args.split_first() else {
+            return;
+        };
+        let Expr::StrLit(ref lit @ StrLit { prefix: None, .. }) = **id else {
+            return;
+        };
+        let Ok(message_id) = lit.unescape() else {
+            return;
+        };
+        let arguments = args
+            .iter()
+            .filter_map(|arg| match **arg {
+                Expr::NamedArgument(name, _) => Some(name),
+                _ => None,
+            })
+            .collect();
+
+        let offset = path.span().offset_from(self.source).unwrap_or_default();
+        let before = &self.source[..offset];
+        let line_start = before.rfind('\n').map_or(0, |pos| pos + 1);
+        self.translations.push(Translation {
+            message_id,
+            arguments,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        });
+    }
+}
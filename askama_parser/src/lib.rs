@@ -3,7 +3,9 @@
 #![deny(unreachable_pub)]
 
 pub mod ascii_str;
+pub mod cst;
 pub mod expr;
+pub mod i18n;
 mod memchr_splitter;
 pub mod node;
 mod target;
@@ -133,6 +135,68 @@ impl<'a> Ast<'a> {
         }
     }
 
+    /// Parses the template like [`Ast::from_str()`], but reports all errors, not only the first
+    ///
+    /// After an error, the tag or comment that contains it is blanked out, and the template is
+    /// parsed again, so the parser resynchronizes at the next block tag or expression. If the
+    /// tag belongs to a block, like `{% if … %}…{% endif %}`, all tags of the block are blanked
+    /// out, but its content is still checked. The errors are returned in source order.
+    ///
+    /// Every error needs another pass over the template, so at most [`MAX_RECOVERED_ERRORS`] errors
+    /// are reported. If there are more, a last error says that the rest of the template was not
+    /// checked.
+    pub fn from_str_recovering(
+        src: &'a str,
+        file_path: Option<Arc<Path>>,
+        syntax: &Syntax<'_>,
+    ) -> Result<Self, Vec<ParseError>> {
+        let mut err = match Self::from_str(src, file_path.clone(), syntax) {
+            Ok(ast) => return Ok(ast),
+            Err(err) => err,
+        };
+
+        // Blanking out a range keeps the offsets of all tokens.
+        let cst = cst::Cst::new(src, syntax);
+        let mut source = src.as_bytes().to_vec();
+        let mut errors = Vec::new();
+        loop {
+            let ranges = match err.message.as_deref() {
+                // Each blanked out level would only reveal the next one.
+                Some(TOO_DEEPLY_NESTED) => Vec::new(),
+                _ => cst.recovery_ranges(err.offset),
+            };
+            if errors.len() == MAX_RECOVERED_ERRORS {
+                errors.push(ParseError {
+                    message: Some(TOO_MANY_ERRORS.into()),
+                    ..err
+                });
+                break;
+            }
+            errors.push(err);
+
+            let mut progress = false;
+            for range in ranges {
+                for byte in &mut source[range] {
+                    progress |= *byte != b' ';
+                    *byte = b' ';
+                }
+            }
+            if !progress {
+                break;
+            }
+            // Only whole characters were blanked out, so the source is still valid UTF-8.
+            let Ok(source) = str::from_utf8(&source) else {
+                break;
+            };
+            err = match Ast::from_str(source, file_path.clone(), syntax) {
+                Ok(_) => break,
+                Err(err) => err,
+            };
+        }
+        errors.sort_by_key(|err| err.offset);
+        Err(errors)
+    }
+
     #[must_use]
     pub fn nodes(&self) -> &[Node<'a>] {
         &self.nodes
@@ -552,6 +616,15 @@ pub struct StrLit<'a> {
     pub content: &'a str,
 }
 
+impl<'a> StrLit<'a> {
+    /// The value of the literal, i.e. its `content` with the escape sequences resolved
+    ///
+    /// Returns an error message if an escape sequence is invalid.
+    pub fn unescape(&self) -> Result<Cow<'a, str>, String> {
+        unescape(self.content)
+    }
+}
+
 fn str_lit_without_prefix<'a>(i: &mut &'a str) -> ParseResult<'a> {
     let s = delimited(
         '"',
@@ -583,6 +656,73 @@ pub struct CharLit<'a> {
     pub content: &'a str,
 }
 
+impl CharLit<'_> {
+    /// The value of the literal, i.e. its `content` with the escape sequence resolved
+    ///
+    /// Returns an error message if the escape sequence is invalid.
+    pub fn unescape(&self) -> Result<char, String> {
+        let s = unescape(self.content)?;
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(format!("invalid char literal {:?}", self.content)),
+        }
+    }
+}
+
+/// Resolve the escape sequences of a Rust string literal
+fn unescape(s: &str) -> Result<Cow<'_, str>, String> {
+    if !s.contains('\\') {
+        return Ok(Cow::Borrowed(s));
+    }
+
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        let c = match chars.next() {
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('0') => '\0',
+            Some(c @ ('\\' | '\'' | '"')) => c,
+            Some('x') => {
+                let hex = chars.as_str().get(..2).unwrap_or_default();
+                let code = u8::from_str_radix(hex, 16)
+                    .ok()
+                    .filter(|code| *code <= 0x7f)
+                    .ok_or_else(|| format!("invalid escape sequence `\\x{hex}`"))?;
+                chars.nth(1);
+                code as char
+            }
+            Some('u') => {
+                let rest = chars.as_str();
+                let end = rest.find('}').unwrap_or(rest.len());
+                let code = rest
+                    .get(1..end)
+                    .map(|code| code.replace('_', ""))
+                    .and_then(|code| u32::from_str_radix(&code, 16).ok())
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| format!("invalid escape sequence `\\u{}`", &rest[..end]))?;
+                chars = rest[end + 1..].chars();
+                code
+            }
+            Some('\n') => {
+                // A line continuation skips all leading whitespace of the next line.
+                chars = chars.as_str().trim_start().chars();
+                continue;
+            }
+            Some(c) => return Err(format!("unknown escape sequence `\\{c}`")),
+            None => return Err("string literal ends with a backslash".into()),
+        };
+        result.push(c);
+    }
+    Ok(Cow::Owned(result))
+}
+
 // Information about allowed character escapes is available at:
 // <https://doc.rust-lang.org/reference/tokens.html#character-literals>.
 fn char_lit<'a>(i: &mut &'a str) -> ParseResult<'a, CharLit<'a>> {
@@ -985,13 +1125,18 @@ impl Level<'_> {
     #[cold]
     #[inline(never)]
     fn _fail(i: &str) -> ParseErr<'_> {
-        winnow::error::ErrMode::Cut(ErrorContext::new(
-            "your template code is too deeply nested, or the last expression is too complex",
-            i,
-        ))
+        winnow::error::ErrMode::Cut(ErrorContext::new(TOO_DEEPLY_NESTED, i))
     }
 }
 
+/// The maximum number of errors that [`Ast::from_str_recovering()`] reports
+pub const MAX_RECOVERED_ERRORS: usize = 20;
+
+const TOO_MANY_ERRORS: &str = "too many errors, the rest of the template was not checked";
+
+const TOO_DEEPLY_NESTED: &str =
+    "your template code is too deeply nested, or the last expression is too complex";
+
 /// Used to keep track how often [`LevelGuard::nest()`] was called and to re-increment the
 /// remaining level counter when it is dropped / falls out of scope.
 #[must_use]
@@ -1034,12 +1179,28 @@ fn filter<'a>(
     ws(('|', not('|'))).parse_next(i)?;
 
     let _level_guard = level.nest(i)?;
-    cut_err((
-        ws(identifier),
-        opt(|i: &mut _| expr::call_generics(i, level)).map(|generics| generics.unwrap_or_default()),
-        opt(|i: &mut _| Expr::arguments(i, level, false)),
-    ))
-    .parse_next(i)
+    cut_err(|i: &mut _| filter_call(i, level)).parse_next(i)
+}
+
+/// Parses the name, generics and arguments of a filter
+#[allow(clippy::type_complexity)]
+fn filter_call<'a>(
+    i: &mut &'a str,
+    level: Level<'_>,
+) -> ParseResult<
+    'a,
+    (
+        &'a str,
+        Vec<WithSpan<'a, TyGenerics<'a>>>,
+        Option<Vec<WithSpan<'a, Expr<'a>>>>,
+    ),
+> {
+    let name = ws(identifier).parse_next(i)?;
+    let generics = opt(|i: &mut _| expr::call_generics(i, level))
+        .map(|generics| generics.unwrap_or_default())
+        .parse_next(i)?;
+    let arguments = opt(|i: &mut _| Expr::arguments(i, level)).parse_next(i)?;
+    Ok((name, generics, arguments))
 }
 
 /// Returns the common parts of two paths.
@@ -1483,6 +1644,24 @@ mod test {
         assert!(str_lit.parse_peek(r#"d"hello""#).is_err());
     }
 
+    #[test]
+    fn test_unescape() {
+        let unescape = |s| str_lit.parse_peek(s).unwrap().1.unescape();
+        assert_eq!(unescape(r#""hello""#).unwrap(), "hello");
+        assert_eq!(
+            unescape(r#""a\"b\\c\n\t\x41\u{e9}""#).unwrap(),
+            "a\"b\\c\n\tA\u{e9}"
+        );
+        assert_eq!(unescape("\"a\\\n    b\"").unwrap(), "ab");
+        assert!(unescape(r#""\q""#).is_err());
+        assert!(unescape(r#""\x80""#).is_err());
+
+        let unescape = |s| char_lit.parse_peek(s).unwrap().1.unescape();
+        assert_eq!(unescape("'a'").unwrap(), 'a');
+        assert_eq!(unescape(r"'\''").unwrap(), '\'');
+        assert_eq!(unescape(r"'\u{1F600}'").unwrap(), '\u{1F600}');
+    }
+
     #[test]
     fn assert_span_size() {
         assert_eq!(
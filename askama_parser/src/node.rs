@@ -11,8 +11,8 @@ use winnow::{ModalParser, Parser};
 
 use crate::memchr_splitter::{Splitter1, Splitter2, Splitter3};
 use crate::{
-    ErrorContext, Expr, Filter, ParseResult, Span, State, Target, WithSpan, filter, identifier,
-    is_rust_keyword, keyword, skip_till, skip_ws0, str_lit_without_prefix, ws,
+    ErrorContext, Expr, Filter, ParseResult, Span, State, Target, WithSpan, filter, filter_call,
+    identifier, is_rust_keyword, keyword, skip_till, skip_ws0, str_lit_without_prefix, ws,
 };
 
 #[derive(Debug, PartialEq)]
@@ -97,7 +97,7 @@ impl<'a> Node<'a> {
             "for" => |i: &mut _, s| Loop::parse(i, s).map(|n| Self::Loop(Box::new(n))),
             "match" => |i: &mut _, s| Match::parse(i, s).map(Self::Match),
             "extends" => |i: &mut _, _s| Extends::parse(i).map(Self::Extends),
-            "include" => |i: &mut _, _s| Include::parse(i).map(Self::Include),
+            "include" => |i: &mut _, s| Include::parse(i, s).map(Self::Include),
             "import" => |i: &mut _, _s| Import::parse(i).map(Self::Import),
             "block" => |i: &mut _, s| BlockDef::parse(i, s).map(Self::BlockDef),
             "macro" => |i: &mut _, s| Macro::parse(i, s).map(Self::Macro),
@@ -267,6 +267,8 @@ fn unexpected_raw_tag<'a>(kind: Option<&'static str>, i: &mut &'a str) -> ParseR
 pub struct When<'a> {
     pub ws: Ws,
     pub target: Vec<Target<'a>>,
+    /// The guard of `{% when Some(n) if n > 10 %}`
+    pub guard: Option<WithSpan<'a, Expr<'a>>>,
     pub nodes: Vec<Node<'a>>,
 }
 
@@ -292,6 +294,7 @@ impl<'a> When<'a> {
             Self {
                 ws: Ws(pws, nws),
                 target: vec![Target::Placeholder(WithSpan::new((), start))],
+                guard: None,
                 nodes,
             },
             start,
@@ -338,6 +341,10 @@ impl<'a> When<'a> {
                 Some("match-when"),
                 (
                     separated(1.., ws(|i: &mut _| Target::parse(i, s)), '|'),
+                    opt(preceded(
+                        ws(keyword("if")),
+                        ws(|i: &mut _| Expr::parse(i, s.level, false)),
+                    )),
                     opt(Whitespace::parse),
                     |i: &mut _| s.tag_block_end(i),
                     cut_node(Some("match-when"), |i: &mut _| Node::many(i, s)),
@@ -345,7 +352,7 @@ impl<'a> When<'a> {
                 ),
             ),
         );
-        let (_, pws, _, (target, nws, _, mut nodes, endwhen)) = p.parse_next(i)?;
+        let (_, pws, _, (target, guard, nws, _, mut nodes, endwhen)) = p.parse_next(i)?;
         if let Some(endwhen) = endwhen {
             nodes.push(endwhen);
         }
@@ -353,6 +360,7 @@ impl<'a> When<'a> {
             Self {
                 ws: Ws(pws, nws),
                 target,
+                guard,
                 nodes,
             },
             start,
@@ -461,7 +469,7 @@ impl Whitespace {
         any.verify_map(Self::parse_char).parse_next(i)
     }
 
-    fn parse_char(c: char) -> Option<Self> {
+    pub(crate) fn parse_char(c: char) -> Option<Self> {
         match c {
             '+' => Some(Self::Preserve),
             '-' => Some(Self::Suppress),
@@ -506,6 +514,8 @@ pub struct Loop<'a> {
     pub var: Target<'a>,
     pub iter: WithSpan<'a, Expr<'a>>,
     pub cond: Option<WithSpan<'a, Expr<'a>>>,
+    /// Set for `{% for … recursive %}`, so the body can call `loop(children)`
+    pub recursive: bool,
     pub body: Vec<Node<'a>>,
     pub ws2: Ws,
     pub else_nodes: Vec<Node<'a>>,
@@ -584,6 +594,7 @@ impl<'a> Loop<'a> {
                         (
                             ws(|i: &mut _| Expr::parse(i, s.level, true)),
                             opt(if_cond),
+                            opt(ws(keyword("recursive"))),
                             opt(Whitespace::parse),
                             |i: &mut _| s.tag_block_end(i),
                             body_and_end,
@@ -592,7 +603,7 @@ impl<'a> Loop<'a> {
                 ),
             ),
         );
-        let (pws1, _, (var, _, (iter, cond, nws1, _, (body, pws2, else_block, nws2)))) =
+        let (pws1, _, (var, _, (iter, cond, recursive, nws1, _, (body, pws2, else_block, nws2)))) =
             p.parse_next(i)?;
         let (nws3, else_nodes, pws3) = else_block.unwrap_or_default();
         Ok(WithSpan::new(
@@ -601,6 +612,7 @@ impl<'a> Loop<'a> {
                 var,
                 iter,
                 cond,
+                recursive: recursive.is_some(),
                 body,
                 ws2: Ws(pws2, nws3),
                 else_nodes,
@@ -773,9 +785,7 @@ impl<'a> FilterBlock<'a> {
             cut_node(
                 Some("filter"),
                 (
-                    ws(identifier),
-                    opt(|i: &mut _| crate::expr::call_generics(i, s.level)),
-                    opt(|i: &mut _| Expr::arguments(i, s.level, false)),
+                    |i: &mut _| filter_call(i, s.level),
                     repeat(0.., |i: &mut _| {
                         #[allow(clippy::explicit_auto_deref)] // false positive
                         level_guard.nest(*i)?;
@@ -790,7 +800,7 @@ impl<'a> FilterBlock<'a> {
                 ),
             ),
         );
-        let (pws1, _, (filter_name, generics, params, extra_filters, (), nws1, _)) =
+        let (pws1, _, ((filter_name, generics, params), extra_filters, (), nws1, _)) =
             start.parse_next(i)?;
 
         let mut arguments = params.unwrap_or_default();
@@ -798,7 +808,7 @@ impl<'a> FilterBlock<'a> {
         let mut filters = Filter {
             name: filter_name,
             arguments,
-            generics: generics.unwrap_or_default(),
+            generics,
         };
         for (filter_name, generics, args, span) in extra_filters {
             filters = Filter {
@@ -881,10 +891,41 @@ pub struct Call<'a> {
     pub scope: Option<&'a str>,
     pub name: &'a str,
     pub args: Vec<WithSpan<'a, Expr<'a>>>,
+    /// The body of a `{% call(args) macro() %}…{% endcall %}` block
+    pub caller: Option<Caller<'a>>,
+}
+
+/// The body of a `{% call(args) macro() %}…{% endcall %}` block, rendered by `{{ caller() }}`
+#[derive(Debug, PartialEq)]
+pub struct Caller<'a> {
+    /// Whitespace handling of the `{% call %}` tag
+    pub ws1: Ws,
+    pub args: Vec<&'a str>,
+    pub nodes: Vec<Node<'a>>,
+    /// Whitespace handling of the `{% endcall %}` tag
+    pub ws2: Ws,
 }
 
 impl<'a> Call<'a> {
     fn parse(i: &mut &'a str, s: &State<'_, '_>) -> ParseResult<'a, WithSpan<'a, Self>> {
+        let caller_args = |i: &mut _| -> ParseResult<'_, Vec<&str>> {
+            let (args, close) = preceded(
+                '(',
+                (
+                    opt(terminated(separated(1.., ws(identifier), ','), opt(','))),
+                    ws(opt(')')),
+                ),
+            )
+            .parse_next(i)?;
+            match close {
+                Some(_) => Ok(args.unwrap_or_default()),
+                None => Err(winnow::error::ErrMode::Cut(ErrorContext::new(
+                    "expected `)` to close caller argument list",
+                    *i,
+                ))),
+            }
+        };
+
         let start = *i;
         let mut p = (
             opt(Whitespace::parse),
@@ -892,22 +933,66 @@ impl<'a> Call<'a> {
             cut_node(
                 Some("call"),
                 (
+                    opt(caller_args),
                     opt((ws(identifier), ws("::"))),
                     ws(identifier),
-                    opt(ws(|nested: &mut _| Expr::arguments(nested, s.level, true))),
+                    opt(ws(|nested: &mut _| Expr::arguments(nested, s.level))),
                     opt(Whitespace::parse),
                 ),
             ),
         );
-        let (pws, _, (scope, name, args, nws)) = p.parse_next(i)?;
+        let (pws, _, (caller_args, scope, name, args, nws)) = p.parse_next(i)?;
         let scope = scope.map(|(scope, _)| scope);
         let args = args.unwrap_or_default();
+
+        // Only `{% call(args) … %}` has a body, so plain `{% call … %}` tags keep working.
+        let Some(caller_args) = caller_args else {
+            return Ok(WithSpan::new(
+                Self {
+                    ws: Ws(pws, nws),
+                    scope,
+                    name,
+                    args,
+                    caller: None,
+                },
+                start,
+            ));
+        };
+        let mut names = HashSet::new();
+        for arg in &caller_args {
+            check_duplicated_name(&mut names, arg, start)?;
+        }
+
+        let mut end = cut_node(
+            Some("call"),
+            (
+                |i: &mut _| s.tag_block_end(i),
+                |i: &mut _| Node::many(i, s),
+                cut_node(
+                    Some("call"),
+                    (
+                        |i: &mut _| check_block_start(i, start, s, "call", "endcall"),
+                        opt(Whitespace::parse),
+                        end_node("call", "endcall"),
+                        opt(Whitespace::parse),
+                    ),
+                ),
+            ),
+        );
+        let (_, nodes, (_, pws2, _, nws2)) = end.parse_next(i)?;
+
         Ok(WithSpan::new(
             Self {
-                ws: Ws(pws, nws),
+                ws: Ws(pws, nws2),
                 scope,
                 name,
                 args,
+                caller: Some(Caller {
+                    ws1: Ws(pws, nws),
+                    args: caller_args,
+                    nodes,
+                    ws2: Ws(pws2, nws2),
+                }),
             },
             start,
         ))
@@ -1159,6 +1244,21 @@ pub struct Let<'a> {
     pub ws: Ws,
     pub var: Target<'a>,
     pub val: Option<WithSpan<'a, Expr<'a>>>,
+    /// The body of a block assignment `{% set x %}…{% endset %}`
+    pub block: Option<LetBlock<'a>>,
+}
+
+/// The body of a block assignment `{% set x | filters %}…{% endset %}`, rendered into a string
+#[derive(Debug, PartialEq)]
+pub struct LetBlock<'a> {
+    /// Whitespace handling of the `{% set %}` tag
+    pub ws1: Ws,
+    /// The filters applied to the rendered body, which is their innermost
+    /// [`Expr::FilterSource`] argument
+    pub filters: Option<Filter<'a>>,
+    pub nodes: Vec<Node<'a>>,
+    /// Whitespace handling of the `{% endset %}` tag
+    pub ws2: Ws,
 }
 
 impl<'a> Let<'a> {
@@ -1175,11 +1275,53 @@ impl<'a> Let<'a> {
                         ws('='),
                         ws(|i: &mut _| Expr::parse(i, s.level, false)),
                     )),
+                    repeat(0.., |i: &mut _| {
+                        let start = *i;
+                        filter(i, s.level)
+                            .map(|(name, generics, params)| (name, generics, params, start))
+                    })
+                    .map(|v: Vec<_>| v),
+                    ws(empty),
                     opt(Whitespace::parse),
                 ),
             ),
         );
-        let (pws, _, (var, val, nws)) = p.parse_next(i)?;
+        let (pws, tag, (var, val, filters, (), nws)) = p.parse_next(i)?;
+        let (tag, end_tag) = match tag {
+            "let" => ("let", "endlet"),
+            _ => ("set", "endset"),
+        };
+
+        let filters = filters
+            .into_iter()
+            .fold(None, |source, (name, generics, args, span)| {
+                let mut arguments = args.unwrap_or_default();
+                arguments.insert(
+                    0,
+                    WithSpan::new(source.map_or(Expr::FilterSource, Expr::Filter), span),
+                );
+                Some(Filter {
+                    name,
+                    arguments,
+                    generics,
+                })
+            });
+
+        // With filters, the tag always opens a block. Without them, only `{% set x %}` does, and
+        // only if it is closed by a matching `{% endset %}`; otherwise it is a forward declaration.
+        let mut block = None;
+        if val.is_none() && (filters.is_some() || (tag == "set" && has_end_tag(i, s))) {
+            let body = (
+                |i: &mut _| s.tag_block_end(i),
+                |i: &mut _| Node::many(i, s),
+                |i: &mut _| check_block_start(i, start, s, tag, end_tag),
+                opt(Whitespace::parse),
+                end_node(tag, end_tag),
+                opt(Whitespace::parse),
+            );
+            block = Some(cut_node(Some(tag), body).parse_next(i)?);
+        }
+
         if val.is_none() {
             let kind = match &var {
                 Target::Name(_) => None,
@@ -1196,27 +1338,119 @@ impl<'a> Let<'a> {
                 }
             };
             if let Some(kind) = kind {
+                let what = match block {
+                    Some(_) => "assign a block to a variable",
+                    None => "forward-define a variable",
+                };
                 return Err(winnow::error::ErrMode::Cut(ErrorContext::new(
-                    format!(
-                        "when you forward-define a variable, you cannot use {kind} in place of \
-                         a variable name"
-                    ),
+                    format!("when you {what}, you cannot use {kind} in place of a variable name"),
                     start,
                 )));
             }
         }
 
+        let Some((_, nodes, _, pws2, _, nws2)) = block else {
+            return Ok(WithSpan::new(
+                Let {
+                    ws: Ws(pws, nws),
+                    var,
+                    val,
+                    block: None,
+                },
+                start,
+            ));
+        };
         Ok(WithSpan::new(
             Let {
-                ws: Ws(pws, nws),
+                ws: Ws(pws, nws2),
                 var,
                 val,
+                block: Some(LetBlock {
+                    ws1: Ws(pws, nws),
+                    filters,
+                    nodes,
+                    ws2: Ws(pws2, nws2),
+                }),
             },
             start,
         ))
     }
 }
 
+/// Tells if the `{% set x %}` tag that ends at `i` is closed by a matching `{% endset %}`
+///
+/// The following tags are only scanned for their names, not parsed, so a template with many
+/// forward declarations is not parsed over and over again. The `{% endset %}` has to be on the
+/// nesting level of the `{% set x %}` tag. Like `x`, a nested `{% set y %}` tag is tentatively a
+/// block: it is closed by the next `{% endset %}`, or dropped at the end of its enclosing block.
+fn has_end_tag(mut i: &str, s: &State<'_, '_>) -> bool {
+    let syntax = s.syntax;
+    // The open blocks, and whether they are tentative
+    let mut stack = Vec::new();
+    let mut in_raw = false;
+    loop {
+        let Some(start) = i.find(syntax.block_start) else {
+            return false;
+        };
+        if let Some(comment) = i[..start].find(syntax.comment_start) {
+            i = &i[comment + syntax.comment_start.len()..];
+            match i.find(syntax.comment_end) {
+                Some(end) => i = &i[end + syntax.comment_end.len()..],
+                None => return false,
+            }
+            continue;
+        }
+        i = &i[start + syntax.block_start.len()..];
+        let Some(end) = i.find(syntax.block_end) else {
+            return false;
+        };
+        let content = i[..end].trim_start_matches(['-', '+', '~']).trim_start();
+        i = &i[end + syntax.block_end.len()..];
+
+        let name_len = content
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(content.len());
+        let (name, args) = content.split_at(name_len);
+        if in_raw {
+            in_raw = name != "endraw";
+            continue;
+        }
+        match name {
+            "block" | "if" | "for" | "match" | "macro" | "filter" => stack.push(false),
+            "call" if args.trim_start().starts_with('(') => stack.push(false),
+            "raw" => in_raw = true,
+            "let" | "set" => match (args.find('|'), args.find('=')) {
+                (Some(filter), Some(assign)) if filter < assign => stack.push(false),
+                (Some(_), None) => stack.push(false),
+                (None, None) if name == "set" => stack.push(true),
+                _ => {}
+            },
+            "endset" => {
+                let Some(_) = stack.pop() else {
+                    return true;
+                };
+            }
+            "elif" | "else" | "when" => {
+                while stack.last() == Some(&true) {
+                    stack.pop();
+                }
+                if stack.is_empty() {
+                    return false;
+                }
+            }
+            name if name.starts_with("end") => {
+                while stack.last() == Some(&true) {
+                    stack.pop();
+                }
+                if stack.pop().is_none() {
+                    return false;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct If<'a> {
     pub ws: Ws,
@@ -1278,25 +1512,119 @@ impl<'a> If<'a> {
 #[derive(Debug, PartialEq)]
 pub struct Include<'a> {
     pub ws: Ws,
-    pub path: &'a str,
+    pub target: IncludeTarget<'a>,
+    /// Set for `{% include … ignore missing %}`: templates that don't exist are not rendered
+    pub ignore_missing: bool,
+    /// The variables passed with `{% include "a.html" with name = value, … %}`
+    pub with: Vec<(&'a str, WithSpan<'a, Expr<'a>>)>,
+    /// Set for `{% include … only %}`: the included template only sees the passed variables
+    pub only: bool,
+}
+
+/// The template(s) that an `{% include %}` node refers to
+#[derive(Debug, PartialEq)]
+pub enum IncludeTarget<'a> {
+    /// `{% include "a.html" %}`
+    Path(&'a str),
+    /// `{% include ["a.html", "b.html"] %}`: the first template that exists is included
+    Fallback(Vec<&'a str>),
+    /// `{% include kind from ["a.html", "b.html"] %}`: the template is selected at runtime by
+    /// the value of the expression
+    Dynamic(WithSpan<'a, Expr<'a>>, Vec<&'a str>),
+}
+
+impl<'a> IncludeTarget<'a> {
+    /// All the templates that can be included
+    #[must_use]
+    pub fn paths(&self) -> &[&'a str] {
+        match self {
+            Self::Path(path) => std::slice::from_ref(path),
+            Self::Fallback(paths) | Self::Dynamic(_, paths) => paths,
+        }
+    }
 }
 
 impl<'a> Include<'a> {
-    fn parse(i: &mut &'a str) -> ParseResult<'a, WithSpan<'a, Self>> {
+    fn parse(i: &mut &'a str, s: &State<'_, '_>) -> ParseResult<'a, WithSpan<'a, Self>> {
         let start = *i;
+        let candidates = |i: &mut _| -> ParseResult<'a, Vec<&'a str>> {
+            let (_, paths, _, close) = (
+                ws('['),
+                cut_node(
+                    Some("include"),
+                    separated(1.., ws(str_lit_without_prefix), ','),
+                ),
+                opt(ws(',')),
+                opt(ws(']')),
+            )
+                .parse_next(i)?;
+            match close {
+                Some(_) => Ok(paths),
+                None => Err(winnow::error::ErrMode::Cut(ErrorContext::new(
+                    "expected `]` to close the list of templates",
+                    *i,
+                ))),
+            }
+        };
         let mut p = (
             opt(Whitespace::parse),
             ws(keyword("include")),
             cut_node(
                 Some("include"),
-                (ws(str_lit_without_prefix), opt(Whitespace::parse)),
+                (
+                    alt((
+                        ws(str_lit_without_prefix).map(IncludeTarget::Path),
+                        candidates.map(IncludeTarget::Fallback),
+                        (
+                            ws(|i: &mut _| Expr::parse(i, s.level, false)),
+                            cut_node(Some("include"), preceded(ws(keyword("from")), candidates)),
+                        )
+                            .map(|(expr, paths)| IncludeTarget::Dynamic(expr, paths)),
+                    )),
+                    opt((ws(keyword("ignore")), ws(keyword("missing")))),
+                    opt(preceded(
+                        ws(keyword("with")),
+                        cut_node(
+                            Some("include"),
+                            separated(
+                                1..,
+                                (
+                                    ws(identifier),
+                                    ws('='),
+                                    ws(|i: &mut _| Expr::parse(i, s.level, false)),
+                                )
+                                    .map(|(name, _, value)| (name, value)),
+                                ',',
+                            ),
+                        ),
+                    )),
+                    opt(ws(keyword("only"))),
+                    opt(Whitespace::parse),
+                ),
             ),
         );
-        let (pws, _, (path, nws)) = p.parse_next(i)?;
+        let (pws, _, (target, ignore_missing, with, only, nws)) = p.parse_next(i)?;
+        let with: Vec<_> = with.unwrap_or_default();
+        let mut names = HashSet::new();
+        for (name, _) in &with {
+            check_duplicated_name(&mut names, name, start)?;
+        }
+        if let IncludeTarget::Fallback(paths) | IncludeTarget::Dynamic(_, paths) = &target {
+            let mut seen = HashSet::new();
+            if let Some(path) = paths.iter().find(|path| !seen.insert(**path)) {
+                return Err(winnow::error::ErrMode::Cut(ErrorContext::new(
+                    format!("template {path:?} is listed more than once"),
+                    start,
+                )));
+            }
+        }
         Ok(WithSpan::new(
             Self {
                 ws: Ws(pws, nws),
-                path,
+                target,
+                ignore_missing: ignore_missing.is_some(),
+                with,
+                only: only.is_some(),
             },
             start,
         ))
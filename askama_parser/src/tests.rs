@@ -1,6 +1,7 @@
-use crate::node::{Lit, Whitespace, Ws};
+use crate::node::{IncludeTarget, Lit, Whitespace, Ws};
 use crate::{
-    Ast, Expr, Filter, InnerSyntax, Node, Num, Span, StrLit, Syntax, SyntaxBuilder, WithSpan,
+    Ast, Expr, Filter, InnerSyntax, MAX_RECOVERED_ERRORS, Node, Num, Span, StrLit, Syntax,
+    SyntaxBuilder, Target, WithSpan,
 };
 
 impl<T> WithSpan<'static, T> {
@@ -101,6 +102,62 @@ fn test_parse_filter() {
     );
 }
 
+#[test]
+fn test_parse_filter_named_arguments() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str(
+            "{{ users|sort(true, attribute = \"name\") }}",
+            None,
+            &syntax
+        )
+        .unwrap()
+        .nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Expr::Filter(Filter {
+                name: "sort",
+                arguments: vec![
+                    WithSpan::no_span(Expr::Var("users")),
+                    WithSpan::no_span(Expr::BoolLit(true)),
+                    WithSpan::no_span(Expr::NamedArgument(
+                        "attribute",
+                        Box::new(WithSpan::no_span(Expr::StrLit(StrLit {
+                            content: "name",
+                            prefix: None,
+                        })))
+                    )),
+                ],
+                generics: vec![],
+            })),
+        )],
+    );
+    assert!(Ast::from_str("{{ a|sort(attribute = \"x\", true) }}", None, &syntax).is_err());
+    assert!(Ast::from_str("{{ a|sort(reverse = a, reverse = b) }}", None, &syntax).is_err());
+
+    // Named arguments are parsed for all filters, the code generator rejects them if the filter
+    // does not take them.
+    assert!(Ast::from_str("{{ a|truncate(length = 3) }}", None, &syntax).is_ok());
+    assert!(Ast::from_str("{{ a|truncate(b == 3) }}", None, &syntax).is_ok());
+    assert!(Ast::from_str("{{ a|custom(b = 1, 2) }}", None, &syntax).is_err());
+    assert!(
+        Ast::from_str(
+            "{% filter sort(attribute = \"x\")|upper %}{% endfilter %}",
+            None,
+            &syntax
+        )
+        .is_ok()
+    );
+    assert!(
+        Ast::from_str(
+            "{% filter upper|truncate(length = 1) %}{% endfilter %}",
+            None,
+            &syntax
+        )
+        .is_ok()
+    );
+}
+
 #[test]
 fn test_parse_numbers() {
     let syntax = Syntax::default();
@@ -243,6 +300,114 @@ fn test_parse_var_call() {
     );
 }
 
+#[test]
+fn test_parse_translation_call() {
+    let s = Syntax::default();
+
+    assert_eq!(
+        Ast::from_str("{{ t(\"welcome\", name = user, count = 3) }}", None, &s)
+            .unwrap()
+            .nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Expr::Call {
+                path: Box::new(WithSpan::no_span(Expr::Var("t"))),
+                args: vec![
+                    WithSpan::no_span(Expr::StrLit(StrLit {
+                        content: "welcome",
+                        prefix: None,
+                    })),
+                    WithSpan::no_span(Expr::NamedArgument(
+                        "name",
+                        Box::new(WithSpan::no_span(Expr::Var("user")))
+                    )),
+                    WithSpan::no_span(Expr::NamedArgument(
+                        "count",
+                        Box::new(WithSpan::no_span(int_lit("3")))
+                    )),
+                ],
+                generics: vec![],
+            }),
+        )],
+    );
+
+    // Named arguments are parsed for every call, the code generator rejects them if unsupported.
+    assert!(Ast::from_str("{{ f(\"welcome\", name = user) }}", None, &s).is_ok());
+    assert!(Ast::from_str("{{ f(a == b) }}", None, &s).is_ok());
+    assert!(Ast::from_str("{{ t(name = user, \"welcome\") }}", None, &s).is_err());
+}
+
+#[test]
+fn test_extract_translations() {
+    use crate::i18n::{Translation, extract_translations};
+
+    let source = "<h1>{{ t(\"title\") }}</h1>\n\
+        {% if user.is_some() %}\n  \
+            ¡{{ t(\"welcome-user\", name = user.name)|upper }}\n\
+        {% endif %}\n\
+        {% for msg in messages %}{{ t(msg) }}{% endfor %}\n\
+        {% let x = t(\"nested\", a = f(t(\"inner\"))) %}";
+    let syntax = Syntax::default();
+    let ast = Ast::from_str(source, None, &syntax).unwrap();
+    assert_eq!(
+        extract_translations(source, ast.nodes()),
+        vec![
+            Translation {
+                message_id: "title".into(),
+                arguments: vec![],
+                line: 1,
+                column: 8,
+            },
+            Translation {
+                message_id: "welcome-user".into(),
+                arguments: vec!["name"],
+                line: 3,
+                column: 7,
+            },
+            Translation {
+                message_id: "nested".into(),
+                arguments: vec!["a"],
+                line: 6,
+                column: 12,
+            },
+            Translation {
+                message_id: "inner".into(),
+                arguments: vec![],
+                line: 6,
+                column: 30,
+            },
+        ],
+    );
+
+    // custom delimiters
+    let syntax = SyntaxBuilder {
+        name: "custom",
+        expr_start: Some("[["),
+        expr_end: Some("]]"),
+        ..SyntaxBuilder::default()
+    }
+    .to_syntax()
+    .unwrap();
+    let source = "{{ t(\"ignored\") }} [[ t(\"used\") ]]";
+    let ast = Ast::from_str(source, None, &syntax).unwrap();
+    let translations = extract_translations(source, ast.nodes());
+    assert_eq!(translations.len(), 1);
+    assert_eq!(translations[0].message_id, "used");
+    assert_eq!(translations[0].column, 23);
+
+    // escape sequences, include and match guard expressions
+    let syntax = Syntax::default();
+    let source = "{{ t(\"say-\\\"hi\\\"\") }}\n\
+        {% include t(\"theme\") from [\"a.html\"] with title = t(\"title\") %}\n\
+        {% match x %}{% when Some(y) if y == t(\"guard\") %}{% endmatch %}";
+    let ast = Ast::from_str(source, None, &syntax).unwrap();
+    let ids = extract_translations(source, ast.nodes())
+        .into_iter()
+        .map(|translation| translation.message_id)
+        .collect::<Vec<_>>();
+    assert_eq!(ids, ["say-\"hi\"", "theme", "title", "guard"]);
+}
+
 #[test]
 fn test_parse_path_call() {
     let s = Syntax::default();
@@ -324,6 +489,44 @@ fn test_parse_root_path() {
     );
 }
 
+#[test]
+fn test_parse_is_none() {
+    let syntax = Syntax::default();
+    let var = || Box::new(WithSpan::no_span(Expr::Var("a")));
+    assert_eq!(
+        Ast::from_str("{{ a is none }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Expr::IsNone(var())),
+        )],
+    );
+    assert_eq!(
+        Ast::from_str("{{ a is not none }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Expr::IsNotNone(var())),
+        )],
+    );
+    // Unlike `is defined`, `is none` can test any expression.
+    assert_eq!(
+        Ast::from_str("{{ -a is none }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Expr::IsNone(Box::new(WithSpan::no_span(Expr::Unary(
+                "-",
+                var()
+            ))))),
+        )],
+    );
+    assert!(Ast::from_str("{{ a is nothing }}", None, &syntax).is_err());
+}
+
 #[test]
 fn test_rust_macro() {
     let syntax = Syntax::default();
@@ -1342,3 +1545,574 @@ fn fuzzed_deeply_tested_if_let() {
         Some("your template code is too deeply nested, or the last expression is too complex"),
     );
 }
+
+#[test]
+fn test_parse_recursive_loop() {
+    let syntax = Syntax::default();
+    let recursive = |source| match &*Ast::from_str(source, None, &syntax).unwrap().nodes {
+        [Node::Loop(l)] => l.recursive,
+        nodes => panic!("expected a single loop, got {nodes:?}"),
+    };
+
+    assert!(!recursive("{% for x in tree %}{% endfor %}"));
+    assert!(recursive(
+        "{% for x in tree recursive %}{{ loop(x.children) }}{% endfor %}"
+    ));
+    assert!(recursive(
+        "{% for x in tree if x.visible recursive -%}{% else %}{% endfor %}"
+    ));
+    assert!(
+        Ast::from_str(
+            "{% for x in tree recursive true %}{% endfor %}",
+            None,
+            &syntax
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_parse_call_with_caller() {
+    let syntax = Syntax::default();
+    let ast = Ast::from_str(
+        "{%- call(a, b) s::m(1) +%}x{% endcall -%}{% call m %}",
+        None,
+        &syntax,
+    )
+    .unwrap();
+    let [Node::Call(block), Node::Call(tag)] = &*ast.nodes else {
+        panic!("expected two calls, got {:?}", ast.nodes);
+    };
+    assert_eq!(
+        (block.scope, block.name, block.args.len()),
+        (Some("s"), "m", 1)
+    );
+    assert_eq!(
+        block.ws,
+        Ws(Some(Whitespace::Suppress), Some(Whitespace::Suppress))
+    );
+    let caller = block.caller.as_ref().unwrap();
+    assert_eq!(caller.args, ["a", "b"]);
+    assert_eq!(
+        caller.ws1,
+        Ws(Some(Whitespace::Suppress), Some(Whitespace::Preserve))
+    );
+    assert_eq!(caller.ws2, Ws(None, Some(Whitespace::Suppress)));
+    assert_eq!(caller.nodes.len(), 1);
+    assert_eq!((tag.name, tag.caller.is_none()), ("m", true));
+
+    assert!(Ast::from_str("{% call() m() %}", None, &syntax).is_err());
+    assert!(Ast::from_str("{% call(a b) m() %}{% endcall %}", None, &syntax).is_err());
+}
+
+#[test]
+fn test_parse_let_block() {
+    let syntax = Syntax::default();
+    let ast = Ast::from_str("{% set a -%} x{{ b }} {%+ endset %}", None, &syntax).unwrap();
+    let [Node::Let(l)] = &*ast.nodes else {
+        panic!("expected a let node, got {:?}", ast.nodes);
+    };
+    assert_eq!((&l.var, l.val.is_none()), (&Target::Name("a"), true));
+    assert_eq!(l.ws, Ws(None, None));
+    let block = l.block.as_ref().unwrap();
+    assert_eq!(block.ws1, Ws(None, Some(Whitespace::Suppress)));
+    assert_eq!(block.ws2, Ws(Some(Whitespace::Preserve), None));
+    assert_eq!((block.nodes.len(), block.filters.is_none()), (3, true));
+
+    let ast = Ast::from_str(
+        "{% let a | upper | truncate(2) %}x{% endlet %}",
+        None,
+        &syntax,
+    )
+    .unwrap();
+    let [Node::Let(l)] = &*ast.nodes else {
+        panic!("expected a let node, got {:?}", ast.nodes);
+    };
+    let filters = l.block.as_ref().unwrap().filters.as_ref().unwrap();
+    assert_eq!(filters.name, "truncate");
+    assert_eq!(filters.arguments.len(), 2);
+    let Expr::Filter(inner) = &*filters.arguments[0] else {
+        panic!("expected a filter, got {:?}", filters.arguments[0]);
+    };
+    assert_eq!(inner.name, "upper");
+    assert_eq!(*inner.arguments[0], Expr::FilterSource);
+
+    // Without filters, only the `set` spelling captures a block, and only if its `{% endset %}`
+    // is on the same nesting level.
+    let ast = Ast::from_str(
+        "{% set a %}{% if b %}{% set c %}{% endif %}{% set d = 1 %}{% endset %}",
+        None,
+        &syntax,
+    )
+    .unwrap();
+    let [Node::Let(l)] = &*ast.nodes else {
+        panic!("expected a let node, got {:?}", ast.nodes);
+    };
+    assert_eq!(l.block.as_ref().unwrap().nodes.len(), 2);
+    assert!(Ast::from_str("{% let a %}x{% endlet %}", None, &syntax).is_err());
+    assert!(
+        Ast::from_str(
+            "{% if b %}{% set a %}{% endif %}{% endset %}",
+            None,
+            &syntax
+        )
+        .is_err()
+    );
+
+    // Without a matching end tag, `let` is still a forward declaration.
+    let ast = Ast::from_str("{% if c %}{% let a %}{% endif %}", None, &syntax).unwrap();
+    let [Node::If(i)] = &*ast.nodes else {
+        panic!("expected an if node, got {:?}", ast.nodes);
+    };
+    let [Node::Let(l)] = &*i.branches[0].nodes else {
+        panic!("expected a let node, got {:?}", i.branches[0].nodes);
+    };
+    assert!(l.block.is_none());
+
+    assert!(Ast::from_str("{% set a | upper %}", None, &syntax).is_err());
+    assert!(Ast::from_str("{% set (a, b) %}x{% endset %}", None, &syntax).is_err());
+    assert!(Ast::from_str("{% let a %}x{% endset %}", None, &syntax).is_err());
+}
+
+#[test]
+fn test_parse_match_guard() {
+    let syntax = Syntax::default();
+    let ast = Ast::from_str(
+        "{% match a %}{% when Some(n) | None if n > 1 %}x{% when _ %}{% endmatch %}",
+        None,
+        &syntax,
+    )
+    .unwrap();
+    let [Node::Match(m)] = &*ast.nodes else {
+        panic!("expected a match node, got {:?}", ast.nodes);
+    };
+    assert_eq!(m.arms[0].target.len(), 2);
+    let guard = m.arms[0].guard.as_ref().unwrap();
+    assert!(matches!(**guard, Expr::BinOp(">", _, _)));
+    assert!(m.arms[1].guard.is_none());
+
+    assert!(Ast::from_str("{% match a %}{% when x if %}{% endmatch %}", None, &syntax).is_err());
+}
+
+#[test]
+fn test_parse_dynamic_include() {
+    let syntax = Syntax::default();
+    let ast = Ast::from_str(
+        r#"{% include "a.html" %}{% include w.kind from ["a.html", "b.html",] %}"#,
+        None,
+        &syntax,
+    )
+    .unwrap();
+    let [Node::Include(a), Node::Include(b)] = &*ast.nodes else {
+        panic!("expected two includes, got {:?}", ast.nodes);
+    };
+    assert_eq!(a.target, IncludeTarget::Path("a.html"));
+    let IncludeTarget::Dynamic(expr, paths) = &b.target else {
+        panic!("expected a dynamic include, got {:?}", b.target);
+    };
+    assert!(matches!(**expr, Expr::Attr(..)));
+    assert_eq!(paths, &["a.html", "b.html"]);
+    assert_eq!(b.target.paths(), ["a.html", "b.html"]);
+
+    for source in [
+        r#"{% include w.kind %}"#,
+        r#"{% include w.kind from [] %}"#,
+        r#"{% include w.kind from ["a.html" %}"#,
+        r#"{% include w.kind from ["a.html", "a.html"] %}"#,
+    ] {
+        assert!(Ast::from_str(source, None, &syntax).is_err(), "{source}");
+    }
+}
+
+#[test]
+fn test_parse_include_fallback() {
+    let syntax = Syntax::default();
+    let ast = Ast::from_str(
+        r#"{% include ["a.html", "b.html"] %}{% include "c.html" ignore missing with x = 1 %}"#,
+        None,
+        &syntax,
+    )
+    .unwrap();
+    let [Node::Include(a), Node::Include(b)] = &*ast.nodes else {
+        panic!("expected two includes, got {:?}", ast.nodes);
+    };
+    assert_eq!(a.target, IncludeTarget::Fallback(vec!["a.html", "b.html"]));
+    assert!(!a.ignore_missing);
+    assert_eq!(b.target, IncludeTarget::Path("c.html"));
+    assert!(b.ignore_missing);
+    assert_eq!(b.with.len(), 1);
+
+    for source in [
+        r#"{% include [] %}"#,
+        r#"{% include ["a.html", "a.html"] %}"#,
+        r#"{% include "a.html" ignore %}"#,
+    ] {
+        assert!(Ast::from_str(source, None, &syntax).is_err(), "{source}");
+    }
+}
+
+#[test]
+fn test_parse_include_with() {
+    let syntax = Syntax::default();
+    let ast = Ast::from_str(
+        r#"{% include "a.html" with x = 1, y = a.b only %}{% include "b.html" only %}"#,
+        None,
+        &syntax,
+    )
+    .unwrap();
+    let [Node::Include(a), Node::Include(b)] = &*ast.nodes else {
+        panic!("expected two includes, got {:?}", ast.nodes);
+    };
+    assert_eq!(
+        a.with.iter().map(|(name, _)| *name).collect::<Vec<_>>(),
+        ["x", "y"]
+    );
+    assert!(a.only);
+    assert!(b.with.is_empty());
+    assert!(b.only);
+
+    for source in [
+        r#"{% include "a.html" with %}"#,
+        r#"{% include "a.html" with x %}"#,
+        r#"{% include "a.html" with x = 1, x = 2 %}"#,
+    ] {
+        assert!(Ast::from_str(source, None, &syntax).is_err(), "{source}");
+    }
+}
+
+#[test]
+fn test_cst_lossless() {
+    use crate::cst::Cst;
+
+    let syntax = Syntax::default();
+    let custom = SyntaxBuilder {
+        name: "custom",
+        block_start: Some("<%"),
+        block_end: Some("%>"),
+        ..SyntaxBuilder::default()
+    }
+    .to_syntax()
+    .unwrap();
+    for (source, syntax) in [
+        ("", &syntax),
+        ("  text \n", &syntax),
+        ("{{ a }}{%- if b -%} x {% endif +%}", &syntax),
+        ("{# a {# nested #} comment #}{{ 'c' ~ b\"s\" }}", &syntax),
+        (
+            "{% for x in 1..=3 %}{{ x % 2 }}{% else %}{% endfor %}",
+            &syntax,
+        ),
+        ("{% raw %} {% if %} {{ {% endraw %}", &syntax),
+        // Invalid and incomplete templates are kept, too.
+        ("{% if a %}{% endfor %}{{ a $ b", &syntax),
+        ("{% raw %}{{ x", &syntax),
+        ("{# unclosed", &syntax),
+        ("{{ \"unclosed }}", &syntax),
+        ("<% if a %>{{ ä }}<%~ endif %>", &custom),
+    ] {
+        let cst = Cst::new(source, syntax);
+        assert_eq!(cst.to_string(), source);
+        let mut offset = 0;
+        for token in cst.tokens() {
+            assert!(!token.text.is_empty(), "{source:?}");
+            assert_eq!(token.offset, offset, "{source:?}");
+            offset += token.text.len();
+        }
+        assert_eq!(offset, source.len());
+    }
+}
+
+#[test]
+fn test_cst_tokens() {
+    use crate::cst::{Cst, Element, TagKind, TokenKind};
+
+    let source = "{{- a.b|f(\"x\", 'c', 1.5) ~}}";
+    let cst = Cst::new(source, &Syntax::default());
+    let [Element::Tag(tag)] = cst.elements() else {
+        panic!("expected a tag, got {:?}", cst.elements());
+    };
+    assert_eq!(tag.kind, TagKind::Expr);
+    assert_eq!(
+        tag.ws(),
+        Ws(Some(Whitespace::Suppress), Some(Whitespace::Minimize))
+    );
+    assert_eq!(
+        tag.tokens
+            .iter()
+            .filter(|token| token.kind != TokenKind::Whitespace)
+            .map(|token| (token.kind, token.text))
+            .collect::<Vec<_>>(),
+        [
+            (TokenKind::ExprStart, "{{"),
+            (TokenKind::WhitespaceControl, "-"),
+            (TokenKind::Ident, "a"),
+            (TokenKind::Punct, "."),
+            (TokenKind::Ident, "b"),
+            (TokenKind::Punct, "|"),
+            (TokenKind::Ident, "f"),
+            (TokenKind::Punct, "("),
+            (TokenKind::Str, "\"x\""),
+            (TokenKind::Punct, ","),
+            (TokenKind::Char, "'c'"),
+            (TokenKind::Punct, ","),
+            (TokenKind::Num, "1.5"),
+            (TokenKind::Punct, ")"),
+            (TokenKind::WhitespaceControl, "~"),
+            (TokenKind::ExprEnd, "}}"),
+        ],
+    );
+}
+
+#[test]
+fn test_cst_blocks() {
+    use crate::cst::{Block, Cst, Element, TokenKind};
+
+    fn keywords<'a>(block: &Block<'a>) -> Vec<Option<&'a str>> {
+        let mut keywords: Vec<_> = block.sections.iter().map(|s| s.tag.keyword()).collect();
+        keywords.push(block.end.as_ref().and_then(|end| end.keyword()));
+        keywords
+    }
+
+    let syntax = Syntax::default();
+    let cst = Cst::new(
+        "{% if a %}{% for x in y %}{% else %}{% endfor %}{% elif b %}{% else %}{% endif %}",
+        &syntax,
+    );
+    let [Element::Block(block)] = cst.elements() else {
+        panic!("expected a block, got {:?}", cst.elements());
+    };
+    assert_eq!(
+        keywords(block),
+        [Some("if"), Some("elif"), Some("else"), Some("endif")]
+    );
+    let [Element::Block(inner)] = &*block.sections[0].children else {
+        panic!("expected a block, got {:?}", block.sections[0].children);
+    };
+    assert_eq!(keywords(inner), [Some("for"), Some("else"), Some("endfor")]);
+
+    let cst = Cst::new(
+        "{% match x %}{% when 1 %}a{% endwhen %}{% else %}b{% endmatch %}",
+        &syntax,
+    );
+    let [Element::Block(block)] = cst.elements() else {
+        panic!("expected a block, got {:?}", cst.elements());
+    };
+    assert_eq!(
+        keywords(block),
+        [
+            Some("match"),
+            Some("when"),
+            Some("endwhen"),
+            Some("else"),
+            Some("endmatch")
+        ],
+    );
+
+    // Without filters, `{% let x %}` is always a declaration, like in the parser.
+    let cst = Cst::new(
+        "{% if a %}{% let x %}{% set y %}b{% endset %}{% call m() %}{% endif %}",
+        &syntax,
+    );
+    let [Element::Block(block)] = cst.elements() else {
+        panic!("expected a block, got {:?}", cst.elements());
+    };
+    let [Element::Tag(decl), Element::Block(set), Element::Tag(call)] =
+        &*block.sections[0].children
+    else {
+        panic!("unexpected children {:?}", block.sections[0].children);
+    };
+    assert_eq!(decl.keyword(), Some("let"));
+    assert_eq!(keywords(set), [Some("set"), Some("endset")]);
+    assert_eq!(call.keyword(), Some("call"));
+
+    // The content of raw blocks is text, and unclosed blocks are kept.
+    let cst = Cst::new("{% block a %}{% raw %}{% if %}{% endraw %}", &syntax);
+    let [Element::Block(block)] = cst.elements() else {
+        panic!("expected a block, got {:?}", cst.elements());
+    };
+    assert!(block.end.is_none());
+    let [Element::Block(raw)] = &*block.sections[0].children else {
+        panic!("expected a block, got {:?}", block.sections[0].children);
+    };
+    assert_eq!(keywords(raw), [Some("raw"), Some("endraw")]);
+    let [Element::Token(text)] = &*raw.sections[0].children else {
+        panic!("expected a token, got {:?}", raw.sections[0].children);
+    };
+    assert_eq!((text.kind, text.text), (TokenKind::Text, "{% if %}"));
+}
+
+#[test]
+fn test_cst_ast_spans() {
+    use crate::cst::{Cst, TokenKind};
+
+    let syntax = Syntax::default();
+    let source = "{% if is_admin %}{{ user.name }}{% endif %}";
+    let cst = Cst::new(source, &syntax);
+    let ast = cst.ast(&syntax).unwrap();
+    let [Node::If(i)] = ast.nodes() else {
+        panic!("expected an if, got {:?}", ast.nodes());
+    };
+    let Some(cond) = &i.branches[0].cond else {
+        panic!("expected a condition");
+    };
+    let token = cst
+        .token_at(cond.expr.span().offset_from(source).unwrap())
+        .unwrap();
+    assert_eq!((token.kind, token.text), (TokenKind::Ident, "is_admin"));
+    assert_eq!(token.offset, 6);
+    assert!(cst.token_at(source.len()).is_none());
+}
+
+#[test]
+fn test_cst_ast_spans_of_fixtures() {
+    use std::path::Path;
+
+    use crate::cst::{Cst, TokenKind};
+
+    // Every node of the AST has to start at the start of a token of the CST, and that token has
+    // to be inside of a tag of the same kind.
+    fn check<'a>(cst: &Cst<'a>, nodes: &[Node<'a>], path: &Path) {
+        for node in nodes {
+            let offset = node.span().offset_from(cst.source()).unwrap();
+            let token = cst.token_at(offset).unwrap();
+            assert_eq!(token.offset, offset, "{}: {node:?}", path.display());
+            let kind = match node {
+                Node::Lit(_) | Node::Comment(_) => token.kind,
+                _ => {
+                    cst.tokens()
+                        .iter()
+                        .rev()
+                        .find(|t| {
+                            t.offset <= offset
+                                && matches!(
+                                    t.kind,
+                                    TokenKind::ExprStart
+                                        | TokenKind::ExprEnd
+                                        | TokenKind::BlockStart
+                                        | TokenKind::BlockEnd
+                                )
+                        })
+                        .unwrap()
+                        .kind
+                }
+            };
+            let expected: &[TokenKind] = match node {
+                Node::Lit(_) => &[TokenKind::Text, TokenKind::Whitespace],
+                Node::Comment(_) => &[TokenKind::Comment],
+                Node::Expr(..) => &[TokenKind::ExprStart],
+                _ => &[TokenKind::BlockStart],
+            };
+            assert!(
+                expected.contains(&kind),
+                "{}: {node:?} starts at {token:?}",
+                path.display(),
+            );
+            match node {
+                Node::If(i) => {
+                    for branch in &i.branches {
+                        check(cst, &branch.nodes, path);
+                    }
+                }
+                Node::Match(m) => {
+                    for arm in &m.arms {
+                        check(cst, &arm.nodes, path);
+                    }
+                }
+                Node::Loop(l) => {
+                    check(cst, &l.body, path);
+                    check(cst, &l.else_nodes, path);
+                }
+                Node::BlockDef(b) => check(cst, &b.nodes, path),
+                Node::Macro(m) => check(cst, &m.nodes, path),
+                Node::FilterBlock(f) => check(cst, &f.nodes, path),
+                _ => {}
+            }
+        }
+    }
+
+    fn visit(dir: &Path, files: &mut Vec<std::path::PathBuf>) {
+        let Ok(entries) = dir.read_dir() else {
+            return;
+        };
+        for entry in entries {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                visit(&path, files);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    let mut files = Vec::new();
+    for dir in ["askama_derive/templates", "testing/templates"] {
+        visit(&root.join(dir), &mut files);
+    }
+
+    let syntax = Syntax::default();
+    let mut checked = 0;
+    for path in &files {
+        let Ok(source) = std::fs::read_to_string(path) else {
+            continue;
+        };
+        let cst = Cst::new(&source, &syntax);
+        // Some fixtures use other delimiters, or are invalid on purpose.
+        let Ok(ast) = cst.ast(&syntax) else {
+            continue;
+        };
+        check(&cst, ast.nodes(), path);
+        checked += 1;
+    }
+    // The fixtures are not part of the published crate.
+    assert!(
+        files.is_empty() || checked > 100,
+        "only {checked} fixtures checked"
+    );
+}
+
+#[test]
+fn test_recovering_errors() {
+    #[track_caller]
+    fn offsets(src: &str) -> Vec<usize> {
+        let errors = Ast::from_str_recovering(src, None, &Syntax::default()).unwrap_err();
+        // The error of `Ast::from_str()` is one of them.
+        let first = Ast::from_str(src, None, &Syntax::default()).unwrap_err();
+        assert!(errors.contains(&first));
+        errors.into_iter().map(|err| err.offset).collect()
+    }
+
+    assert!(
+        Ast::from_str_recovering("{% if a %}{{ b }}{% endif %}", None, &Syntax::default()).is_ok()
+    );
+    // Each error needs another pass, so their number is limited.
+    let src = "{{ a + }}".repeat(100);
+    let errors = Ast::from_str_recovering(&src, None, &Syntax::default()).unwrap_err();
+    assert_eq!(errors.len(), MAX_RECOVERED_ERRORS + 1);
+    assert_eq!(
+        errors.last().unwrap().message.as_deref(),
+        Some("too many errors, the rest of the template was not checked"),
+    );
+    assert_eq!(
+        offsets("{{ a + }} x {{ b | }} {% if c %}{{ d. }}{% endif %}"),
+        [7, 19, 38]
+    );
+    // The content of a broken block is still checked, and its end tag is no error.
+    assert_eq!(
+        offsets("{% if a + %}{{ b + }}{% else %}{{ ok }}{% endif %}{{ c ++ }}"),
+        [9, 19, 56],
+    );
+    assert_eq!(offsets("{% if a %}{% elif + %}{% endif %}{{ +}}"), [18, 36]);
+    assert_eq!(offsets("{{ a }}{% endif %}{% foo %}{{ b( }}"), [9, 20, 33]);
+    assert_eq!(
+        offsets("{% block a %}{{ é + }}{% endblock %}{{ a + }}"),
+        [21, 44]
+    );
+    // A wrong end tag does not leave its block unclosed.
+    assert_eq!(offsets("{% if a %}{% endfor %}{{ b + }}"), [12, 29]);
+    assert_eq!(offsets("{% set a | upper %}x{% endlet %}"), [22]);
+    // Unclosed tags, blocks and comments extend to the end of the template.
+    assert_eq!(offsets("{% if a %}{{ b + }}"), [2, 17]);
+    assert_eq!(offsets("{# {{ a + }}"), [2]);
+    assert_eq!(offsets("{{ a + }}{{ b"), [7, 9]);
+}
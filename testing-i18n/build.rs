@@ -0,0 +1 @@
+../testing/build.rs
\ No newline at end of file
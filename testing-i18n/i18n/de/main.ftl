@@ -0,0 +1,9 @@
+greeting = Hallo, Welt!
+welcome-user = Willkommen, { $name }!
+unread-emails =
+    { $count ->
+        [one] Du hast eine ungelesene E-Mail.
+       *[other] Du hast { $count } ungelesene E-Mails.
+    }
+login-input = Vordefinierter Wert
+    .placeholder = email@beispiel.de
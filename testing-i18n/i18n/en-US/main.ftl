@@ -0,0 +1,9 @@
+greeting = Hello, world!
+welcome-user = Welcome, { $name }!
+unread-emails =
+    { $count ->
+        [one] You have one unread email.
+       *[other] You have { $count } unread emails.
+    }
+login-input = Predefined value
+    .placeholder = email@example.com
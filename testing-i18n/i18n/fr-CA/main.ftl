@@ -0,0 +1,4 @@
+greeting = Bonjour, le monde!
+welcome-user = Bienvenue, { $name }!
+login-input = Valeur prédéfinie
+    .placeholder = courriel@exemple.ca
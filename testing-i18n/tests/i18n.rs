@@ -0,0 +1,185 @@
+use std::any::Any;
+use std::collections::HashMap;
+
+use askama::Template;
+use askama::i18n::LanguageIdentifier;
+
+#[test]
+fn test_translate() {
+    #[derive(Template)]
+    #[template(source = r#"{{ t("greeting") }}"#, ext = "txt")]
+    struct Greeting;
+
+    assert_eq!(Greeting.render().unwrap(), "Hello, world!");
+}
+
+#[test]
+fn test_translate_arguments() {
+    #[derive(Template)]
+    #[template(source = r#"{{ t("welcome-user", name = user.name) }}"#, ext = "html")]
+    struct Welcome<'a> {
+        user: User<'a>,
+        locale: &'a str,
+    }
+
+    struct User<'a> {
+        name: &'a str,
+    }
+
+    let tmpl = Welcome {
+        user: User { name: "<Alice>" },
+        locale: "en-US",
+    };
+    assert_eq!(tmpl.render().unwrap(), "Welcome, &#60;Alice&#62;!");
+
+    let tmpl = Welcome {
+        user: User { name: "Bob" },
+        locale: "de",
+    };
+    assert_eq!(tmpl.render().unwrap(), "Willkommen, Bob!");
+
+    // "de-AT" is not available, so the closest match "de" is used
+    let tmpl = Welcome {
+        user: User { name: "Carol" },
+        locale: "de-AT",
+    };
+    assert_eq!(tmpl.render().unwrap(), "Willkommen, Carol!");
+
+    // unknown locales fall back to the default locale
+    let tmpl = Welcome {
+        user: User { name: "Dave" },
+        locale: "ja",
+    };
+    assert_eq!(tmpl.render().unwrap(), "Welcome, Dave!");
+}
+
+#[test]
+fn test_translate_attribute() {
+    #[derive(Template)]
+    #[template(source = r#"{{ t("login-input.placeholder") }}"#, ext = "txt")]
+    struct Placeholder {
+        locale: String,
+    }
+
+    let tmpl = Placeholder {
+        locale: "de".to_owned(),
+    };
+    assert_eq!(tmpl.render().unwrap(), "email@beispiel.de");
+}
+
+#[test]
+fn test_translate_locale_from_values() {
+    #[derive(Template)]
+    #[template(source = r#"{{ t("welcome-user", name = name) }}"#, ext = "txt")]
+    struct Welcome<'a> {
+        name: &'a str,
+    }
+
+    let tmpl = Welcome { name: "Erin" };
+    let mut values: HashMap<String, Box<dyn Any>> = HashMap::new();
+    assert_eq!(tmpl.render_with_values(&values).unwrap(), "Welcome, Erin!");
+    values.insert("locale".to_owned(), Box::new("de"));
+    assert_eq!(
+        tmpl.render_with_values(&values).unwrap(),
+        "Willkommen, Erin!"
+    );
+    values.insert("locale".to_owned(), Box::new("fr-CA".to_owned()));
+    assert_eq!(
+        tmpl.render_with_values(&values).unwrap(),
+        "Bienvenue, Erin!"
+    );
+    let langid: LanguageIdentifier = "de-CH".parse().unwrap();
+    values.insert("locale".to_owned(), Box::new(langid));
+    assert_eq!(
+        tmpl.render_with_values(&values).unwrap(),
+        "Willkommen, Erin!"
+    );
+}
+
+#[test]
+fn test_translate_locale_from_variable() {
+    #[derive(Template)]
+    #[template(
+        source = r#"{% for locale in locales %}{{ t("greeting") }}
+{% endfor %}"#,
+        ext = "txt"
+    )]
+    struct Greetings<'a> {
+        locales: &'a [&'a str],
+    }
+
+    let tmpl = Greetings {
+        locales: &["de", "en-US", "fr-CA"],
+    };
+    assert_eq!(
+        tmpl.render().unwrap(),
+        "Hallo, Welt!\nHello, world!\nBonjour, le monde!\n"
+    );
+}
+
+// "unread-emails" is missing in the locale "fr-CA", which is only accepted with `missing = "ignore"`
+// or `missing = "warn"`.
+mod missing {
+    use askama::Template;
+
+    #[derive(Template)]
+    #[template(
+        source = r#"{{ t("unread-emails", count = count) }}"#,
+        ext = "txt",
+        config = "i18n-missing-ignore.toml"
+    )]
+    struct Unread {
+        count: u32,
+        locale: &'static str,
+    }
+
+    #[test]
+    fn test_translate_plural() {
+        let tmpl = Unread {
+            count: 1,
+            locale: "en-US",
+        };
+        assert_eq!(tmpl.render().unwrap(), "You have one unread email.");
+        let tmpl = Unread {
+            count: 3,
+            locale: "de",
+        };
+        assert_eq!(tmpl.render().unwrap(), "Du hast 3 ungelesene E-Mails.");
+    }
+
+    #[test]
+    fn test_translate_missing_in_locale() {
+        let tmpl = Unread {
+            count: 2,
+            locale: "fr-CA",
+        };
+        assert_eq!(tmpl.render().unwrap(), "You have 2 unread emails.");
+    }
+}
+
+// With `missing = "warn"`, `#[derive(Template)]` reports the missing message as a deprecation
+// warning.
+#[allow(deprecated)]
+mod missing_warn {
+    use askama::Template;
+
+    #[derive(Template)]
+    #[template(
+        source = r#"{{ t("unread-emails", count = count) }}"#,
+        ext = "txt",
+        config = "i18n-missing-warn.toml"
+    )]
+    struct Unread {
+        count: u32,
+        locale: &'static str,
+    }
+
+    #[test]
+    fn test_translate_missing_in_locale() {
+        let tmpl = Unread {
+            count: 2,
+            locale: "fr-CA",
+        };
+        assert_eq!(tmpl.render().unwrap(), "You have 2 unread emails.");
+    }
+}
@@ -0,0 +1,13 @@
+#![deny(deprecated)]
+
+use askama::Template;
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ t("unread-emails", count = 1) }}"#,
+    ext = "txt",
+    config = "i18n-missing-warn.toml"
+)]
+struct MissingTranslation;
+
+fn main() {}
@@ -0,0 +1,33 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(source = r#"{{ t("no-such-message") }}"#, ext = "txt")]
+struct UnknownMessage;
+
+#[derive(Template)]
+#[template(source = r#"{{ t(id) }}"#, ext = "txt")]
+struct NonLiteralId<'a> {
+    id: &'a str,
+}
+
+#[derive(Template)]
+#[template(source = r#"{{ t("welcome-user", name) }}"#, ext = "txt")]
+struct PositionalArgument<'a> {
+    name: &'a str,
+}
+
+#[derive(Template)]
+#[template(source = r#"{{ t("unread-emails", count = 1) }}"#, ext = "txt")]
+struct MissingTranslation;
+
+#[derive(Template)]
+#[template(source = r#"{{ t("greeting") }}"#, ext = "txt")]
+struct ShadowedMethod;
+
+impl ShadowedMethod {
+    fn t(&self, id: &str) -> String {
+        id.to_uppercase()
+    }
+}
+
+fn main() {}
@@ -45,6 +45,26 @@ fn hello_world() {
     assert_matches!(tmpl.render_into(&mut cursor), Err(askama::Error::Fmt));
 }
 
+#[test]
+fn loop_length() {
+    // Without the feature "alloc", `loop.length` needs an `ExactSizeIterator`.
+    #[derive(Template)]
+    #[template(
+        ext = "txt",
+        source = "{% for x in values %}{{ x }}/{{ loop.length }} {% endfor %}"
+    )]
+    struct Length<'a> {
+        values: &'a [u8],
+    }
+
+    let mut buffer = [0; 32];
+
+    let tmpl = Length { values: &[1, 2, 3] };
+    let mut cursor = Cursor::new(&mut buffer);
+    assert_matches!(tmpl.render_into(&mut cursor), Ok(()));
+    assert_eq!(cursor.finalize(), Ok("1/3 2/3 3/3 "));
+}
+
 struct Cursor<'a> {
     data: &'a mut [u8],
     pos: usize,
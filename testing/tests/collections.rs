@@ -0,0 +1,302 @@
+use askama::Template;
+
+struct Product<'a> {
+    name: &'a str,
+    category: &'a str,
+    price: u32,
+    tags: (&'a str, u32),
+}
+
+const PRODUCTS: &[Product<'static>] = &[
+    Product {
+        name: "pear",
+        category: "fruit",
+        price: 3,
+        tags: ("green", 2),
+    },
+    Product {
+        name: "bagel",
+        category: "bread",
+        price: 2,
+        tags: ("round", 1),
+    },
+    Product {
+        name: "apple",
+        category: "fruit",
+        price: 4,
+        tags: ("red", 3),
+    },
+];
+
+#[test]
+fn test_sort() {
+    #[derive(Template)]
+    #[template(
+        source = "{% for p in products|sort(attribute=\"name\") %}{{ p.name }} {% endfor %}|\
+                  {% for p in products|sort(true, \"price\") %}{{ p.name }} {% endfor %}|\
+                  {% for p in products|sort(reverse=true, attribute=\"tags.1\") %}\
+                      {{ p.name }} \
+                  {% endfor %}|\
+                  {% for n in numbers|sort %}{{ n }}{% endfor %}|\
+                  {% for n in numbers|reverse %}{{ n }}{% endfor %}|\
+                  {% for n in numbers|sort|reverse %}{{ n }}{% endfor %}",
+        ext = "txt",
+        config = "builtin-filters.toml"
+    )]
+    struct Sort<'a> {
+        products: &'a [Product<'a>],
+        numbers: Vec<i32>,
+    }
+
+    let tmpl = Sort {
+        products: PRODUCTS,
+        numbers: vec![2, 3, 1],
+    };
+    assert_eq!(
+        tmpl.to_string(),
+        "apple bagel pear |apple pear bagel |apple pear bagel |123|132|321"
+    );
+}
+
+#[test]
+fn test_unique_groupby() {
+    #[derive(Template)]
+    #[template(
+        source = "{% for n in numbers|unique %}{{ n.0 }}{% endfor %}|\
+                  {% for p in products|unique(attribute=\"category\") %}{{ p.name }} {% endfor %}|\
+                  {% for (category, items) in products|groupby(\"category\") -%}\
+                      {{ category }}: \
+                      {%- for p in items|sort(attribute=\"name\") %} {{ p.name }}{% endfor %}; \
+                  {%- endfor %}|\
+                  {% for (n, items) in numbers|groupby(attribute=\"0\") %}\
+                      {{ n }}x{{ items|length }}\
+                  {% endfor %}",
+        ext = "txt",
+        config = "builtin-filters.toml"
+    )]
+    struct UniqueGroupBy<'a> {
+        products: &'a [Product<'a>],
+        numbers: [(u8,); 5],
+    }
+
+    let tmpl = UniqueGroupBy {
+        products: PRODUCTS,
+        numbers: [(3,), (1,), (3,), (2,), (3,)],
+    };
+    assert_eq!(
+        tmpl.to_string(),
+        "312|pear bagel |bread: bagel;fruit: apple pear;|1x12x13x3"
+    );
+}
+
+#[test]
+fn test_batch_slice() {
+    #[derive(Template)]
+    #[template(
+        source = "{% for row in items|batch(3) %}[{{ row|join(\",\") }}]{% endfor %}|\
+                  {% for row in items|batch(3, &\"-\") %}[{{ row|join(\",\") }}]{% endfor %}|\
+                  {% for col in items|slice(3) %}[{{ col|join(\",\") }}]{% endfor %}|\
+                  {% for col in items|slice(slices=3, fill_with=&\"-\") %}\
+                      [{{ col|join(\",\") }}]\
+                  {% endfor %}|\
+                  {% for row in (1_u32..8)|batch(linecount=4) %}{{ row|sum }} {% endfor %}",
+        ext = "txt",
+        config = "builtin-filters.toml"
+    )]
+    struct BatchSlice<'a> {
+        items: Vec<&'a str>,
+    }
+
+    let tmpl = BatchSlice {
+        items: vec!["a", "b", "c", "d", "e"],
+    };
+    assert_eq!(
+        tmpl.to_string(),
+        "[a,b,c][d,e]|[a,b,c][d,e,-]|[a,b][c,d][e]|[a,b][c,d][e,-]|10 18 "
+    );
+}
+
+#[test]
+fn test_aggregates() {
+    #[derive(Template)]
+    #[template(
+        source = "{{ products|length }} {{ products|sum(attribute=\"price\") }} \
+                  {{ products|sum(\"tags.1\", 10) }} {{ numbers|sum }} \
+                  {%- if let Some(p) = products|first %} {{ p.name }}{% endif %}
+                  {%- if let Some(p) = products|last %} {{ p.name }}{% endif %}
+                  {%- if let Some(p) = products|min(attribute=\"price\") %} {{ p.name }}{% endif %}
+                  {%- if let Some(p) = products|max(attribute=\"tags.0\") %} {{ p.name }}{% endif %}
+                  {%- if let Some(n) = numbers|max %} {{ n }}{% endif %}
+                  {%- if let Some(n) = empty|first %} {{ n }}{% else %} -{% endif %}",
+        ext = "txt",
+        config = "builtin-filters.toml"
+    )]
+    struct Aggregates<'a> {
+        products: &'a [Product<'a>],
+        numbers: Vec<f32>,
+        empty: Vec<u32>,
+    }
+
+    let tmpl = Aggregates {
+        products: PRODUCTS,
+        numbers: vec![0.5, 2.0, 0.25],
+        empty: vec![],
+    };
+    assert_eq!(tmpl.to_string(), "3 9 16 2.75 pear apple bagel bagel 2 -");
+}
+
+#[test]
+fn test_nested_loops() {
+    #[derive(Template)]
+    #[template(
+        source = "{% for row in rows %}{% for n in row|sort|unique %}{{ n }}{% endfor %};\
+                  {% endfor %}",
+        ext = "txt",
+        config = "builtin-filters.toml"
+    )]
+    struct NestedLoops {
+        rows: Vec<Vec<u8>>,
+    }
+
+    let tmpl = NestedLoops {
+        rows: vec![vec![3, 1, 3], vec![], vec![2, 2]],
+    };
+    assert_eq!(tmpl.to_string(), "13;;2;");
+}
+
+struct User<'a> {
+    name: &'a str,
+    email: String,
+    active: bool,
+    deleted: Option<&'a str>,
+    age: u8,
+}
+
+fn users() -> Vec<User<'static>> {
+    vec![
+        User {
+            name: "Kim",
+            email: "kim@example.org".into(),
+            active: true,
+            deleted: None,
+            age: 42,
+        },
+        User {
+            name: "Ann",
+            email: "ann@example.org".into(),
+            active: false,
+            deleted: Some("2024-01-01"),
+            age: 17,
+        },
+        User {
+            name: "Bob",
+            email: "bob@example.org".into(),
+            active: true,
+            deleted: None,
+            age: 18,
+        },
+    ]
+}
+
+#[test]
+fn test_map_select() {
+    #[derive(Template)]
+    #[template(
+        source = "{{ users|map(attribute=\"email\")|join(\", \") }}\n\
+                  {{ users|selectattr(\"active\")|map(attribute=\"name\")|join(\",\") }}\n\
+                  {{ users|rejectattr(\"deleted\")|map(attribute=\"name\")|join(\",\") }}\n\
+                  {{ users|selectattr(\"age\", \">=\", 18)|map(attribute=\"name\")|join(\",\") }}\n\
+                  {{ users|rejectattr(\"name\", \"eq\", name)|map(attribute=\"name\")|join(\",\") }}\n\
+                  {{ users|selectattr(\"deleted\", \"none\")|map(attribute=\"age\")|join(\",\") }}\n\
+                  {% for user in users|selectattr(\"email\", \"lt\", \"b\") %}{{ user.name }}{% endfor %}",
+        ext = "html",
+        config = "builtin-filters.toml"
+    )]
+    struct MapSelect<'a> {
+        users: Vec<User<'a>>,
+        name: String,
+    }
+
+    let tmpl = MapSelect {
+        users: users(),
+        name: "Bob".into(),
+    };
+    assert_eq!(
+        tmpl.to_string(),
+        "kim@example.org, ann@example.org, bob@example.org\n\
+         Kim,Bob\n\
+         Kim,Bob\n\
+         Kim,Bob\n\
+         Kim,Ann\n\
+         42,18\n\
+         Ann"
+    );
+}
+
+#[test]
+fn test_select_reject() {
+    #[derive(Template)]
+    #[template(
+        source = "{{ numbers|select(\"odd\")|join(\",\") }}|\
+                  {{ numbers|reject(\"even\")|join(\",\") }}|\
+                  {{ numbers|select(\"divisibleby\", 3)|join(\",\") }}|\
+                  {{ numbers|select|join(\",\") }}|\
+                  {{ numbers|reject(\"gt\", -1)|join(\",\") }}|\
+                  {{ numbers|select(\"ne\", limit)|select(\"<=\", 4)|join(\",\") }}|\
+                  {{ words|select|join(\",\") }}|\
+                  {{ words|reject(\"equalto\", \"b\")|sort|join(\",\") }}|\
+                  {{ flags|select(\"false\")|length }}|\
+                  {{ maybe|select(\"none\")|length }}|\
+                  {% for n in numbers|select(\"odd\")|reject(\"eq\", 5) %}{{ n }}{% endfor %}",
+        ext = "txt",
+        config = "builtin-filters.toml"
+    )]
+    struct SelectReject<'a> {
+        numbers: &'a [i32],
+        limit: i32,
+        words: Vec<&'a str>,
+        flags: [bool; 3],
+        maybe: Vec<Option<u8>>,
+    }
+
+    let tmpl = SelectReject {
+        numbers: &[0, 1, 2, 3, 4, 5, 6],
+        limit: 2,
+        words: vec!["c", "", "b", "a"],
+        flags: [true, false, false],
+        maybe: vec![None, Some(0), None],
+    };
+    assert_eq!(
+        tmpl.to_string(),
+        "1,3,5|1,3,5|0,3,6|1,2,3,4,5,6||0,1,3,4|c,b,a|,a,c|2|2|13"
+    );
+}
+
+#[test]
+fn test_custom_collection_filters() {
+    // Without `builtin_filters = ["collections"]`, the names refer to the custom filters.
+    mod filters {
+        pub fn length(value: &str) -> askama::Result<usize> {
+            Ok(value.chars().count())
+        }
+
+        pub fn first(value: &str) -> askama::Result<char> {
+            Ok(value.chars().next().unwrap_or('-'))
+        }
+
+        pub fn select(value: &str, idx: usize) -> askama::Result<char> {
+            Ok(value.chars().nth(idx).unwrap_or('-'))
+        }
+    }
+
+    #[derive(Template)]
+    #[template(
+        source = "{{ word|length }}|{{ word|first }}|{{ word|select(2) }}",
+        ext = "txt"
+    )]
+    struct Custom<'a> {
+        word: &'a str,
+    }
+
+    assert_eq!(Custom { word: "äbc" }.to_string(), "3|ä|c");
+}
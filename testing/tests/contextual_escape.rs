@@ -0,0 +1,202 @@
+use askama::Template;
+
+#[test]
+fn test_contextual_escape_text_and_attrs() {
+    #[derive(Template)]
+    #[template(
+        source = r#"<p title="{{ s }}" class={{ s }}>{{ s }}</p><!-- {{ s }} -->"#,
+        ext = "html",
+        contextual_escape = true
+    )]
+    struct Attrs<'a> {
+        s: &'a str,
+    }
+
+    assert_eq!(
+        Attrs { s: "a \"b\" <c>" }.render().unwrap(),
+        "<p title=\"a &#34;b&#34; &#60;c&#62;\" class=a&#32;&#34;b&#34;&#32;&#60;c&#62;>\
+         a &#34;b&#34; &#60;c&#62;</p><!-- a &#34;b&#34; &#60;c&#62; -->"
+    );
+}
+
+#[test]
+fn test_contextual_escape_url() {
+    #[derive(Template)]
+    #[template(
+        source = r#"<a href="{{ url }}">x</a> <img src='/img/{{ name }}'>"#,
+        ext = "html",
+        contextual_escape = true
+    )]
+    struct Url<'a> {
+        url: &'a str,
+        name: &'a str,
+    }
+
+    assert_eq!(
+        Url {
+            url: "https://example.com/?a=1&b=2",
+            name: "my picture.png",
+        }
+        .render()
+        .unwrap(),
+        r#"<a href="https://example.com/?a=1&#38;b=2">x</a> <img src='/img/my%20picture.png'>"#
+    );
+    assert_eq!(
+        Url {
+            url: "javascript:alert(1)",
+            name: "\"><script>",
+        }
+        .render()
+        .unwrap(),
+        r#"<a href="javascript%3Aalert(1)">x</a> <img src='/img/%22%3E%3Cscript%3E'>"#
+    );
+}
+
+#[test]
+fn test_contextual_escape_url_query() {
+    #[derive(Template)]
+    #[template(
+        source = r#"<a href="{{ base }}?q={{ q }}#{{ q }}">x</a>"#,
+        ext = "html",
+        contextual_escape = true
+    )]
+    struct Query<'a> {
+        base: &'a str,
+        q: &'a str,
+    }
+
+    // the value cannot add query parameters, or end the query
+    assert_eq!(
+        Query {
+            base: "/search",
+            q: "a b&admin=1#x+y",
+        }
+        .render()
+        .unwrap(),
+        r#"<a href="/search?q=a%20b%26admin%3D1%23x%2By#a%20b%26admin%3D1%23x%2By">x</a>"#
+    );
+}
+
+#[test]
+fn test_contextual_escape_js() {
+    #[derive(Template)]
+    #[template(
+        source = r#"<script>
+// {{ s }}
+let a = "{{ s }}", b = '{{ s }}';
+</script>
+<button onclick="alert('{{ s }}')">{{ s }}</button>"#,
+        ext = "html",
+        contextual_escape = true
+    )]
+    struct Js<'a> {
+        s: &'a str,
+    }
+
+    assert_eq!(
+        Js {
+            s: "</script>'\"\n"
+        }
+        .render()
+        .unwrap(),
+        r#"<script>
+// \u003C\u002Fscript\u003E\u0027\u0022\u000A
+let a = "\u003C\u002Fscript\u003E\u0027\u0022\u000A", b = '\u003C\u002Fscript\u003E\u0027\u0022\u000A';
+</script>
+<button onclick="alert('\u003C\u002Fscript\u003E\u0027\u0022\u000A')">&#60;/script&#62;&#39;&#34;
+</button>"#
+    );
+}
+
+#[test]
+fn test_contextual_escape_js_template_literal() {
+    #[derive(Template)]
+    #[template(
+        source = r#"<script>let r = /"/, a = `${ r.source } {{ s }}`;</script>"#,
+        ext = "html",
+        contextual_escape = true
+    )]
+    struct Js<'a> {
+        s: &'a str,
+    }
+
+    assert_eq!(
+        Js { s: "${alert(1)}`" }.render().unwrap(),
+        r#"<script>let r = /"/, a = `${ r.source } \u0024{alert(1)}\u0060`;</script>"#
+    );
+}
+
+#[test]
+fn test_contextual_escape_css() {
+    #[derive(Template)]
+    #[template(
+        source = r#"<style>p { color: {{ color }}; }</style><p style="color: {{ color }}">"#,
+        ext = "html",
+        contextual_escape = true
+    )]
+    struct Css<'a> {
+        color: &'a str,
+    }
+
+    assert_eq!(
+        Css {
+            color: "red;}</style>"
+        }
+        .render()
+        .unwrap(),
+        r#"<style>p { color: red\3b \7d \3c \2f style\3e ; }</style><p style="color: red\3b \7d \3c \2f style\3e ">"#
+    );
+}
+
+#[test]
+fn test_contextual_escape_branches() {
+    #[derive(Template)]
+    #[template(
+        source = r#"<ul>{% for item in items %}<li {% if loop.first %}class="first"{% endif %}>
+            <a href="/items/{{ item }}">{{ item }}</a></li>{% endfor %}</ul>
+            <script>var x = {% if items.is_empty() %}"none"{% else %}"{{ items[0] }}"{% endif %};</script>"#,
+        ext = "html",
+        contextual_escape = true
+    )]
+    struct Branches<'a> {
+        items: &'a [&'a str],
+    }
+
+    assert_eq!(
+        Branches {
+            items: &["a b", "<c>"]
+        }
+        .render()
+        .unwrap(),
+        r#"<ul><li class="first">
+            <a href="/items/a%20b">a b</a></li><li >
+            <a href="/items/%3Cc%3E">&#60;c&#62;</a></li></ul>
+            <script>var x = "a b";</script>"#
+    );
+}
+
+#[test]
+fn test_contextual_escape_safe() {
+    #[derive(Template)]
+    #[template(
+        source = r#"<{{ tag|safe }} {{ attrs|safe }}><script>var a = {{ json|safe }};</script>"#,
+        ext = "html",
+        contextual_escape = true
+    )]
+    struct Safe<'a> {
+        tag: &'a str,
+        attrs: &'a str,
+        json: &'a str,
+    }
+
+    assert_eq!(
+        Safe {
+            tag: "div",
+            attrs: "hidden",
+            json: "[1, 2]",
+        }
+        .render()
+        .unwrap(),
+        "<div hidden><script>var a = [1, 2];</script>"
+    );
+}
@@ -0,0 +1,219 @@
+#![cfg(all(feature = "chrono", feature = "jiff", feature = "time"))]
+
+use askama::Template;
+
+#[test]
+fn test_date_time_filters_chrono() {
+    #[derive(Template)]
+    #[template(
+        source = "{{ value|date }}|{{ value|time }}|{{ value|datetime }}|\
+                  {{ value|datetime(\"%a, %-d %b %Y %I:%M %p %:z\") }}|{{ value|date(fmt) }}",
+        ext = "html",
+        config = "builtin-filters.toml"
+    )]
+    struct Chrono<'a> {
+        value: chrono::DateTime<chrono::FixedOffset>,
+        fmt: &'a str,
+    }
+
+    let offset = chrono::FixedOffset::east_opt(2 * 3600).unwrap();
+    let value = chrono::NaiveDate::from_ymd_opt(2024, 2, 29)
+        .unwrap()
+        .and_hms_opt(18, 5, 9)
+        .unwrap()
+        .and_local_timezone(offset)
+        .unwrap();
+    assert_eq!(
+        Chrono {
+            value,
+            fmt: "%d.%m.%y"
+        }
+        .render()
+        .unwrap(),
+        "2024-02-29|18:05:09|2024-02-29 18:05:09|Thu, 29 Feb 2024 06:05 PM +02:00|29.02.24",
+    );
+}
+
+#[test]
+fn test_date_time_filters_time() {
+    #[derive(Template)]
+    #[template(
+        source = "{{ date|date(\"%B %e, %Y\") }} {{ clock|time(\"%H:%M:%S%.3f\") }} \
+                  {{ value|datetime(\"%FT%T%z\") }}",
+        ext = "html",
+        config = "builtin-filters.toml"
+    )]
+    struct Time {
+        date: time::Date,
+        clock: time::Time,
+        value: time::OffsetDateTime,
+    }
+
+    let tmpl = Time {
+        date: time::macros::date!(1999 - 12 - 31),
+        clock: time::macros::time!(23:59:59.123_456),
+        value: time::macros::datetime!(2000-01-01 00:00 -05:30),
+    };
+    assert_eq!(
+        tmpl.render().unwrap(),
+        "December 31, 1999 23:59:59.123 2000-01-01T00:00:00-0530",
+    );
+}
+
+#[test]
+fn test_date_time_filters_jiff() {
+    #[derive(Template)]
+    #[template(
+        source = "{{ civil|datetime }} {{ timestamp|datetime(\"%s %Z\") }}",
+        ext = "html",
+        config = "builtin-filters.toml"
+    )]
+    struct Jiff {
+        civil: jiff::civil::DateTime,
+        timestamp: jiff::Timestamp,
+    }
+
+    let tmpl = Jiff {
+        civil: jiff::civil::date(2025, 10, 17).at(8, 0, 0, 0),
+        timestamp: jiff::Timestamp::from_second(1_000_000_000).unwrap(),
+    };
+    assert_eq!(tmpl.render().unwrap(), "2025-10-17 08:00:00 1000000000 UTC");
+}
+
+#[test]
+fn test_date_time_filter_errors() {
+    #[derive(Template)]
+    #[template(
+        source = "{{ value|date(fmt) }}",
+        ext = "txt",
+        config = "builtin-filters.toml"
+    )]
+    struct Format<'a, T: askama::filters::DateTimeValue> {
+        value: T,
+        fmt: &'a str,
+    }
+
+    let date = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+    let err = Format {
+        value: date,
+        fmt: "%H",
+    }
+    .render()
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "format specifier `%H` needs a value with a time"
+    );
+    let err = Format {
+        value: date,
+        fmt: "%Q",
+    }
+    .render()
+    .unwrap_err();
+    assert_eq!(err.to_string(), "invalid format specifier `%Q`");
+    let naive = date.and_hms_opt(0, 0, 0).unwrap();
+    let err = Format {
+        value: naive,
+        fmt: "%z",
+    }
+    .render()
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "format specifier `%z` needs a value with a UTC offset"
+    );
+}
+
+#[test]
+fn test_timesince_timeuntil() {
+    #[derive(Template)]
+    #[template(
+        source = "{{ past|timesince(now) }}|{{ future|timeuntil(now) }}|{{ future|timesince(now) }}",
+        ext = "html",
+        config = "builtin-filters.toml"
+    )]
+    struct Relative {
+        past: jiff::civil::DateTime,
+        future: jiff::civil::DateTime,
+        now: jiff::civil::DateTime,
+    }
+
+    let now = jiff::civil::date(2025, 10, 17).at(12, 0, 0, 0);
+    let tmpl = Relative {
+        past: jiff::civil::date(2024, 8, 1).at(12, 0, 0, 0),
+        future: jiff::civil::date(2025, 10, 19).at(15, 30, 0, 0),
+        now,
+    };
+    assert_eq!(
+        tmpl.render().unwrap(),
+        "1 year, 2 months|2 days, 3 hours|0 minutes"
+    );
+}
+
+#[test]
+fn test_timesince_default_now() {
+    #[derive(Template)]
+    #[template(
+        source = "{{ value|timesince }}|{{ value|timeuntil }}",
+        ext = "html",
+        config = "builtin-filters.toml"
+    )]
+    struct Now {
+        value: chrono::DateTime<chrono::Utc>,
+    }
+
+    let value = chrono::DateTime::from_timestamp(0, 0).unwrap();
+    let output = Now { value }.render().unwrap();
+    let (since, until) = output.split_once('|').unwrap();
+    assert!(since.contains(" years"), "{output}");
+    assert_eq!(until, "0 minutes");
+}
+
+#[test]
+fn test_mixed_libraries() {
+    #[derive(Template)]
+    #[template(
+        source = "{{ since|timesince(now) }}",
+        ext = "html",
+        config = "builtin-filters.toml"
+    )]
+    struct Mixed {
+        since: time::OffsetDateTime,
+        now: std::time::SystemTime,
+    }
+
+    let since = time::macros::datetime!(2000-01-01 00:00 UTC);
+    let now = std::time::UNIX_EPOCH + std::time::Duration::from_secs(946_684_800 + 3 * 3600 + 120);
+    assert_eq!(Mixed { since, now }.render().unwrap(), "3 hours, 2 minutes");
+}
+
+// Without `builtin_filters = ["datetime"]` in the configuration file, the names refer to the
+// custom filters of the crate.
+mod custom {
+    use askama::Template;
+
+    mod filters {
+        use chrono::Datelike;
+
+        pub fn date(value: &chrono::NaiveDate) -> askama::Result<String> {
+            Ok(format!(
+                "{}/{}/{}",
+                value.day(),
+                value.month(),
+                value.year()
+            ))
+        }
+    }
+
+    #[test]
+    fn test_custom_date_filter() {
+        #[derive(Template)]
+        #[template(source = "{{ value|date }}", ext = "txt")]
+        struct Custom {
+            value: chrono::NaiveDate,
+        }
+
+        let value = chrono::NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        assert_eq!(Custom { value }.render().unwrap(), "29/2/2024");
+    }
+}
@@ -74,6 +74,50 @@ fn filter_opt_escaper_html() {
     );
 }
 
+#[test]
+fn filter_opt_escaper_js() {
+    #[derive(Template)]
+    #[template(
+        source = r#"<script>const a = "{{ s|escape("js") }}", b = '{{ s|e("js") }}';</script>"#,
+        ext = "html"
+    )]
+    struct OptEscaperJsTemplate<'a> {
+        s: &'a str,
+    }
+
+    let t = OptEscaperJsTemplate {
+        s: "</script>\"'\\ \u{2028}\n",
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        r#"<script>const a = "\u003C\u002Fscript\u003E\u0022\u0027\u005C \u2028\u000A", b = '\u003C\u002Fscript\u003E\u0022\u0027\u005C \u2028\u000A';</script>"#
+    );
+}
+
+#[test]
+fn filter_js_extension() {
+    #[derive(Template)]
+    #[template(source = "window.user = `{{ user }}`;", ext = "js")]
+    struct JsTemplate<'a> {
+        user: &'a str,
+    }
+
+    #[derive(Template)]
+    #[template(source = "export const user = '{{ user }}';", ext = "mjs")]
+    struct MjsTemplate<'a> {
+        user: &'a str,
+    }
+
+    assert_eq!(
+        JsTemplate { user: "`${x}`" }.render().unwrap(),
+        r"window.user = `\u0060\u0024{x}\u0060`;"
+    );
+    assert_eq!(
+        MjsTemplate { user: "it's" }.render().unwrap(),
+        r"export const user = 'it\u0027s';"
+    );
+}
+
 #[test]
 fn filter_format() {
     #[derive(Template)]
@@ -450,3 +494,121 @@ fn test_whitespace_around_filter_operator() {
 
     assert_eq!(S.render().unwrap(), "12\n8\n4");
 }
+
+#[test]
+fn test_default() {
+    #[derive(Template)]
+    #[template(
+        source = "{{ a|default(\"-\") }}|{{ b|default(\"-\") }}|{{ b|default(\"-\", true) }}|\
+                  {{ c|default(fallback) }}|{{ d|default(\"-\", true) }}|{{ n|default(0) }}",
+        ext = "html",
+        config = "builtin-filters.toml"
+    )]
+    struct Default<'a> {
+        a: Option<&'a str>,
+        b: &'a str,
+        c: Option<Option<String>>,
+        d: String,
+        n: Option<u32>,
+        fallback: String,
+    }
+
+    let tmpl = Default {
+        a: None,
+        b: "",
+        c: Some(None),
+        d: String::new(),
+        n: None,
+        fallback: "<none>".into(),
+    };
+    assert_eq!(tmpl.to_string(), "-||-|&#60;none&#62;|-|0");
+
+    let tmpl = Default {
+        a: Some("<a>"),
+        b: "b",
+        c: Some(Some("c".into())),
+        d: "d".into(),
+        n: Some(7),
+        fallback: "<none>".into(),
+    };
+    assert_eq!(tmpl.to_string(), "&#60;a&#62;|b|b|c|d|7");
+}
+
+#[test]
+fn test_default_any_value() {
+    struct Name(&'static str);
+
+    impl std::fmt::Display for Name {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(self.0)
+        }
+    }
+
+    #[derive(Template)]
+    #[template(
+        source = "{{ name|default(fallback) }}|{{ fallback|default(\"-\") }}",
+        ext = "txt",
+        config = "builtin-filters.toml"
+    )]
+    struct DefaultAny {
+        name: Option<Name>,
+        fallback: Name,
+    }
+
+    let tmpl = DefaultAny {
+        name: None,
+        fallback: Name("nobody"),
+    };
+    assert_eq!(tmpl.to_string(), "nobody|nobody");
+    let tmpl = DefaultAny {
+        name: Some(Name("kim")),
+        fallback: Name("nobody"),
+    };
+    assert_eq!(tmpl.to_string(), "kim|nobody");
+}
+
+#[test]
+fn test_default_custom_filter() {
+    mod filters {
+        pub fn default<T: std::fmt::Display>(value: &Option<T>) -> askama::Result<String> {
+            Ok(match value {
+                Some(value) => value.to_string(),
+                None => "custom".into(),
+            })
+        }
+    }
+
+    #[derive(Template)]
+    #[template(source = "{{ a|default }}", ext = "txt")]
+    struct CustomDefault {
+        a: Option<u32>,
+    }
+
+    assert_eq!(CustomDefault { a: None }.to_string(), "custom");
+    assert_eq!(CustomDefault { a: Some(1) }.to_string(), "1");
+}
+
+#[test]
+fn test_default_loop() {
+    #[derive(Template)]
+    #[template(
+        source = "{% for x in a|default([\"x\", \"y\"]) %}{{ x }}{% endfor %}|\
+                  {% for x in b|default([\"z\"], true) %}{{ x }}{% endfor %}",
+        ext = "txt",
+        config = "builtin-filters.toml"
+    )]
+    struct DefaultLoop<'a> {
+        a: Option<Vec<&'a str>>,
+        b: &'a [&'a str],
+    }
+
+    assert_eq!(DefaultLoop { a: None, b: &[] }.to_string(), "xy|z");
+    assert_eq!(
+        DefaultLoop {
+            a: Some(vec!["a"]),
+            b: &["b", "c"]
+        }
+        .to_string(),
+        "a|bc"
+    );
+}
@@ -0,0 +1,99 @@
+// The derived code only checks the template files in `testing/templates`, which must not be
+// modified by the tests. So the modifications are tested on a copy of the template, by calling
+// the hot-reloading runtime support directly.
+#![cfg(all(feature = "hot-reload", debug_assertions))]
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use askama::Template;
+use askama::helpers::hot_reload;
+use askama::interpreter::Whitespace;
+
+#[derive(Template)]
+#[template(path = "hot-reload.html")]
+struct HotReload<'a> {
+    name: &'a str,
+    items: Vec<u32>,
+    started: std::time::Instant,
+}
+
+/// A temporary template directory, removed at the end of the test
+struct TempDir(PathBuf);
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Write `source` to `path`, and set a distinct modification time
+fn write(path: &Path, source: &str, seconds: u64) {
+    std::fs::write(path, source).unwrap();
+    File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+        .unwrap();
+}
+
+fn leak<T>(value: T) -> &'static T {
+    Box::leak(Box::new(value))
+}
+
+#[test]
+fn test_hot_reload() {
+    let tmpl = HotReload {
+        name: "<world>",
+        items: vec![1, 2, 3],
+        started: std::time::Instant::now(),
+    };
+    assert_eq!(tmpl.render().unwrap(), "Hello, &#60;world&#62;!");
+
+    let dir =
+        TempDir(std::env::temp_dir().join(format!("askama-hot-reload-{}", std::process::id())));
+    std::fs::create_dir_all(&dir.0).unwrap();
+    let path = dir.0.join("hot-reload.html");
+    write(&path, "Hello, {{ name }}!\n", 1);
+
+    let template = leak(hot_reload::Template {
+        path: "hot-reload.html",
+        sources: leak([(
+            leak(path.to_str().unwrap().to_owned()).as_str(),
+            "Hello, {{ name }}!",
+        )]),
+        dirs: leak([leak(dir.0.to_str().unwrap().to_owned()).as_str()]),
+        syntax: ["{%", "%}", "{{", "}}", "{#", "#}"],
+        whitespace: Whitespace::Preserve,
+    });
+    let render = || {
+        let mut buf = String::new();
+        hot_reload::render_into(&mut buf, template, askama::filters::Html, &tmpl)
+            .map(|result| result.map(|()| buf).unwrap())
+    };
+
+    // An unchanged template is not interpreted.
+    assert_eq!(render(), None);
+
+    // A modified template is interpreted.
+    write(
+        &path,
+        "Bye, {{ name }}!{% for i in items %} {{ i }}{% endfor %}\n",
+        2,
+    );
+    assert_eq!(render().as_deref(), Some("Bye, &#60;world&#62;! 1 2 3"));
+
+    // The compiled code is used if the template cannot be parsed …
+    write(&path, "Bye, {{ name !\n", 3);
+    assert_eq!(render(), None);
+
+    // … or if it uses a field that cannot be reflected.
+    write(&path, "{{ started.elapsed().as_secs() }}\n", 4);
+    assert_eq!(render(), None);
+
+    // The compiled code is used again if the template was restored.
+    write(&path, "Hello, {{ name }}!\n", 5);
+    assert_eq!(render(), None);
+}
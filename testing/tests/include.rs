@@ -102,3 +102,96 @@ fn block_in_include() {
         "block_in_partial: from partial!\n"
     );
 }
+
+#[test]
+fn test_include_dynamic() {
+    #[derive(Template)]
+    #[template(path = "include-dynamic.html")]
+    struct Dashboard<'a> {
+        widgets: Vec<Widget<'a>>,
+    }
+
+    struct Widget<'a> {
+        kind: &'a str,
+        title: &'a str,
+    }
+
+    let dashboard = Dashboard {
+        widgets: vec![
+            Widget {
+                kind: "widgets/list.html",
+                title: "Todo",
+            },
+            Widget {
+                kind: "widgets/chart.html",
+                title: "Sales",
+            },
+        ],
+    };
+    assert_eq!(dashboard.render().unwrap(), "list: Todo\nchart: Sales\n");
+
+    let dashboard = Dashboard {
+        widgets: vec![Widget {
+            kind: "widgets/map.html",
+            title: "Offices",
+        }],
+    };
+    assert!(matches!(
+        dashboard.render(),
+        Err(askama::Error::UnknownTemplate)
+    ));
+}
+
+#[test]
+fn test_include_with() {
+    #[derive(Template)]
+    #[template(path = "include-with.html")]
+    struct Report<'a> {
+        heading: &'a str,
+        chart: Widget<'a>,
+        list: Widget<'a>,
+    }
+
+    struct Widget<'a> {
+        title: &'a str,
+    }
+
+    let report = Report {
+        heading: "Quarterly report",
+        chart: Widget { title: "Sales" },
+        list: Widget { title: "Todo" },
+    };
+    assert_eq!(
+        report.render().unwrap(),
+        "chart: Sales\n\
+         list: Todo\n\
+         Quarterly report: Sales\n\
+         Title: Sales"
+    );
+}
+
+#[test]
+fn test_include_fallback() {
+    #[derive(Template)]
+    #[template(path = "include-fallback.html")]
+    struct Page<'a> {
+        kind: &'a str,
+        list: Widget<'a>,
+    }
+
+    struct Widget<'a> {
+        title: &'a str,
+    }
+
+    let page = Page {
+        kind: "widgets/chart.html",
+        list: Widget { title: "Todo" },
+    };
+    assert_eq!(page.render().unwrap(), "list: Todo\n[]\n[]\n[chart: Todo]");
+
+    let page = Page {
+        kind: "theme/header.html",
+        list: Widget { title: "Todo" },
+    };
+    assert_eq!(page.render().unwrap(), "list: Todo\n[]\n[]\n[]");
+}
@@ -0,0 +1,847 @@
+#![cfg(feature = "interpreter")]
+
+use std::collections::HashMap;
+
+use askama::Template;
+use askama::interpreter::{Environment, ErrorKind, Value, Whitespace};
+use serde::Serialize;
+
+fn env() -> Environment {
+    let mut env = Environment::new();
+    env.add_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/templates"));
+    env
+}
+
+/// Renders `tmpl` with the derived implementation and with the interpreter,
+/// and checks that both outputs are identical.
+#[track_caller]
+fn assert_same<T: Template + Serialize>(name: &str, tmpl: &T) -> String {
+    let compiled = tmpl.render().unwrap();
+    let interpreted = env().render(name, tmpl).unwrap();
+    assert_eq!(compiled, interpreted, "template {name:?}");
+    interpreted
+}
+
+#[test]
+fn test_simple() {
+    #[derive(Template, Serialize)]
+    #[template(path = "simple.html")]
+    struct VariablesTemplate<'a> {
+        strvar: &'a str,
+        num: i64,
+        i18n: String,
+    }
+
+    let s = VariablesTemplate {
+        strvar: "foo",
+        num: 42,
+        i18n: "Iñtërnâtiônàlizætiøn".to_string(),
+    };
+    assert_same("simple.html", &s);
+}
+
+#[test]
+fn test_escape() {
+    #[derive(Template, Serialize)]
+    #[template(path = "filters.html")]
+    struct TestTemplate<'a> {
+        strvar: &'a str,
+    }
+
+    let s = TestTemplate {
+        strvar: "// my <html> is \"unsafe\" & should be 'escaped'",
+    };
+    let output = assert_same("filters.html", &s);
+    assert_eq!(
+        output,
+        "// my &#60;html&#62; is &#34;unsafe&#34; &#38; should be &#39;escaped&#39;"
+    );
+}
+
+#[test]
+fn test_if() {
+    #[derive(Template, Serialize)]
+    #[template(path = "if.html")]
+    struct IfTemplate {
+        cond: bool,
+    }
+
+    assert_same("if.html", &IfTemplate { cond: true });
+    assert_same("if.html", &IfTemplate { cond: false });
+}
+
+#[test]
+fn test_for() {
+    #[derive(Template, Serialize)]
+    #[template(path = "for.html")]
+    struct ForTemplate<'a> {
+        strings: Vec<&'a str>,
+        tuple_strings: Vec<(&'a str, &'a str)>,
+    }
+
+    let s = ForTemplate {
+        strings: vec!["A", "alfa", "1"],
+        tuple_strings: vec![("B", "beta")],
+    };
+    assert_same("for.html", &s);
+}
+
+#[test]
+fn test_nested_for() {
+    #[derive(Template, Serialize)]
+    #[template(path = "nested-for.html")]
+    struct NestedForTemplate<'a> {
+        seqs: &'a [&'a [&'a str]],
+    }
+
+    let s = NestedForTemplate {
+        seqs: &[&["a", "b"], &["c", "d"]],
+    };
+    assert_same("nested-for.html", &s);
+}
+
+#[test]
+fn test_for_break_continue() {
+    #[derive(Template, Serialize)]
+    #[template(
+        source = "{% for v in values %}{% if *v > 3 %}{% break %}{% endif %}\
+                  {% if *v % 2 == 0 %}{% continue %}{% endif %}{{ v }}\
+                  {% else %}empty{% endfor %}",
+        ext = "txt"
+    )]
+    struct ForBreakContinue {
+        values: Vec<i32>,
+    }
+
+    let mut env = Environment::new();
+    env.add_template(
+        "break.txt",
+        "{% for v in values %}{% if *v > 3 %}{% break %}{% endif %}\
+         {% if *v % 2 == 0 %}{% continue %}{% endif %}{{ v }}\
+         {% else %}empty{% endfor %}",
+    )
+    .unwrap();
+    for values in [vec![1, 2, 3, 4, 5], vec![]] {
+        let s = ForBreakContinue { values };
+        assert_eq!(s.render().unwrap(), env.render("break.txt", &s).unwrap());
+    }
+}
+
+#[test]
+fn test_for_loop_variables() {
+    #[derive(Template, Serialize)]
+    #[template(
+        source = "{% for (category, name) in items -%}\
+                  {% if loop.changed(category) %}[{{ category }}]{% endif %}\
+                  {{ loop.index }}/{{ loop.length }}:{{ name }}\
+                  ({{ loop.revindex }},{{ loop.revindex0 }},{{ loop.depth }})\
+                  {% if let Some((_, prev)) = loop.previtem %}<{{ prev }}{% endif %}\
+                  {% if let Some((_, next)) = loop.nextitem %}>{{ next }}{% endif %} \
+                  {% endfor %}",
+        ext = "txt"
+    )]
+    struct ForLoopVariables<'a> {
+        items: Vec<(&'a str, &'a str)>,
+    }
+
+    let mut env = Environment::new();
+    env.add_template(
+        "loop.txt",
+        "{% for (category, name) in items -%}\
+         {% if loop.changed(category) %}[{{ category }}]{% endif %}\
+         {{ loop.index }}/{{ loop.length }}:{{ name }}\
+         ({{ loop.revindex }},{{ loop.revindex0 }},{{ loop.depth }})\
+         {% if let Some((_, prev)) = loop.previtem %}<{{ prev }}{% endif %}\
+         {% if let Some((_, next)) = loop.nextitem %}>{{ next }}{% endif %} \
+         {% endfor %}",
+    )
+    .unwrap();
+    let s = ForLoopVariables {
+        items: vec![("fruit", "pear"), ("fruit", "kiwi"), ("bread", "bagel")],
+    };
+    let output = s.render().unwrap();
+    assert_eq!(output, env.render("loop.txt", &s).unwrap());
+    assert_eq!(
+        output,
+        "[fruit]1/3:pear(3,2,1)>kiwi 2/3:kiwi(2,1,1)<pear>bagel [bread]3/3:bagel(1,0,1)<kiwi "
+    );
+}
+
+#[test]
+fn test_recursive_loop() {
+    #[derive(Serialize)]
+    struct Item {
+        name: &'static str,
+        children: Vec<Item>,
+    }
+
+    #[derive(Template, Serialize)]
+    #[template(
+        source = "{% for item in items recursive -%}\
+                  <{{ item.name }}:{{ loop.depth }}{{ loop.depth0 }}\
+                  {%- if !item.children.is_empty() %}[{{ loop(item.children) }}]{% endif %}>\
+                  {%- endfor %}",
+        ext = "html"
+    )]
+    struct RecursiveLoop {
+        items: Vec<Item>,
+    }
+
+    let mut env = Environment::new();
+    env.add_template(
+        "recursive.html",
+        "{% for item in items recursive -%}\
+         <{{ item.name }}:{{ loop.depth }}{{ loop.depth0 }}\
+         {%- if !item.children.is_empty() %}[{{ loop(item.children) }}]{% endif %}>\
+         {%- endfor %}",
+    )
+    .unwrap();
+    let leaf = |name| Item {
+        name,
+        children: vec![],
+    };
+    let s = RecursiveLoop {
+        items: vec![
+            Item {
+                name: "a",
+                children: vec![leaf("a1"), leaf("a2")],
+            },
+            leaf("b"),
+        ],
+    };
+    let output = s.render().unwrap();
+    assert_eq!(output, env.render("recursive.html", &s).unwrap());
+    assert_eq!(output, "<a:10[<a1:21><a2:21>]><b:10>");
+}
+
+#[test]
+fn test_let_block() {
+    #[derive(Template, Serialize)]
+    #[template(
+        source = "{%- set title | lower | capitalize -%}\
+                  {{ name }} AND {% for i in 1..=2 %}{{ i }}{% endfor %}\
+                  {%- endset -%}\
+                  <title>{{ title }}</title><h1>{{ title }}</h1>",
+        ext = "html"
+    )]
+    struct LetBlock<'a> {
+        name: &'a str,
+    }
+
+    let mut env = Environment::new();
+    env.add_template(
+        "let_block.html",
+        "{%- set title | lower | capitalize -%}\
+         {{ name }} AND {% for i in 1..=2 %}{{ i }}{% endfor %}\
+         {%- endset -%}\
+         <title>{{ title }}</title><h1>{{ title }}</h1>",
+    )
+    .unwrap();
+    let s = LetBlock { name: "<A>" };
+    let output = s.render().unwrap();
+    assert_eq!(output, env.render("let_block.html", &s).unwrap());
+    assert_eq!(
+        output,
+        "<title>&#60;a&#62; and 12</title><h1>&#60;a&#62; and 12</h1>"
+    );
+}
+
+#[test]
+fn test_caller() {
+    #[derive(Template, Serialize)]
+    #[template(
+        source = "{%- macro list(items) -%}\
+                  <ul>{% for item in items %}<li>{{ caller(item, loop.index) }}</li>{% endfor %}</ul>\
+                  {%- endmacro -%}\
+                  {%- call(name, index) list(names) -%}\
+                  {{ index }}: {{ name }} ({{ title }})\
+                  {%- endcall -%}",
+        ext = "html"
+    )]
+    struct Caller<'a> {
+        title: &'a str,
+        names: Vec<&'a str>,
+    }
+
+    let mut env = Environment::new();
+    env.add_template(
+        "caller.html",
+        "{%- macro list(items) -%}\
+         <ul>{% for item in items %}<li>{{ caller(item, loop.index) }}</li>{% endfor %}</ul>\
+         {%- endmacro -%}\
+         {%- call(name, index) list(names) -%}\
+         {{ index }}: {{ name }} ({{ title }})\
+         {%- endcall -%}",
+    )
+    .unwrap();
+    let s = Caller {
+        title: "<T>",
+        names: vec!["a", "b"],
+    };
+    let output = s.render().unwrap();
+    assert_eq!(output, env.render("caller.html", &s).unwrap());
+    assert_eq!(
+        output,
+        "<ul><li>1: a (&#60;T&#62;)</li><li>2: b (&#60;T&#62;)</li></ul>"
+    );
+}
+
+#[test]
+fn test_let() {
+    #[derive(Template, Serialize)]
+    #[template(path = "let.html")]
+    struct LetTemplate<'a> {
+        s: &'a str,
+        t: (&'a str, &'a str),
+    }
+
+    let s = LetTemplate {
+        s: "foo",
+        t: ("bar", "baz"),
+    };
+    assert_same("let.html", &s);
+}
+
+#[test]
+fn test_match() {
+    #[derive(Template, Serialize)]
+    #[template(path = "match-opt.html")]
+    struct MatchOptTemplate<'a> {
+        item: Option<&'a str>,
+    }
+
+    assert_same("match-opt.html", &MatchOptTemplate { item: Some("foo") });
+    assert_same("match-opt.html", &MatchOptTemplate { item: Some("bar") });
+    assert_same("match-opt.html", &MatchOptTemplate { item: None });
+}
+
+#[test]
+fn test_match_guard() {
+    #[derive(Template, Serialize)]
+    #[template(path = "match-guard.html")]
+    struct MatchGuardTemplate {
+        item: Option<u32>,
+    }
+
+    for item in [Some(12), Some(4), Some(5), None] {
+        assert_same("match-guard.html", &MatchGuardTemplate { item });
+    }
+}
+
+#[test]
+fn test_match_custom_enum() {
+    #[allow(dead_code)]
+    #[derive(Serialize)]
+    enum Color {
+        Rgb { r: u32, g: u32, b: u32 },
+        GrayScale(u8),
+        Cmyk(u8, u8, u8, u8),
+    }
+
+    #[derive(Template, Serialize)]
+    #[template(path = "match-custom-enum.html")]
+    struct MatchCustomEnumTemplate {
+        color: Color,
+    }
+
+    for color in [
+        Color::Rgb {
+            r: 160,
+            g: 0,
+            b: 255,
+        },
+        Color::GrayScale(128),
+        Color::Cmyk(1, 2, 3, 4),
+    ] {
+        assert_same("match-custom-enum.html", &MatchCustomEnumTemplate { color });
+    }
+}
+
+#[test]
+fn test_macro() {
+    #[derive(Template, Serialize)]
+    #[template(path = "macro.html")]
+    struct MacroTemplate<'a> {
+        s: &'a str,
+    }
+
+    assert_same("macro.html", &MacroTemplate { s: "foo" });
+
+    #[derive(Template, Serialize)]
+    #[template(path = "nested-macro-args.html")]
+    struct NestedMacroArgs;
+
+    assert_same("nested-macro-args.html", &NestedMacroArgs);
+}
+
+#[test]
+fn test_include() {
+    #[derive(Template, Serialize)]
+    #[template(path = "include.html")]
+    struct IncludeTemplate<'a> {
+        strs: &'a [&'a str],
+    }
+
+    let s = IncludeTemplate {
+        strs: &["foo", "bar"],
+    };
+    assert_same("include.html", &s);
+
+    #[derive(Template, Serialize)]
+    #[template(path = "include-extends.html")]
+    struct IncludeExtendsTemplate<'a> {
+        name: &'a str,
+    }
+
+    assert_same(
+        "include-extends.html",
+        &IncludeExtendsTemplate { name: "Alice" },
+    );
+}
+
+#[test]
+fn test_include_dynamic() {
+    #[derive(Serialize)]
+    struct Widget<'a> {
+        kind: &'a str,
+        title: &'a str,
+    }
+
+    #[derive(Template, Serialize)]
+    #[template(path = "include-dynamic.html")]
+    struct Dashboard<'a> {
+        widgets: Vec<Widget<'a>>,
+    }
+
+    let s = Dashboard {
+        widgets: vec![
+            Widget {
+                kind: "widgets/list.html",
+                title: "Todo",
+            },
+            Widget {
+                kind: "widgets/chart.html",
+                title: "Sales",
+            },
+        ],
+    };
+    assert_same("include-dynamic.html", &s);
+
+    let s = Dashboard {
+        widgets: vec![Widget {
+            kind: "widgets/map.html",
+            title: "Offices",
+        }],
+    };
+    let err = env().render("include-dynamic.html", &s).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Render);
+    assert!(err.to_string().contains("widgets/map.html"), "{err}");
+}
+
+#[test]
+fn test_include_fallback() {
+    #[derive(Serialize)]
+    struct Widget<'a> {
+        title: &'a str,
+    }
+
+    #[derive(Template, Serialize)]
+    #[template(path = "include-fallback.html")]
+    struct Page<'a> {
+        kind: &'a str,
+        list: Widget<'a>,
+    }
+
+    for kind in ["widgets/chart.html", "theme/header.html"] {
+        let s = Page {
+            kind,
+            list: Widget { title: "Todo" },
+        };
+        assert_same("include-fallback.html", &s);
+    }
+
+    let mut env = Environment::new();
+    env.add_template("page.html", r#"{% include ["a.html", "b.html"] %}"#)
+        .unwrap();
+    let err = env.render("page.html", &()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
+
+#[test]
+fn test_include_with() {
+    #[derive(Serialize)]
+    struct Widget<'a> {
+        title: &'a str,
+    }
+
+    #[derive(Template, Serialize)]
+    #[template(path = "include-with.html")]
+    struct Report<'a> {
+        heading: &'a str,
+        chart: Widget<'a>,
+        list: Widget<'a>,
+    }
+
+    let s = Report {
+        heading: "Quarterly report",
+        chart: Widget { title: "Sales" },
+        list: Widget { title: "Todo" },
+    };
+    assert_same("include-with.html", &s);
+
+    let mut env = Environment::new();
+    env.add_template("widget.html", "{{ heading }}").unwrap();
+    env.add_template("page.html", r#"{% include "widget.html" only %}"#)
+        .unwrap();
+    let err = env.render("page.html", &s).unwrap_err();
+    assert!(err.to_string().contains("only"), "{err}");
+}
+
+#[test]
+fn test_extends() {
+    #[derive(Template, Serialize)]
+    #[template(path = "child.html")]
+    struct ChildTemplate<'a> {
+        title: &'a str,
+    }
+
+    assert_same("child.html", &ChildTemplate { title: "Bar" });
+
+    #[derive(Template, Serialize)]
+    #[template(path = "nested-child.html")]
+    struct NestedChildTemplate;
+
+    assert_same("nested-child.html", &NestedChildTemplate);
+}
+
+#[test]
+fn test_deep_extends() {
+    #[derive(Template, Serialize)]
+    #[template(path = "deep-kid.html")]
+    struct DeepKidTemplate<'a> {
+        item: &'a str,
+        title: &'a str,
+        year: u16,
+    }
+
+    let s = DeepKidTemplate {
+        item: "foo",
+        title: "bar",
+        year: 2017,
+    };
+    assert_same("deep-kid.html", &s);
+}
+
+#[test]
+fn test_whitespace_control() {
+    #[derive(Template, Serialize)]
+    #[template(
+        source = "a  {%- if true %}  b  {%~ endif +%}  c\n {% if true %} d {% endif %} \n",
+        ext = "txt"
+    )]
+    struct Preserve;
+
+    #[derive(Template, Serialize)]
+    #[template(
+        source = "a  {%- if true %}  b  {%~ endif +%}  c\n {% if true %} d {% endif %} \n",
+        ext = "txt",
+        whitespace = "suppress"
+    )]
+    struct Suppress;
+
+    #[derive(Template, Serialize)]
+    #[template(
+        source = "a  {%- if true %}  b  {%~ endif +%}  c\n {% if true %} d {% endif %} \n",
+        ext = "txt",
+        whitespace = "minimize"
+    )]
+    struct Minimize;
+
+    let mut env = Environment::new();
+    env.add_template(
+        "ws.txt",
+        "a  {%- if true %}  b  {%~ endif +%}  c\n {% if true %} d {% endif %} \n",
+    )
+    .unwrap();
+    assert_eq!(
+        env.render("ws.txt", &Preserve).unwrap(),
+        Preserve.render().unwrap()
+    );
+    env.set_whitespace(Whitespace::Suppress);
+    assert_eq!(
+        env.render("ws.txt", &Suppress).unwrap(),
+        Suppress.render().unwrap()
+    );
+    env.set_whitespace(Whitespace::Minimize);
+    assert_eq!(
+        env.render("ws.txt", &Minimize).unwrap(),
+        Minimize.render().unwrap()
+    );
+}
+
+#[test]
+fn test_is_defined() {
+    let mut env = Environment::new();
+    env.add_template(
+        "defined.txt",
+        "{% if x is defined %}{{ x }}{% else %}none{% endif %}",
+    )
+    .unwrap();
+
+    let mut ctx = HashMap::new();
+    assert_eq!(env.render("defined.txt", &ctx).unwrap(), "none");
+    ctx.insert("x", 12);
+    assert_eq!(env.render("defined.txt", &ctx).unwrap(), "12");
+}
+
+#[test]
+fn test_is_none() {
+    let mut env = Environment::new();
+    env.add_template("none.txt", "{{ x is none }} {{ x is not none }}")
+        .unwrap();
+
+    let ctx = HashMap::from([("x", None::<u32>)]);
+    assert_eq!(env.render("none.txt", &ctx).unwrap(), "true false");
+    let ctx = HashMap::from([("x", Some(12))]);
+    assert_eq!(env.render("none.txt", &ctx).unwrap(), "false true");
+}
+
+#[test]
+fn test_template_names() {
+    let mut env = env();
+    let ctx = HashMap::from([("name", "world")]);
+    assert_eq!(
+        env.render("widgets/../hello.html", &ctx).unwrap(),
+        "Hello, world!"
+    );
+
+    // names must not leave the template directory
+    for name in [
+        "../Cargo.toml",
+        "widgets/../../Cargo.toml",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/templates/hello.html"),
+    ] {
+        let err = env.render(name, &ctx).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound, "{name}");
+    }
+
+    env.add_template("outside.html", r#"{% include "../Cargo.toml" %}"#)
+        .unwrap();
+    let err = env.render("outside.html", &ctx).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
+
+#[test]
+fn test_compiled_cache() {
+    let mut env = Environment::new();
+    env.add_template("base.txt", "[{% block a %}{% endblock %}]")
+        .unwrap();
+    env.add_template(
+        "child.txt",
+        r#"{% extends "base.txt" %}{% block a %}{{ x }}{% endblock %}"#,
+    )
+    .unwrap();
+
+    let ctx = HashMap::from([("x", 1)]);
+    assert_eq!(env.render("child.txt", &ctx).unwrap(), "[1]");
+    assert_eq!(env.render("child.txt", &ctx).unwrap(), "[1]");
+
+    // a modified parent template is used, even if the rendered template is unchanged
+    env.add_template("base.txt", "<{% block a %}{% endblock %}>")
+        .unwrap();
+    assert_eq!(env.render("child.txt", &ctx).unwrap(), "<1>");
+}
+
+#[test]
+fn test_custom_filters_and_functions() {
+    let mut env = Environment::new();
+    env.add_filter("double", |value, _| match value {
+        Value::Int(i) => Ok(Value::Int(i * 2)),
+        _ => Ok(Value::None),
+    });
+    env.add_function("greet", |_, args| {
+        Ok(Value::Str(format!("Hello, {}!", args[0]).into()))
+    });
+    env.add_template("custom.html", "{{ n|double }} {{ greet(name) }}")
+        .unwrap();
+
+    let ctx = serde_json::json!({ "n": 21, "name": "<world>" });
+    assert_eq!(
+        env.render("custom.html", &ctx).unwrap(),
+        "42 Hello, &#60;world&#62;!"
+    );
+
+    env.add_template("named.html", "{{ greet(name = name) }}")
+        .unwrap();
+    let err = env.render("named.html", &ctx).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Render);
+}
+
+#[test]
+fn test_render_value() {
+    let mut env = Environment::new();
+    env.add_template("value.txt", "{{ a }}-{{ b.c }}").unwrap();
+
+    let ctx = Value::from_serialize(&serde_json::json!({ "a": 1, "b": { "c": "d" } })).unwrap();
+    let mut buf = String::new();
+    env.render_value_into(&mut buf, "value.txt", &ctx).unwrap();
+    assert_eq!(buf, "1-d");
+}
+
+#[test]
+fn test_errors() {
+    let mut env = Environment::new();
+
+    let err = env.add_template("broken.html", "{% if %}").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Syntax);
+    assert_eq!(err.template(), Some("broken.html"));
+    assert_eq!(err.line_column(), Some((1, 7)));
+
+    let err = env.render("missing.html", &()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    env.add_template("include.html", "\n{% include \"missing.html\" %}")
+        .unwrap();
+    let err = env.render("include.html", &()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert_eq!(err.template(), Some("include.html"));
+    assert_eq!(err.line_column(), Some((2, 13)));
+
+    env.add_template("render.html", "{{ a + b }}").unwrap();
+    let ctx = serde_json::json!({ "a": 1, "b": "2" });
+    let err = env.render("render.html", &ctx).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Render);
+    assert_eq!(err.template(), Some("render.html"));
+}
+
+#[test]
+fn test_max_depth() {
+    let mut env = Environment::new();
+    env.add_template(
+        "self.html",
+        "{% if n > 0 %}{% include \"self.html\" %}{% endif %}",
+    )
+    .unwrap();
+    let err = env
+        .render("self.html", &serde_json::json!({ "n": 1 }))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Compile);
+    assert!(
+        err.to_string()
+            .contains("includes and macro calls are nested more than 64 levels deep"),
+        "{err}"
+    );
+
+    env.add_template(
+        "tree.html",
+        "{% for node in nodes recursive %}({{ loop(node.children) }}){% endfor %}",
+    )
+    .unwrap();
+    let ctx = serde_json::json!({
+        "nodes": [{ "children": [{ "children": [{ "children": [] }] }] }],
+    });
+    assert_eq!(env.render("tree.html", &ctx).unwrap(), "((()))");
+
+    env.set_max_depth(2);
+    let err = env.render("tree.html", &ctx).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Render);
+    assert!(
+        err.to_string()
+            .contains("recursive loop is nested more than 2 levels deep"),
+        "{err}"
+    );
+
+    env.add_template(
+        "macros.html",
+        "{% macro a() %}{% endmacro %}{% macro b() %}{% call a() %}{% endmacro %}\
+         {% call b() %}",
+    )
+    .unwrap();
+    assert_eq!(env.render("macros.html", &()).unwrap(), "");
+    env.set_max_depth(1);
+    let err = env.render("macros.html", &()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Compile);
+}
+
+#[test]
+fn test_max_output() {
+    let mut env = Environment::new();
+    env.add_template("list.html", "{% for x in items %}{{ x }},{% endfor %}")
+        .unwrap();
+    let ctx = serde_json::json!({ "items": ["a", "b", "c"] });
+
+    env.set_max_output(Some(6));
+    assert_eq!(env.render("list.html", &ctx).unwrap(), "a,b,c,");
+
+    env.set_max_output(Some(5));
+    let err = env.render("list.html", &ctx).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Render);
+    assert!(
+        err.to_string()
+            .contains("the rendered output exceeds the limit of 5 bytes"),
+        "{err}"
+    );
+
+    // Captured output counts against the limit, too.
+    env.add_template(
+        "capture.html",
+        "{% set x %}{% for x in items %}{{ x }}{% endfor %}{% endset %}",
+    )
+    .unwrap();
+    env.set_max_output(Some(2));
+    let err = env.render("capture.html", &ctx).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Render);
+
+    env.set_max_output(None);
+    assert_eq!(env.render("capture.html", &ctx).unwrap(), "");
+}
+
+#[test]
+fn test_unsupported_builtins() {
+    let mut env = Environment::new();
+    for (name, source, message) in [
+        (
+            "sort.html",
+            "{% for x in items|sort %}{{ x }}{% endfor %}",
+            "the `sort` filter is not supported by the interpreter",
+        ),
+        (
+            "default.html",
+            "\n{% if true %}{{ a|default(1) }}{% endif %}",
+            "the `default` filter is not supported by the interpreter",
+        ),
+        (
+            "date.html",
+            "{% filter date(\"%Y\") %}{{ now }}{% endfilter %}",
+            "the `date` filter is not supported by the interpreter",
+        ),
+        (
+            "named.html",
+            "{{ a|truncate(length = 3) }}",
+            "named arguments are not supported by the filter `truncate`",
+        ),
+        (
+            "t.html",
+            "{{ t(\"greeting\") }}",
+            "the translation function `t()` is not supported by the interpreter",
+        ),
+    ] {
+        env.add_template(name, source).unwrap();
+        let err = env.render(name, &()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Compile, "{name}");
+        assert_eq!(err.template(), Some(name));
+        assert!(err.to_string().contains(message), "{name}: {err}");
+    }
+    let err = env.render("default.html", &()).unwrap_err();
+    assert_eq!(err.line_column(), Some((2, 17)));
+
+    // A registered filter or function with the same name is used instead.
+    env.add_filter("default", |value, args| match value {
+        Value::None => Ok(args[0].clone()),
+        value => Ok(value.clone()),
+    });
+    env.add_function("t", |_, args| Ok(args[0].clone()));
+    let ctx = serde_json::json!({ "a": null });
+    assert_eq!(env.render("default.html", &ctx).unwrap(), "\n1");
+    assert_eq!(env.render("t.html", &()).unwrap(), "greeting");
+}
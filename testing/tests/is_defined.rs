@@ -44,3 +44,28 @@ fn is_defined_chaining() {
 
     assert_eq!(IsDefinedChaining.render().unwrap(), r"bla");
 }
+
+#[test]
+fn is_none() {
+    #[derive(Template)]
+    #[template(
+        source = r#"{{ x is none }} {{ x is not none }} {{ self.y() is none }}
+{%- if x is not none && x.unwrap() > 1 %} big{% endif %}"#,
+        ext = "txt"
+    )]
+    struct IsNone {
+        x: Option<u32>,
+    }
+
+    impl IsNone {
+        fn y(&self) -> &Option<u32> {
+            &self.x
+        }
+    }
+
+    assert_eq!(IsNone { x: None }.render().unwrap(), "true false true");
+    assert_eq!(
+        IsNone { x: Some(2) }.render().unwrap(),
+        "false true false big"
+    );
+}
@@ -51,3 +51,68 @@ fn underscore_ident2() {
 
     assert_eq!(X.render().unwrap(), "hey\nhoy\nmatched");
 }
+
+#[test]
+fn let_block() {
+    #[derive(Template)]
+    #[template(
+        source = r#"{%- set title -%}
+    {{ name }} & {% for i in 1..=2 %}{{ i }}{% endfor %}
+{%- endset -%}
+<title>{{ title }}</title><h1>{{ title }}</h1>"#,
+        ext = "html"
+    )]
+    struct X<'a> {
+        name: &'a str,
+    }
+
+    assert_eq!(
+        X { name: "<a>" }.render().unwrap(),
+        "<title>&#60;a&#62; & 12</title><h1>&#60;a&#62; & 12</h1>"
+    );
+}
+
+#[test]
+fn let_block_filters() {
+    #[derive(Template)]
+    #[template(
+        source = r#"{% let title | lower | capitalize %}{{ name }} AND {{ other }}{% endlet -%}
+{{ title }}"#,
+        ext = "html"
+    )]
+    struct X<'a> {
+        name: &'a str,
+        other: &'a str,
+    }
+
+    assert_eq!(
+        X {
+            name: "FOO",
+            other: "<B>"
+        }
+        .render()
+        .unwrap(),
+        "Foo and &#60;b&#62;"
+    );
+}
+
+#[test]
+fn let_block_forward_declared() {
+    #[derive(Template)]
+    #[template(
+        source = r#"{%- let x -%}
+{%- if y -%}
+    {%- set x %}yes {{ y }}{% endset -%}
+{%- else -%}
+    {%- set x %}no{% endset -%}
+{%- endif -%}
+[{{ x }}]"#,
+        ext = "html"
+    )]
+    struct X {
+        y: bool,
+    }
+
+    assert_eq!(X { y: true }.render().unwrap(), "[yes true]");
+    assert_eq!(X { y: false }.render().unwrap(), "[no]");
+}
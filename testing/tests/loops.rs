@@ -464,3 +464,199 @@ fn test_loop_locals() {
     let t = LoopLocalsContext { bla: 10 };
     assert_eq!(t.render().unwrap(), "10");
 }
+
+#[test]
+fn test_loop_length() {
+    #[derive(Template)]
+    #[template(
+        source = "{% for v in values %}{{ v }}:{{ loop.index }}/{{ loop.length }}\
+                  ({{ loop.revindex }},{{ loop.revindex0 }}) {% endfor %}|\
+                  {% for i in 0..limit if i % 2 == 0 %}{{ i }}/{{ loop.length }} {% endfor %}|\
+                  {% for v in values %}{% for w in 0..loop.index %}{{ loop.length }}{% endfor %}\
+                  {% endfor %}",
+        ext = "txt"
+    )]
+    struct LoopLength<'a> {
+        values: &'a [&'a str],
+        limit: usize,
+    }
+
+    let t = LoopLength {
+        values: &["a", "b", "c"],
+        limit: 5,
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "a:1/3(3,2) b:2/3(2,1) c:3/3(1,0) |0/3 2/3 4/3 |122333"
+    );
+}
+
+#[test]
+fn test_loop_previtem_nextitem() {
+    #[derive(Template)]
+    #[template(
+        source = "{% for v in values -%}\
+                      {% if let Some(prev) = loop.previtem %}{{ prev }}{% else %}-{% endif %}<\
+                      {{- v -}}\
+                      >{% if let Some(next) = loop.nextitem %}{{ next }}{% else %}-{% endif %} \
+                  {% endfor %}|\
+                  {% for (a, b) in pairs %}{{ a }}{{ b }}\
+                      {% if let Some((c, _)) = loop.nextitem %}{{ c }}{% endif %};\
+                  {% endfor %}",
+        ext = "txt"
+    )]
+    struct LoopItems<'a> {
+        values: Vec<&'a str>,
+        pairs: Vec<(u8, char)>,
+    }
+
+    let t = LoopItems {
+        values: vec!["a", "b", "c"],
+        pairs: vec![(1, 'x'), (2, 'y')],
+    };
+    assert_eq!(t.render().unwrap(), "-<a>b a<b>c b<c>- |1x2;2y;");
+}
+
+#[test]
+fn test_loop_previtem_not_clone() {
+    struct Item(u32);
+
+    impl std::fmt::Display for Item {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "#{}", self.0)
+        }
+    }
+
+    #[derive(Template)]
+    #[template(
+        source = "{% for item in self.items() -%}\
+                      {% if let Some(prev) = loop.previtem %}{{ prev }}{% else %}-{% endif %}<\
+                      {{- item }} \
+                  {%- endfor %}",
+        ext = "txt"
+    )]
+    struct LoopItems {
+        count: u32,
+    }
+
+    impl LoopItems {
+        fn items(&self) -> impl Iterator<Item = Item> {
+            (1..=self.count).map(Item)
+        }
+    }
+
+    assert_eq!(LoopItems { count: 3 }.render().unwrap(), "-<#1#1<#2#2<#3");
+}
+
+#[test]
+fn test_loop_changed() {
+    #[derive(Template)]
+    #[template(
+        source = "{% for (category, name) in items -%}\
+                      {% if loop.changed(category) %}[{{ category }}]{% endif %}{{ name }} \
+                      {%- if loop.changed(category, name.len()) %}!{% endif %} \
+                  {% endfor %}",
+        ext = "txt"
+    )]
+    struct LoopChanged<'a> {
+        items: Vec<(&'a str, &'a str)>,
+    }
+
+    let t = LoopChanged {
+        items: vec![
+            ("fruit", "pear"),
+            ("fruit", "kiwi"),
+            ("fruit", "apple"),
+            ("bread", "bagel"),
+        ],
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "[fruit]pear! kiwi apple! [bread]bagel! "
+    );
+}
+
+#[test]
+fn test_loop_depth() {
+    #[derive(Template)]
+    #[template(
+        source = "{% for row in rows %}{{ loop.depth }}{{ loop.depth0 }}\
+                  {% for v in row %}{{ loop.depth }}{% endfor %}{% endfor %}",
+        ext = "txt"
+    )]
+    struct LoopDepth {
+        rows: Vec<Vec<u8>>,
+    }
+
+    let t = LoopDepth {
+        rows: vec![vec![1, 2], vec![3]],
+    };
+    assert_eq!(t.render().unwrap(), "1011101");
+}
+
+struct TreeNode<'a> {
+    name: &'a str,
+    children: Vec<TreeNode<'a>>,
+}
+
+fn tree() -> Vec<TreeNode<'static>> {
+    let leaf = |name| TreeNode {
+        name,
+        children: vec![],
+    };
+    vec![
+        TreeNode {
+            name: "a",
+            children: vec![
+                leaf("a1"),
+                TreeNode {
+                    name: "a2",
+                    children: vec![leaf("a2x")],
+                },
+            ],
+        },
+        leaf("b"),
+    ]
+}
+
+#[test]
+fn test_recursive_loop() {
+    #[derive(Template)]
+    #[template(
+        source = "{% for node in nodes recursive -%}\
+                      <{{ node.name }}:{{ loop.depth }}{{ loop.depth0 }}\
+                      {%- if !node.children.is_empty() %}[{{ loop(node.children) }}]{% endif -%}\
+                      {% if !loop.last %},{% endif %}>\
+                  {%- endfor %}",
+        ext = "html"
+    )]
+    struct RecursiveLoop<'a> {
+        nodes: Vec<TreeNode<'a>>,
+    }
+
+    let t = RecursiveLoop { nodes: tree() };
+    assert_eq!(
+        t.render().unwrap(),
+        "<a:10[<a1:21,><a2:21[<a2x:32>]>],><b:10>"
+    );
+}
+
+#[test]
+fn test_recursive_loop_else_and_cond() {
+    #[derive(Template)]
+    #[template(
+        source = "{% for node in nodes if !node.name.ends_with('1') recursive -%}\
+                      {{ node.name }}{% for _ in 0..1 %}{{ loop.depth }}{% endfor %}\
+                      ({{ loop(node.children) }})\
+                  {%- else -%}\
+                      -\
+                  {%- endfor %}",
+        ext = "txt"
+    )]
+    struct RecursiveLoopElse<'a> {
+        nodes: Vec<TreeNode<'a>>,
+    }
+
+    let t = RecursiveLoopElse { nodes: tree() };
+    assert_eq!(t.render().unwrap(), "a1(a21(a2x1(-)))b1(-)");
+}
@@ -358,3 +358,70 @@ fn test_rust_keywords_as_args_with_default_expr() {
     // primarily checking for compilation
     assert_eq!(MacroRustKwArgsDefaultExpr.render().unwrap(), "3\n1\n1\n");
 }
+
+#[test]
+fn test_caller() {
+    #[derive(Template)]
+    #[template(
+        source = "
+{%- macro card(title) -%}
+<div><h1>{{ title }}</h1>{{ caller() }}</div>
+{%- endmacro -%}
+
+{%- call() card(\"Hello\") -%}
+    <p>{{ title }} & {{ name }}</p>
+{%- endcall %}
+{% for name in names -%}
+{%- call() card(name) %}{{ loop.index }}{% endcall -%}
+{%- endfor %}",
+        ext = "html"
+    )]
+    struct Caller<'a> {
+        title: &'a str,
+        name: &'a str,
+        names: Vec<&'a str>,
+    }
+
+    let t = Caller {
+        title: "<T>",
+        name: "N",
+        names: vec!["a", "b"],
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "<div><h1>Hello</h1><p>&#60;T&#62; & N</p></div>\n\
+         <div><h1>a</h1>1</div><div><h1>b</h1>2</div>"
+    );
+}
+
+#[test]
+fn test_caller_with_args() {
+    #[derive(Template)]
+    #[template(
+        source = "
+{%- macro list(items) -%}
+<ul>{% for item in items %}<li>{{ caller(item, loop.index) }}</li>{% endfor %}</ul>
+{%- endmacro -%}
+
+{%- macro wrap() -%}
+[{{ caller() }}]
+{%- endmacro -%}
+
+{%- call(user, index) list(users) -%}
+    {{ index }}: {{ user.0 }}
+    {%- call() wrap() %}{{ user.1 }}{% endcall -%}
+{%- endcall -%}",
+        ext = "html"
+    )]
+    struct CallerWithArgs<'a> {
+        users: Vec<(&'a str, u32)>,
+    }
+
+    let t = CallerWithArgs {
+        users: vec![("Kim", 42), ("Bob", 18)],
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "<ul><li>1: Kim[42]</li><li>2: Bob[18]</li></ul>"
+    );
+}
@@ -300,3 +300,36 @@ fn test_end_when() {
     let tmpl = EndWhen { result: None };
     assert_eq!(tmpl.to_string(), "unprocessed\n");
 }
+
+#[test]
+fn test_match_guard() {
+    #[derive(Template)]
+    #[template(
+        ext = "txt",
+        source = r#"
+{%- match n -%}
+    {%- when Some(n) if *n > 10 -%}
+        big {{ n }}
+    {%- when Some(1 or 2) | Some(3) if !small -%}
+        listed
+    {%- when Some(n) if n % 2 == 0 && *n > 2 -%}
+        even {{ n }}
+    {%- when Some(n) -%}
+        other {{ n }}
+    {%- when None -%}
+        none
+{%- endmatch -%}"#
+    )]
+    struct MatchGuard {
+        n: Option<u32>,
+        small: bool,
+    }
+
+    let render = |n, small| MatchGuard { n, small }.render().unwrap();
+    assert_eq!(render(Some(12), false), "big 12");
+    assert_eq!(render(Some(2), false), "listed");
+    assert_eq!(render(Some(2), true), "other 2");
+    assert_eq!(render(Some(4), false), "even 4");
+    assert_eq!(render(Some(5), false), "other 5");
+    assert_eq!(render(None, false), "none");
+}
@@ -0,0 +1,191 @@
+#![cfg(feature = "icu")]
+
+use std::any::Any;
+use std::collections::HashMap;
+
+use askama::Template;
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ amount|number }}|{{ amount|number(1) }}|{{ count|intcomma }}"#,
+    ext = "html",
+    config = "builtin-filters.toml"
+)]
+struct Numbers<'a> {
+    amount: f64,
+    count: u64,
+    locale: &'a str,
+}
+
+#[test]
+fn test_number_filters_locales() {
+    for (locale, expected) in [
+        ("en-US", "1,234,567.25|1,234,567.2|9,876,543"),
+        ("de-DE", "1.234.567,25|1.234.567,2|9.876.543"),
+        (
+            "fr-FR",
+            "1\u{202f}234\u{202f}567,25|1\u{202f}234\u{202f}567,2|9\u{202f}876\u{202f}543",
+        ),
+        ("de-CH", "1’234’567.25|1’234’567.2|9’876’543"),
+        ("hi-IN", "12,34,567.25|12,34,567.2|98,76,543"),
+        ("ja", "1,234,567.25|1,234,567.2|9,876,543"),
+    ] {
+        let tmpl = Numbers {
+            amount: 1_234_567.25,
+            count: 9_876_543,
+            locale,
+        };
+        assert_eq!(tmpl.render().unwrap(), expected, "{locale}");
+    }
+}
+
+#[cfg(feature = "icu-unstable")]
+#[test]
+fn test_percent_currency_locales() {
+    #[derive(Template)]
+    #[template(
+        source = r#"{{ ratio|percent }}|{{ amount|currency("EUR") }}"#,
+        ext = "html",
+        config = "builtin-filters.toml"
+    )]
+    struct Numbers<'a> {
+        amount: f64,
+        ratio: f32,
+        locale: &'a str,
+    }
+
+    for (locale, expected) in [
+        ("en-US", "42%|€1,234,567.25"),
+        ("de-DE", "42\u{a0}%|1.234.567,25\u{a0}€"),
+        ("fr-FR", "42\u{a0}%|1\u{202f}234\u{202f}567,25\u{a0}€"),
+        ("de-CH", "42%|EUR\u{a0}1’234’567.25"),
+        ("hi-IN", "42%|€12,34,567.25"),
+        ("ja", "42%|€1,234,567.25"),
+    ] {
+        let tmpl = Numbers {
+            amount: 1_234_567.25,
+            ratio: 0.42,
+            locale,
+        };
+        assert_eq!(tmpl.render().unwrap(), expected, "{locale}");
+    }
+}
+
+#[cfg(feature = "icu-unstable")]
+#[test]
+fn test_currency() {
+    #[derive(Template)]
+    #[template(
+        source = r#"{{ amount|currency("USD") }} {{ amount|currency(code) }} {{ amount|currency("eur", 0) }}"#,
+        ext = "html",
+        config = "builtin-filters.toml"
+    )]
+    struct Currency {
+        amount: i32,
+        code: String,
+        locale: askama::filters::Locale,
+    }
+
+    let tmpl = Currency {
+        amount: -1500,
+        code: "JPY".to_owned(),
+        locale: "en".parse().unwrap(),
+    };
+    assert_eq!(tmpl.render().unwrap(), "-$1,500.00 -¥1,500 -€1,500");
+
+    let tmpl = Currency {
+        amount: 1500,
+        code: "EURO".to_owned(),
+        locale: "en".parse().unwrap(),
+    };
+    assert_eq!(
+        tmpl.render().unwrap_err().to_string(),
+        r#"invalid currency code "EURO""#
+    );
+}
+
+#[test]
+fn test_number_locale_from_values() {
+    #[derive(Template)]
+    #[template(
+        source = "{{ value|number(2) }}",
+        ext = "html",
+        config = "builtin-filters.toml"
+    )]
+    struct Number {
+        value: f32,
+    }
+
+    let tmpl = Number { value: 1234.5 };
+    let mut values: HashMap<String, Box<dyn Any>> = HashMap::new();
+    assert_eq!(tmpl.render_with_values(&values).unwrap(), "1,234.50");
+    values.insert("locale".to_owned(), Box::new("de"));
+    assert_eq!(tmpl.render_with_values(&values).unwrap(), "1.234,50");
+    values.insert("locale".to_owned(), Box::new("fr".to_owned()));
+    assert_eq!(tmpl.render_with_values(&values).unwrap(), "1\u{202f}234,50");
+    let locale: askama::filters::Locale = "es".parse().unwrap();
+    values.insert("locale".to_owned(), Box::new(locale));
+    assert_eq!(tmpl.render_with_values(&values).unwrap(), "1234,50");
+}
+
+#[cfg(feature = "icu-unstable")]
+#[test]
+fn test_number_locale_from_variable() {
+    #[derive(Template)]
+    #[template(
+        source = r#"{% for locale in ["en", "de"] %}{{ value|percent(1) }} {% endfor %}"#,
+        ext = "html",
+        config = "builtin-filters.toml"
+    )]
+    struct Percent {
+        value: f64,
+    }
+
+    assert_eq!(
+        Percent { value: -0.0625 }.render().unwrap(),
+        "-6.2% -6,2\u{a0}% "
+    );
+}
+
+#[test]
+fn test_number_errors() {
+    #[derive(Template)]
+    #[template(
+        source = "{{ value|number }}",
+        ext = "txt",
+        config = "builtin-filters.toml"
+    )]
+    struct Number {
+        value: f64,
+    }
+
+    assert_eq!(
+        Number { value: f64::NAN }.render().unwrap_err().to_string(),
+        "cannot format an infinite or NaN number"
+    );
+
+    #[derive(Template)]
+    #[template(
+        source = "{{ value|number(decimals) }}",
+        ext = "txt",
+        config = "builtin-filters.toml"
+    )]
+    struct Decimals {
+        value: f64,
+        decimals: i32,
+    }
+
+    let tmpl = Decimals {
+        value: 1.5,
+        decimals: 258,
+    };
+    assert_eq!(
+        tmpl.render().unwrap_err().to_string(),
+        "the number of decimal places must be between 0 and 255"
+    );
+    let tmpl = Decimals {
+        value: 1.5,
+        decimals: 2,
+    };
+    assert_eq!(tmpl.render().unwrap(), "1.50");
+}
@@ -0,0 +1,196 @@
+use std::any::Any;
+use std::collections::HashMap;
+
+use askama::Template;
+
+#[derive(Template, Clone)]
+#[template(
+    source = "{% for i in 0..count %}{{ i }}: {{ line }}\n{% endfor %}",
+    ext = "txt"
+)]
+struct Lines {
+    count: usize,
+    line: String,
+}
+
+#[test]
+fn test_render_chunks() {
+    let tmpl = Lines {
+        count: 100,
+        line: "x".repeat(100),
+    };
+    let expected = tmpl.render().unwrap();
+
+    let chunks = tmpl
+        .clone()
+        .render_chunks(1000)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(chunks.concat(), expected.as_bytes());
+    let (last, init) = chunks.split_last().unwrap();
+    assert!(init.iter().all(|chunk| chunk.len() == 1000));
+    assert!(!last.is_empty() && last.len() <= 1000);
+    assert_eq!(chunks.len(), expected.len().div_ceil(1000));
+
+    // chunks bigger than the output
+    let chunks = tmpl
+        .clone()
+        .render_chunks(1 << 20)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(chunks, [expected.as_bytes()]);
+
+    // a chunk size of zero is treated like one
+    let chunks = tmpl
+        .render_chunks(0)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(chunks.len(), expected.len());
+}
+
+#[test]
+fn test_render_chunks_empty() {
+    let tmpl = Lines {
+        count: 0,
+        line: String::new(),
+    };
+    assert_eq!(tmpl.render_chunks(10).count(), 0);
+}
+
+#[test]
+fn test_render_chunks_with_values() {
+    #[derive(Template)]
+    #[template(
+        source = r#"{% if let Ok(name) = "name" | value::<String> %}Hello, {{ name }}!{% endif %}"#,
+        ext = "txt"
+    )]
+    struct Hello;
+
+    let mut values: HashMap<String, Box<dyn Any + Send>> = HashMap::default();
+    values.insert("name".to_string(), Box::new("world".to_string()));
+
+    let chunks = Hello
+        .render_chunks_with_values(5, values)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(chunks, [&b"Hello"[..], b", wor", b"ld!"]);
+}
+
+#[test]
+fn test_render_chunks_error() {
+    #[derive(Template)]
+    #[template(
+        source = "{{ a }}{% if true %}{{ self.fail()? }}{% endif %}",
+        ext = "txt"
+    )]
+    struct Fails {
+        a: &'static str,
+    }
+
+    impl Fails {
+        fn fail(&self) -> Result<&str, askama::Error> {
+            Err(askama::Error::ValueMissing)
+        }
+    }
+
+    let mut chunks = Fails { a: "0123456789" }.render_chunks(4);
+    assert_eq!(chunks.next().unwrap().unwrap(), b"0123");
+    assert_eq!(chunks.next().unwrap().unwrap(), b"4567");
+    assert!(matches!(
+        chunks.next(),
+        Some(Err(askama::Error::ValueMissing))
+    ));
+    assert!(chunks.next().is_none());
+}
+
+#[test]
+fn test_render_chunks_renders_once() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Template)]
+    #[template(
+        source = "{% for _ in 0..10 %}{{ self.next() }},{% endfor %}",
+        ext = "txt"
+    )]
+    struct Counter {
+        count: AtomicUsize,
+    }
+
+    impl Counter {
+        fn next(&self) -> usize {
+            self.count.fetch_add(1, Ordering::Relaxed)
+        }
+    }
+
+    // the output of a template that renders differently every time must not get mixed up
+    let tmpl = Counter {
+        count: AtomicUsize::new(0),
+    };
+    let chunks = tmpl
+        .render_chunks(3)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(chunks.concat(), b"0,1,2,3,4,5,6,7,8,9,");
+}
+
+#[test]
+fn test_render_chunks_dropped_early() {
+    // the background thread must not block forever if the iterator is dropped
+    let tmpl = Lines {
+        count: 10_000,
+        line: "x".repeat(100),
+    };
+    let mut chunks = tmpl.render_chunks(10);
+    assert_eq!(chunks.next().unwrap().unwrap(), b"0: xxxxxxx");
+    drop(chunks);
+}
+
+#[test]
+fn test_render_chunks_panic() {
+    #[derive(Template)]
+    #[template(
+        source = "{{ a }}{% if true %}{{ self.fail() }}{% endif %}",
+        ext = "txt"
+    )]
+    struct Panics {
+        a: &'static str,
+    }
+
+    impl Panics {
+        fn fail(&self) -> &str {
+            panic!("template panicked")
+        }
+    }
+
+    // the panic must not end the iterator as if the template was rendered successfully
+    let mut chunks = Panics { a: "0123456789" }.render_chunks(4);
+    assert_eq!(chunks.next().unwrap().unwrap(), b"0123");
+    assert_eq!(chunks.next().unwrap().unwrap(), b"4567");
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| chunks.next()));
+    assert!(result.is_err());
+}
+
+#[cfg(any(feature = "futures-io", feature = "tokio"))]
+#[test]
+fn test_render_chunks_stream() {
+    use std::future::poll_fn;
+    use std::pin::Pin;
+
+    use futures_core::Stream;
+    use futures_executor::block_on;
+
+    let tmpl = Lines {
+        count: 100,
+        line: "x".repeat(100),
+    };
+    let expected = tmpl.render().unwrap();
+
+    let mut chunks = tmpl.render_chunks(1000);
+    let mut output = Vec::new();
+    block_on(async {
+        while let Some(chunk) = poll_fn(|cx| Pin::new(&mut chunks).poll_next(cx)).await {
+            output.extend(chunk.unwrap());
+        }
+    });
+    assert_eq!(output, expected.as_bytes());
+}
@@ -0,0 +1,32 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(
+    source = "{% macro m() %}{{ caller(1) }}{% endmacro %}{% call() m() %}x{% endcall %}",
+    ext = "txt"
+)]
+struct WrongArgumentCount;
+
+#[derive(Template)]
+#[template(
+    source = "{% macro m() %}{{ caller()|upper }}{% endmacro %}{% call() m() %}x{% endcall %}",
+    ext = "txt"
+)]
+struct CallerInExpression;
+
+#[derive(Template)]
+#[template(
+    source = "{% macro m() %}{{ caller(1, 2) }}{% endmacro %}{% call(a, a) m() %}x{% endcall %}",
+    ext = "txt"
+)]
+struct DuplicatedArgument;
+
+#[derive(Template)]
+#[template(
+    source = "{% macro m() %}{{ caller() }}{% endmacro %}{% call() m() %}x",
+    ext = "txt"
+)]
+struct MissingEndcall;
+
+fn main() {
+}
@@ -0,0 +1,122 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ items|upper(attribute = "name") }}"#,
+    ext = "txt",
+    config = "builtin-filters.toml",
+)]
+struct NamedArgumentInOtherFilter<'a> {
+    items: &'a [&'a str],
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% for x in items|sort(attribute = name) %}{% endfor %}"#,
+    ext = "txt",
+    config = "builtin-filters.toml",
+)]
+struct AttributeNotALiteral<'a> {
+    items: &'a [&'a str],
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% for x in items|sort(attribute = "a..b") %}{% endfor %}"#,
+    ext = "txt",
+    config = "builtin-filters.toml",
+)]
+struct InvalidAttribute<'a> {
+    items: &'a [&'a str],
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% for x in items|sort(true, reverse = false) %}{% endfor %}"#,
+    ext = "txt",
+    config = "builtin-filters.toml",
+)]
+struct DuplicatedArgument<'a> {
+    items: &'a [&'a str],
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% for x in items|unique(case_sensitive = true) %}{% endfor %}"#,
+    ext = "txt",
+    config = "builtin-filters.toml",
+)]
+struct UnknownArgument<'a> {
+    items: &'a [&'a str],
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% for x in items|groupby %}{% endfor %}"#,
+    ext = "txt",
+    config = "builtin-filters.toml",
+)]
+struct GroupByWithoutAttribute<'a> {
+    items: &'a [&'a str],
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% for x in items|batch %}{% endfor %}"#,
+    ext = "txt",
+    config = "builtin-filters.toml",
+)]
+struct BatchWithoutCount<'a> {
+    items: &'a [&'a str],
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ items|map("upper")|join(",") }}"#,
+    ext = "txt",
+    config = "builtin-filters.toml",
+)]
+struct MapWithFilterName<'a> {
+    items: &'a [&'a str],
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ items|select("odd", 2)|join(",") }}"#,
+    ext = "txt",
+    config = "builtin-filters.toml",
+)]
+struct TestWithUnexpectedArgument<'a> {
+    items: &'a [u32],
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ items|reject("prime")|join(",") }}"#,
+    ext = "txt",
+    config = "builtin-filters.toml",
+)]
+struct UnknownTest<'a> {
+    items: &'a [u32],
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ items|selectattr|join(",") }}"#,
+    ext = "txt",
+    config = "builtin-filters.toml",
+)]
+struct SelectAttrWithoutAttribute<'a> {
+    items: &'a [u32],
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% for x in items|sort(attribute = "name") %}{% endfor %}"#,
+    ext = "txt",
+)]
+struct CollectionFiltersNotEnabled<'a> {
+    items: &'a [&'a str],
+}
+
+fn main() {}
@@ -0,0 +1,85 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(
+    source = "<{{ tag }}></{{ tag }}>",
+    ext = "html",
+    contextual_escape = true
+)]
+struct TagName<'a> {
+    tag: &'a str,
+}
+
+#[derive(Template)]
+#[template(source = "<input {{ attr }}>", ext = "html", contextual_escape = true)]
+struct AttrName<'a> {
+    attr: &'a str,
+}
+
+#[derive(Template)]
+#[template(
+    source = "<script>var a = {{ a }};</script>",
+    ext = "html",
+    contextual_escape = true
+)]
+struct JsCode<'a> {
+    a: &'a str,
+}
+
+#[derive(Template)]
+#[template(
+    source = "<button onclick=f('{{ a }}')>",
+    ext = "html",
+    contextual_escape = true
+)]
+struct UnquotedHandler<'a> {
+    a: &'a str,
+}
+
+#[derive(Template)]
+#[template(source = "<p style={{ a }}>", ext = "html", contextual_escape = true)]
+struct UnquotedStyle<'a> {
+    a: &'a str,
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"<a {% if b %}href="{% endif %}">{{ b }}"#,
+    ext = "html",
+    contextual_escape = true
+)]
+struct Branches {
+    b: bool,
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% for a in b %}<p{% endfor %}",
+    ext = "html",
+    contextual_escape = true
+)]
+struct Loop<'a> {
+    b: &'a [u32],
+}
+
+#[derive(Template)]
+#[template(
+    source = "<script>var a = `${ {{ x }} }`;</script>",
+    ext = "html",
+    contextual_escape = true
+)]
+struct TemplateLiteralSubstitution<'a> {
+    x: &'a str,
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"<script>var r = /"/, a = {{ x }};</script>"#,
+    ext = "html",
+    contextual_escape = true
+)]
+struct AfterRegex<'a> {
+    x: &'a str,
+}
+
+fn main() {}
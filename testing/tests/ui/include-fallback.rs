@@ -0,0 +1,18 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(
+    ext = "txt",
+    source = r#"{% include ["theme/header.html", "theme/default.html"] %}"#
+)]
+struct NoneFound;
+
+#[derive(Template)]
+#[template(
+    ext = "txt",
+    source = r#"{% include ["widgets/list.html", "widgets/list.html"] %}"#
+)]
+struct Duplicated;
+
+fn main() {
+}
@@ -0,0 +1,27 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(
+    ext = "txt",
+    source = r#"{% include "widgets/title.html" with label = "Title" only %}"#
+)]
+struct MissingVariable<'a> {
+    widget: &'a str,
+}
+
+#[derive(Template)]
+#[template(ext = "txt", source = r#"{% include "include-only-self.html" only %}"#)]
+struct SelfIsHidden;
+
+#[derive(Template)]
+#[template(
+    ext = "txt",
+    source = r#"{% include "widgets/list.html" with widget = a, widget = b %}"#
+)]
+struct DuplicatedName<'a> {
+    a: &'a str,
+    b: &'a str,
+}
+
+fn main() {
+}
@@ -0,0 +1,15 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(source = "{% set title | upper %}x", ext = "txt")]
+struct MissingEndset;
+
+#[derive(Template)]
+#[template(source = "{% set title | upper %}x{% endlet %}", ext = "txt")]
+struct WrongEndTag;
+
+#[derive(Template)]
+#[template(source = "{% set (a, b) %}x{% endset %}", ext = "txt")]
+struct TupleTarget;
+
+fn main() {}
@@ -0,0 +1,35 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(
+    source = "{% for v in values %}{{ loop(v.children) }}{% endfor %}",
+    ext = "txt"
+)]
+struct NotRecursive<'a> {
+    values: &'a [Node],
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% for v in values recursive %}{{ loop(v.children, 1) }}{% endfor %}",
+    ext = "txt"
+)]
+struct TooManyArguments<'a> {
+    values: &'a [Node],
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% for v in values recursive %}{{ loop(v.children) }}{% endfor %}",
+    ext = "txt"
+)]
+struct NonHomogeneous<'a> {
+    values: &'a [Node],
+}
+
+struct Node {
+    children: Option<Box<Node>>,
+}
+
+fn main() {
+}
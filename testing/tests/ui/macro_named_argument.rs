@@ -53,5 +53,12 @@ struct MissingArgs;
 {%- call thrice() -%}", ext = "html")]
 struct MissingArgs2;
 
+// Ensures that named arguments are rejected in function calls.
+#[derive(Template)]
+#[template(source = "{{ greet(name = user) }}", ext = "html")]
+struct NamedArgInFunctionCall {
+    user: String,
+}
+
 fn main() {
 }
@@ -0,0 +1,24 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(
+    source = r#"
+{% if user.is_admin( %}
+    <p>{{ user.name + }}</p>
+{% else %}
+    <p>{{ "guest" }}</p>
+{% endif %}
+{% for item in items %}
+    {{ item|truncate(10 }}
+{% endfor %}
+"#,
+    ext = "html"
+)]
+struct MultipleErrors;
+
+#[derive(Template)]
+#[template(source = "{{ a + }}{% if b %}{% endfor %}{# unclosed", ext = "txt")]
+struct EveryKindOfError;
+
+fn main() {
+}
@@ -0,0 +1,260 @@
+#![cfg(all(feature = "futures-io", feature = "tokio"))]
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use askama::Template;
+use futures_executor::block_on;
+
+#[derive(Template, Clone)]
+#[template(
+    source = "{% for i in 0..count %}{{ i }}: {{ line }}\n{% endfor %}",
+    ext = "txt"
+)]
+struct Lines {
+    count: usize,
+    line: String,
+}
+
+/// Records every chunk it receives, and pretends to be busy every other time it is polled
+#[derive(Default)]
+struct Recorder {
+    chunks: Vec<Vec<u8>>,
+    busy: bool,
+    flushed: bool,
+}
+
+impl Recorder {
+    fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.busy = !self.busy;
+        if self.busy {
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        } else {
+            self.chunks.push(buf.to_vec());
+            Poll::Ready(Ok(buf.len()))
+        }
+    }
+
+    fn output(&self) -> String {
+        String::from_utf8(self.chunks.concat()).unwrap()
+    }
+}
+
+impl futures_io::AsyncWrite for Recorder {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().flushed = true;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl tokio::io::AsyncWrite for Recorder {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().flushed = true;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[test]
+fn test_write_into_async() {
+    let tmpl = Lines {
+        count: 3,
+        line: "hello".into(),
+    };
+    let mut buf = Vec::new();
+    block_on(tmpl.clone().write_into_async(&mut buf)).unwrap();
+    assert_eq!(buf, b"0: hello\n1: hello\n2: hello\n");
+
+    let mut buf = Vec::new();
+    block_on(tmpl.write_into_tokio(&mut buf)).unwrap();
+    assert_eq!(buf, b"0: hello\n1: hello\n2: hello\n");
+}
+
+#[test]
+fn test_write_into_async_chunked() {
+    let tmpl = Lines {
+        count: 100,
+        line: "x".repeat(1000),
+    };
+    let expected = tmpl.render().unwrap();
+
+    let mut writer = Recorder::default();
+    block_on(tmpl.clone().write_into_async(&mut writer)).unwrap();
+    assert!(writer.flushed);
+    assert_eq!(writer.output(), expected);
+    // the output was handed to the writer while the template was still being evaluated
+    assert!(writer.chunks.len() > 1);
+    assert!(
+        writer
+            .chunks
+            .iter()
+            .all(|chunk| chunk.len() < expected.len())
+    );
+
+    let mut writer = Recorder::default();
+    block_on(tmpl.write_into_tokio(&mut writer)).unwrap();
+    assert!(writer.flushed);
+    assert_eq!(writer.output(), expected);
+    assert!(writer.chunks.len() > 1);
+}
+
+#[test]
+fn test_write_into_async_with_values() {
+    #[derive(Template)]
+    #[template(
+        source = r#"{% if let Ok(name) = "name" | value::<&str> %}Hello, {{ name }}!{% endif %}"#,
+        ext = "txt"
+    )]
+    struct Hello;
+
+    let mut values: HashMap<String, Box<dyn Any + Send>> = HashMap::default();
+    values.insert("name".to_string(), Box::new("world"));
+
+    let mut buf = Vec::new();
+    block_on(Hello.write_into_async_with_values(&mut buf, values)).unwrap();
+    assert_eq!(buf, b"Hello, world!");
+
+    let mut values: HashMap<String, Box<dyn Any + Send>> = HashMap::default();
+    values.insert("name".to_string(), Box::new("world"));
+
+    let mut buf = Vec::new();
+    block_on(Hello.write_into_tokio_with_values(&mut buf, values)).unwrap();
+    assert_eq!(buf, b"Hello, world!");
+}
+
+#[test]
+fn test_write_into_async_error() {
+    #[derive(Template)]
+    #[template(source = "{{ self.fail()? }}", ext = "txt")]
+    struct Fails;
+
+    impl Fails {
+        fn fail(&self) -> Result<&str, io::Error> {
+            Err(io::Error::new(io::ErrorKind::InvalidData, "oops"))
+        }
+    }
+
+    let mut buf = Vec::new();
+    let err = block_on(Fails.write_into_async(&mut buf)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Other);
+    assert!(buf.is_empty());
+
+    // errors of the writer are passed through
+    let tmpl = Lines {
+        count: 100,
+        line: "x".repeat(1000),
+    };
+    let mut buf = [0; 10_000];
+    let mut cursor = io::Cursor::new(&mut buf[..]);
+    let err = block_on(tmpl.write_into_tokio(&mut cursor)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+}
+
+#[test]
+fn test_write_into_async_is_send() {
+    fn assert_send<T: Send>(_: T) {}
+
+    let tmpl = Lines {
+        count: 1,
+        line: String::new(),
+    };
+    let mut buf = Vec::new();
+    assert_send(tmpl.clone().write_into_async(&mut buf));
+    assert_send(tmpl.write_into_tokio(&mut buf));
+}
+
+#[test]
+fn test_write_into_async_backpressure() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Wake;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    #[derive(Template)]
+    #[template(
+        source = "{% for _ in 0..10_000 %}{{ self.line() }}{% endfor %}",
+        ext = "txt"
+    )]
+    struct Counted {
+        lines: Arc<AtomicUsize>,
+    }
+
+    impl Counted {
+        fn line(&self) -> String {
+            self.lines.fetch_add(1, Ordering::Relaxed);
+            "x".repeat(100)
+        }
+    }
+
+    /// A writer that never accepts any data
+    struct Stalled;
+
+    impl futures_io::AsyncWrite for Stalled {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            _: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            Poll::Pending
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Pending
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Pending
+        }
+    }
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    let lines = Arc::new(AtomicUsize::new(0));
+    let tmpl = Counted {
+        lines: Arc::clone(&lines),
+    };
+    let mut writer = Stalled;
+    let mut future = tmpl.write_into_async(&mut writer);
+    let waker = Arc::new(NoopWaker).into();
+    let mut cx = Context::from_waker(&waker);
+    for _ in 0..10 {
+        assert!(Pin::new(&mut future).poll(&mut cx).is_pending());
+        sleep(Duration::from_millis(10));
+    }
+    // the evaluation stops after a few chunks, because the writer does not accept any data
+    let lines = lines.load(Ordering::Relaxed);
+    assert!(lines > 0 && lines < 1000, "{lines}");
+}